@@ -0,0 +1,211 @@
+use std::time::Instant;
+
+use axum::{
+    Json, Router,
+    extract::State,
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::AppState;
+use crate::config::EffectiveConfig;
+use crate::db::{self, DbSize, DbStats};
+use crate::webhooks::{self, WebhookPayload};
+
+#[derive(Deserialize, ToSchema)]
+pub struct TestWebhookRequest {
+    url: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TestWebhookResponse {
+    status: String,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    http_status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency_ms: Option<u64>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/test-webhook",
+    request_body = TestWebhookRequest,
+    responses((status = 200, body = TestWebhookResponse))
+)]
+pub async fn test_webhook(
+    State(state): State<AppState>,
+    Json(body): Json<TestWebhookRequest>,
+) -> impl IntoResponse {
+    if let Err(e) = webhooks::validate_url(&body.url) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(TestWebhookResponse {
+                status: "error".into(),
+                message: e.to_string(),
+                http_status: None,
+                latency_ms: None,
+            }),
+        );
+    }
+
+    let started = Instant::now();
+    let result = webhooks::deliver(
+        &body.url,
+        &WebhookPayload::sample(),
+        state.webhooks.private_address_guard.as_ref(),
+    )
+    .await
+    .map_err(super::sync::explain_connect_error);
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    match result {
+        Ok(status) => (
+            StatusCode::OK,
+            Json(TestWebhookResponse {
+                status: if status.is_success() {
+                    "success"
+                } else {
+                    "error"
+                }
+                .into(),
+                message: format!("Receiver responded with {}", status),
+                http_status: Some(status.as_u16()),
+                latency_ms: Some(latency_ms),
+            }),
+        ),
+        Err(e) => (
+            StatusCode::BAD_GATEWAY,
+            Json(TestWebhookResponse {
+                status: "error".into(),
+                message: format!("Webhook delivery failed: {}", e),
+                http_status: None,
+                latency_ms: Some(latency_ms),
+            }),
+        ),
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DbStatsResponse {
+    status: String,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<DbStats>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/db-stats",
+    responses((status = 200, body = DbStatsResponse))
+)]
+pub async fn db_stats(State(state): State<AppState>) -> impl IntoResponse {
+    match state.conn().and_then(|db| db::db_stats(&db)) {
+        Ok(stats) => (
+            StatusCode::OK,
+            Json(DbStatsResponse {
+                status: "success".into(),
+                message: format!("{} tables", stats.tables.len()),
+                stats: Some(stats),
+            }),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(DbStatsResponse {
+                status: "error".into(),
+                message: e.to_string(),
+                stats: None,
+            }),
+        ),
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct VacuumResponse {
+    status: String,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    before: Option<DbSize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    after: Option<DbSize>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/vacuum",
+    responses((status = 200, body = VacuumResponse))
+)]
+pub async fn vacuum(State(state): State<AppState>) -> impl IntoResponse {
+    match state.conn().and_then(|db| db::vacuum(&db)) {
+        Ok((before, after)) => {
+            let freed = (before.page_count - after.page_count) * after.page_size;
+            tracing::info!("VACUUM reclaimed {} bytes", freed.max(0));
+            (
+                StatusCode::OK,
+                Json(VacuumResponse {
+                    status: "success".into(),
+                    message: format!(
+                        "Vacuumed {} pages down to {}",
+                        before.page_count, after.page_count
+                    ),
+                    before: Some(before),
+                    after: Some(after),
+                }),
+            )
+        }
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(VacuumResponse {
+                status: "error".into(),
+                message: e.to_string(),
+                before: None,
+                after: None,
+            }),
+        ),
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ConfigResponse {
+    status: String,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    config: Option<EffectiveConfig>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/config",
+    responses((status = 200, body = ConfigResponse))
+)]
+pub async fn effective_config(State(state): State<AppState>) -> impl IntoResponse {
+    match &state.config {
+        Some(config) => (
+            StatusCode::OK,
+            Json(ConfigResponse {
+                status: "success".into(),
+                message: "Effective configuration (secrets redacted)".into(),
+                config: Some(config.as_ref().clone()),
+            }),
+        ),
+        None => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ConfigResponse {
+                status: "error".into(),
+                message: "Configuration not loaded".into(),
+                config: None,
+            }),
+        ),
+    }
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/admin/test-webhook", post(test_webhook))
+        .route("/admin/db-stats", get(db_stats))
+        .route("/admin/vacuum", post(vacuum))
+        .route("/admin/config", get(effective_config))
+}
@@ -1,16 +1,26 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+
 use axum::{
     Json, Router,
-    extract::{Path, State},
-    http::StatusCode,
+    body::Body,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
     response::IntoResponse,
     routing::{delete, get, post, put},
 };
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
 use utoipa::ToSchema;
 
 use super::AppState;
+use super::reverse_sync::{
+    self, ReverseSyncOptions, SuspiciousShrink, SyncProgress, UnparseableDate,
+};
 use crate::auto_sync::{self, AutoSyncKey};
 use crate::db;
+use crate::host_allowlist::HostNotAllowed;
+use crate::webhooks;
 
 #[derive(Serialize, ToSchema)]
 pub struct DestinationResponse {
@@ -33,6 +43,42 @@ pub struct ReverseSyncResult {
     skipped: usize,
     deleted: usize,
     total: usize,
+    conflicts: usize,
+    /// Feed events whose DTSTART/DTEND could not be parsed.
+    unparseable_dates: Vec<UnparseableDate>,
+    /// Only with `?details=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uploaded_uids: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skipped_uids: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deleted_uids: Option<Vec<String>>,
+}
+
+impl ReverseSyncResult {
+    fn error(message: String) -> Self {
+        Self {
+            status: "error".into(),
+            message,
+            uploaded: 0,
+            skipped: 0,
+            deleted: 0,
+            total: 0,
+            conflicts: 0,
+            unparseable_dates: vec![],
+            uploaded_uids: None,
+            skipped_uids: None,
+            deleted_uids: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SyncQuery {
+    #[serde(default)]
+    details: bool,
+    #[serde(default)]
+    force: bool,
 }
 
 pub fn routes() -> Router<AppState> {
@@ -42,13 +88,51 @@ pub fn routes() -> Router<AppState> {
         .route("/destinations/check-overlap", get(check_overlap))
         .route("/destinations/{id}", put(update_destination))
         .route("/destinations/{id}", delete(delete_destination))
+        .route("/destinations/{id}/duplicate", post(duplicate_destination))
         .route("/destinations/{id}/sync", post(sync_destination))
+        .route(
+            "/destinations/{id}/sync/stream",
+            post(sync_destination_stream),
+        )
+}
+
+/// Overlap rejections are conflicts; everything else is a validation error.
+fn save_error_status(e: &anyhow::Error) -> StatusCode {
+    if e.is::<db::DestinationOverlap>() {
+        StatusCode::CONFLICT
+    } else if e.is::<HostNotAllowed>() {
+        StatusCode::FORBIDDEN
+    } else {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// Checks the URLs a destination reads from and writes to against
+/// `DESTINATION_HOST_ALLOWLIST`; every feed listed in `ics_url` is checked.
+fn check_hosts(
+    state: &AppState,
+    ics_url: Option<&str>,
+    caldav_url: Option<&str>,
+    calendar_url_override: Option<&str>,
+) -> anyhow::Result<()> {
+    for url in ics_url
+        .map(reverse_sync::split_ics_urls)
+        .unwrap_or_default()
+    {
+        state.host_allowlist.check("ics_url", url)?;
+    }
+    if let Some(url) = caldav_url {
+        state.host_allowlist.check("caldav_url", url)?;
+    }
+    if let Some(url) = calendar_url_override.filter(|u| !u.trim().is_empty()) {
+        state.host_allowlist.check("calendar_url_override", url)?;
+    }
+    Ok(())
 }
 
 #[utoipa::path(get, path = "/api/destinations", responses((status = 200, body = DestinationListResponse)))]
 pub async fn list_destinations(State(state): State<AppState>) -> impl IntoResponse {
-    let db = state.db.lock().unwrap();
-    match db::list_destinations(&db) {
+    match state.conn().and_then(|db| db::list_destinations(&db)) {
         Ok(destinations) => (
             StatusCode::OK,
             Json(DestinationListResponse { destinations }),
@@ -66,21 +150,41 @@ pub async fn list_destinations(State(state): State<AppState>) -> impl IntoRespon
     }
 }
 
-#[utoipa::path(post, path = "/api/destinations", request_body = db::CreateDestination, responses((status = 201, body = DestinationResponse)))]
+#[utoipa::path(post, path = "/api/destinations", request_body = db::CreateDestination, responses((status = 201, body = DestinationResponse), (status = 403, body = DestinationResponse), (status = 409, body = DestinationResponse)))]
 pub async fn create_destination(
     State(state): State<AppState>,
     Json(body): Json<db::CreateDestination>,
 ) -> impl IntoResponse {
     let (id, dest) = {
-        let db = state.db.lock().unwrap();
-        match db::create_destination(&db, &body) {
+        let db = match state.conn() {
+            Ok(db) => db,
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(DestinationResponse {
+                        status: "error".into(),
+                        message: e.to_string(),
+                        destination: None,
+                    }),
+                )
+                    .into_response();
+            }
+        };
+        let created = check_hosts(
+            &state,
+            Some(&body.ics_url),
+            Some(&body.caldav_url),
+            body.calendar_url_override.as_deref(),
+        )
+        .and_then(|()| db::create_destination_with_policy(&db, &body, state.overlap_policy));
+        match created {
             Ok(id) => {
                 let dest = db::get_destination(&db, id).ok().flatten();
                 (id, dest)
             }
             Err(e) => {
                 return (
-                    StatusCode::BAD_REQUEST,
+                    save_error_status(&e),
                     Json(DestinationResponse {
                         status: "error".into(),
                         message: e.to_string(),
@@ -107,15 +211,109 @@ pub async fn create_destination(
         .into_response()
 }
 
-#[utoipa::path(put, path = "/api/destinations/{id}", request_body = db::UpdateDestination, responses((status = 200, body = DestinationResponse)))]
+#[utoipa::path(post, path = "/api/destinations/{id}/duplicate", request_body = db::DuplicateDestination, responses((status = 201, body = DestinationResponse), (status = 403, body = DestinationResponse), (status = 404, body = DestinationResponse), (status = 409, body = DestinationResponse)))]
+pub async fn duplicate_destination(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(body): Json<db::DuplicateDestination>,
+) -> impl IntoResponse {
+    let (new_id, dest) = {
+        let db = match state.conn() {
+            Ok(db) => db,
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(DestinationResponse {
+                        status: "error".into(),
+                        message: e.to_string(),
+                        destination: None,
+                    }),
+                )
+                    .into_response();
+            }
+        };
+        let duplicated = db::get_destination(&db, id).and_then(|existing| {
+            if let Some(d) = &existing {
+                check_hosts(
+                    &state,
+                    Some(&d.ics_url),
+                    Some(&d.caldav_url),
+                    d.calendar_url_override.as_deref(),
+                )?;
+            }
+            db::duplicate_destination_with_policy(&db, id, &body, state.overlap_policy)
+        });
+        match duplicated {
+            Ok(Some(new_id)) => (new_id, db::get_destination(&db, new_id).ok().flatten()),
+            Ok(None) => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    Json(DestinationResponse {
+                        status: "error".into(),
+                        message: "Destination not found".into(),
+                        destination: None,
+                    }),
+                )
+                    .into_response();
+            }
+            Err(e) => {
+                return (
+                    save_error_status(&e),
+                    Json(DestinationResponse {
+                        status: "error".into(),
+                        message: e.to_string(),
+                        destination: None,
+                    }),
+                )
+                    .into_response();
+            }
+        }
+    };
+
+    if let Some(ref d) = dest {
+        auto_sync::register_destination(&state.sync_tasks, &state, d);
+    }
+
+    (
+        StatusCode::CREATED,
+        Json(DestinationResponse {
+            status: "success".into(),
+            message: format!("Destination {} duplicated as id {}", id, new_id),
+            destination: dest,
+        }),
+    )
+        .into_response()
+}
+
+#[utoipa::path(put, path = "/api/destinations/{id}", request_body = db::UpdateDestination, responses((status = 200, body = DestinationResponse), (status = 403, body = DestinationResponse), (status = 409, body = DestinationResponse)))]
 pub async fn update_destination(
     State(state): State<AppState>,
     Path(id): Path<i64>,
     Json(body): Json<db::UpdateDestination>,
 ) -> impl IntoResponse {
     let dest = {
-        let db = state.db.lock().unwrap();
-        match db::update_destination(&db, id, &body) {
+        let db = match state.conn() {
+            Ok(db) => db,
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(DestinationResponse {
+                        status: "error".into(),
+                        message: e.to_string(),
+                        destination: None,
+                    }),
+                )
+                    .into_response();
+            }
+        };
+        let updated = check_hosts(
+            &state,
+            body.ics_url.as_deref(),
+            body.caldav_url.as_deref(),
+            body.calendar_url_override.as_deref(),
+        )
+        .and_then(|()| db::update_destination_with_policy(&db, id, &body, state.overlap_policy));
+        match updated {
             Ok(true) => db::get_destination(&db, id).ok().flatten(),
             Ok(false) => {
                 return (
@@ -130,7 +328,7 @@ pub async fn update_destination(
             }
             Err(e) => {
                 return (
-                    StatusCode::BAD_REQUEST,
+                    save_error_status(&e),
                     Json(DestinationResponse {
                         status: "error".into(),
                         message: e.to_string(),
@@ -162,10 +360,7 @@ pub async fn delete_destination(
     State(state): State<AppState>,
     Path(id): Path<i64>,
 ) -> impl IntoResponse {
-    let result = {
-        let db = state.db.lock().unwrap();
-        db::delete_destination(&db, id)
-    };
+    let result = state.conn().and_then(|db| db::delete_destination(&db, id));
 
     match result {
         Ok(true) => {
@@ -201,71 +396,162 @@ pub async fn delete_destination(
     }
 }
 
-#[utoipa::path(post, path = "/api/destinations/{id}/sync", responses((status = 200, body = ReverseSyncResult)))]
+#[utoipa::path(
+    post,
+    path = "/api/destinations/{id}/sync",
+    params(
+        ("id" = i64, Path, description = "Destination ID"),
+        ("details" = Option<bool>, Query, description = "Also list the UIDs uploaded, skipped and deleted"),
+        ("force" = Option<bool>, Query, description = "Sync even if the feed shrank more than `max_shrink_percent`"),
+    ),
+    responses(
+        (status = 200, body = ReverseSyncResult),
+        (status = 409, description = "The feed shrank suspiciously; nothing was changed", body = ReverseSyncResult),
+    )
+)]
 pub async fn sync_destination(
     State(state): State<AppState>,
     Path(id): Path<i64>,
+    Query(q): Query<SyncQuery>,
 ) -> impl IntoResponse {
-    let (ics_url, caldav_url, calendar_name, username, password, sync_all, keep_local) = {
-        let db = state.db.lock().unwrap();
-        match db::get_destination(&db, id) {
-            Ok(Some(d)) => (
-                d.ics_url,
-                d.caldav_url,
-                d.calendar_name,
-                d.username,
-                d.password,
-                d.sync_all,
-                d.keep_local,
-            ),
-            Ok(None) => {
-                return (
-                    StatusCode::NOT_FOUND,
-                    Json(ReverseSyncResult {
-                        status: "error".into(),
-                        message: "Destination not found".into(),
-                        uploaded: 0,
-                        skipped: 0,
-                        deleted: 0,
-                        total: 0,
-                    }),
-                )
-                    .into_response();
-            }
-            Err(e) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ReverseSyncResult {
-                        status: "error".into(),
-                        message: e.to_string(),
-                        uploaded: 0,
-                        skipped: 0,
-                        deleted: 0,
-                        total: 0,
-                    }),
-                )
-                    .into_response();
-            }
+    let (d, known_hrefs) = match load_for_sync(&state, id) {
+        Ok(loaded) => loaded,
+        Err((status, message)) => {
+            return (status, Json(ReverseSyncResult::error(message))).into_response();
+        }
+    };
+    let (status, result) = run_destination_sync(&state, d, known_hrefs, &q, None).await;
+    (status, Json(result)).into_response()
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/destinations/{id}/sync/stream",
+    params(
+        ("id" = i64, Path, description = "Destination ID"),
+        ("details" = Option<bool>, Query, description = "Also list the UIDs uploaded, skipped and deleted"),
+        ("force" = Option<bool>, Query, description = "Sync even if the feed shrank more than `max_shrink_percent`"),
+    ),
+    responses(
+        (status = 200, description = "Newline-delimited JSON: a `SyncProgress` line after each feed event, then the `ReverseSyncResult`", content_type = "application/x-ndjson"),
+        (status = 404, body = ReverseSyncResult),
+    )
+)]
+pub async fn sync_destination_stream(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(q): Query<SyncQuery>,
+) -> impl IntoResponse {
+    let (d, known_hrefs) = match load_for_sync(&state, id) {
+        Ok(loaded) => loaded,
+        Err((status, message)) => {
+            return (status, Json(ReverseSyncResult::error(message))).into_response();
+        }
+    };
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    // The sync runs to completion even if the client goes away.
+    let sync = tokio::spawn(async move {
+        run_destination_sync(&state, d, known_hrefs, &q, Some(tx))
+            .await
+            .1
+    });
+    let stream = async_stream::stream! {
+        while let Some(progress) = rx.recv().await {
+            yield Ok::<_, Infallible>(ndjson_line(&progress));
         }
+        let result = sync
+            .await
+            .unwrap_or_else(|e| ReverseSyncResult::error(format!("Sync task failed: {}", e)));
+        yield Ok(ndjson_line(&result));
     };
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(stream),
+    )
+        .into_response()
+}
+
+fn ndjson_line<T: Serialize>(value: &T) -> String {
+    let mut line = serde_json::to_string(value).unwrap_or_default();
+    line.push('\n');
+    line
+}
+
+/// Loads a destination and, with `uuid_hrefs`, its stored object names.
+fn load_for_sync(
+    state: &AppState,
+    id: i64,
+) -> Result<(db::Destination, HashMap<String, String>), (StatusCode, String)> {
+    let loaded = state.conn().and_then(|db| {
+        let Some(d) = db::get_destination(&db, id)? else {
+            return Ok(None);
+        };
+        let known_hrefs = if d.uuid_hrefs {
+            db::get_destination_hrefs(&db, id)?
+        } else {
+            Default::default()
+        };
+        Ok(Some((d, known_hrefs)))
+    });
+    match loaded {
+        Ok(Some(loaded)) => Ok(loaded),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "Destination not found".into())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
 
+/// Runs a manual sync and records its outcome, as both sync endpoints do.
+async fn run_destination_sync(
+    state: &AppState,
+    d: db::Destination,
+    known_hrefs: HashMap<String, String>,
+    q: &SyncQuery,
+    progress: Option<mpsc::UnboundedSender<SyncProgress>>,
+) -> (StatusCode, ReverseSyncResult) {
+    let id = d.id;
+    let _guard = state.active_syncs.start();
+    let _host = state.host_locks.acquire(&d.caldav_url).await;
     match crate::api::reverse_sync::run_reverse_sync(
-        &ics_url,
-        &caldav_url,
-        &calendar_name,
-        &username,
-        &password,
-        sync_all,
-        keep_local,
+        &d.ics_url,
+        &d.caldav_url,
+        &d.calendar_name,
+        &d.username,
+        &d.password,
+        d.sync_all,
+        d.keep_local,
+        &ReverseSyncOptions {
+            collect_uids: q.details,
+            known_hrefs,
+            progress,
+            max_shrink_percent: if q.force {
+                0
+            } else {
+                d.max_shrink_percent as u32
+            },
+            ..ReverseSyncOptions::from(&d).with_http(&state.http)
+        },
     )
     .await
     {
-        Ok(stats) => {
-            let db = state.db.lock().unwrap();
-            let _ = db::update_destination_sync_status(&db, id, "ok", None);
+        Ok(mut stats) => {
+            if let Ok(db) = state.conn() {
+                let _ = db::update_destination_sync_status(&db, id, "ok", None);
+                // An empty feed is skipped without syncing, so it is no baseline.
+                if stats.total > 0 {
+                    let _ = db::update_destination_total_events(&db, id, stats.total);
+                }
+                if let Some(hrefs) = &stats.hrefs {
+                    let _ = db::save_destination_hrefs(&db, id, hrefs);
+                }
+            }
+            webhooks::notify_destination(&state.webhooks, &state.events, &d, Ok(&stats));
+            let (uploaded_uids, skipped_uids, deleted_uids) = match stats.uids.take() {
+                Some(uids) => (Some(uids.uploaded), Some(uids.skipped), Some(uids.deleted)),
+                None => (None, None, None),
+            };
             (
                 StatusCode::OK,
-                Json(ReverseSyncResult {
+                ReverseSyncResult {
                     status: "success".into(),
                     message: format!(
                         "Uploaded {} of {} events ({} unchanged); deleted {} orphans",
@@ -275,26 +561,26 @@ pub async fn sync_destination(
                     skipped: stats.skipped,
                     deleted: stats.deleted,
                     total: stats.total,
-                }),
+                    conflicts: stats.conflicts,
+                    unparseable_dates: stats.unparseable_dates,
+                    uploaded_uids,
+                    skipped_uids,
+                    deleted_uids,
+                },
             )
-                .into_response()
         }
         Err(e) => {
             tracing::error!("Reverse sync error for destination {}: {}", id, e);
-            let db = state.db.lock().unwrap();
-            let _ = db::update_destination_sync_status(&db, id, "error", Some(&e.to_string()));
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ReverseSyncResult {
-                    status: "error".into(),
-                    message: e.to_string(),
-                    uploaded: 0,
-                    skipped: 0,
-                    deleted: 0,
-                    total: 0,
-                }),
-            )
-                .into_response()
+            if let Ok(db) = state.conn() {
+                let _ = db::update_destination_sync_status(&db, id, "error", Some(&e.to_string()));
+            }
+            webhooks::notify_destination(&state.webhooks, &state.events, &d, Err(&e.to_string()));
+            let status = if e.is::<SuspiciousShrink>() {
+                StatusCode::CONFLICT
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            (status, ReverseSyncResult::error(e.to_string()))
         }
     }
 }
@@ -334,8 +620,9 @@ pub async fn check_overlap(
     State(state): State<AppState>,
     axum::extract::Query(q): axum::extract::Query<OverlapQuery>,
 ) -> impl IntoResponse {
-    let db = state.db.lock().unwrap();
-    match db::find_overlapping_destinations(&db, &q.caldav_url, &q.calendar_name, q.exclude_id) {
+    match state.conn().and_then(|db| {
+        db::find_overlapping_destinations(&db, &q.caldav_url, &q.calendar_name, q.exclude_id)
+    }) {
         Ok(dests) => (
             StatusCode::OK,
             Json(OverlapResponse {
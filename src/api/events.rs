@@ -0,0 +1,52 @@
+use std::convert::Infallible;
+
+use axum::{
+    Router,
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+    routing::get,
+};
+use futures_util::Stream;
+use tokio::sync::broadcast::error::RecvError;
+
+use super::AppState;
+
+#[utoipa::path(
+    get,
+    path = "/api/events",
+    responses((status = 200, description = "Server-sent stream of sync results", content_type = "text/event-stream"))
+)]
+pub async fn stream_events(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let mut rx = state.events.subscribe();
+    let shutdown = state.shutdown.clone();
+
+    let stream = async_stream::stream! {
+        loop {
+            let received = tokio::select! {
+                r = rx.recv() => r,
+                _ = shutdown.cancelled() => break,
+            };
+            match received {
+                Ok(payload) => match Event::default().event("sync").json_data(&payload) {
+                    Ok(event) => yield Ok(event),
+                    Err(e) => tracing::warn!("Failed to encode sync event: {}", e),
+                },
+                // A slow client skipped ahead; tell it instead of dropping the stream.
+                Err(RecvError::Lagged(n)) => {
+                    yield Ok(Event::default()
+                        .event("lagged")
+                        .data(format!("missed {} events", n)));
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    };
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/events", get(stream_events))
+}
@@ -0,0 +1,167 @@
+use axum::{
+    Json, Router,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::AppState;
+use crate::cron::CronSchedule;
+use crate::db;
+
+/// Slack past the expected sync time before an entity counts as overdue,
+/// covering the run itself and scheduler jitter.
+const OVERDUE_GRACE_SECS: i64 = 60;
+
+#[derive(Debug, Clone, Copy, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EntityKind {
+    Source,
+    Destination,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct EntityFreshness {
+    kind: EntityKind,
+    id: i64,
+    name: String,
+    /// Last successful sync; failed runs do not count as fresh.
+    last_synced: Option<String>,
+    last_sync_status: Option<String>,
+    /// `None` before the first sync.
+    seconds_since_sync: Option<i64>,
+    /// Auto-sync should have run by now but has not. Always false for
+    /// entities that do not auto-sync or are disabled.
+    overdue: bool,
+}
+
+/// Sync timestamps are SQLite `datetime('now')` values in UTC.
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .map(|dt| dt.and_utc())
+        .ok()
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(value)
+                .ok()
+                .map(|dt| dt.with_timezone(&Utc))
+        })
+}
+
+/// When the next auto-sync after `since` was due: a cron expression takes
+/// precedence over the interval, as in `auto_sync`. `None` when the entity
+/// does not auto-sync.
+fn next_due(since: DateTime<Utc>, interval_secs: i64, cron: Option<&str>) -> Option<DateTime<Utc>> {
+    match cron.map(str::trim).filter(|c| !c.is_empty()) {
+        Some(expr) => {
+            let since = Local.from_utc_datetime(&since.naive_utc());
+            let next = CronSchedule::parse(expr).ok()?.next_after(&since)?;
+            Some(next.with_timezone(&Utc))
+        }
+        None => (interval_secs > 0).then(|| since + chrono::Duration::seconds(interval_secs)),
+    }
+}
+
+struct Schedule<'a> {
+    last_synced: Option<&'a str>,
+    created_at: &'a str,
+    interval_secs: i64,
+    cron: Option<&'a str>,
+    enabled: bool,
+}
+
+impl Schedule<'_> {
+    /// (seconds since the last sync, overdue). An entity that never synced
+    /// is measured from its creation.
+    fn freshness(&self, now: DateTime<Utc>) -> (Option<i64>, bool) {
+        let last = self.last_synced.and_then(parse_timestamp);
+        let since = last.map(|t| (now - t).num_seconds().max(0));
+        let overdue = self.enabled
+            && last
+                .or_else(|| parse_timestamp(self.created_at))
+                .and_then(|t| next_due(t, self.interval_secs, self.cron))
+                .is_some_and(|due| now > due + chrono::Duration::seconds(OVERDUE_GRACE_SECS));
+        (since, overdue)
+    }
+}
+
+fn entity(
+    kind: EntityKind,
+    id: i64,
+    name: String,
+    last_sync_status: Option<String>,
+    schedule: &Schedule,
+    now: DateTime<Utc>,
+) -> EntityFreshness {
+    let (seconds_since_sync, overdue) = schedule.freshness(now);
+    EntityFreshness {
+        kind,
+        id,
+        name,
+        last_synced: schedule.last_synced.map(str::to_string),
+        last_sync_status,
+        seconds_since_sync,
+        overdue,
+    }
+}
+
+/// Freshness of every source and destination in one call, for monitors.
+#[utoipa::path(
+    get,
+    path = "/api/sync/freshness",
+    responses((status = 200, body = Vec<EntityFreshness>))
+)]
+pub async fn sync_freshness(State(state): State<AppState>) -> Response {
+    let listed = state
+        .conn()
+        .and_then(|db| Ok((db::list_sources(&db)?, db::list_destinations(&db)?)));
+    let (sources, destinations) = match listed {
+        Ok(listed) => listed,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    let now = Utc::now();
+    let mut entities = Vec::with_capacity(sources.len() + destinations.len());
+    for s in sources {
+        let schedule = Schedule {
+            last_synced: s.last_synced.as_deref(),
+            created_at: &s.created_at,
+            interval_secs: s.sync_interval_secs,
+            cron: s.cron_schedule.as_deref(),
+            enabled: s.enabled,
+        };
+        entities.push(entity(
+            EntityKind::Source,
+            s.id,
+            s.name,
+            s.last_sync_status,
+            &schedule,
+            now,
+        ));
+    }
+    for d in destinations {
+        let schedule = Schedule {
+            last_synced: d.last_success.as_deref(),
+            created_at: &d.created_at,
+            interval_secs: d.sync_interval_secs,
+            cron: d.cron_schedule.as_deref(),
+            enabled: true,
+        };
+        entities.push(entity(
+            EntityKind::Destination,
+            d.id,
+            d.name,
+            d.last_sync_status,
+            &schedule,
+            now,
+        ));
+    }
+    (StatusCode::OK, Json(entities)).into_response()
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/sync/freshness", get(sync_freshness))
+}
@@ -1,3 +1,5 @@
+use std::sync::atomic::Ordering;
+
 use crate::api::AppState;
 use axum::{Json, Router, extract::State, http::StatusCode, response::IntoResponse, routing::get};
 use serde::Serialize;
@@ -28,12 +30,9 @@ pub async fn health() -> impl IntoResponse {
 
 #[utoipa::path(get, path = "/api/health/detailed", responses((status = 200, body = DetailedHealthResponse)))]
 pub async fn health_detailed(State(state): State<AppState>) -> impl IntoResponse {
-    let (source_count, db_ok) = {
-        let db = state.db.lock().unwrap();
-        match crate::db::list_sources(&db) {
-            Ok(sources) => (sources.len(), true),
-            Err(_) => (0, false),
-        }
+    let (source_count, db_ok) = match state.conn().and_then(|db| crate::db::list_sources(&db)) {
+        Ok(sources) => (sources.len(), true),
+        Err(_) => (0, false),
     };
     let uptime = state.start_time.elapsed().as_secs();
     (
@@ -47,8 +46,53 @@ pub async fn health_detailed(State(state): State<AppState>) -> impl IntoResponse
     )
 }
 
+#[derive(Serialize, ToSchema)]
+pub struct ReadinessResponse {
+    pub status: String,
+    pub db_ok: bool,
+    pub scheduler_ready: bool,
+}
+
+/// Liveness: the process is up and serving requests.
+#[utoipa::path(get, path = "/api/health/live", responses((status = 200, body = HealthResponse)))]
+pub async fn health_live() -> impl IntoResponse {
+    health().await
+}
+
+/// Readiness: the database answers and auto-sync has been scheduled.
+#[utoipa::path(
+    get,
+    path = "/api/health/ready",
+    responses(
+        (status = 200, body = ReadinessResponse),
+        (status = 503, body = ReadinessResponse)
+    )
+)]
+pub async fn health_ready(State(state): State<AppState>) -> impl IntoResponse {
+    let db_ok = state
+        .conn()
+        .and_then(|db| crate::db::list_sources(&db))
+        .is_ok();
+    let scheduler_ready = state.scheduler_ready.load(Ordering::Acquire);
+    let ready = db_ok && scheduler_ready;
+    (
+        if ready {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        },
+        Json(ReadinessResponse {
+            status: if ready { "ok" } else { "unavailable" }.into(),
+            db_ok,
+            scheduler_ready,
+        }),
+    )
+}
+
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/health", get(health))
         .route("/health/detailed", get(health_detailed))
+        .route("/health/live", get(health_live))
+        .route("/health/ready", get(health_ready))
 }
@@ -0,0 +1,187 @@
+use std::borrow::Cow;
+
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::AppState;
+use crate::db;
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    since: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct HistoryResponse {
+    status: String,
+    message: String,
+    total: i64,
+    entries: Vec<db::SyncHistoryEntry>,
+}
+
+fn error_response(status: StatusCode, message: String) -> Response {
+    (
+        status,
+        Json(HistoryResponse {
+            status: "error".into(),
+            message,
+            total: 0,
+            entries: vec![],
+        }),
+    )
+        .into_response()
+}
+
+/// Normalizes `since` to the `YYYY-MM-DD HH:MM:SS` UTC form history rows use.
+/// Accepts a bare date, that same datetime form, or RFC 3339.
+fn parse_since(since: &str) -> Option<String> {
+    let since = since.trim();
+    if let Ok(d) = chrono::NaiveDate::parse_from_str(since, "%Y-%m-%d") {
+        return Some(format!("{} 00:00:00", d));
+    }
+    if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(since, "%Y-%m-%d %H:%M:%S") {
+        return Some(dt.format("%Y-%m-%d %H:%M:%S").to_string());
+    }
+    chrono::DateTime::parse_from_rfc3339(since)
+        .ok()
+        .map(|dt| dt.naive_utc().format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// Loads one page of history, returning the status and message to report on failure.
+fn load_history(
+    state: &AppState,
+    id: i64,
+    q: &HistoryQuery,
+    limit: i64,
+) -> Result<(i64, Vec<db::SyncHistoryEntry>), (StatusCode, String)> {
+    let since = match q.since.as_deref() {
+        Some(raw) => match parse_since(raw) {
+            Some(s) => Some(s),
+            None => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid since timestamp: {}", raw),
+                ));
+            }
+        },
+        None => None,
+    };
+    let offset = q.offset.unwrap_or(0).max(0);
+
+    let result = state.conn().and_then(|db| {
+        if db::get_source(&db, id)?.is_none() {
+            return Ok(None);
+        }
+        let total = db::count_sync_history(&db, id, since.as_deref())?;
+        let entries = db::list_sync_history(&db, id, since.as_deref(), limit, offset)?;
+        Ok(Some((total, entries)))
+    });
+    match result {
+        Ok(Some(page)) => Ok(page),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "Source not found".into())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sources/{id}/history",
+    params(
+        ("id" = i64, Path, description = "Source ID"),
+        ("since" = Option<String>, Query, description = "Only runs started at or after this date/time (UTC)"),
+        ("limit" = Option<i64>, Query, description = "Page size (default 50, max 500)"),
+        ("offset" = Option<i64>, Query, description = "Number of entries to skip"),
+    ),
+    responses((status = 200, body = HistoryResponse))
+)]
+pub async fn source_history(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(q): Query<HistoryQuery>,
+) -> Response {
+    let limit = q.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    match load_history(&state, id, &q, limit) {
+        Ok((total, entries)) => (
+            StatusCode::OK,
+            Json(HistoryResponse {
+                status: "success".into(),
+                message: format!("{} of {} entries", entries.len(), total),
+                total,
+                entries,
+            }),
+        )
+            .into_response(),
+        Err((status, message)) => error_response(status, message),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sources/{id}/history.csv",
+    params(
+        ("id" = i64, Path, description = "Source ID"),
+        ("since" = Option<String>, Query, description = "Only runs started at or after this date/time (UTC)"),
+    ),
+    responses((status = 200, content_type = "text/csv", body = String))
+)]
+pub async fn source_history_csv(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(q): Query<HistoryQuery>,
+) -> Response {
+    let entries = match load_history(&state, id, &q, -1) {
+        Ok((_, entries)) => entries,
+        Err((status, message)) => return error_response(status, message),
+    };
+
+    let mut csv = String::from("started_at,finished_at,status,events,error\r\n");
+    for e in &entries {
+        csv.push_str(&format!(
+            "{},{},{},{},{}\r\n",
+            csv_field(&e.started_at),
+            csv_field(&e.finished_at),
+            csv_field(&e.status),
+            e.events,
+            csv_field(e.error.as_deref().unwrap_or(""))
+        ));
+    }
+
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"source-{}-history.csv\"", id),
+            ),
+        ],
+        csv,
+    )
+        .into_response()
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/sources/{id}/history", get(source_history))
+        .route("/sources/{id}/history.csv", get(source_history_csv))
+}
@@ -0,0 +1,211 @@
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use reqwest::Url;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::AppState;
+use super::reverse_sync;
+use crate::db::{self, Source, SourcePath};
+
+/// A destination that consumes one of the source's own feeds.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SuspectedLoop {
+    destination_id: i64,
+    destination_name: String,
+    /// The destination's `ics_url`, pointing at this source's feed.
+    feed_url: String,
+    /// Calendar collection the destination writes to.
+    calendar_url: String,
+    /// The destination writes into a calendar this source reads from.
+    closes_loop: bool,
+    /// Each hop, starting and (when `closes_loop`) ending at the source.
+    chain: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct LoopCheckResponse {
+    status: String,
+    message: String,
+    loops: Vec<SuspectedLoop>,
+}
+
+fn error_response(status: StatusCode, message: String) -> Response {
+    (
+        status,
+        Json(LoopCheckResponse {
+            status: "error".into(),
+            message,
+            loops: vec![],
+        }),
+    )
+        .into_response()
+}
+
+/// URL paths this server serves the source's feed at.
+fn feed_paths(source: &Source, extra: &[SourcePath]) -> Vec<String> {
+    let mut paths = vec![
+        format!("/ics/{}", source.ics_path),
+        format!("/ics/by-id/{}", source.id),
+    ];
+    if source.public_ics {
+        paths.push(format!("/ics/public/by-id/{}", source.id));
+    }
+    if let Some(public) = source.public_ics_path.as_deref().filter(|p| !p.is_empty())
+        && source.public_ics
+    {
+        paths.push(format!("/ics/public/{}", public));
+    }
+    for sp in extra {
+        paths.push(format!("/ics/{}", sp.path));
+        if sp.is_public {
+            paths.push(format!("/ics/public/{}", sp.path));
+        }
+    }
+    paths
+}
+
+/// Hosts that reach this server: the request's own `Host`, the configured
+/// bind addresses and loopback names.
+fn local_hosts(state: &AppState, headers: &HeaderMap) -> Vec<String> {
+    let mut hosts: Vec<String> = ["localhost", "127.0.0.1", "[::1]", "0.0.0.0"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    let request_host = headers
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| Url::parse(&format!("http://{}", h)).ok());
+    if let Some(host) = request_host.as_ref().and_then(|u| u.host_str()) {
+        hosts.push(host.to_lowercase());
+    }
+    if let Some(config) = &state.config {
+        hosts.push(config.server_host.to_lowercase());
+        if let Some(addr) = &config.public_addr
+            && let Ok(url) = Url::parse(&format!("http://{}", addr))
+            && let Some(host) = url.host_str()
+        {
+            hosts.push(host.to_lowercase());
+        }
+    }
+    hosts
+}
+
+/// `calendar` lies under `source_url` (the source lists it) or is the
+/// collection the source points at directly.
+fn source_reads(source_url: &str, calendar: &str) -> bool {
+    let (Ok(source), Ok(calendar)) = (Url::parse(source_url), Url::parse(calendar)) else {
+        return false;
+    };
+    if source.host_str() != calendar.host_str()
+        || source.port_or_known_default() != calendar.port_or_known_default()
+    {
+        return false;
+    }
+    let source_path = source.path().trim_end_matches('/');
+    let calendar_path = calendar.path().trim_end_matches('/');
+    calendar_path.starts_with(source_path) || source_path.starts_with(calendar_path)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sources/{id}/loop-check",
+    params(("id" = i64, Path, description = "Source ID")),
+    responses((status = 200, body = LoopCheckResponse))
+)]
+pub async fn loop_check(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    headers: HeaderMap,
+) -> Response {
+    let db = match state.conn() {
+        Ok(db) => db,
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    let source = match db::get_source(&db, id) {
+        Ok(Some(source)) => source,
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "Source not found".into()),
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    let (extra, destinations) = match (db::list_source_paths(&db, id), db::list_destinations(&db)) {
+        (Ok(extra), Ok(destinations)) => (extra, destinations),
+        (Err(e), _) | (_, Err(e)) => {
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+        }
+    };
+
+    let paths = feed_paths(&source, &extra);
+    let hosts = local_hosts(&state, &headers);
+    let origin = format!("source {} ({})", source.id, source.name);
+    let mut loops = Vec::new();
+    for dest in destinations {
+        for url in reverse_sync::split_ics_urls(&dest.ics_url) {
+            let Ok(feed) = Url::parse(url) else {
+                continue;
+            };
+            let is_local = feed
+                .host_str()
+                .is_some_and(|h| hosts.contains(&h.to_lowercase()));
+            if !is_local || !paths.iter().any(|p| p == feed.path()) {
+                continue;
+            }
+            let calendar_url = reverse_sync::destination_calendar_base(
+                dest.calendar_url_override.as_deref(),
+                &dest.caldav_url,
+                &dest.calendar_name,
+            );
+            let closes_loop = source_reads(&source.caldav_url, &calendar_url);
+            let mut chain = vec![
+                origin.clone(),
+                feed.path().to_string(),
+                format!("destination {} ({})", dest.id, dest.name),
+                calendar_url.clone(),
+            ];
+            if closes_loop {
+                chain.push(origin.clone());
+            }
+            loops.push(SuspectedLoop {
+                destination_id: dest.id,
+                destination_name: dest.name.clone(),
+                feed_url: url.to_string(),
+                calendar_url,
+                closes_loop,
+                chain,
+            });
+        }
+    }
+
+    let closed = loops.iter().filter(|l| l.closes_loop).count();
+    let message = match (loops.len(), closed) {
+        (0, _) => "No destinations consume this source's feed".to_string(),
+        (n, 0) => format!(
+            "{} destination(s) consume this feed but none write back to its calendars",
+            n
+        ),
+        (n, c) => format!(
+            "Possible sync loop: {} of {} destination(s) consuming this feed write back to calendars it reads",
+            c, n
+        ),
+    };
+    if closed > 0 {
+        tracing::warn!("Source {}: {}", id, message);
+    }
+    (
+        StatusCode::OK,
+        Json(LoopCheckResponse {
+            status: "success".into(),
+            message,
+            loops,
+        }),
+    )
+        .into_response()
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/sources/{id}/loop-check", get(loop_check))
+}
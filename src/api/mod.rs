@@ -1,28 +1,124 @@
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+
 use axum::Router;
-use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
 
-use crate::auto_sync::AutoSyncRegistry;
+use crate::auto_sync::{self, ActiveSyncs, AutoSyncRegistry, HostLocks};
+use crate::config::{EffectiveConfig, LineEnding};
+use crate::db::{DEFAULT_BATCH_SIZE, DbConn, DbPool, OverlapPolicy};
+use crate::events::SyncEvents;
+use crate::host_allowlist::HostAllowlist;
+use crate::http::HttpClients;
+use crate::webhooks::WebhookConfig;
 
+pub mod admin;
 pub mod destinations;
+pub mod events;
+pub mod freshness;
 pub mod health;
+pub mod history;
+pub mod loop_check;
 pub mod openapi;
 pub mod reverse_sync;
+pub mod source_events;
 pub mod source_paths;
 pub mod sources;
 pub mod sync;
+pub mod validate;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub db: Arc<Mutex<rusqlite::Connection>>,
+    pub db: DbPool,
     pub start_time: std::time::Instant,
     pub sync_tasks: AutoSyncRegistry,
+    pub active_syncs: ActiveSyncs,
+    pub shutdown: CancellationToken,
+    pub webhooks: WebhookConfig,
+    pub host_locks: HostLocks,
+    pub events: SyncEvents,
+    pub http: HttpClients,
+    pub overlap_policy: OverlapPolicy,
+    /// Set once `auto_sync::register_all` has scheduled every saved source and destination.
+    pub scheduler_ready: Arc<AtomicBool>,
+    /// Upper bound on `POST /api/sources/{id}/sync`; auto-sync is not affected.
+    pub manual_sync_timeout: std::time::Duration,
+    /// Serve every public source merged at `/ics/public/all`.
+    pub public_all_feed: bool,
+    /// A served feed whose source missed this many sync intervals is marked
+    /// stale; 0 disables the check.
+    pub stale_feed_factor: u32,
+    /// Answer 503 for a stale feed instead of serving it with `X-Stale`.
+    pub stale_feed_unavailable: bool,
+    /// Rows written per transaction when saving a sync's calendar objects.
+    pub db_batch_size: usize,
+    /// Line ending feeds are served with.
+    pub ics_line_ending: LineEnding,
+    /// Key for signed public feed links; without it they cannot be minted
+    /// and sources requiring them are never served publicly.
+    pub public_url_secret: Option<String>,
+    /// Disable a source's auto-sync after this many scheduled syncs fail in
+    /// a row; 0 never does.
+    pub auto_disable_after_failures: u32,
+    /// Methods forwarded to the frontend proxy; `None` forwards all of them
+    /// and anything else is answered with 405.
+    pub proxy_allowed_methods: Option<Vec<axum::http::Method>>,
+    /// Gzip feeds written by syncs; rows already stored stay readable either way.
+    pub compress_ics_storage: bool,
+    /// Hosts sources and destinations may be saved with; checked on create
+    /// and update, so rows saved before it was set keep syncing.
+    pub host_allowlist: HostAllowlist,
+    /// Resolved settings for `GET /api/admin/config`; `None` outside the server binary.
+    pub config: Option<Arc<EffectiveConfig>>,
+}
+
+impl AppState {
+    pub fn new(db: DbPool) -> Self {
+        Self {
+            db,
+            start_time: std::time::Instant::now(),
+            sync_tasks: auto_sync::new_registry(),
+            active_syncs: ActiveSyncs::default(),
+            shutdown: CancellationToken::new(),
+            webhooks: WebhookConfig::default(),
+            host_locks: HostLocks::default(),
+            events: SyncEvents::default(),
+            http: HttpClients::default(),
+            overlap_policy: OverlapPolicy::default(),
+            scheduler_ready: Arc::default(),
+            manual_sync_timeout: std::time::Duration::from_secs(120),
+            public_all_feed: false,
+            stale_feed_factor: 3,
+            stale_feed_unavailable: false,
+            db_batch_size: DEFAULT_BATCH_SIZE,
+            ics_line_ending: LineEnding::default(),
+            public_url_secret: None,
+            auto_disable_after_failures: 0,
+            proxy_allowed_methods: None,
+            compress_ics_storage: false,
+            host_allowlist: HostAllowlist::default(),
+            config: None,
+        }
+    }
+
+    /// Checks out a pooled DB connection.
+    pub fn conn(&self) -> anyhow::Result<DbConn> {
+        Ok(self.db.get()?)
+    }
 }
 
 pub fn routes() -> Router<AppState> {
     Router::new()
         .merge(sources::routes())
+        .merge(history::routes())
         .merge(source_paths::routes())
+        .merge(source_events::routes())
+        .merge(loop_check::routes())
         .merge(destinations::routes())
+        .merge(validate::routes())
+        .merge(admin::routes())
+        .merge(events::routes())
         .merge(health::routes())
+        .merge(freshness::routes())
         .merge(openapi::routes())
 }
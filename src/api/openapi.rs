@@ -1,15 +1,45 @@
 use crate::api::AppState;
+use crate::api::admin::{
+    ConfigResponse, DbStatsResponse, TestWebhookRequest, TestWebhookResponse, VacuumResponse,
+};
 use crate::api::destinations::{
     DestinationListResponse, DestinationResponse, OverlapEntry, OverlapResponse, ReverseSyncResult,
 };
-use crate::api::health::{DetailedHealthResponse, HealthResponse};
-use crate::api::source_paths::{SourcePathListResponse, SourcePathResponse};
-use crate::api::sources::{SourceListResponse, SourceResponse, SyncResult};
+use crate::api::freshness::{EntityFreshness, EntityKind};
+use crate::api::health::{DetailedHealthResponse, HealthResponse, ReadinessResponse};
+use crate::api::history::HistoryResponse;
+use crate::api::loop_check::{LoopCheckResponse, SuspectedLoop};
+use crate::api::reverse_sync::{SyncProgress, UnparseableDate};
+use crate::api::source_events::{
+    EventPreview, SourceEventsResponse, SourceTimezone, SourceTimezonesResponse,
+};
+use crate::api::source_paths::{
+    SourcePathBatchResponse, SourcePathListResponse, SourcePathResponse, SourceUrl, SourceUrlKind,
+    SourceUrlListResponse,
+};
+use crate::api::sources::{
+    CalendarListResponse, PreviewSource, SetSyncStatus, SignUrlRequest, SignedUrlResponse,
+    SourceListResponse, SourceResponse, SyncResult,
+};
+use crate::api::sync::{CalendarInfo, SyncTimings};
+use crate::api::validate::{CronValidationRequest, CronValidationResponse, IcsValidationResponse};
+use crate::config::{EffectiveConfig, LineEnding, RetrySettings};
 use crate::db::{
-    CreateDestination, CreateSource, CreateSourcePath, Destination, Source, SourcePath,
-    UpdateDestination, UpdateSource, UpdateSourcePath,
+    CreateDestination, CreateSource, CreateSourcePath, CreateStaticSource,
+    CreateSubscriptionSource, DbSize, DbStats, DesiredSourcePath, Destination,
+    DuplicateDestination, DuplicateSource, MatchBy, Source, SourceKind, SourcePath,
+    SourcePathBatch, SourcePathBatchItem, SyncHistoryEntry, TableRowCount, UpdateDestination,
+    UpdateSource, UpdateSourcePath,
+};
+use axum::{
+    Json, Router,
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
 };
-use axum::{Json, Router, response::IntoResponse, routing::get};
+use serde::Serialize;
+use serde_json::{Map, Value, json};
 use utoipa::OpenApi;
 
 #[derive(OpenApi)]
@@ -18,44 +48,120 @@ use utoipa::OpenApi;
         crate::api::sources::list_sources,
         crate::api::sources::create_source,
         crate::api::sources::update_source,
+        crate::api::sources::duplicate_source,
+        crate::api::sources::sign_source_url,
+        crate::api::sources::create_static_source,
+        crate::api::sources::create_subscription_source,
+        crate::api::sources::preview_source,
+        crate::api::sources::replace_source_ics,
+        crate::api::sources::clear_source_ics,
         crate::api::sources::delete_source_handler,
         crate::api::sources::sync_source,
         crate::api::sources::source_status,
+        crate::api::sources::set_source_status,
+        crate::api::sources::source_calendars,
+        crate::api::history::source_history,
+        crate::api::history::source_history_csv,
+        crate::api::source_events::source_events,
+        crate::api::source_events::source_timezones,
+        crate::api::loop_check::loop_check,
         crate::api::source_paths::list_source_paths,
+        crate::api::source_paths::list_all_source_paths,
         crate::api::source_paths::create_source_path,
+        crate::api::source_paths::create_source_paths_batch,
+        crate::api::source_paths::replace_source_paths,
         crate::api::source_paths::update_source_path,
         crate::api::source_paths::delete_source_path,
         crate::api::destinations::list_destinations,
         crate::api::destinations::create_destination,
         crate::api::destinations::update_destination,
+        crate::api::destinations::duplicate_destination,
         crate::api::destinations::delete_destination,
         crate::api::destinations::sync_destination,
+        crate::api::destinations::sync_destination_stream,
         crate::api::destinations::check_overlap,
+        crate::api::validate::validate_ics_handler,
+        crate::api::validate::validate_cron_handler,
+        crate::api::admin::test_webhook,
+        crate::api::admin::db_stats,
+        crate::api::admin::vacuum,
+        crate::api::admin::effective_config,
+        crate::api::events::stream_events,
         crate::api::health::health,
         crate::api::health::health_detailed,
+        crate::api::health::health_live,
+        crate::api::health::health_ready,
+        crate::api::freshness::sync_freshness,
     ),
     components(schemas(
         Source,
         CreateSource,
         UpdateSource,
+        DuplicateSource,
+        CreateStaticSource,
+        CreateSubscriptionSource,
+        PreviewSource,
+        SetSyncStatus,
+        SourceKind,
         SourceResponse,
+        SignUrlRequest,
+        SignedUrlResponse,
         SourceListResponse,
         SyncResult,
+        SyncTimings,
+        CalendarInfo,
+        CalendarListResponse,
+        SyncHistoryEntry,
+        HistoryResponse,
+        EventPreview,
+        SourceEventsResponse,
+        SourceTimezone,
+        SourceTimezonesResponse,
+        SuspectedLoop,
+        LoopCheckResponse,
         SourcePath,
         CreateSourcePath,
         UpdateSourcePath,
         SourcePathResponse,
         SourcePathListResponse,
+        SourceUrl,
+        SourceUrlKind,
+        SourceUrlListResponse,
+        SourcePathBatch,
+        SourcePathBatchItem,
+        SourcePathBatchResponse,
+        DesiredSourcePath,
         Destination,
+        MatchBy,
         CreateDestination,
         UpdateDestination,
+        DuplicateDestination,
         DestinationResponse,
         DestinationListResponse,
+        UnparseableDate,
+        SyncProgress,
         ReverseSyncResult,
         OverlapEntry,
         OverlapResponse,
+        IcsValidationResponse,
+        CronValidationRequest,
+        CronValidationResponse,
+        TestWebhookRequest,
+        TestWebhookResponse,
+        DbSize,
+        TableRowCount,
+        DbStats,
+        DbStatsResponse,
+        VacuumResponse,
+        RetrySettings,
+        LineEnding,
+        EffectiveConfig,
+        ConfigResponse,
         HealthResponse,
         DetailedHealthResponse,
+        ReadinessResponse,
+        EntityFreshness,
+        EntityKind,
     )),
     info(
         title = "CalDAV/ICS Sync API",
@@ -65,10 +171,137 @@ use utoipa::OpenApi;
 )]
 pub struct ApiDoc;
 
+/// One documented operation, as listed by `GET /api/openapi/paths`.
+#[derive(Debug, Serialize)]
+pub struct RouteEntry {
+    pub method: String,
+    pub path: String,
+}
+
+/// Flattens the generated document into `(method, path)` pairs, sorted by
+/// path. The document is built on every call, so it always reflects the
+/// handlers registered above.
+pub fn route_inventory() -> Vec<RouteEntry> {
+    let mut routes = Vec::new();
+    for (path, item) in ApiDoc::openapi().paths.paths {
+        let operations = [
+            ("GET", &item.get),
+            ("PUT", &item.put),
+            ("POST", &item.post),
+            ("DELETE", &item.delete),
+            ("OPTIONS", &item.options),
+            ("HEAD", &item.head),
+            ("PATCH", &item.patch),
+            ("TRACE", &item.trace),
+        ];
+        for (method, operation) in operations {
+            if operation.is_some() {
+                routes.push(RouteEntry {
+                    method: method.into(),
+                    path: path.clone(),
+                });
+            }
+        }
+    }
+    routes.sort_by(|a, b| a.path.cmp(&b.path).then_with(|| a.method.cmp(&b.method)));
+    routes
+}
+
+/// Request bodies served by `GET /api/schema/{type}`, keyed by that type.
+const SCHEMA_TYPES: &[(&str, &str)] = &[
+    ("source", "CreateSource"),
+    ("destination", "CreateDestination"),
+    ("source_path", "CreateSourcePath"),
+];
+
+const COMPONENT_REF_PREFIX: &str = "#/components/schemas/";
+
+/// Rewrites component references to `#/$defs/...` and records their names.
+fn localize_refs(value: &mut Value, found: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::String(target)) = map.get_mut("$ref")
+                && let Some(name) = target.strip_prefix(COMPONENT_REF_PREFIX).map(String::from)
+            {
+                *target = format!("#/$defs/{}", name);
+                found.push(name);
+            }
+            for child in map.values_mut() {
+                localize_refs(child, found);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                localize_refs(item, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// One component of the OpenAPI document as a self-contained JSON Schema:
+/// the components it references travel along under `$defs`.
+pub fn standalone_schema(name: &str) -> Option<Value> {
+    let doc = serde_json::to_value(ApiDoc::openapi()).ok()?;
+    let schemas = doc.pointer("/components/schemas")?;
+    let mut root = schemas.get(name)?.clone();
+    let mut pending = Vec::new();
+    localize_refs(&mut root, &mut pending);
+    let mut defs = Map::new();
+    while let Some(dep) = pending.pop() {
+        if defs.contains_key(&dep) {
+            continue;
+        }
+        if let Some(mut def) = schemas.get(&dep).cloned() {
+            localize_refs(&mut def, &mut pending);
+            defs.insert(dep, def);
+        }
+    }
+
+    let schema = root.as_object_mut()?;
+    schema.insert(
+        "$schema".into(),
+        "https://json-schema.org/draft/2020-12/schema".into(),
+    );
+    schema.insert("title".into(), name.into());
+    if !defs.is_empty() {
+        schema.insert("$defs".into(), Value::Object(defs));
+    }
+    Some(root)
+}
+
+async fn json_schema(Path(kind): Path<String>) -> Response {
+    let schema = SCHEMA_TYPES
+        .iter()
+        .find(|(k, _)| *k == kind)
+        .and_then(|(_, name)| standalone_schema(name));
+    match schema {
+        Some(schema) => Json(schema).into_response(),
+        None => {
+            let known: Vec<&str> = SCHEMA_TYPES.iter().map(|(k, _)| *k).collect();
+            (
+                StatusCode::NOT_FOUND,
+                Json(json!({
+                    "status": "error",
+                    "message": format!("Unknown schema type '{}'; expected one of: {}", kind, known.join(", ")),
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
 async fn openapi_json() -> impl IntoResponse {
     Json(ApiDoc::openapi())
 }
 
+async fn openapi_paths() -> impl IntoResponse {
+    Json(route_inventory())
+}
+
 pub fn routes() -> Router<AppState> {
-    Router::new().route("/openapi.json", get(openapi_json))
+    Router::new()
+        .route("/openapi.json", get(openapi_json))
+        .route("/openapi/paths", get(openapi_paths))
+        .route("/schema/{kind}", get(json_schema))
 }
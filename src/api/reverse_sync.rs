@@ -1,22 +1,280 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, ensure};
 use chrono::NaiveDateTime;
-use reqwest::{Client, header};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use reqwest::{StatusCode, Url, header};
+use serde::Serialize;
+use tokio::sync::mpsc;
+use utoipa::ToSchema;
 
-use crate::api::sync;
+use crate::api::sync::{self, CaldavClient};
+use crate::db::{Destination, MatchBy};
+use crate::http::{self, HttpClients, HttpPoolConfig, PrivateAddressGuard};
+
+/// PRODID written on every calendar this service generates.
+const OWN_PRODID: &str = "-//CalDAV/ICS Sync//EN";
 
 const VOLATILE_FIELDS: &[&str] = &["DTSTAMP", "SEQUENCE", "LAST-MODIFIED", "CREATED"];
 
+/// RFC 7240 preference asking the server not to echo the uploaded event.
+const RETURN_MINIMAL: &str = "return=minimal";
+
+/// Bytes of a UID left as-is in an event href: RFC 3986 unreserved characters.
+const HREF_UNRESERVED: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Attempts per ICS feed request; connection errors and 5xx responses are retried.
+const FEED_FETCH_ATTEMPTS: u32 = 3;
+/// Delay before the first feed retry, doubled for each one after.
+const FEED_RETRY_BASE_MS: u64 = 250;
+
+/// Per-destination knobs for `run_reverse_sync` beyond the core connection settings.
+#[derive(Debug, Clone)]
+pub struct ReverseSyncOptions {
+    pub allow_redirects: bool,
+    pub max_redirects: usize,
+    pub redirect_cross_host_headers: bool,
+    pub trust_last_modified: bool,
+    pub match_by: MatchBy,
+    pub allow_invalid_certs: bool,
+    /// PROPPATCH the collection's display name to the calendar name.
+    pub set_display_name: bool,
+    /// Log CalDAV requests and responses; see `CaldavClient::with_debug`.
+    pub debug_http: bool,
+    /// Written to each uploaded VEVENT as `X-SYNC-ORIGIN`. When set, only
+    /// events carrying this marker are ever deleted.
+    pub origin: Option<String>,
+    /// Send `Prefer: return=minimal` on PUTs so servers skip echoing the event.
+    pub prefer_minimal: bool,
+    /// Gzip PUT bodies; dropped for the rest of a run after a 415.
+    pub compress_uploads: bool,
+    /// Record which UIDs were uploaded, skipped and deleted in `ReverseSyncStats::uids`.
+    pub collect_uids: bool,
+    /// Added to every feed request; dropped on cross-origin redirects like credentials.
+    pub extra_headers: BTreeMap<String, String>,
+    /// Event count of the last successful run, for the shrink guard.
+    pub previous_total: Option<usize>,
+    /// Refuse to sync when the feed lost more than this percentage of
+    /// `previous_total`; 0 disables the guard.
+    pub max_shrink_percent: u32,
+    /// Name new events with a random UUID instead of their UID, remembering
+    /// the choice in `known_hrefs`.
+    pub uuid_hrefs: bool,
+    /// Object names from earlier `uuid_hrefs` runs, keyed by UID.
+    pub known_hrefs: HashMap<String, String>,
+    /// Drop feed events that came from this service; see `drop_own_events`.
+    pub skip_own_origin: bool,
+    /// Only sync events starting within this many days; 0 keeps every
+    /// future event.
+    pub future_window_days: u32,
+    /// Treat events differing only in TRANSP (free/busy) as unchanged.
+    pub ignore_transp: bool,
+    /// Timezone whose date decides whether an all-day event is past;
+    /// `None` uses the server's local date.
+    pub filter_timezone: Option<chrono_tz::Tz>,
+    /// Collection URL to use verbatim; see `destination_calendar_base`.
+    pub calendar_url_override: Option<String>,
+    /// Receives a `SyncProgress` after each feed event is handled.
+    pub progress: Option<mpsc::UnboundedSender<SyncProgress>>,
+    /// Shared connection pool; `None` builds one-off clients.
+    pub http: Option<HttpClients>,
+}
+
+impl Default for ReverseSyncOptions {
+    fn default() -> Self {
+        Self {
+            allow_redirects: true,
+            max_redirects: 10,
+            redirect_cross_host_headers: false,
+            trust_last_modified: false,
+            match_by: MatchBy::Uid,
+            allow_invalid_certs: false,
+            set_display_name: false,
+            debug_http: false,
+            origin: None,
+            prefer_minimal: false,
+            compress_uploads: false,
+            collect_uids: false,
+            extra_headers: BTreeMap::new(),
+            previous_total: None,
+            max_shrink_percent: 0,
+            uuid_hrefs: false,
+            known_hrefs: HashMap::new(),
+            skip_own_origin: false,
+            future_window_days: 0,
+            ignore_transp: false,
+            filter_timezone: None,
+            calendar_url_override: None,
+            progress: None,
+            http: None,
+        }
+    }
+}
+
+impl From<&Destination> for ReverseSyncOptions {
+    fn from(d: &Destination) -> Self {
+        Self {
+            allow_redirects: d.allow_redirects,
+            max_redirects: d.max_redirects.max(0) as usize,
+            redirect_cross_host_headers: d.redirect_cross_host_headers,
+            trust_last_modified: d.trust_last_modified,
+            match_by: d.match_by,
+            allow_invalid_certs: d.allow_invalid_certs,
+            set_display_name: d.set_display_name,
+            debug_http: d.debug_http,
+            origin: Some(d.id.to_string()),
+            prefer_minimal: d.prefer_minimal,
+            compress_uploads: d.compress_uploads,
+            collect_uids: false,
+            extra_headers: d.extra_headers.clone(),
+            previous_total: d.last_total_events.map(|n| n.max(0) as usize),
+            max_shrink_percent: d.max_shrink_percent.clamp(0, 100) as u32,
+            uuid_hrefs: d.uuid_hrefs,
+            known_hrefs: HashMap::new(),
+            skip_own_origin: d.skip_own_origin,
+            future_window_days: d.future_window_days.clamp(0, u32::MAX as i64) as u32,
+            ignore_transp: d.ignore_transp,
+            filter_timezone: d.filter_timezone.as_deref().and_then(|tz| tz.parse().ok()),
+            calendar_url_override: d.calendar_url_override.clone(),
+            progress: None,
+            http: None,
+        }
+    }
+}
+
+impl ReverseSyncOptions {
+    pub fn with_http(mut self, http: &HttpClients) -> Self {
+        self.http = Some(http.clone());
+        self
+    }
+}
+
+/// The CalDAV server answered a PUT with 507 Insufficient Storage. Retrying
+/// won't help until the account has room again, so callers treat this as
+/// permanent for the current cycle.
+#[derive(Debug, thiserror::Error)]
+#[error("CalDAV quota exceeded (507 Insufficient Storage) after uploading {uploaded} events")]
+pub struct QuotaExceeded {
+    pub uploaded: usize,
+}
+
+/// The feed lost more events since the last successful run than the
+/// destination allows, which usually means a stale or truncated copy.
+/// Nothing is uploaded or deleted; a forced sync skips the check.
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "Suspicious feed shrink: {current} events, down from {previous} (more than {limit}% fewer); sync with force=true if this is expected"
+)]
+pub struct SuspiciousShrink {
+    pub previous: usize,
+    pub current: usize,
+    pub limit: u32,
+}
+
+fn check_shrink(
+    previous: Option<usize>,
+    current: usize,
+    limit: u32,
+) -> std::result::Result<(), SuspiciousShrink> {
+    match previous {
+        Some(previous) if limit > 0 && current < previous => {
+            let lost = (previous - current) * 100;
+            if lost > previous * limit as usize {
+                return Err(SuspiciousShrink {
+                    previous,
+                    current,
+                    limit,
+                });
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
 #[derive(Debug)]
 pub struct ReverseSyncStats {
     pub uploaded: usize,
     pub skipped: usize,
     pub deleted: usize,
     pub total: usize,
+    /// VEVENTs dropped because they repeated a UID without a distinguishing RECURRENCE-ID.
+    pub conflicts: usize,
+    /// Feed events whose end could not be determined; they are treated as
+    /// upcoming, so they are uploaded even when `sync_all` is off.
+    pub unparseable_dates: Vec<UnparseableDate>,
+    /// Set when `ReverseSyncOptions::collect_uids` is on.
+    pub uids: Option<SyncedUids>,
+    /// With `uuid_hrefs`, the UID to object name map to keep for the next
+    /// run; `None` when nothing was synced.
+    pub hrefs: Option<BTreeMap<String, String>>,
+}
+
+/// How far the upload phase of a reverse sync has got.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SyncProgress {
+    /// Feed events handled so far, uploaded or not.
+    pub processed: usize,
+    pub total: usize,
+    pub uploaded: usize,
+    pub skipped: usize,
+}
+
+/// The UIDs behind `ReverseSyncStats`' counts, each list sorted.
+#[derive(Debug, Default)]
+pub struct SyncedUids {
+    pub uploaded: Vec<String>,
+    pub skipped: Vec<String>,
+    pub deleted: Vec<String>,
+}
+
+/// A feed event without a usable DTSTART or DTEND.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct UnparseableDate {
+    pub uid: String,
+    pub reason: String,
 }
 
-fn unfold_ics(text: &str) -> String {
+/// Longest content line before folding, per RFC 5545.
+pub(crate) const MAX_LINE_OCTETS: usize = 75;
+
+/// Folds one content line at 75 octets and terminates it with CRLF.
+pub(crate) fn fold_line(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + 8);
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+    out
+}
+
+/// Folds every line longer than `MAX_LINE_OCTETS` and normalizes line
+/// endings to CRLF. Shorter lines, including existing continuations, are
+/// left as they are.
+pub fn fold_long_lines(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + text.len() / 25);
+    for line in text.lines() {
+        if line.len() > MAX_LINE_OCTETS {
+            out.push_str(&fold_line(line));
+        } else {
+            out.push_str(line);
+            out.push_str("\r\n");
+        }
+    }
+    out
+}
+
+pub(crate) fn unfold_ics(text: &str) -> String {
     let mut lines: Vec<String> = Vec::new();
     for line in text.lines() {
         if (line.starts_with(' ') || line.starts_with('\t')) && !lines.is_empty() {
@@ -30,14 +288,15 @@ fn unfold_ics(text: &str) -> String {
     lines.join("\n")
 }
 
-fn normalize_vevent(vevent_data: &str) -> Vec<String> {
+/// Sorted, unfolded property lines without `VOLATILE_FIELDS` or `ignored`.
+fn normalize_vevent(vevent_data: &str, ignored: &[&str]) -> Vec<String> {
     let unfolded = unfold_ics(vevent_data);
     let mut lines: Vec<String> = unfolded
         .lines()
         .map(str::trim)
         .filter(|line| {
             !line.is_empty()
-                && !VOLATILE_FIELDS.iter().any(|&field| {
+                && !VOLATILE_FIELDS.iter().chain(ignored).any(|&field| {
                     line.starts_with(field)
                         && line
                             .as_bytes()
@@ -51,27 +310,247 @@ fn normalize_vevent(vevent_data: &str) -> Vec<String> {
     lines
 }
 
-fn events_equal(existing: &[String], incoming: &[String]) -> bool {
-    if existing.len() != incoming.len() {
-        return false;
+/// Whether a normalized VEVENT has nothing between BEGIN and END, like the
+/// stray empty blocks some feeds emit.
+fn is_blank_vevent(lines: &[String]) -> bool {
+    lines
+        .iter()
+        .all(|line| line == "BEGIN:VEVENT" || line == "END:VEVENT")
+}
+
+/// Compares two instance sets ignoring volatile fields and blank VEVENTs.
+fn events_equal(existing: &[String], incoming: &[String], ignored: &[&str]) -> bool {
+    let normalized = |vevents: &[String]| {
+        let mut out: Vec<Vec<String>> = vevents
+            .iter()
+            .map(|v| normalize_vevent(v, ignored))
+            .filter(|lines| !is_blank_vevent(lines))
+            .collect();
+        out.sort();
+        out
+    };
+    normalized(existing) == normalized(incoming)
+}
+
+/// Sorted LAST-MODIFIED values, or `None` if any VEVENT lacks one.
+fn last_modified_stamps(vevents: &[String]) -> Option<Vec<String>> {
+    let mut stamps = vevents
+        .iter()
+        .map(|v| {
+            unfold_ics(v).lines().map(str::trim).find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                (name.split(';').next() == Some("LAST-MODIFIED")).then(|| value.to_string())
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    stamps.sort();
+    Some(stamps)
+}
+
+/// With `trust_last_modified`, matching LAST-MODIFIED stamps decide the
+/// comparison on their own; events without stamps get the full diff, which
+/// skips the `ignored` properties.
+fn events_unchanged(
+    existing: &[String],
+    incoming: &[String],
+    trust_last_modified: bool,
+    ignored: &[&str],
+) -> bool {
+    if trust_last_modified
+        && existing.len() == incoming.len()
+        && let (Some(a), Some(b)) = (
+            last_modified_stamps(existing),
+            last_modified_stamps(incoming),
+        )
+    {
+        return a == b;
     }
-    let mut a: Vec<Vec<String>> = existing.iter().map(|v| normalize_vevent(v)).collect();
-    let mut b: Vec<Vec<String>> = incoming.iter().map(|v| normalize_vevent(v)).collect();
-    a.sort();
-    b.sort();
-    a == b
+    events_equal(existing, incoming, ignored)
 }
 
-#[derive(Debug)]
-enum EventEnd {
+/// DTSTART line (with parameters) of the master VEVENT, used as the
+/// secondary match key for `MatchBy::UidAndDtstart`.
+fn dtstart_key(vevents: &[String]) -> Option<String> {
+    let master = vevents
+        .iter()
+        .find(|v| !v.contains("RECURRENCE-ID"))
+        .or(vevents.first())?;
+    unfold_ics(master)
+        .lines()
+        .map(str::trim)
+        .find(|line| line.starts_with("DTSTART:") || line.starts_with("DTSTART;"))
+        .map(String::from)
+}
+
+/// Drops VEVENTs that repeat an instance (same UID and same RECURRENCE-ID,
+/// or both without one) so the PUT body stays a valid object. The first
+/// occurrence wins. Returns how many dropped blocks differed from it.
+fn drop_duplicate_instances(uid: &str, vevents: &mut Vec<String>) -> usize {
+    let mut seen: HashMap<Option<String>, Vec<String>> = HashMap::new();
+    let mut conflicts = 0;
+    vevents.retain(|v| {
+        let recurrence_id = unfold_ics(v)
+            .lines()
+            .map(str::trim)
+            .find(|l| l.starts_with("RECURRENCE-ID:") || l.starts_with("RECURRENCE-ID;"))
+            .map(String::from);
+        let normalized = normalize_vevent(v, &[]);
+        match seen.get(&recurrence_id) {
+            None => {
+                seen.insert(recurrence_id, normalized);
+                true
+            }
+            Some(first) => {
+                if *first != normalized {
+                    tracing::warn!(
+                        "Feed has conflicting VEVENTs for UID {}; keeping the first",
+                        uid
+                    );
+                    conflicts += 1;
+                }
+                false
+            }
+        }
+    });
+    conflicts
+}
+
+/// RFC 5545 requires DTSTAMP and strict servers reject PUTs without it.
+/// Inserts `stamp` right after BEGIN:VEVENT when the block has none.
+fn ensure_dtstamp(vevent: &str, stamp: &str) -> String {
+    let has_dtstamp = vevent
+        .lines()
+        .any(|l| l.starts_with("DTSTAMP:") || l.starts_with("DTSTAMP;"));
+    if has_dtstamp {
+        return vevent.to_string();
+    }
+    vevent.replacen(
+        "BEGIN:VEVENT\r\n",
+        &format!("BEGIN:VEVENT\r\nDTSTAMP:{}\r\n", stamp),
+        1,
+    )
+}
+
+const ORIGIN_PROPERTY: &str = "X-SYNC-ORIGIN";
+
+/// Stamps a VEVENT with `X-SYNC-ORIGIN:<origin>`, replacing any marker the
+/// feed already carried.
+fn mark_origin(vevent: &str, origin: &str) -> String {
+    let mut marked: String = vevent
+        .split_inclusive('\n')
+        .filter(|line| !line.starts_with(ORIGIN_PROPERTY))
+        .collect();
+    if let Some(end) = marked.rfind("END:VEVENT") {
+        marked.insert_str(end, &format!("{}:{}\r\n", ORIGIN_PROPERTY, origin));
+    }
+    marked
+}
+
+/// Whether any of an event's VEVENTs was uploaded by `origin`.
+fn has_origin(vevents: &[String], origin: &str) -> bool {
+    vevents.iter().any(|v| {
+        unfold_ics(v).lines().any(|line| {
+            line.split_once(':').is_some_and(|(name, value)| {
+                name.split(';').next() == Some(ORIGIN_PROPERTY) && value.trim() == origin
+            })
+        })
+    })
+}
+
+/// Whether a VEVENT carries an `X-SYNC-ORIGIN` marker from any destination.
+fn has_any_origin(vevent: &str) -> bool {
+    unfold_ics(vevent).lines().any(|line| {
+        line.split_once(':')
+            .is_some_and(|(name, _)| name.split(';').next() == Some(ORIGIN_PROPERTY))
+    })
+}
+
+/// Removes VCALENDAR objects generated by this service (by PRODID), so a
+/// feed served from our own source is not uploaded again.
+fn drop_own_calendars(ics_text: &str) -> String {
+    let mut starts: Vec<usize> = ics_text
+        .match_indices("BEGIN:VCALENDAR")
+        .map(|(i, _)| i)
+        .collect();
+    starts.push(ics_text.len());
+    let mut kept = String::from(&ics_text[..starts[0]]);
+    for bounds in starts.windows(2) {
+        let block = &ics_text[bounds[0]..bounds[1]];
+        if !has_own_prodid(block) {
+            kept.push_str(block);
+        }
+    }
+    kept
+}
+
+/// Whether a VCALENDAR was generated by this service.
+fn has_own_prodid(calendar: &str) -> bool {
+    unfold_ics(calendar).lines().any(|line| {
+        line.strip_prefix("PRODID:")
+            .is_some_and(|v| v.trim() == OWN_PRODID)
+    })
+}
+
+/// Drops feed events produced by this service: whole calendars with our
+/// PRODID and events marked with `X-SYNC-ORIGIN`. Returns the events left
+/// and how many UIDs were dropped.
+fn drop_own_events(ics_text: &str) -> (ExtractedEvents, usize) {
+    let all = extract_events(ics_text).events.len();
+    let mut extracted = extract_events(&drop_own_calendars(ics_text));
+    extracted
+        .events
+        .retain(|_, vevents| !vevents.iter().any(|v| has_any_origin(v)));
+    let dropped = all - extracted.events.len();
+    (extracted, dropped)
+}
+
+fn normalize_without_uid(vevents: &[String]) -> Vec<Vec<String>> {
+    let mut out: Vec<Vec<String>> = vevents
+        .iter()
+        .map(|v| {
+            normalize_vevent(v, &[])
+                .into_iter()
+                .filter(|l| !l.starts_with("UID:") && !l.starts_with("UID;"))
+                .collect()
+        })
+        .collect();
+    out.sort();
+    out
+}
+
+/// Finds an existing event under a different UID that has the same DTSTART
+/// and is otherwise identical to `incoming`.
+fn find_match_by_dtstart<'a>(
+    index: &HashMap<String, Vec<&'a str>>,
+    existing: &HashMap<String, Vec<String>>,
+    incoming: &[String],
+) -> Option<&'a str> {
+    let candidates = index.get(&dtstart_key(incoming)?)?;
+    let wanted = normalize_without_uid(incoming);
+    candidates.iter().copied().find(|uid| {
+        existing
+            .get(*uid)
+            .is_some_and(|e| normalize_without_uid(e) == wanted)
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum EventEnd {
     Date(chrono::NaiveDate),
     DateTime(NaiveDateTime),
 }
 
+/// Parses a DATE or DATE-TIME value. The extended form (`2026-03-01T10:00:00Z`)
+/// and fractional seconds are accepted too; the fraction is dropped.
 fn parse_ics_value(value: &str, tzid: Option<&str>) -> Option<EventEnd> {
-    let trimmed = value.trim();
-    let is_utc = trimmed.ends_with('Z');
-    let stripped = trimmed.trim_end_matches('Z');
+    let compact: String = value
+        .trim()
+        .chars()
+        .filter(|c| !matches!(c, '-' | ':'))
+        .collect();
+    let is_utc = compact.ends_with('Z');
+    let without_zone = compact.trim_end_matches('Z');
+    let stripped = without_zone.split('.').next().unwrap_or(without_zone);
     match stripped.len() {
         8 => chrono::NaiveDate::parse_from_str(stripped, "%Y%m%d")
             .ok()
@@ -97,7 +576,94 @@ fn parse_ics_value(value: &str, tzid: Option<&str>) -> Option<EventEnd> {
     }
 }
 
-fn event_end_parsed(vevent_text: &str) -> Option<EventEnd> {
+/// A parsed DTSTART or DTEND.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum IcsValue {
+    Instant(EventEnd),
+    /// A `VALUE=PERIOD` value, `start/end` or `start/duration`. `end` is
+    /// `None` when only the start could be read.
+    Period {
+        start: EventEnd,
+        end: Option<EventEnd>,
+    },
+}
+
+impl IcsValue {
+    fn start(self) -> EventEnd {
+        match self {
+            Self::Instant(at) | Self::Period { start: at, .. } => at,
+        }
+    }
+
+    /// The period's end when known, otherwise the start.
+    fn end(self) -> EventEnd {
+        match self {
+            Self::Instant(at) => at,
+            Self::Period { start, end } => end.unwrap_or(start),
+        }
+    }
+}
+
+/// Parses an RFC 5545 DURATION such as `PT1H30M`, `P1D` or `-P2W`.
+fn parse_ics_duration(value: &str) -> Option<chrono::Duration> {
+    let (sign, rest) = match value.trim().strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, value.trim().trim_start_matches('+')),
+    };
+    let mut number = String::new();
+    let mut in_time = false;
+    let mut total = 0i64;
+    let mut any = false;
+    for c in rest.strip_prefix('P')?.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' if number.is_empty() && !in_time => in_time = true,
+            _ => {
+                let unit = match (c, in_time) {
+                    ('W', false) => 604_800,
+                    ('D', false) => 86_400,
+                    ('H', true) => 3_600,
+                    ('M', true) => 60,
+                    ('S', true) => 1,
+                    _ => return None,
+                };
+                total += number.parse::<i64>().ok()? * unit;
+                number.clear();
+                any = true;
+            }
+        }
+    }
+    (any && number.is_empty()).then(|| chrono::Duration::seconds(sign * total))
+}
+
+/// Parses a DTSTART/DTEND value, including the PERIOD form.
+fn parse_ics_property(value: &str, tzid: Option<&str>) -> Option<IcsValue> {
+    let Some((start, end)) = value.split_once('/') else {
+        return parse_ics_value(value, tzid).map(IcsValue::Instant);
+    };
+    let start = parse_ics_value(start, tzid)?;
+    let end = if end.trim_start_matches(['+', '-']).starts_with('P') {
+        parse_ics_duration(end).and_then(|d| match start {
+            EventEnd::Date(date) => date.checked_add_signed(d).map(EventEnd::Date),
+            EventEnd::DateTime(dt) => dt.checked_add_signed(d).map(EventEnd::DateTime),
+        })
+    } else {
+        parse_ics_value(end, tzid)
+    };
+    if end.is_none() {
+        tracing::debug!("Using only the start of PERIOD value '{}'", value);
+    }
+    Some(IcsValue::Period { start, end })
+}
+
+/// A DTSTART or DTEND as written, with its parse when the value is valid.
+struct DateProperty {
+    value: String,
+    parsed: Option<IcsValue>,
+}
+
+/// Raw (DTSTART, DTEND) of a VEVENT, with TZID-qualified times in UTC.
+fn date_properties(vevent_text: &str) -> (Option<DateProperty>, Option<DateProperty>) {
     let unfolded = unfold_ics(vevent_text);
     let mut dtend = None;
     let mut dtstart = None;
@@ -113,50 +679,194 @@ fn event_end_parsed(vevent_text: &str) -> Option<EventEnd> {
             .skip(1)
             .find_map(|p| p.strip_prefix("TZID="));
         let value = &trimmed[colon_pos + 1..];
+        let property = || {
+            Some(DateProperty {
+                value: value.to_string(),
+                parsed: parse_ics_property(value, tzid),
+            })
+        };
         match prop_name {
-            "DTEND" => dtend = parse_ics_value(value, tzid),
-            "DTSTART" => dtstart = parse_ics_value(value, tzid),
+            "DTEND" => dtend = property(),
+            "DTSTART" => dtstart = property(),
             _ => {}
         }
     }
-    dtend.or(dtstart)
+    (dtstart, dtend)
 }
 
-fn is_event_in_future(vevent_text: &str) -> bool {
+/// Parsed (DTSTART, DTEND) of a VEVENT, with TZID-qualified times in UTC.
+/// A DTSTART PERIOD supplies the end when there is no DTEND.
+pub(crate) fn event_bounds(vevent_text: &str) -> (Option<EventEnd>, Option<EventEnd>) {
+    let (dtstart, dtend) = date_properties(vevent_text);
+    let start = dtstart.and_then(|d| d.parsed);
+    let end = match dtend.and_then(|d| d.parsed) {
+        Some(end) => Some(end.end()),
+        None => match start {
+            Some(IcsValue::Period { end, .. }) => end,
+            _ => None,
+        },
+    };
+    (start.map(IcsValue::start), end)
+}
+
+/// Why [`event_end_parsed`] found no usable end.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub(crate) enum EventDateError {
+    #[error("no DTSTART or DTEND")]
+    Missing,
+    #[error("{property} value '{value}' is not a valid date")]
+    Unparseable {
+        property: &'static str,
+        value: String,
+    },
+}
+
+/// DTEND, or DTSTART when DTEND is absent or invalid. A PERIOD counts as
+/// ending at its end.
+pub(crate) fn event_end_parsed(vevent_text: &str) -> Result<EventEnd, EventDateError> {
+    let (dtstart, dtend) = date_properties(vevent_text);
+    if let Some(end) = [&dtend, &dtstart]
+        .into_iter()
+        .find_map(|d| d.as_ref().and_then(|d| d.parsed))
+    {
+        return Ok(end.end());
+    }
+    match (dtend, dtstart) {
+        (Some(d), _) => Err(EventDateError::Unparseable {
+            property: "DTEND",
+            value: d.value,
+        }),
+        (None, Some(d)) => Err(EventDateError::Unparseable {
+            property: "DTSTART",
+            value: d.value,
+        }),
+        (None, None) => Err(EventDateError::Missing),
+    }
+}
+
+/// DTSTART as a sortable timestamp; all-day events start at midnight.
+pub(crate) fn event_start_parsed(vevent_text: &str) -> Option<NaiveDateTime> {
+    match event_bounds(vevent_text).0? {
+        EventEnd::Date(d) => d.and_hms_opt(0, 0, 0),
+        EventEnd::DateTime(dt) => Some(dt),
+    }
+}
+
+/// Events with an instance that has no usable end, sorted by UID.
+fn find_unparseable_dates(events: &HashMap<String, Vec<String>>) -> Vec<UnparseableDate> {
+    let mut found: Vec<UnparseableDate> = events
+        .iter()
+        .filter_map(|(uid, vevents)| {
+            let error = vevents.iter().find_map(|v| event_end_parsed(v).err())?;
+            Some(UnparseableDate {
+                uid: uid.clone(),
+                reason: error.to_string(),
+            })
+        })
+        .collect();
+    found.sort_by(|a, b| a.uid.cmp(&b.uid));
+    found
+}
+
+fn gzip(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    use std::io::Write;
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// Whether a response confirms `Prefer: return=minimal` via `Preference-Applied`.
+fn preference_applied(headers: &header::HeaderMap) -> bool {
+    headers
+        .get_all("Preference-Applied")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|p| p.trim().eq_ignore_ascii_case(RETURN_MINIMAL))
+}
+
+fn is_event_in_future(vevent_text: &str, tz: Option<chrono_tz::Tz>) -> bool {
+    is_event_in_future_at(vevent_text, chrono::Utc::now(), tz)
+}
+
+/// Whether the event ends after `now`. All-day events are compared against
+/// the date `now` falls on in `tz`, or in the server's local timezone.
+fn is_event_in_future_at(
+    vevent_text: &str,
+    now: chrono::DateTime<chrono::Utc>,
+    tz: Option<chrono_tz::Tz>,
+) -> bool {
+    let today = match tz {
+        Some(tz) => now.with_timezone(&tz).date_naive(),
+        None => now.with_timezone(&chrono::Local).date_naive(),
+    };
     match event_end_parsed(vevent_text) {
-        Some(EventEnd::Date(d)) => d > chrono::Local::now().date_naive(),
-        Some(EventEnd::DateTime(dt)) => dt > chrono::Utc::now().naive_utc(),
-        None => true,
+        Ok(EventEnd::Date(d)) => d > today,
+        Ok(EventEnd::DateTime(dt)) => dt > now.naive_utc(),
+        Err(e) => {
+            tracing::debug!("Treating event as upcoming: {}", e);
+            true
+        }
+    }
+}
+
+/// Like `is_event_in_future`, but also requires the event to start within
+/// `window_days` from now. A window of 0 is unbounded.
+fn is_event_in_window(vevent_text: &str, window_days: u32, tz: Option<chrono_tz::Tz>) -> bool {
+    if !is_event_in_future(vevent_text, tz) {
+        return false;
+    }
+    if window_days == 0 {
+        return true;
     }
+    let horizon = chrono::Utc::now().naive_utc() + chrono::Duration::days(window_days.into());
+    event_start_parsed(vevent_text).is_none_or(|start| start <= horizon)
 }
 
-struct ExtractedEvents {
-    events: HashMap<String, Vec<String>>,
-    vtimezones: Vec<String>,
+pub(crate) struct ExtractedEvents {
+    pub(crate) events: HashMap<String, Vec<String>>,
+    pub(crate) vtimezones: Vec<String>,
 }
 
-fn extract_events(ics_text: &str) -> ExtractedEvents {
+/// Collects VEVENTs and VTIMEZONEs from a feed, which may be several
+/// VCALENDAR documents concatenated. A component left open at a VCALENDAR
+/// boundary is dropped, and a TZID repeated across documents is kept once.
+pub(crate) fn extract_events(ics_text: &str) -> ExtractedEvents {
     let unfolded = unfold_ics(ics_text);
     let mut events: HashMap<String, Vec<String>> = HashMap::new();
     let mut vtimezones: Vec<String> = Vec::new();
+    let mut seen_tzids: HashSet<String> = HashSet::new();
     let mut in_vevent = false;
     let mut in_vtimezone = false;
     let mut current_event = String::new();
     let mut current_uid = String::new();
     let mut current_tz = String::new();
+    let mut current_tzid = String::new();
 
     for line in unfolded.lines() {
+        if line.starts_with("BEGIN:VCALENDAR") || line.starts_with("END:VCALENDAR") {
+            in_vevent = false;
+            in_vtimezone = false;
+            continue;
+        }
+
         if line.starts_with("BEGIN:VTIMEZONE") {
             in_vtimezone = true;
             current_tz.clear();
+            current_tzid.clear();
         }
 
         if in_vtimezone {
             current_tz.push_str(line);
             current_tz.push_str("\r\n");
+            if let Some(tzid) = line.strip_prefix("TZID:") {
+                current_tzid = tzid.trim().to_string();
+            }
             if line.starts_with("END:VTIMEZONE") {
                 in_vtimezone = false;
-                vtimezones.push(current_tz.clone());
+                if seen_tzids.insert(current_tzid.clone()) {
+                    vtimezones.push(current_tz.clone());
+                }
             }
         } else {
             if line.starts_with("BEGIN:VEVENT") {
@@ -185,23 +895,335 @@ fn extract_events(ics_text: &str) -> ExtractedEvents {
     ExtractedEvents { events, vtimezones }
 }
 
+fn same_origin(a: &Url, b: &Url) -> bool {
+    a.scheme() == b.scheme()
+        && a.host_str() == b.host_str()
+        && a.port_or_known_default() == b.port_or_known_default()
+}
+
+/// Sends one feed request, retrying transient failures (connection errors
+/// and 5xx responses) with a short exponential backoff. This is separate
+/// from the auto-sync retry so a momentary blip doesn't fail the whole run.
+async fn send_feed_request(
+    client: &reqwest::Client,
+    fallback: Option<&reqwest::Client>,
+    build: impl Fn(&reqwest::Client) -> reqwest::RequestBuilder,
+) -> Result<reqwest::Response> {
+    let mut attempt = 1;
+    loop {
+        let outcome = match (build(client).send().await, fallback) {
+            (Err(e), Some(fallback)) => {
+                tracing::info!(
+                    "HTTP/2 ICS feed request failed ({}); retrying over HTTP/1.1",
+                    e
+                );
+                build(fallback).send().await
+            }
+            (outcome, _) => outcome,
+        };
+        let failure = match &outcome {
+            Ok(res) if res.status().is_server_error() => Some(res.status().to_string()),
+            Ok(_) => None,
+            Err(e) => Some(e.to_string()),
+        };
+        let Some(failure) = failure else {
+            return outcome.map_err(Into::into);
+        };
+        if attempt >= FEED_FETCH_ATTEMPTS {
+            let res = outcome.context("Failed to fetch ICS file")?;
+            anyhow::bail!(
+                "ICS feed returned {} after {} attempts",
+                res.status(),
+                attempt
+            );
+        }
+        let delay = FEED_RETRY_BASE_MS << (attempt - 1);
+        tracing::warn!(
+            "ICS feed fetch attempt {}/{} failed ({}); retrying in {}ms",
+            attempt,
+            FEED_FETCH_ATTEMPTS,
+            failure,
+            delay
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+        attempt += 1;
+    }
+}
+
+/// Fetches the ICS feed, following redirects by hand so the hop limit and
+/// cross-host header forwarding are under our control. Credentials embedded
+/// in the URL are sent as Basic auth.
+pub(crate) async fn fetch_ics_feed(ics_url: &str, opts: &ReverseSyncOptions) -> Result<String> {
+    let (client, fallback) = match &opts.http {
+        Some(h) => (
+            h.get(opts.allow_invalid_certs, false)?,
+            h.http1_fallback(opts.allow_invalid_certs, false)?,
+        ),
+        None => (
+            http::build_client(&HttpPoolConfig::default(), opts.allow_invalid_certs, false)?,
+            None,
+        ),
+    };
+
+    let mut url = Url::parse(ics_url).context("Invalid ICS URL")?;
+    let mut headers = header::HeaderMap::new();
+    for (name, value) in &opts.extra_headers {
+        headers.insert(
+            header::HeaderName::from_bytes(name.as_bytes())
+                .with_context(|| format!("Invalid header name: {:?}", name))?,
+            header::HeaderValue::from_str(value)
+                .with_context(|| format!("Invalid value for header {}", name))?,
+        );
+    }
+    if !url.username().is_empty() {
+        let auth = format!("{}:{}", url.username(), url.password().unwrap_or(""));
+        let auth_header = format!(
+            "Basic {}",
+            base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &auth)
+        );
+        headers.insert(
+            header::AUTHORIZATION,
+            header::HeaderValue::from_str(&auth_header)?,
+        );
+        let _ = url.set_username("");
+        let _ = url.set_password(None);
+    }
+    let original = url.clone();
+
+    let block_private = opts
+        .http
+        .as_ref()
+        .is_some_and(HttpClients::blocks_private_addresses);
+    let mut hops = 0;
+    loop {
+        if block_private {
+            PrivateAddressGuard::check_url(&url)?;
+        }
+        let res = send_feed_request(&client, fallback.as_ref(), |c| {
+            c.get(url.clone()).headers(headers.clone())
+        })
+        .await?;
+        let status = res.status();
+        if !status.is_redirection() {
+            if hops > 0 {
+                tracing::info!(
+                    "ICS feed {} resolved to {} after {} redirect(s)",
+                    sync::redact_url(original.as_str()),
+                    sync::redact_url(url.as_str()),
+                    hops
+                );
+            }
+            // Error pages have bodies too, which would parse as an empty feed.
+            ensure!(
+                status.is_success(),
+                "ICS feed at {} returned {}",
+                sync::redact_url(original.as_str()),
+                status
+            );
+            return res.text().await.context("Failed to read ICS body");
+        }
+
+        ensure!(
+            opts.allow_redirects,
+            "ICS feed returned {} but redirects are disabled for this destination",
+            status
+        );
+        ensure!(
+            hops < opts.max_redirects,
+            "ICS feed exceeded the limit of {} redirects",
+            opts.max_redirects
+        );
+        let location = res
+            .headers()
+            .get(header::LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .with_context(|| format!("ICS feed returned {} without a Location header", status))?;
+        let next = url
+            .join(location)
+            .with_context(|| format!("Invalid redirect location: {}", location))?;
+        if let Some(http) = &opts.http {
+            http.host_allowlist()
+                .check("ICS feed redirect", next.as_str())?;
+        }
+        if !opts.redirect_cross_host_headers && !same_origin(&url, &next) {
+            headers.remove(header::AUTHORIZATION);
+            headers.remove(header::COOKIE);
+            for name in opts.extra_headers.keys() {
+                headers.remove(name.as_str());
+            }
+        }
+        tracing::debug!(
+            "ICS feed redirect {} -> {}",
+            sync::redact_url(url.as_str()),
+            sync::redact_url(next.as_str())
+        );
+        url = next;
+        hops += 1;
+    }
+}
+
+/// Fails unless `ics` is a calendar document, so an HTML or JSON body that
+/// came back with a success status is not taken for an empty feed.
+pub(crate) fn ensure_vcalendar(ics: &str, ics_url: &str) -> Result<()> {
+    ensure!(
+        ics.trim_start_matches('\u{feff}')
+            .trim_start()
+            .starts_with("BEGIN:VCALENDAR"),
+        "ICS feed at {} did not return a VCALENDAR",
+        sync::redact_url(ics_url)
+    );
+    Ok(())
+}
+
+pub(crate) fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Sets the collection's `displayname`. Many servers restrict PROPPATCH, so
+/// a rejection is logged and the sync carries on.
+async fn set_display_name(client: &CaldavClient, calendar_base: &str, name: &str) {
+    let body = format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:propertyupdate xmlns:d="DAV:">
+  <d:set>
+    <d:prop>
+      <d:displayname>{}</d:displayname>
+    </d:prop>
+  </d:set>
+</d:propertyupdate>"#,
+        xml_escape(name)
+    );
+    let req = client
+        .request(
+            reqwest::Method::from_bytes(b"PROPPATCH").unwrap(),
+            calendar_base,
+        )
+        .header(header::CONTENT_TYPE, "application/xml; charset=utf-8")
+        .body(body);
+    match client.send(req).await {
+        Ok(res) if res.status().is_success() => {
+            tracing::debug!(
+                "Set display name of {} to {}",
+                sync::redact_url(calendar_base),
+                name
+            );
+        }
+        Ok(res) => {
+            tracing::warn!(
+                "PROPPATCH {} returned {}",
+                sync::redact_url(calendar_base),
+                res.status()
+            );
+        }
+        Err(e) => {
+            tracing::warn!(
+                "PROPPATCH {} failed: {}",
+                sync::redact_url(calendar_base),
+                e
+            );
+        }
+    }
+}
+
+/// Events already in the calendar keyed by UID, the object name (last href
+/// segment) each UID was found under, and the UIDs stored in objects with
+/// our PRODID.
+#[allow(clippy::type_complexity)]
 async fn fetch_existing_events(
-    client: &Client,
+    client: &CaldavClient,
     calendar_base: &str,
-) -> Result<HashMap<String, Vec<String>>> {
-    let existing_data = sync::fetch_events(client, calendar_base, calendar_base)
+) -> Result<(
+    HashMap<String, Vec<String>>,
+    HashMap<String, String>,
+    HashSet<String>,
+)> {
+    let objects = sync::fetch_objects(client, calendar_base, calendar_base)
         .await
         .context("Failed to fetch existing CalDAV events")?;
 
     let mut map: HashMap<String, Vec<String>> = HashMap::new();
-    for ics_str in &existing_data {
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut own = HashSet::new();
+    for (href, ics_str) in &objects {
+        let name = href
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or_default();
+        let own_object = has_own_prodid(ics_str);
         for (uid, vevents) in extract_events(ics_str).events {
+            if !name.is_empty() {
+                names.entry(uid.clone()).or_insert_with(|| name.to_string());
+            }
+            if own_object {
+                own.insert(uid.clone());
+            }
             map.entry(uid).or_default().extend(vevents);
         }
     }
-    Ok(map)
+    Ok((map, names, own))
+}
+
+/// Object name of an event stored under its UID, percent-encoded so UIDs
+/// with `/`, `@` or spaces still give a single valid path segment.
+pub(crate) fn uid_object_name(uid: &str) -> String {
+    format!("{}.ics", utf8_percent_encode(uid, HREF_UNRESERVED))
+}
+
+/// Collection URL a destination writes to: `caldav_url` plus the calendar
+/// name, unless the URL already ends with it.
+pub(crate) fn calendar_base(caldav_url: &str, calendar_name: &str) -> String {
+    let normalized_url = caldav_url.trim_end_matches('/');
+    if normalized_url.ends_with(&format!("/{}", calendar_name)) {
+        format!("{}/", normalized_url)
+    } else {
+        format!("{}/{}/", normalized_url, calendar_name)
+    }
+}
+
+/// Collection URL a destination writes to: `override_url` with a trailing
+/// slash when set, otherwise `calendar_base`.
+pub(crate) fn destination_calendar_base(
+    override_url: Option<&str>,
+    caldav_url: &str,
+    calendar_name: &str,
+) -> String {
+    match override_url.map(str::trim).filter(|url| !url.is_empty()) {
+        Some(url) => format!("{}/", url.trim_end_matches('/')),
+        None => calendar_base(caldav_url, calendar_name),
+    }
 }
 
+/// The feeds of a destination: `ics_url` may list several URLs separated
+/// by newlines or commas. A comma only separates feeds when what follows it
+/// is blank or another absolute URL, so a single URL with a comma in its
+/// query string stays whole.
+pub fn split_ics_urls(ics_url: &str) -> Vec<&str> {
+    let starts_feed = |rest: &str| {
+        let next = rest.split(',').next().unwrap_or_default().trim();
+        next.is_empty() || Url::parse(next).is_ok_and(|url| url.has_host())
+    };
+    let mut urls = Vec::new();
+    for line in ics_url.lines() {
+        let mut start = 0;
+        for (i, _) in line.match_indices(',') {
+            if starts_feed(&line[i + 1..]) {
+                urls.push(&line[start..i]);
+                start = i + 1;
+            }
+        }
+        urls.push(&line[start..]);
+    }
+    urls.into_iter()
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn run_reverse_sync(
     ics_url: &str,
     caldav_url: &str,
@@ -210,107 +1232,303 @@ pub async fn run_reverse_sync(
     password: &str,
     sync_all: bool,
     keep_local: bool,
+    opts: &ReverseSyncOptions,
 ) -> Result<ReverseSyncStats> {
-    let ics_client = Client::new();
-    let ics_response = ics_client
-        .get(ics_url)
-        .send()
-        .await
-        .context("Failed to fetch ICS file")?;
-    let ics_text = ics_response
-        .text()
-        .await
-        .context("Failed to read ICS body")?;
+    if opts.allow_invalid_certs {
+        tracing::warn!(
+            "TLS certificate verification is DISABLED for {} and {} (allow_invalid_certs)",
+            sync::redact_url(ics_url),
+            sync::redact_url(caldav_url)
+        );
+    }
+    let feed_urls = split_ics_urls(ics_url);
+    ensure!(!feed_urls.is_empty(), "No ICS URL configured");
+    let feed_label = feed_urls
+        .iter()
+        .map(|url| sync::redact_url(url))
+        .collect::<Vec<_>>()
+        .join(", ");
+    // Every feed must load: a missing one would look like its events were
+    // deleted. extract_events accepts the concatenated documents and groups
+    // events by UID, so a UID repeated across feeds keeps the first feed's copy.
+    let mut ics_text = String::new();
+    for url in &feed_urls {
+        let text = fetch_ics_feed(url, opts)
+            .await
+            .map_err(sync::explain_connect_error)?;
+        ensure_vcalendar(&text, url)?;
+        ics_text.push_str(&text);
+        ics_text.push_str("\r\n");
+    }
 
-    let extracted = extract_events(&ics_text);
+    let mut extracted = if opts.skip_own_origin {
+        let (extracted, dropped) = drop_own_events(&ics_text);
+        if dropped > 0 {
+            tracing::info!(
+                "Ignoring {} event(s) from {} that this service produced",
+                dropped,
+                feed_label
+            );
+        }
+        extracted
+    } else {
+        extract_events(&ics_text)
+    };
 
     if extracted.events.is_empty() {
-        tracing::warn!("ICS feed at {} returned 0 events, skipping sync", ics_url);
+        tracing::warn!(
+            "ICS feed at {} returned 0 events, skipping sync",
+            feed_label
+        );
         return Ok(ReverseSyncStats {
             uploaded: 0,
             skipped: 0,
             deleted: 0,
             total: 0,
+            conflicts: 0,
+            unparseable_dates: Vec::new(),
+            uids: opts.collect_uids.then(SyncedUids::default),
+            hrefs: None,
         });
     }
 
+    let conflicts: usize = extracted
+        .events
+        .iter_mut()
+        .map(|(uid, vevents)| drop_duplicate_instances(uid, vevents))
+        .sum();
+    let unparseable_dates = find_unparseable_dates(&extracted.events);
+    if !unparseable_dates.is_empty() {
+        tracing::warn!(
+            "ICS feed at {} has {} event(s) without a usable DTSTART/DTEND, e.g. {}: {}",
+            feed_label,
+            unparseable_dates.len(),
+            unparseable_dates[0].uid,
+            unparseable_dates[0].reason
+        );
+    }
+
     let tz_block = extracted.vtimezones.join("");
     let all_remote_uids: HashSet<String> = extracted.events.keys().cloned().collect();
-    let events: HashMap<String, Vec<String>> = if sync_all {
+    let mut events: HashMap<String, Vec<String>> = if sync_all {
         extracted.events
     } else {
         extracted
             .events
             .into_iter()
-            .filter(|(_, vevents)| vevents.iter().any(|v| is_event_in_future(v)))
+            .filter(|(_, vevents)| {
+                vevents
+                    .iter()
+                    .any(|v| is_event_in_window(v, opts.future_window_days, opts.filter_timezone))
+            })
             .collect()
     };
+    check_shrink(opts.previous_total, events.len(), opts.max_shrink_percent)?;
+    // Marked before diffing so unchanged events still compare equal to the
+    // stored, marked copies.
+    if let Some(origin) = &opts.origin {
+        for vevents in events.values_mut() {
+            for vevent in vevents.iter_mut() {
+                *vevent = mark_origin(vevent, origin);
+            }
+        }
+    }
 
-    let auth = format!("{}:{}", username, password);
-    let auth_header = format!(
-        "Basic {}",
-        base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &auth)
-    );
+    let caldav_client = CaldavClient::connect(
+        opts.http.as_ref(),
+        username,
+        password,
+        opts.allow_invalid_certs,
+    )?
+    .with_debug(opts.debug_http);
 
-    let mut headers = header::HeaderMap::new();
-    headers.insert(
-        header::AUTHORIZATION,
-        header::HeaderValue::from_str(&auth_header)?,
+    let calendar_base = destination_calendar_base(
+        opts.calendar_url_override.as_deref(),
+        caldav_url,
+        calendar_name,
     );
-    let caldav_client = Client::builder().default_headers(headers).build()?;
 
-    let normalized_url = caldav_url.trim_end_matches('/');
-    let calendar_base = if normalized_url.ends_with(&format!("/{}", calendar_name)) {
-        format!("{}/", normalized_url)
-    } else {
-        format!("{}/{}/", normalized_url, calendar_name)
-    };
-
-    let existing = fetch_existing_events(&caldav_client, &calendar_base).await?;
+    let (existing, existing_names, own_uids) =
+        fetch_existing_events(&caldav_client, &calendar_base)
+            .await
+            .map_err(sync::explain_connect_error)?;
     tracing::info!(
         "Fetched {} existing events from CalDAV for diff",
         existing.len()
     );
 
+    if opts.set_display_name {
+        set_display_name(&caldav_client, &calendar_base, calendar_name).await;
+    }
+
+    let mut dtstart_index: HashMap<String, Vec<&str>> = HashMap::new();
+    if opts.match_by == MatchBy::UidAndDtstart {
+        for (uid, vevents) in &existing {
+            if let Some(key) = dtstart_key(vevents) {
+                dtstart_index.entry(key).or_default().push(uid.as_str());
+            }
+        }
+    }
+
+    let dtstamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
     let mut uploaded = 0;
     let mut skipped = 0;
     let mut errors = 0;
+    let mut matched_other_uids: HashSet<String> = HashSet::new();
+    let mut minimal_applied = 0;
+    let mut compress = opts.compress_uploads;
+    let mut uids = opts.collect_uids.then(SyncedUids::default);
+    // A UID already on the server keeps the name it was found under, so
+    // switching to UUID hrefs does not create a second copy of the event.
+    let mut hrefs: BTreeMap<String, String> = opts
+        .known_hrefs
+        .iter()
+        .map(|(uid, name)| (uid.clone(), name.clone()))
+        .collect();
 
-    for (uid, vevent_blocks) in &events {
+    let ignored_fields: &[&str] = if opts.ignore_transp { &["TRANSP"] } else { &[] };
+    let report_progress = |processed: usize, uploaded: usize, skipped: usize| {
+        if let Some(progress) = &opts.progress {
+            let _ = progress.send(SyncProgress {
+                processed,
+                total: events.len(),
+                uploaded,
+                skipped,
+            });
+        }
+    };
+    report_progress(0, 0, 0);
+
+    for (index, (uid, vevent_blocks)) in events.iter().enumerate() {
         if let Some(existing_vevents) = existing.get(uid)
-            && events_equal(existing_vevents, vevent_blocks)
+            && events_unchanged(
+                existing_vevents,
+                vevent_blocks,
+                opts.trust_last_modified,
+                ignored_fields,
+            )
+        {
+            skipped += 1;
+            if let Some(uids) = &mut uids {
+                uids.skipped.push(uid.clone());
+            }
+            report_progress(index + 1, uploaded, skipped);
+            continue;
+        }
+
+        if opts.match_by == MatchBy::UidAndDtstart
+            && !existing.contains_key(uid)
+            && let Some(other_uid) = find_match_by_dtstart(&dtstart_index, &existing, vevent_blocks)
         {
+            tracing::debug!("Event {} already present as {}, skipping", uid, other_uid);
+            matched_other_uids.insert(other_uid.to_string());
             skipped += 1;
+            if let Some(uids) = &mut uids {
+                uids.skipped.push(uid.clone());
+            }
+            report_progress(index + 1, uploaded, skipped);
             continue;
         }
 
-        let vevent_block = vevent_blocks.join("");
+        let vevent_block: String = vevent_blocks
+            .iter()
+            .map(|v| ensure_dtstamp(v, &dtstamp))
+            .collect();
         let wrapped = format!(
-            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//CalDAV/ICS Sync//EN\r\n{}{}END:VCALENDAR\r\n",
-            tz_block, vevent_block
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:{}\r\n{}{}END:VCALENDAR\r\n",
+            OWN_PRODID, tz_block, vevent_block
         );
 
-        let event_url = format!("{}{}.ics", calendar_base, uid);
+        let object_name = if opts.uuid_hrefs {
+            let name = existing_names
+                .get(uid)
+                .or_else(|| hrefs.get(uid))
+                .cloned()
+                .unwrap_or_else(|| format!("{}.ics", uuid::Uuid::new_v4()));
+            hrefs.insert(uid.clone(), name.clone());
+            name
+        } else {
+            uid_object_name(uid)
+        };
+        let event_url = format!("{}{}", calendar_base, object_name);
 
-        match caldav_client
-            .put(&event_url)
-            .header("Content-Type", "text/calendar; charset=utf-8")
-            .body(wrapped)
-            .send()
-            .await
+        let put = |compressed: bool| {
+            let mut req = caldav_client
+                .request(reqwest::Method::PUT, &event_url)
+                .header("Content-Type", "text/calendar; charset=utf-8");
+            if opts.prefer_minimal {
+                req = req.header("Prefer", RETURN_MINIMAL);
+            }
+            match compressed.then(|| gzip(wrapped.as_bytes())) {
+                Some(Ok(body)) => req.header(header::CONTENT_ENCODING, "gzip").body(body),
+                Some(Err(e)) => {
+                    tracing::warn!("Failed to gzip event {}: {}; sending it as is", uid, e);
+                    req.body(wrapped.clone())
+                }
+                None => req.body(wrapped.clone()),
+            }
+        };
+        let mut result = caldav_client.send(put(compress)).await;
+        if compress
+            && matches!(&result, Ok(res) if res.status() == StatusCode::UNSUPPORTED_MEDIA_TYPE)
         {
+            tracing::warn!(
+                "{} rejected a gzip-encoded PUT; sending uncompressed for the rest of this sync",
+                sync::redact_url(&calendar_base)
+            );
+            compress = false;
+            result = caldav_client.send(put(false)).await;
+        }
+        match result {
             Ok(res) if res.status().is_success() => {
                 uploaded += 1;
+                if let Some(uids) = &mut uids {
+                    uids.uploaded.push(uid.clone());
+                }
+                if preference_applied(res.headers()) {
+                    minimal_applied += 1;
+                }
+            }
+            Ok(res) if res.status() == StatusCode::INSUFFICIENT_STORAGE => {
+                tracing::warn!(
+                    "PUT {} returned {}, stopping uploads",
+                    sync::redact_url(&event_url),
+                    res.status()
+                );
+                return Err(QuotaExceeded { uploaded }.into());
             }
             Ok(res) => {
-                tracing::warn!("PUT {} returned {}", event_url, res.status());
+                tracing::warn!(
+                    "PUT {} returned {}",
+                    sync::redact_url(&event_url),
+                    res.status()
+                );
                 errors += 1;
             }
             Err(e) => {
-                tracing::error!("PUT {} failed: {}", event_url, e);
+                tracing::error!("PUT {} failed: {}", sync::redact_url(&event_url), e);
                 errors += 1;
             }
         }
+        report_progress(index + 1, uploaded, skipped);
+    }
+
+    if opts.prefer_minimal && uploaded > 0 {
+        if minimal_applied == 0 {
+            tracing::info!(
+                "{} ignored Prefer: {}; uploads still succeed, so the flag can be turned off",
+                sync::redact_url(&calendar_base),
+                RETURN_MINIMAL
+            );
+        } else {
+            tracing::debug!(
+                "{} of {} PUT(s) to {} applied Prefer: {}",
+                minimal_applied,
+                uploaded,
+                sync::redact_url(&calendar_base),
+                RETURN_MINIMAL
+            );
+        }
     }
 
     if errors > 0 {
@@ -320,38 +1538,81 @@ pub async fn run_reverse_sync(
     let mut deleted = 0;
 
     if !keep_local {
-        let deletion_candidates: HashSet<String> = if sync_all {
-            existing.keys().cloned().collect()
-        } else {
-            existing
-                .iter()
-                .filter(|(_, vevents)| vevents.iter().any(|v| is_event_in_future(v)))
-                .map(|(uid, _)| uid.clone())
-                .collect()
-        };
+        // Events created by hand or by other tools lack our marker. Events
+        // uploaded before markers existed have none either, but were
+        // written with our PRODID; they stay deletable until re-marked.
+        let deletion_candidates: HashSet<String> = existing
+            .iter()
+            .filter(|(_, vevents)| {
+                sync_all
+                    || vevents.iter().any(|v| {
+                        is_event_in_window(v, opts.future_window_days, opts.filter_timezone)
+                    })
+            })
+            .filter(|(uid, vevents)| {
+                opts.origin.as_deref().is_none_or(|origin| {
+                    has_origin(vevents, origin)
+                        || (own_uids.contains(*uid) && !vevents.iter().any(|v| has_any_origin(v)))
+                })
+            })
+            .map(|(uid, _)| uid.clone())
+            .collect();
 
-        for uid in deletion_candidates.difference(&all_remote_uids) {
-            let event_url = format!("{}{}.ics", calendar_base, uid);
-            match caldav_client.delete(&event_url).send().await {
+        for uid in deletion_candidates
+            .difference(&all_remote_uids)
+            .filter(|uid| !matched_other_uids.contains(*uid))
+        {
+            // Delete what the server reported for the UID: objects written
+            // before hrefs were encoded, or by other clients, live elsewhere.
+            let stored = if opts.uuid_hrefs {
+                hrefs.remove(uid)
+            } else {
+                None
+            };
+            let object_name = existing_names
+                .get(uid)
+                .cloned()
+                .or(stored)
+                .unwrap_or_else(|| uid_object_name(uid));
+            let event_url = format!("{}{}", calendar_base, object_name);
+            let req = caldav_client.request(reqwest::Method::DELETE, &event_url);
+            match caldav_client.send(req).await {
                 Ok(res) if res.status().is_success() || res.status().as_u16() == 404 => {
                     deleted += 1;
+                    if let Some(uids) = &mut uids {
+                        uids.deleted.push(uid.clone());
+                    }
                     tracing::info!("Deleted orphan event: {}", uid);
                 }
                 Ok(res) => {
-                    tracing::warn!("DELETE {} returned {}", event_url, res.status());
+                    tracing::warn!(
+                        "DELETE {} returned {}",
+                        sync::redact_url(&event_url),
+                        res.status()
+                    );
                 }
                 Err(e) => {
-                    tracing::error!("DELETE {} failed: {}", event_url, e);
+                    tracing::error!("DELETE {} failed: {}", sync::redact_url(&event_url), e);
                 }
             }
         }
     }
 
+    if let Some(uids) = &mut uids {
+        uids.uploaded.sort();
+        uids.skipped.sort();
+        uids.deleted.sort();
+    }
+
     Ok(ReverseSyncStats {
         uploaded,
         skipped,
         deleted,
         total: events.len(),
+        conflicts,
+        unparseable_dates,
+        uids,
+        hrefs: opts.uuid_hrefs.then_some(hrefs),
     })
 }
 
@@ -360,6 +1621,62 @@ mod tests {
     use super::*;
     use chrono::Timelike;
 
+    #[test]
+    fn check_shrink_allows_drops_up_to_the_limit() {
+        assert!(check_shrink(None, 1, 50).is_ok());
+        assert!(check_shrink(Some(10), 1, 0).is_ok());
+        assert!(check_shrink(Some(10), 5, 50).is_ok());
+        assert!(check_shrink(Some(10), 20, 50).is_ok());
+        let err = check_shrink(Some(10), 4, 50).unwrap_err();
+        assert_eq!((err.previous, err.current, err.limit), (10, 4, 50));
+    }
+
+    #[test]
+    fn drop_duplicate_instances_keeps_first_and_counts_conflicts() {
+        let mut vevents = vec![
+            "BEGIN:VEVENT\r\nUID:a\r\nSUMMARY:One\r\nEND:VEVENT\r\n".to_string(),
+            "BEGIN:VEVENT\r\nUID:a\r\nSUMMARY:Two\r\nEND:VEVENT\r\n".to_string(),
+            "BEGIN:VEVENT\r\nUID:a\r\nSUMMARY:One\r\nEND:VEVENT\r\n".to_string(),
+        ];
+        assert_eq!(drop_duplicate_instances("a", &mut vevents), 1);
+        assert_eq!(vevents.len(), 1);
+        assert!(vevents[0].contains("SUMMARY:One"));
+    }
+
+    #[test]
+    fn drop_duplicate_instances_keeps_recurrence_overrides() {
+        let mut vevents = vec![
+            "BEGIN:VEVENT\r\nUID:a\r\nRRULE:FREQ=DAILY\r\nEND:VEVENT\r\n".to_string(),
+            "BEGIN:VEVENT\r\nUID:a\r\nRECURRENCE-ID:20270102T090000Z\r\nEND:VEVENT\r\n".to_string(),
+        ];
+        assert_eq!(drop_duplicate_instances("a", &mut vevents), 0);
+        assert_eq!(vevents.len(), 2);
+    }
+
+    #[test]
+    fn ensure_dtstamp_inserts_only_when_missing() {
+        let bare = "BEGIN:VEVENT\r\nUID:a\r\nEND:VEVENT\r\n";
+        assert_eq!(
+            ensure_dtstamp(bare, "20270101T000000Z"),
+            "BEGIN:VEVENT\r\nDTSTAMP:20270101T000000Z\r\nUID:a\r\nEND:VEVENT\r\n"
+        );
+
+        let stamped = "BEGIN:VEVENT\r\nDTSTAMP:20260101T000000Z\r\nUID:a\r\nEND:VEVENT\r\n";
+        assert_eq!(ensure_dtstamp(stamped, "20270101T000000Z"), stamped);
+    }
+
+    #[test]
+    fn mark_origin_replaces_existing_marker() {
+        let vevent = "BEGIN:VEVENT\r\nUID:a\r\nX-SYNC-ORIGIN:9\r\nEND:VEVENT\r\n";
+        let marked = vec![mark_origin(vevent, "3")];
+        assert_eq!(
+            marked[0],
+            "BEGIN:VEVENT\r\nUID:a\r\nX-SYNC-ORIGIN:3\r\nEND:VEVENT\r\n"
+        );
+        assert!(has_origin(&marked, "3"));
+        assert!(!has_origin(&marked, "9"));
+    }
+
     #[test]
     fn unfold_joins_continuation_lines() {
         let folded = "SUMMARY:Long event\r\n  name here";
@@ -369,12 +1686,31 @@ mod tests {
     #[test]
     fn normalize_strips_volatile_fields() {
         let vevent = "BEGIN:VEVENT\r\nUID:1\r\nDTSTAMP:20260101T000000Z\r\nSUMMARY:Test\r\nSEQUENCE:3\r\nEND:VEVENT";
-        let lines = normalize_vevent(vevent);
+        let lines = normalize_vevent(vevent, &[]);
         assert!(!lines.iter().any(|l| l.starts_with("DTSTAMP")));
         assert!(!lines.iter().any(|l| l.starts_with("SEQUENCE")));
         assert!(lines.iter().any(|l| l.starts_with("SUMMARY")));
     }
 
+    #[test]
+    fn events_equal_ignores_stray_empty_vevent() {
+        let event =
+            "BEGIN:VEVENT\r\nUID:1\r\nDTSTAMP:20260101T000000Z\r\nSUMMARY:Test\r\nEND:VEVENT";
+        let a = vec![event.to_string()];
+        let b = vec![
+            event.to_string(),
+            "BEGIN:VEVENT\r\n\r\n  \r\nEND:VEVENT".to_string(),
+            "BEGIN:VEVENT\r\nDTSTAMP:20260102T000000Z\r\nEND:VEVENT".to_string(),
+        ];
+        assert!(events_equal(&a, &b, &[]));
+        assert!(events_equal(&b, &a, &[]));
+        let c = vec![
+            event.to_string(),
+            "BEGIN:VEVENT\r\nSUMMARY:Other\r\nEND:VEVENT".to_string(),
+        ];
+        assert!(!events_equal(&a, &c, &[]));
+    }
+
     #[test]
     fn events_equal_ignores_dtstamp_difference() {
         let a = vec![
@@ -385,14 +1721,26 @@ mod tests {
             "BEGIN:VEVENT\r\nUID:1\r\nDTSTAMP:20260221T120000Z\r\nSUMMARY:Test\r\nEND:VEVENT"
                 .to_string(),
         ];
-        assert!(events_equal(&a, &b));
+        assert!(events_equal(&a, &b, &[]));
     }
 
     #[test]
     fn events_not_equal_when_summary_differs() {
         let a = vec!["BEGIN:VEVENT\r\nUID:1\r\nSUMMARY:Meeting A\r\nEND:VEVENT".to_string()];
         let b = vec!["BEGIN:VEVENT\r\nUID:1\r\nSUMMARY:Meeting B\r\nEND:VEVENT".to_string()];
-        assert!(!events_equal(&a, &b));
+        assert!(!events_equal(&a, &b, &[]));
+    }
+
+    #[test]
+    fn events_equal_ignores_transp_only_when_asked() {
+        let a = vec![
+            "BEGIN:VEVENT\r\nUID:1\r\nSUMMARY:Test\r\nTRANSP:OPAQUE\r\nEND:VEVENT".to_string(),
+        ];
+        let b = vec![
+            "BEGIN:VEVENT\r\nUID:1\r\nSUMMARY:Test\r\nTRANSP:TRANSPARENT\r\nEND:VEVENT".to_string(),
+        ];
+        assert!(!events_unchanged(&a, &b, false, &[]));
+        assert!(events_unchanged(&a, &b, false, &["TRANSP"]));
     }
 
     #[test]
@@ -402,7 +1750,77 @@ mod tests {
             "BEGIN:VEVENT\r\nUID:1\r\nSUMMARY:Test\r\nEND:VEVENT".to_string(),
             "BEGIN:VEVENT\r\nUID:1\r\nRECURRENCE-ID:20260308T100000Z\r\nSUMMARY:Override\r\nEND:VEVENT".to_string(),
         ];
-        assert!(!events_equal(&a, &b));
+        assert!(!events_equal(&a, &b, &[]));
+    }
+
+    #[test]
+    fn last_modified_strategy_agrees_with_full_diff_on_changed_summary() {
+        let a = vec![
+            "BEGIN:VEVENT\r\nUID:1\r\nLAST-MODIFIED:20260101T000000Z\r\nSUMMARY:Meeting A\r\nEND:VEVENT"
+                .to_string(),
+        ];
+        let b = vec![
+            "BEGIN:VEVENT\r\nUID:1\r\nLAST-MODIFIED:20260102T000000Z\r\nSUMMARY:Meeting B\r\nEND:VEVENT"
+                .to_string(),
+        ];
+        assert!(!events_unchanged(&a, &b, false, &[]));
+        assert!(!events_unchanged(&a, &b, true, &[]));
+    }
+
+    #[test]
+    fn last_modified_strategy_agrees_with_full_diff_on_unchanged_event() {
+        let a = vec![
+            "BEGIN:VEVENT\r\nUID:1\r\nLAST-MODIFIED:20260101T000000Z\r\nDTSTAMP:20260101T000000Z\r\nSUMMARY:Meeting\r\nEND:VEVENT"
+                .to_string(),
+        ];
+        let b = vec![
+            "BEGIN:VEVENT\r\nUID:1\r\nLAST-MODIFIED:20260101T000000Z\r\nDTSTAMP:20260301T000000Z\r\nSUMMARY:Meeting\r\nEND:VEVENT"
+                .to_string(),
+        ];
+        assert!(events_unchanged(&a, &b, false, &[]));
+        assert!(events_unchanged(&a, &b, true, &[]));
+    }
+
+    #[test]
+    fn trust_last_modified_skips_on_matching_stamp_alone() {
+        let a = vec![
+            "BEGIN:VEVENT\r\nUID:1\r\nLAST-MODIFIED:20260101T000000Z\r\nSUMMARY:Meeting A\r\nEND:VEVENT"
+                .to_string(),
+        ];
+        let b = vec![
+            "BEGIN:VEVENT\r\nUID:1\r\nLAST-MODIFIED:20260101T000000Z\r\nSUMMARY:Meeting B\r\nEND:VEVENT"
+                .to_string(),
+        ];
+        assert!(events_unchanged(&a, &b, true, &[]));
+    }
+
+    #[test]
+    fn trust_last_modified_falls_back_when_stamp_missing() {
+        let a = vec!["BEGIN:VEVENT\r\nUID:1\r\nSUMMARY:Meeting A\r\nEND:VEVENT".to_string()];
+        let b = vec![
+            "BEGIN:VEVENT\r\nUID:1\r\nLAST-MODIFIED:20260101T000000Z\r\nSUMMARY:Meeting B\r\nEND:VEVENT"
+                .to_string(),
+        ];
+        assert!(!events_unchanged(&a, &b, true, &[]));
+    }
+
+    #[test]
+    fn find_match_by_dtstart_ignores_uid_difference() {
+        let mut existing = HashMap::new();
+        existing.insert(
+            "old@test".to_string(),
+            vec!["BEGIN:VEVENT\r\nUID:old@test\r\nDTSTART:20260301T100000Z\r\nSUMMARY:Standup\r\nEND:VEVENT".to_string()],
+        );
+        let mut index = HashMap::new();
+        index.insert("DTSTART:20260301T100000Z".to_string(), vec!["old@test"]);
+        let incoming = vec!["BEGIN:VEVENT\r\nUID:new@test\r\nDTSTART:20260301T100000Z\r\nSUMMARY:Standup\r\nEND:VEVENT".to_string()];
+        assert_eq!(
+            find_match_by_dtstart(&index, &existing, &incoming),
+            Some("old@test")
+        );
+
+        let changed = vec!["BEGIN:VEVENT\r\nUID:new@test\r\nDTSTART:20260301T100000Z\r\nSUMMARY:Retro\r\nEND:VEVENT".to_string()];
+        assert_eq!(find_match_by_dtstart(&index, &existing, &changed), None);
     }
 
     #[test]
@@ -444,7 +1862,7 @@ mod tests {
     #[test]
     fn normalize_handles_parameterized_volatile_fields() {
         let vevent = "BEGIN:VEVENT\r\nUID:1\r\nDTSTAMP;VALUE=DATE-TIME:20260101T000000Z\r\nLAST-MODIFIED:20260101T000000Z\r\nSUMMARY:Test\r\nEND:VEVENT";
-        let lines = normalize_vevent(vevent);
+        let lines = normalize_vevent(vevent, &[]);
         assert!(!lines.iter().any(|l| l.starts_with("DTSTAMP")));
         assert!(!lines.iter().any(|l| l.starts_with("LAST-MODIFIED")));
     }
@@ -495,12 +1913,78 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_ics_value_accepts_extended_and_fractional_forms() {
+        match parse_ics_value("2026-03-01T10:15:00.250Z", None) {
+            Some(EventEnd::DateTime(dt)) => {
+                assert_eq!(dt.to_string(), "2026-03-01 10:15:00")
+            }
+            other => panic!("Expected EventEnd::DateTime, got {:?}", other),
+        }
+        assert!(matches!(
+            parse_ics_value("2026-03-01", None),
+            Some(EventEnd::Date(_))
+        ));
+    }
+
+    #[test]
+    fn parse_ics_property_reads_periods() {
+        match parse_ics_property("20260301T100000Z/20260301T113000Z", None) {
+            Some(IcsValue::Period {
+                start: EventEnd::DateTime(start),
+                end: Some(EventEnd::DateTime(end)),
+            }) => {
+                assert_eq!(start.hour(), 10);
+                assert_eq!(end.to_string(), "2026-03-01 11:30:00");
+            }
+            other => panic!("Expected a PERIOD, got {:?}", other),
+        }
+        match parse_ics_property("20260301T100000Z/PT1H30M", None) {
+            Some(IcsValue::Period {
+                end: Some(EventEnd::DateTime(end)),
+                ..
+            }) => assert_eq!(end.to_string(), "2026-03-01 11:30:00"),
+            other => panic!("Expected a PERIOD with a duration, got {:?}", other),
+        }
+        // An unreadable end still yields the start.
+        match parse_ics_property("20260301T100000Z/later", None) {
+            Some(IcsValue::Period {
+                start: EventEnd::DateTime(start),
+                end: None,
+            }) => assert_eq!(start.hour(), 10),
+            other => panic!("Expected a PERIOD without end, got {:?}", other),
+        }
+        assert!(parse_ics_property("soon/PT1H", None).is_none());
+    }
+
+    #[test]
+    fn period_valued_event_is_judged_by_its_end() {
+        let past = "BEGIN:VEVENT\r\nDTSTART;VALUE=PERIOD:20200101T100000Z/PT1H\r\nEND:VEVENT";
+        assert!(!is_event_in_future(past, None));
+        let ongoing =
+            "BEGIN:VEVENT\r\nDTSTART;VALUE=PERIOD:20200101T100000Z/20990101T000000Z\r\nEND:VEVENT";
+        assert!(is_event_in_future(ongoing, None));
+        let (start, end) = event_bounds(past);
+        assert!(matches!(start, Some(EventEnd::DateTime(dt)) if dt.hour() == 10));
+        assert!(matches!(end, Some(EventEnd::DateTime(dt)) if dt.hour() == 11));
+    }
+
+    #[test]
+    fn event_start_parsed_handles_dates_and_missing_start() {
+        let all_day = "BEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20260101\r\nEND:VEVENT";
+        assert_eq!(
+            event_start_parsed(all_day).map(|dt| dt.to_string()),
+            Some("2026-01-01 00:00:00".into())
+        );
+        assert!(event_start_parsed("BEGIN:VEVENT\r\nSUMMARY:x\r\nEND:VEVENT").is_none());
+    }
+
     #[test]
     fn event_end_parsed_uses_dtend() {
         let vevent =
             "BEGIN:VEVENT\r\nDTSTART:20260101T090000Z\r\nDTEND:20260101T100000Z\r\nEND:VEVENT";
         match event_end_parsed(vevent) {
-            Some(EventEnd::DateTime(dt)) => assert_eq!(dt.hour(), 10),
+            Ok(EventEnd::DateTime(dt)) => assert_eq!(dt.hour(), 10),
             other => panic!("Expected EventEnd::DateTime, got {:?}", other),
         }
     }
@@ -509,7 +1993,7 @@ mod tests {
     fn event_end_parsed_falls_back_to_dtstart() {
         let vevent = "BEGIN:VEVENT\r\nDTSTART:20260101T090000Z\r\nEND:VEVENT";
         match event_end_parsed(vevent) {
-            Some(EventEnd::DateTime(dt)) => assert_eq!(dt.hour(), 9),
+            Ok(EventEnd::DateTime(dt)) => assert_eq!(dt.hour(), 9),
             other => panic!("Expected EventEnd::DateTime, got {:?}", other),
         }
     }
@@ -519,27 +2003,128 @@ mod tests {
         // March 1 in America/New_York is EST (UTC-5), so 10:00 local = 15:00 UTC
         let vevent = "BEGIN:VEVENT\r\nDTEND;TZID=America/New_York:20260301T100000\r\nEND:VEVENT";
         match event_end_parsed(vevent) {
-            Some(EventEnd::DateTime(dt)) => assert_eq!(dt.hour(), 15),
+            Ok(EventEnd::DateTime(dt)) => assert_eq!(dt.hour(), 15),
             other => panic!("Expected EventEnd::DateTime, got {:?}", other),
         }
     }
 
+    #[test]
+    fn event_end_parsed_reports_missing_dates() {
+        let vevent = "BEGIN:VEVENT\r\nUID:a\r\nSUMMARY:No dates\r\nEND:VEVENT";
+        assert_eq!(
+            event_end_parsed(vevent).unwrap_err(),
+            EventDateError::Missing
+        );
+    }
+
+    #[test]
+    fn event_end_parsed_reports_unparseable_value() {
+        let vevent = "BEGIN:VEVENT\r\nDTSTART:2026-01-01 09:00\r\nEND:VEVENT";
+        assert_eq!(
+            event_end_parsed(vevent).unwrap_err(),
+            EventDateError::Unparseable {
+                property: "DTSTART",
+                value: "2026-01-01 09:00".into()
+            }
+        );
+
+        // A valid DTSTART still stands in for a broken DTEND.
+        let vevent = "BEGIN:VEVENT\r\nDTSTART:20260101T090000Z\r\nDTEND:tomorrow\r\nEND:VEVENT";
+        assert!(event_end_parsed(vevent).is_ok());
+    }
+
+    #[test]
+    fn find_unparseable_dates_lists_uids_with_reasons() {
+        let events = HashMap::from([
+            (
+                "good".to_string(),
+                vec!["BEGIN:VEVENT\r\nDTSTART:20260101T090000Z\r\nEND:VEVENT".to_string()],
+            ),
+            (
+                "undated".to_string(),
+                vec!["BEGIN:VEVENT\r\nSUMMARY:x\r\nEND:VEVENT".to_string()],
+            ),
+            (
+                "garbled".to_string(),
+                vec!["BEGIN:VEVENT\r\nDTEND:soon\r\nEND:VEVENT".to_string()],
+            ),
+        ]);
+        let found = find_unparseable_dates(&events);
+        let uids: Vec<&str> = found.iter().map(|f| f.uid.as_str()).collect();
+        assert_eq!(uids, ["garbled", "undated"]);
+        assert_eq!(found[0].reason, "DTEND value 'soon' is not a valid date");
+        assert_eq!(found[1].reason, "no DTSTART or DTEND");
+    }
+
+    #[test]
+    fn preference_applied_matches_return_minimal() {
+        let mut headers = header::HeaderMap::new();
+        assert!(!preference_applied(&headers));
+        headers.insert("Preference-Applied", "respond-async".parse().unwrap());
+        assert!(!preference_applied(&headers));
+        headers.insert(
+            "Preference-Applied",
+            "wait=5, Return=Minimal".parse().unwrap(),
+        );
+        assert!(preference_applied(&headers));
+    }
+
     #[test]
     fn is_event_in_future_past_event() {
         let vevent = "BEGIN:VEVENT\r\nDTEND:20200101T100000Z\r\nEND:VEVENT";
-        assert!(!is_event_in_future(vevent));
+        assert!(!is_event_in_future(vevent, None));
     }
 
     #[test]
     fn is_event_in_future_future_event() {
         let vevent = "BEGIN:VEVENT\r\nDTEND:20990101T100000Z\r\nEND:VEVENT";
-        assert!(is_event_in_future(vevent));
+        assert!(is_event_in_future(vevent, None));
     }
 
     #[test]
     fn is_event_in_future_unparseable_defaults_true() {
         let vevent = "BEGIN:VEVENT\r\nSUMMARY:No dates\r\nEND:VEVENT";
-        assert!(is_event_in_future(vevent));
+        assert!(is_event_in_future(vevent, None));
+    }
+
+    #[test]
+    fn all_day_event_uses_filter_timezone_date() {
+        use chrono::TimeZone;
+        // Ends (exclusive) on the 16th: still on for the whole of the 15th.
+        let vevent = "BEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20250615\r\nDTEND;VALUE=DATE:20250616\r\nEND:VEVENT";
+        let tokyo = Some(chrono_tz::Asia::Tokyo);
+        let los_angeles = Some(chrono_tz::America::Los_Angeles);
+        // 14:59 UTC is 23:59 on the 15th in Tokyo, 15:00 UTC is midnight.
+        let before = chrono::Utc
+            .with_ymd_and_hms(2025, 6, 15, 14, 59, 0)
+            .unwrap();
+        let after = chrono::Utc.with_ymd_and_hms(2025, 6, 15, 15, 0, 0).unwrap();
+        assert!(is_event_in_future_at(vevent, before, tokyo));
+        assert!(!is_event_in_future_at(vevent, after, tokyo));
+        // Still the 15th in Los Angeles until 07:00 UTC on the 16th.
+        let late = chrono::Utc.with_ymd_and_hms(2025, 6, 16, 6, 59, 0).unwrap();
+        assert!(is_event_in_future_at(vevent, late, los_angeles));
+        assert!(!is_event_in_future_at(vevent, late, tokyo));
+        let next_day = chrono::Utc.with_ymd_and_hms(2025, 6, 16, 7, 0, 0).unwrap();
+        assert!(!is_event_in_future_at(vevent, next_day, los_angeles));
+    }
+
+    #[test]
+    fn filter_timezone_does_not_affect_timed_events() {
+        use chrono::TimeZone;
+        let vevent = "BEGIN:VEVENT\r\nDTEND:20250615T120000Z\r\nEND:VEVENT";
+        let now = chrono::Utc.with_ymd_and_hms(2025, 6, 15, 11, 0, 0).unwrap();
+        assert!(is_event_in_future_at(
+            vevent,
+            now,
+            Some(chrono_tz::Asia::Tokyo)
+        ));
+        let now = chrono::Utc.with_ymd_and_hms(2025, 6, 15, 13, 0, 0).unwrap();
+        assert!(!is_event_in_future_at(
+            vevent,
+            now,
+            Some(chrono_tz::Pacific::Honolulu)
+        ));
     }
 
     #[test]
@@ -582,4 +2167,84 @@ mod tests {
         assert!(extracted.vtimezones[0].starts_with("BEGIN:VTIMEZONE"));
         assert!(extracted.vtimezones[0].contains("END:VTIMEZONE"));
     }
+
+    #[test]
+    fn drop_own_events_skips_our_prodid_and_origin_markers() {
+        let ics = format!(
+            "BEGIN:VCALENDAR\r\nPRODID:-//Other//EN\r\n\
+             BEGIN:VEVENT\r\nUID:external\r\nEND:VEVENT\r\n\
+             BEGIN:VEVENT\r\nUID:marked\r\nX-SYNC-ORIGIN:4\r\nEND:VEVENT\r\n\
+             END:VCALENDAR\r\n\
+             BEGIN:VCALENDAR\r\nPRODID:{OWN_PRODID}\r\n\
+             BEGIN:VEVENT\r\nUID:served\r\nEND:VEVENT\r\n\
+             END:VCALENDAR\r\n"
+        );
+        let (extracted, dropped) = drop_own_events(&ics);
+        assert_eq!(dropped, 2);
+        assert_eq!(
+            extracted.events.keys().collect::<Vec<_>>(),
+            [&"external".to_string()]
+        );
+    }
+
+    #[test]
+    fn extract_events_handles_concatenated_vcalendars() {
+        let tz = "BEGIN:VTIMEZONE\r\nTZID:Europe/Berlin\r\nEND:VTIMEZONE\r\n";
+        let ics = format!(
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{tz}\
+             BEGIN:VEVENT\r\nUID:first\r\nSUMMARY:One\r\nEND:VEVENT\r\n\
+             END:VCALENDAR\r\n\
+             BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{tz}\
+             BEGIN:VEVENT\r\nUID:second\r\nSUMMARY:Two\r\nEND:VEVENT\r\n\
+             END:VCALENDAR\r\n"
+        );
+        let extracted = extract_events(&ics);
+        assert_eq!(extracted.events.len(), 2);
+        assert!(extracted.events.contains_key("first"));
+        assert!(extracted.events.contains_key("second"));
+        assert_eq!(extracted.vtimezones.len(), 1);
+    }
+
+    #[test]
+    fn extract_events_drops_component_left_open_at_vcalendar_boundary() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+            BEGIN:VTIMEZONE\r\nTZID:Broken/Zone\r\n\
+            END:VCALENDAR\r\n\
+            BEGIN:VCALENDAR\r\n\
+            BEGIN:VEVENT\r\nUID:whole\r\nEND:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+        let extracted = extract_events(ics);
+        assert!(extracted.events.contains_key("whole"));
+        assert!(extracted.vtimezones.is_empty());
+    }
+
+    #[test]
+    fn calendar_url_override_is_used_as_is() {
+        assert_eq!(
+            destination_calendar_base(
+                Some("https://dav.example.com/remote.php/calendars/u/personal"),
+                "https://dav.example.com/dav",
+                "Work"
+            ),
+            "https://dav.example.com/remote.php/calendars/u/personal/"
+        );
+        assert_eq!(
+            destination_calendar_base(Some("https://dav.example.com/c/x//"), "ignored", "Work"),
+            "https://dav.example.com/c/x/"
+        );
+    }
+
+    #[test]
+    fn calendar_base_heuristic_applies_without_override() {
+        for override_url in [None, Some(""), Some("  ")] {
+            assert_eq!(
+                destination_calendar_base(override_url, "https://dav.example.com/dav/", "Work"),
+                "https://dav.example.com/dav/Work/"
+            );
+        }
+        assert_eq!(
+            destination_calendar_base(None, "https://dav.example.com/dav/Work", "Work"),
+            "https://dav.example.com/dav/Work/"
+        );
+    }
 }
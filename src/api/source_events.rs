@@ -0,0 +1,277 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::AppState;
+use super::reverse_sync::{self, EventEnd};
+use crate::db;
+
+#[derive(Deserialize)]
+pub struct EventsQuery {
+    start: Option<String>,
+    end: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EventPreview {
+    uid: String,
+    summary: Option<String>,
+    /// `YYYY-MM-DD` for all-day events, otherwise `YYYY-MM-DDTHH:MM:SSZ`.
+    start: String,
+    end: Option<String>,
+    all_day: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SourceEventsResponse {
+    status: String,
+    message: String,
+    events: Vec<EventPreview>,
+}
+
+/// One VTIMEZONE block from a source's stored feed.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SourceTimezone {
+    tzid: String,
+    /// The block as stored, unfolded, from `BEGIN:VTIMEZONE` to `END:VTIMEZONE`.
+    raw: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SourceTimezonesResponse {
+    status: String,
+    message: String,
+    timezones: Vec<SourceTimezone>,
+}
+
+fn error_response(status: StatusCode, message: String) -> Response {
+    (
+        status,
+        Json(SourceEventsResponse {
+            status: "error".into(),
+            message,
+            events: vec![],
+        }),
+    )
+        .into_response()
+}
+
+/// Accepts a bare date (midnight UTC) or an RFC 3339 timestamp.
+pub(crate) fn parse_bound(raw: &str) -> Option<NaiveDateTime> {
+    let raw = raw.trim();
+    if let Ok(d) = NaiveDate::parse_from_str(raw, "%Y-%m-%d") {
+        return d.and_hms_opt(0, 0, 0);
+    }
+    chrono::DateTime::parse_from_rfc3339(raw)
+        .ok()
+        .map(|dt| dt.naive_utc())
+}
+
+fn as_datetime(value: EventEnd) -> NaiveDateTime {
+    match value {
+        EventEnd::Date(d) => d.and_time(chrono::NaiveTime::MIN),
+        EventEnd::DateTime(dt) => dt,
+    }
+}
+
+fn format_value(value: EventEnd) -> String {
+    match value {
+        EventEnd::Date(d) => d.format("%Y-%m-%d").to_string(),
+        EventEnd::DateTime(dt) => dt.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+    }
+}
+
+fn unescape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+fn summary(vevent: &str) -> Option<String> {
+    reverse_sync::unfold_ics(vevent).lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name.split(';').next() == Some("SUMMARY")).then(|| unescape_text(value.trim_end()))
+    })
+}
+
+/// Parses stored VEVENTs and keeps those overlapping `[start, end)`.
+/// Recurrence rules are not expanded; each VEVENT is listed once.
+fn collect_events(
+    ics: &str,
+    start: Option<NaiveDateTime>,
+    end: Option<NaiveDateTime>,
+) -> Vec<EventPreview> {
+    let mut found = Vec::new();
+    for (uid, vevents) in reverse_sync::extract_events(ics).events {
+        for vevent in &vevents {
+            let (Some(dtstart), dtend) = reverse_sync::event_bounds(vevent) else {
+                continue;
+            };
+            let all_day = matches!(dtstart, EventEnd::Date(_));
+            let from = as_datetime(dtstart);
+            // All-day events without DTEND last one day; timed ones are instants.
+            let until = match (dtend, dtstart) {
+                (Some(e), _) => as_datetime(e),
+                (None, EventEnd::Date(d)) => as_datetime(EventEnd::Date(d + chrono::Days::new(1))),
+                (None, EventEnd::DateTime(dt)) => dt,
+            };
+            if end.is_some_and(|end| from >= end) || start.is_some_and(|start| until < start) {
+                continue;
+            }
+            found.push((
+                from,
+                EventPreview {
+                    uid: uid.clone(),
+                    summary: summary(vevent),
+                    start: format_value(dtstart),
+                    end: dtend.map(format_value),
+                    all_day,
+                },
+            ));
+        }
+    }
+    found.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.uid.cmp(&b.1.uid)));
+    found.into_iter().map(|(_, e)| e).collect()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sources/{id}/events",
+    params(
+        ("id" = i64, Path, description = "Source ID"),
+        ("start" = Option<String>, Query, description = "Only events ending at or after this date or RFC 3339 time"),
+        ("end" = Option<String>, Query, description = "Only events starting before this date or RFC 3339 time"),
+    ),
+    responses((status = 200, body = SourceEventsResponse))
+)]
+pub async fn source_events(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(q): Query<EventsQuery>,
+) -> Response {
+    let mut bounds = [None, None];
+    for (slot, raw) in bounds.iter_mut().zip([&q.start, &q.end]) {
+        if let Some(raw) = raw {
+            match parse_bound(raw) {
+                Some(dt) => *slot = Some(dt),
+                None => {
+                    return error_response(
+                        StatusCode::BAD_REQUEST,
+                        format!("Invalid date or timestamp: {}", raw),
+                    );
+                }
+            }
+        }
+    }
+    let [start, end] = bounds;
+
+    let db = match state.conn() {
+        Ok(db) => db,
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    match db::get_source(&db, id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return error_response(StatusCode::NOT_FOUND, "Source not found".into()),
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+    let ics = match db::get_ics_data(&db, id) {
+        Ok(ics) => ics.unwrap_or_default(),
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+
+    let events = collect_events(&ics, start, end);
+    (
+        StatusCode::OK,
+        Json(SourceEventsResponse {
+            status: "success".into(),
+            message: format!("{} events", events.len()),
+            events,
+        }),
+    )
+        .into_response()
+}
+
+/// Lists the VTIMEZONEs in stored ICS, in feed order, each TZID once.
+fn collect_timezones(ics: &str) -> Vec<SourceTimezone> {
+    reverse_sync::extract_events(ics)
+        .vtimezones
+        .into_iter()
+        .map(|raw| SourceTimezone {
+            tzid: raw
+                .lines()
+                .find_map(|line| line.strip_prefix("TZID:"))
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+            raw,
+        })
+        .collect()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sources/{id}/timezones",
+    params(("id" = i64, Path, description = "Source ID")),
+    responses((status = 200, body = SourceTimezonesResponse))
+)]
+pub async fn source_timezones(State(state): State<AppState>, Path(id): Path<i64>) -> Response {
+    let error = |status: StatusCode, message: String| {
+        (
+            status,
+            Json(SourceTimezonesResponse {
+                status: "error".into(),
+                message,
+                timezones: vec![],
+            }),
+        )
+            .into_response()
+    };
+    let db = match state.conn() {
+        Ok(db) => db,
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    match db::get_source(&db, id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return error(StatusCode::NOT_FOUND, "Source not found".into()),
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+    let ics = match db::get_ics_data(&db, id) {
+        Ok(ics) => ics.unwrap_or_default(),
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+
+    let timezones = collect_timezones(&ics);
+    (
+        StatusCode::OK,
+        Json(SourceTimezonesResponse {
+            status: "success".into(),
+            message: format!("{} timezones", timezones.len()),
+            timezones,
+        }),
+    )
+        .into_response()
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/sources/{id}/events", get(source_events))
+        .route("/sources/{id}/timezones", get(source_timezones))
+}
@@ -2,12 +2,12 @@ use crate::api::AppState;
 use crate::db;
 use axum::{
     Json, Router,
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode, header},
     response::IntoResponse,
     routing::get,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 #[derive(Serialize, ToSchema)]
@@ -33,8 +33,10 @@ pub async fn list_source_paths(
     State(state): State<AppState>,
     Path(source_id): Path<i64>,
 ) -> impl IntoResponse {
-    let db = state.db.lock().unwrap();
-    match db::list_source_paths(&db, source_id) {
+    match state
+        .conn()
+        .and_then(|db| db::list_source_paths(&db, source_id))
+    {
         Ok(paths) => (StatusCode::OK, Json(SourcePathListResponse { paths })).into_response(),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -60,7 +62,20 @@ pub async fn create_source_path(
     Path(source_id): Path<i64>,
     Json(body): Json<db::CreateSourcePath>,
 ) -> impl IntoResponse {
-    let db = state.db.lock().unwrap();
+    let db = match state.conn() {
+        Ok(db) => db,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SourcePathResponse {
+                    status: "error".into(),
+                    message: e.to_string(),
+                    path: None,
+                }),
+            )
+                .into_response();
+        }
+    };
     match db::create_source_path(&db, source_id, &body) {
         Ok(id) => {
             let sp = db::get_source_path(&db, id).ok().flatten();
@@ -86,6 +101,140 @@ pub async fn create_source_path(
     }
 }
 
+#[utoipa::path(
+    put,
+    path = "/api/sources/{source_id}/paths",
+    params(("source_id" = i64, Path, description = "Source ID")),
+    request_body = Vec<db::DesiredSourcePath>,
+    responses(
+        (status = 200, description = "The source's paths after the change", body = SourcePathListResponse),
+        (status = 400, description = "Nothing was changed", body = SourcePathResponse),
+        (status = 404, body = SourcePathResponse)
+    )
+)]
+pub async fn replace_source_paths(
+    State(state): State<AppState>,
+    Path(source_id): Path<i64>,
+    Json(body): Json<Vec<db::DesiredSourcePath>>,
+) -> impl IntoResponse {
+    let error = |code: StatusCode, message: String| {
+        (
+            code,
+            Json(SourcePathResponse {
+                status: "error".into(),
+                message,
+                path: None,
+            }),
+        )
+            .into_response()
+    };
+    let db = match state.conn() {
+        Ok(db) => db,
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    match db::get_source(&db, source_id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return error(StatusCode::NOT_FOUND, "Source not found".into()),
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+    match db::replace_source_paths(&db, source_id, &body) {
+        Ok(paths) => (StatusCode::OK, Json(SourcePathListResponse { paths })).into_response(),
+        Err(e) => error(StatusCode::BAD_REQUEST, e.to_string()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct BatchQuery {
+    #[serde(default)]
+    partial: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SourcePathBatchResponse {
+    status: String,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    batch: Option<db::SourcePathBatch>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/sources/{source_id}/paths/batch",
+    params(
+        ("source_id" = i64, Path, description = "Source ID"),
+        ("partial" = Option<bool>, Query, description = "Keep the valid paths when some fail instead of rolling back"),
+    ),
+    request_body = Vec<db::CreateSourcePath>,
+    responses(
+        (status = 201, body = SourcePathBatchResponse),
+        (status = 400, description = "Nothing was created", body = SourcePathBatchResponse),
+        (status = 404, body = SourcePathBatchResponse)
+    )
+)]
+pub async fn create_source_paths_batch(
+    State(state): State<AppState>,
+    Path(source_id): Path<i64>,
+    Query(q): Query<BatchQuery>,
+    Json(body): Json<Vec<db::CreateSourcePath>>,
+) -> impl IntoResponse {
+    let error = |code: StatusCode, message: String| {
+        (
+            code,
+            Json(SourcePathBatchResponse {
+                status: "error".into(),
+                message,
+                batch: None,
+            }),
+        )
+    };
+    if body.is_empty() {
+        return error(StatusCode::BAD_REQUEST, "No paths given".into());
+    }
+    let db = match state.conn() {
+        Ok(db) => db,
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    match db::get_source(&db, source_id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return error(StatusCode::NOT_FOUND, "Source not found".into()),
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+    match db::create_source_paths_batch(&db, source_id, &body, q.partial) {
+        Ok(batch) => {
+            let created = batch.results.iter().filter(|r| r.id.is_some()).count();
+            let failed = batch.results.iter().filter(|r| r.error.is_some()).count();
+            let (code, status, message) = if created > 0 {
+                (
+                    StatusCode::CREATED,
+                    "success",
+                    format!("Created {} paths, {} failed", created, failed),
+                )
+            } else if batch.committed {
+                (
+                    StatusCode::BAD_REQUEST,
+                    "error",
+                    format!("All {} paths failed", failed),
+                )
+            } else {
+                (
+                    StatusCode::BAD_REQUEST,
+                    "error",
+                    format!("{} paths failed; nothing was created", failed),
+                )
+            };
+            (
+                code,
+                Json(SourcePathBatchResponse {
+                    status: status.into(),
+                    message,
+                    batch: Some(batch),
+                }),
+            )
+        }
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
 #[utoipa::path(
     put,
     path = "/api/sources/{source_id}/paths/{path_id}",
@@ -101,7 +250,20 @@ pub async fn update_source_path(
     Path((source_id, path_id)): Path<(i64, i64)>,
     Json(body): Json<db::UpdateSourcePath>,
 ) -> impl IntoResponse {
-    let db = state.db.lock().unwrap();
+    let db = match state.conn() {
+        Ok(db) => db,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SourcePathResponse {
+                    status: "error".into(),
+                    message: e.to_string(),
+                    path: None,
+                }),
+            )
+                .into_response();
+        }
+    };
     match db::get_source_path(&db, path_id) {
         Ok(Some(sp)) if sp.source_id != source_id => {
             return (
@@ -163,7 +325,20 @@ pub async fn delete_source_path(
     State(state): State<AppState>,
     Path((source_id, path_id)): Path<(i64, i64)>,
 ) -> impl IntoResponse {
-    let db = state.db.lock().unwrap();
+    let db = match state.conn() {
+        Ok(db) => db,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SourcePathResponse {
+                    status: "error".into(),
+                    message: e.to_string(),
+                    path: None,
+                }),
+            )
+                .into_response();
+        }
+    };
     match db::get_source_path(&db, path_id) {
         Ok(Some(sp)) if sp.source_id != source_id => {
             return (
@@ -209,11 +384,149 @@ pub async fn delete_source_path(
     }
 }
 
+/// Where a feed URL of a source comes from.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceUrlKind {
+    /// The source's own `ics_path`.
+    Primary,
+    /// The source's `public_ics_path`.
+    Public,
+    /// A row of `source_paths`.
+    SourcePath,
+}
+
+/// One URL a source's feed is served at.
+#[derive(Serialize, ToSchema)]
+pub struct SourceUrl {
+    kind: SourceUrlKind,
+    /// ID of the `source_paths` row, for `source_path` entries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<i64>,
+    path: String,
+    url: String,
+    /// Reachable without credentials.
+    is_public: bool,
+    /// Unauthenticated URL of a public `source_path`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    public_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SourceUrlListResponse {
+    paths: Vec<SourceUrl>,
+}
+
+/// `scheme://host` of the request, from `Host` and `X-Forwarded-Proto`;
+/// empty when there is no `Host`, leaving URLs server-relative.
+fn request_origin(headers: &HeaderMap) -> String {
+    let header_value = |name| headers.get(name).and_then(|v| v.to_str().ok());
+    match header_value(header::HOST) {
+        Some(host) => {
+            let scheme = header_value(header::HeaderName::from_static("x-forwarded-proto"))
+                .unwrap_or("http");
+            format!("{}://{}", scheme, host)
+        }
+        None => String::new(),
+    }
+}
+
+fn source_urls(origin: &str, source: &db::Source, extra: Vec<db::SourcePath>) -> Vec<SourceUrl> {
+    let public_path = source
+        .public_ics_path
+        .clone()
+        .filter(|p| source.public_ics && !p.is_empty());
+    let mut urls = vec![SourceUrl {
+        kind: SourceUrlKind::Primary,
+        id: None,
+        url: format!("{}/ics/{}", origin, source.ics_path),
+        path: source.ics_path.clone(),
+        is_public: source.public_ics && public_path.is_none(),
+        public_url: None,
+        expires_at: None,
+    }];
+    if let Some(path) = public_path {
+        urls.push(SourceUrl {
+            kind: SourceUrlKind::Public,
+            id: None,
+            url: format!("{}/ics/public/{}", origin, path),
+            path,
+            is_public: true,
+            public_url: None,
+            expires_at: None,
+        });
+    }
+    urls.extend(extra.into_iter().map(|sp| {
+        SourceUrl {
+            kind: SourceUrlKind::SourcePath,
+            id: Some(sp.id),
+            url: format!("{}/ics/{}", origin, sp.path),
+            public_url: sp
+                .is_public
+                .then(|| format!("{}/ics/public/{}", origin, sp.path)),
+            path: sp.path,
+            is_public: sp.is_public,
+            expires_at: sp.expires_at,
+        }
+    }));
+    urls
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sources/{source_id}/all-paths",
+    params(("source_id" = i64, Path, description = "Source ID")),
+    responses(
+        (status = 200, description = "The primary path, the public path if any, then every source path", body = SourceUrlListResponse),
+        (status = 404, body = SourcePathResponse)
+    )
+)]
+pub async fn list_all_source_paths(
+    State(state): State<AppState>,
+    Path(source_id): Path<i64>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let loaded = state.conn().and_then(|db| {
+        let Some(source) = db::get_source(&db, source_id)? else {
+            return Ok(None);
+        };
+        Ok(Some((source, db::list_source_paths(&db, source_id)?)))
+    });
+    let error = |status: StatusCode, message: String| {
+        (
+            status,
+            Json(SourcePathResponse {
+                status: "error".into(),
+                message,
+                path: None,
+            }),
+        )
+            .into_response()
+    };
+    match loaded {
+        Ok(Some((source, extra))) => {
+            let paths = source_urls(&request_origin(&headers), &source, extra);
+            (StatusCode::OK, Json(SourceUrlListResponse { paths })).into_response()
+        }
+        Ok(None) => error(StatusCode::NOT_FOUND, "Source not found".into()),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route(
             "/sources/{source_id}/paths",
-            get(list_source_paths).post(create_source_path),
+            get(list_source_paths)
+                .post(create_source_path)
+                .put(replace_source_paths),
+        )
+        .route("/sources/{source_id}/all-paths", get(list_all_source_paths))
+        .route(
+            "/sources/{source_id}/paths/batch",
+            axum::routing::post(create_source_paths_batch),
         )
         .route(
             "/sources/{source_id}/paths/{path_id}",
@@ -1,14 +1,21 @@
 use crate::api::AppState;
+use crate::api::sync::{
+    CaldavClient, MAX_ICS_BYTES, SKIPPED_EMPTY, SKIPPED_EMPTY_MESSAGE, SyncOptions, SyncOutput,
+    SyncTimeout, SyncTimings, keeps_previous_feed, with_timeout,
+};
 use crate::auto_sync::{self, AutoSyncKey};
 use crate::db;
+use crate::host_allowlist::HostNotAllowed;
+use crate::signed_url;
+use crate::webhooks;
 use axum::{
     Json, Router,
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
     response::IntoResponse,
     routing::{get, post, put},
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 #[derive(Serialize, ToSchema)]
@@ -22,6 +29,20 @@ pub struct SourceResponse {
 #[derive(Serialize, ToSchema)]
 pub struct SourceListResponse {
     sources: Vec<db::Source>,
+    /// Pass back as `changed_since` on the next poll.
+    server_time: String,
+}
+
+#[derive(Deserialize)]
+pub struct SourceListQuery {
+    changed_since: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CalendarListResponse {
+    status: String,
+    message: String,
+    calendars: Vec<crate::api::sync::CalendarInfo>,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -30,13 +51,79 @@ pub struct SyncResult {
     message: String,
     events: usize,
     calendars: usize,
+    /// Events shrunk to fit the source's `max_event_bytes`.
+    trimmed: usize,
+    /// Per-phase durations; only with `?timings=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timings: Option<SyncTimings>,
 }
 
-#[utoipa::path(get, path = "/api/sources", responses((status = 200, body = SourceListResponse)))]
-async fn list_sources(State(state): State<AppState>) -> impl IntoResponse {
-    let db = state.db.lock().unwrap();
-    match db::list_sources(&db) {
-        Ok(sources) => (StatusCode::OK, Json(SourceListResponse { sources })).into_response(),
+impl SyncResult {
+    fn error(message: String) -> Self {
+        Self {
+            status: "error".into(),
+            message,
+            events: 0,
+            calendars: 0,
+            trimmed: 0,
+            timings: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SourceSyncQuery {
+    #[serde(default)]
+    timings: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sources",
+    params(("changed_since" = Option<String>, Query, description = "Only sources updated at or after this RFC 3339 time")),
+    responses((status = 200, body = SourceListResponse))
+)]
+async fn list_sources(
+    State(state): State<AppState>,
+    Query(q): Query<SourceListQuery>,
+) -> impl IntoResponse {
+    let since = match q.changed_since.as_deref() {
+        Some(raw) => match chrono::DateTime::parse_from_rfc3339(raw.trim()) {
+            Ok(dt) => Some(
+                dt.with_timezone(&chrono::Utc)
+                    .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+                    .to_string(),
+            ),
+            Err(_) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(SourceResponse {
+                        status: "error".into(),
+                        message: format!("Invalid changed_since timestamp: {}", raw),
+                        source: None,
+                    }),
+                )
+                    .into_response();
+            }
+        },
+        None => None,
+    };
+
+    // Taken before the query so a change racing with it shows up next poll.
+    let server_time = db::change_timestamp();
+    let result = state.conn().and_then(|db| match &since {
+        Some(since) => db::list_sources_changed_since(&db, since),
+        None => db::list_sources(&db),
+    });
+    match result {
+        Ok(sources) => (
+            StatusCode::OK,
+            Json(SourceListResponse {
+                sources,
+                server_time,
+            }),
+        )
+            .into_response(),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(SourceResponse {
@@ -49,21 +136,49 @@ async fn list_sources(State(state): State<AppState>) -> impl IntoResponse {
     }
 }
 
-#[utoipa::path(post, path = "/api/sources", request_body = db::CreateSource, responses((status = 201, body = SourceResponse)))]
+/// 403 for hosts outside `DESTINATION_HOST_ALLOWLIST`, 400 for anything
+/// else that stops a source from being saved.
+fn save_error_status(e: &anyhow::Error) -> StatusCode {
+    if e.is::<HostNotAllowed>() {
+        StatusCode::FORBIDDEN
+    } else {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+#[utoipa::path(post, path = "/api/sources", request_body = db::CreateSource, responses((status = 201, body = SourceResponse), (status = 403, body = SourceResponse)))]
 async fn create_source(
     State(state): State<AppState>,
     Json(body): Json<db::CreateSource>,
 ) -> impl IntoResponse {
     let (id, source) = {
-        let db = state.db.lock().unwrap();
-        match db::create_source(&db, &body) {
+        let db = match state.conn() {
+            Ok(db) => db,
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(SourceResponse {
+                        status: "error".into(),
+                        message: e.to_string(),
+                        source: None,
+                    }),
+                )
+                    .into_response();
+            }
+        };
+        let created = state
+            .host_allowlist
+            .check("caldav_url", &body.caldav_url)
+            .map_err(anyhow::Error::from)
+            .and_then(|()| db::create_source(&db, &body));
+        match created {
             Ok(id) => {
                 let source = db::get_source(&db, id).ok().flatten();
                 (id, source)
             }
             Err(e) => {
                 return (
-                    StatusCode::BAD_REQUEST,
+                    save_error_status(&e),
                     Json(SourceResponse {
                         status: "error".into(),
                         message: e.to_string(),
@@ -90,15 +205,34 @@ async fn create_source(
         .into_response()
 }
 
-#[utoipa::path(put, path = "/api/sources/{id}", request_body = db::UpdateSource, responses((status = 200, body = SourceResponse)))]
+#[utoipa::path(put, path = "/api/sources/{id}", request_body = db::UpdateSource, responses((status = 200, body = SourceResponse), (status = 403, body = SourceResponse)))]
 async fn update_source(
     State(state): State<AppState>,
     Path(id): Path<i64>,
     Json(body): Json<db::UpdateSource>,
 ) -> impl IntoResponse {
     let source = {
-        let db = state.db.lock().unwrap();
-        match db::update_source(&db, id, &body) {
+        let db = match state.conn() {
+            Ok(db) => db,
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(SourceResponse {
+                        status: "error".into(),
+                        message: e.to_string(),
+                        source: None,
+                    }),
+                )
+                    .into_response();
+            }
+        };
+        let updated = body
+            .caldav_url
+            .as_deref()
+            .map_or(Ok(()), |url| state.host_allowlist.check("caldav_url", url))
+            .map_err(anyhow::Error::from)
+            .and_then(|()| db::update_source(&db, id, &body));
+        match updated {
             Ok(true) => db::get_source(&db, id).ok().flatten(),
             Ok(false) => {
                 return (
@@ -113,7 +247,7 @@ async fn update_source(
             }
             Err(e) => {
                 return (
-                    StatusCode::BAD_REQUEST,
+                    save_error_status(&e),
                     Json(SourceResponse {
                         status: "error".into(),
                         message: e.to_string(),
@@ -140,29 +274,103 @@ async fn update_source(
         .into_response()
 }
 
-#[utoipa::path(delete, path = "/api/sources/{id}", responses((status = 200, body = SourceResponse)))]
-async fn delete_source_handler(
+#[utoipa::path(post, path = "/api/sources/static", request_body = db::CreateStaticSource, responses((status = 201, body = SourceResponse)))]
+async fn create_static_source(
     State(state): State<AppState>,
-    Path(id): Path<i64>,
+    Json(body): Json<db::CreateStaticSource>,
 ) -> impl IntoResponse {
-    let result = {
-        let db = state.db.lock().unwrap();
-        db::delete_source(&db, id)
-    };
+    let result = state.conn().and_then(|db| {
+        let id = db::create_static_source(&db, &body)?;
+        Ok((id, db::get_source(&db, id)?))
+    });
+    match result {
+        Ok((id, source)) => (
+            StatusCode::CREATED,
+            Json(SourceResponse {
+                status: "success".into(),
+                message: format!("Static source created with id {}", id),
+                source,
+            }),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(SourceResponse {
+                status: "error".into(),
+                message: e.to_string(),
+                source: None,
+            }),
+        ),
+    }
+}
 
+#[utoipa::path(post, path = "/api/sources/subscription", request_body = db::CreateSubscriptionSource, responses((status = 201, body = SourceResponse), (status = 403, body = SourceResponse)))]
+async fn create_subscription_source(
+    State(state): State<AppState>,
+    Json(body): Json<db::CreateSubscriptionSource>,
+) -> impl IntoResponse {
+    let result = state.conn().and_then(|db| {
+        state.host_allowlist.check("ics_url", &body.ics_url)?;
+        let id = db::create_subscription_source(&db, &body)?;
+        Ok((id, db::get_source(&db, id)?))
+    });
     match result {
-        Ok(true) => {
-            auto_sync::cancel(&state.sync_tasks, &AutoSyncKey::Source(id));
+        Ok((id, source)) => {
+            if let Some(ref s) = source {
+                auto_sync::register_source(&state.sync_tasks, &state, s);
+            }
             (
-                StatusCode::OK,
+                StatusCode::CREATED,
                 Json(SourceResponse {
                     status: "success".into(),
-                    message: "Source deleted".into(),
-                    source: None,
+                    message: format!("Subscription source created with id {}", id),
+                    source,
                 }),
             )
-                .into_response()
         }
+        Err(e) => (
+            save_error_status(&e),
+            Json(SourceResponse {
+                status: "error".into(),
+                message: e.to_string(),
+                source: None,
+            }),
+        ),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/sources/{id}/ics",
+    request_body(content = String, content_type = "text/calendar"),
+    responses((status = 200, body = SourceResponse))
+)]
+async fn replace_source_ics(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    body: String,
+) -> impl IntoResponse {
+    let db = match state.conn() {
+        Ok(db) => db,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SourceResponse {
+                    status: "error".into(),
+                    message: e.to_string(),
+                    source: None,
+                }),
+            );
+        }
+    };
+    match db::replace_static_ics(&db, id, &body) {
+        Ok(true) => (
+            StatusCode::OK,
+            Json(SourceResponse {
+                status: "success".into(),
+                message: "ICS content replaced".into(),
+                source: db::get_source(&db, id).ok().flatten(),
+            }),
+        ),
         Ok(false) => (
             StatusCode::NOT_FOUND,
             Json(SourceResponse {
@@ -170,8 +378,44 @@ async fn delete_source_handler(
                 message: "Source not found".into(),
                 source: None,
             }),
-        )
-            .into_response(),
+        ),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(SourceResponse {
+                status: "error".into(),
+                message: e.to_string(),
+                source: None,
+            }),
+        ),
+    }
+}
+
+#[utoipa::path(delete, path = "/api/sources/{id}/ics", responses((status = 200, body = SourceResponse)))]
+async fn clear_source_ics(State(state): State<AppState>, Path(id): Path<i64>) -> impl IntoResponse {
+    let result = state.conn().and_then(|db| {
+        let source = db::get_source(&db, id)?;
+        if source.is_some() {
+            db::clear_ics_data(&db, id)?;
+        }
+        Ok(source)
+    });
+    match result {
+        Ok(Some(source)) => (
+            StatusCode::OK,
+            Json(SourceResponse {
+                status: "success".into(),
+                message: "Served ICS cleared until the next sync".into(),
+                source: Some(source),
+            }),
+        ),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(SourceResponse {
+                status: "error".into(),
+                message: "Source not found".into(),
+                source: None,
+            }),
+        ),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(SourceResponse {
@@ -179,37 +423,51 @@ async fn delete_source_handler(
                 message: e.to_string(),
                 source: None,
             }),
-        )
-            .into_response(),
+        ),
     }
 }
 
-#[utoipa::path(post, path = "/api/sources/{id}/sync", responses((status = 200, body = SyncResult)))]
-async fn sync_source(State(state): State<AppState>, Path(id): Path<i64>) -> impl IntoResponse {
-    let (caldav_url, username, password) = {
-        let db = state.db.lock().unwrap();
-        match db::get_source(&db, id) {
-            Ok(Some(s)) => (s.caldav_url, s.username, s.password),
+#[utoipa::path(post, path = "/api/sources/{id}/duplicate", request_body = db::DuplicateSource, responses((status = 201, body = SourceResponse)))]
+async fn duplicate_source(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(body): Json<db::DuplicateSource>,
+) -> impl IntoResponse {
+    let (new_id, source) = {
+        let db = match state.conn() {
+            Ok(db) => db,
+            Err(e) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(SourceResponse {
+                        status: "error".into(),
+                        message: e.to_string(),
+                        source: None,
+                    }),
+                )
+                    .into_response();
+            }
+        };
+        match db::duplicate_source(&db, id, &body) {
+            Ok(Some(new_id)) => (new_id, db::get_source(&db, new_id).ok().flatten()),
             Ok(None) => {
                 return (
                     StatusCode::NOT_FOUND,
-                    Json(SyncResult {
+                    Json(SourceResponse {
                         status: "error".into(),
                         message: "Source not found".into(),
-                        events: 0,
-                        calendars: 0,
+                        source: None,
                     }),
                 )
                     .into_response();
             }
             Err(e) => {
                 return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(SyncResult {
+                    StatusCode::BAD_REQUEST,
+                    Json(SourceResponse {
                         status: "error".into(),
                         message: e.to_string(),
-                        events: 0,
-                        calendars: 0,
+                        source: None,
                     }),
                 )
                     .into_response();
@@ -217,52 +475,420 @@ async fn sync_source(State(state): State<AppState>, Path(id): Path<i64>) -> impl
         }
     };
 
-    match crate::api::sync::run_sync(&caldav_url, &username, &password).await {
-        Ok((events, calendars, ics_data)) => {
-            let db = state.db.lock().unwrap();
-            if let Err(e) = db::save_ics_data(&db, id, &ics_data) {
-                tracing::error!("Failed to save ICS data: {}", e);
-            }
-            if let Err(e) = db::update_last_synced(&db, id) {
-                tracing::error!("Failed to update last_synced: {}", e);
-            }
-            let _ = db::update_sync_status(&db, id, "ok", None);
+    if let Some(ref s) = source {
+        auto_sync::register_source(&state.sync_tasks, &state, s);
+    }
+
+    (
+        StatusCode::CREATED,
+        Json(SourceResponse {
+            status: "success".into(),
+            message: format!("Source {} duplicated as id {}", id, new_id),
+            source,
+        }),
+    )
+        .into_response()
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SignUrlRequest {
+    /// Lifetime of the link; omitted, it never expires.
+    pub expires_in_secs: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SignedUrlResponse {
+    status: String,
+    message: String,
+    /// Server-relative URL of the signed public feed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<String>,
+}
+
+impl SignedUrlResponse {
+    fn error(message: String) -> Self {
+        Self {
+            status: "error".into(),
+            message,
+            url: None,
+            expires_at: None,
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/sources/{id}/sign-url",
+    params(("id" = i64, Path, description = "Source ID")),
+    request_body = SignUrlRequest,
+    responses(
+        (status = 200, body = SignedUrlResponse),
+        (status = 400, description = "The source is not published behind signed links", body = SignedUrlResponse),
+        (status = 404, body = SignedUrlResponse),
+        (status = 503, description = "PUBLIC_URL_SECRET is not set", body = SignedUrlResponse),
+    )
+)]
+async fn sign_source_url(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(body): Json<SignUrlRequest>,
+) -> impl IntoResponse {
+    let Some(secret) = state.public_url_secret.as_deref() else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(SignedUrlResponse::error(
+                "Signed links need PUBLIC_URL_SECRET to be set".into(),
+            )),
+        );
+    };
+    let source = match state.conn().and_then(|db| db::get_source(&db, id)) {
+        Ok(Some(source)) => source,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(SignedUrlResponse::error("Source not found".into())),
+            );
+        }
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SignedUrlResponse::error(e.to_string())),
+            );
+        }
+    };
+    let public_path = source
+        .public_ics_path
+        .as_deref()
+        .filter(|path| source.public_ics && !path.is_empty());
+    let Some(public_path) = public_path.filter(|_| source.signed_public_urls) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(SignedUrlResponse::error(
+                "Source needs public_ics, a public_ics_path and signed_public_urls".into(),
+            )),
+        );
+    };
+    if body.expires_in_secs.is_some_and(|secs| secs <= 0) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(SignedUrlResponse::error(
+                "expires_in_secs must be positive".into(),
+            )),
+        );
+    }
+
+    let expires = body
+        .expires_in_secs
+        .map(|secs| chrono::Utc::now() + chrono::Duration::seconds(secs));
+    let exp = expires.map(|at| at.timestamp());
+    let sig = signed_url::sign(secret, public_path, exp);
+    let url = match exp {
+        Some(exp) => format!("/ics/public/{}?sig={}&exp={}", public_path, sig, exp),
+        None => format!("/ics/public/{}?sig={}", public_path, sig),
+    };
+    (
+        StatusCode::OK,
+        Json(SignedUrlResponse {
+            status: "success".into(),
+            message: format!("Signed link for source {}", id),
+            url: Some(url),
+            expires_at: expires.map(|at| at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+        }),
+    )
+}
+
+#[utoipa::path(delete, path = "/api/sources/{id}", responses((status = 200, body = SourceResponse)))]
+async fn delete_source_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    let result = state.conn().and_then(|db| db::delete_source(&db, id));
+
+    match result {
+        Ok(true) => {
+            auto_sync::cancel(&state.sync_tasks, &AutoSyncKey::Source(id));
             (
                 StatusCode::OK,
-                Json(SyncResult {
+                Json(SourceResponse {
                     status: "success".into(),
-                    message: format!(
-                        "Synchronized {} events from {} calendars",
-                        events, calendars
-                    ),
-                    events,
-                    calendars,
+                    message: "Source deleted".into(),
+                    source: None,
                 }),
             )
                 .into_response()
         }
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(SourceResponse {
+                status: "error".into(),
+                message: "Source not found".into(),
+                source: None,
+            }),
+        )
+            .into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(SourceResponse {
+                status: "error".into(),
+                message: e.to_string(),
+                source: None,
+            }),
+        )
+            .into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/sources/{id}/sync",
+    params(("timings" = Option<bool>, Query, description = "Include a per-phase timing breakdown in milliseconds")),
+    responses(
+        (status = 200, body = SyncResult),
+        (status = 409, description = "Source is disabled", body = SyncResult),
+        (status = 504, description = "Sync exceeded MANUAL_SYNC_TIMEOUT_SECS", body = SyncResult)
+    )
+)]
+async fn sync_source(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(q): Query<SourceSyncQuery>,
+) -> impl IntoResponse {
+    let source = match state.conn().and_then(|db| db::get_source(&db, id)) {
+        Ok(Some(s)) => s,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(SyncResult::error("Source not found".into())),
+            )
+                .into_response();
+        }
         Err(e) => {
-            tracing::error!("Sync error for source {}: {}", id, e);
-            let db = state.db.lock().unwrap();
-            let _ = db::update_sync_status(&db, id, "error", Some(&e.to_string()));
-            (
+            return (
                 StatusCode::INTERNAL_SERVER_ERROR,
+                Json(SyncResult::error(e.to_string())),
+            )
+                .into_response();
+        }
+    };
+
+    if source.kind == db::SourceKind::Static {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(SyncResult::error("Static sources have no CalDAV server; upload new content with PUT /api/sources/{id}/ics".into())),
+        )
+            .into_response();
+    }
+
+    if !source.enabled {
+        return (
+            StatusCode::CONFLICT,
+            Json(SyncResult::error(
+                "Source is disabled; enable it before syncing".into(),
+            )),
+        )
+            .into_response();
+    }
+
+    let _guard = state.active_syncs.start();
+    let _host = state.host_locks.acquire(&source.caldav_url).await;
+    let started_at = db::now_timestamp();
+    let sync_state = state
+        .conn()
+        .and_then(|db| db::get_calendar_sync_state(&db, id))
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to load sync state for source {}: {}", id, e);
+            Default::default()
+        });
+    let options = SyncOptions::from(&source)
+        .with_http(&state.http)
+        .with_sync_state(sync_state);
+    let sync = crate::api::sync::run_source_sync(&source, &options);
+    match with_timeout(state.manual_sync_timeout, sync).await {
+        Ok(SyncOutput {
+            events,
+            calendars,
+            ics: ics_data,
+            calendar_ics,
+            trimmed,
+            sync_state,
+            resolved_url,
+            mut timings,
+        }) => {
+            let db_started = std::time::Instant::now();
+            let saved = state.conn().and_then(|db| {
+                if keeps_previous_feed(&db, &source, events).unwrap_or(false) {
+                    let _ =
+                        db::update_sync_status(&db, id, SKIPPED_EMPTY, Some(SKIPPED_EMPTY_MESSAGE));
+                    let _ = db::record_sync_history(
+                        &db,
+                        id,
+                        &started_at,
+                        SKIPPED_EMPTY,
+                        0,
+                        Some(SKIPPED_EMPTY_MESSAGE),
+                    );
+                    return Ok(true);
+                }
+                // The feed, status and history land together or not at all.
+                db::with_transaction(&db, |tx| {
+                    db::save_ics_data_with(tx, id, &ics_data, state.compress_ics_storage)?;
+                    db::save_calendar_ics(tx, id, &calendar_ics)?;
+                    db::update_last_synced(tx, id)?;
+                    db::update_sync_status(tx, id, "ok", None)?;
+                    db::update_trimmed_events(tx, id, trimmed)?;
+                    if let Some(url) = &resolved_url {
+                        db::update_resolved_caldav_url(tx, id, url)?;
+                    }
+                    let _ = db::record_sync_history(tx, id, &started_at, "ok", events, None);
+                    Ok(())
+                })?;
+                if let Err(e) =
+                    db::save_calendar_sync_state(&db, id, &sync_state, state.db_batch_size)
+                {
+                    tracing::error!("Failed to save sync state: {}", e);
+                }
+                Ok(false)
+            });
+            let kept_previous = match saved {
+                Ok(kept_previous) => kept_previous,
+                Err(e) => {
+                    tracing::error!("Failed to save sync result for source {}: {}", id, e);
+                    let message = format!("Failed to save sync result: {}", e);
+                    if let Ok(db) = state.conn() {
+                        let _ = db::update_sync_status(&db, id, "error", Some(&message));
+                        let _ = db::record_sync_history(
+                            &db,
+                            id,
+                            &started_at,
+                            "error",
+                            0,
+                            Some(&message),
+                        );
+                    }
+                    webhooks::notify_source(&state.webhooks, &state.events, &source, Err(&message));
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(SyncResult::error(message)),
+                    )
+                        .into_response();
+                }
+            };
+            timings.record_db_write(db_started.elapsed());
+            webhooks::notify_source(
+                &state.webhooks,
+                &state.events,
+                &source,
+                Ok((events, calendars)),
+            );
+            (
+                StatusCode::OK,
                 Json(SyncResult {
-                    status: "error".into(),
-                    message: e.to_string(),
-                    events: 0,
-                    calendars: 0,
+                    status: "success".into(),
+                    message: if kept_previous {
+                        SKIPPED_EMPTY_MESSAGE.to_string()
+                    } else {
+                        format!(
+                            "Synchronized {} events from {} calendars",
+                            events, calendars
+                        )
+                    },
+                    events,
+                    calendars,
+                    trimmed,
+                    timings: q.timings.then_some(timings),
                 }),
             )
                 .into_response()
         }
+        Err(e) => {
+            tracing::error!("Sync error for source {}: {}", id, e);
+            if let Ok(db) = state.conn() {
+                let _ = db::update_sync_status(&db, id, "error", Some(&e.to_string()));
+                let _ =
+                    db::record_sync_history(&db, id, &started_at, "error", 0, Some(&e.to_string()));
+            }
+            webhooks::notify_source(&state.webhooks, &state.events, &source, Err(&e.to_string()));
+            let code = if e.is::<SyncTimeout>() {
+                StatusCode::GATEWAY_TIMEOUT
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            (code, Json(SyncResult::error(e.to_string()))).into_response()
+        }
+    }
+}
+
+/// Connection settings for a one-off preview; nothing is stored.
+#[derive(Deserialize, ToSchema)]
+pub struct PreviewSource {
+    pub caldav_url: String,
+    pub username: String,
+    pub password: String,
+    /// Only include calendars whose href or display name contains this text.
+    #[serde(default)]
+    pub calendar_filter: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/sources/preview",
+    request_body = PreviewSource,
+    responses(
+        (status = 200, description = "The merged feed", content_type = "text/calendar", body = String),
+        (status = 403, description = "Host not allowed by DESTINATION_HOST_ALLOWLIST", body = SyncResult),
+        (status = 413, description = "Feed exceeds MAX_ICS_BYTES", body = SyncResult),
+        (status = 504, description = "Sync exceeded MANUAL_SYNC_TIMEOUT_SECS", body = SyncResult)
+    )
+)]
+async fn preview_source(
+    State(state): State<AppState>,
+    Json(req): Json<PreviewSource>,
+) -> impl IntoResponse {
+    let error = |code: StatusCode, message: String| {
+        (code, Json(SyncResult::error(message))).into_response()
+    };
+    if req.caldav_url.trim().is_empty() {
+        return error(StatusCode::BAD_REQUEST, "CalDAV URL is required".into());
+    }
+    if let Err(e) = state.host_allowlist.check("caldav_url", &req.caldav_url) {
+        return error(StatusCode::FORBIDDEN, e.to_string());
+    }
+
+    let _guard = state.active_syncs.start();
+    let options = SyncOptions {
+        calendar_filter: req.calendar_filter.filter(|f| !f.trim().is_empty()),
+        ..Default::default()
+    }
+    .with_http(&state.http);
+    let sync = crate::api::sync::run_sync(&req.caldav_url, &req.username, &req.password, &options);
+    match with_timeout(state.manual_sync_timeout, sync).await {
+        Ok(output) if output.ics.len() > MAX_ICS_BYTES => error(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!(
+                "Preview is {} bytes, over the {} byte limit; narrow it with calendar_filter",
+                output.ics.len(),
+                MAX_ICS_BYTES
+            ),
+        ),
+        Ok(output) => ([(header::CONTENT_TYPE, "text/calendar")], output.ics).into_response(),
+        Err(e) => {
+            // The request carries credentials, so only the error is logged.
+            tracing::warn!("Source preview failed: {}", e);
+            let code = if e.is::<SyncTimeout>() {
+                StatusCode::GATEWAY_TIMEOUT
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            error(code, e.to_string())
+        }
     }
 }
 
 #[utoipa::path(get, path = "/api/sources/{id}/status", responses((status = 200, body = SourceResponse)))]
 async fn source_status(State(state): State<AppState>, Path(id): Path<i64>) -> impl IntoResponse {
-    let db = state.db.lock().unwrap();
-    match db::get_source(&db, id) {
+    match state.conn().and_then(|db| db::get_source(&db, id)) {
         Ok(Some(s)) => (
             StatusCode::OK,
             Json(SourceResponse {
@@ -296,13 +922,183 @@ async fn source_status(State(state): State<AppState>, Path(id): Path<i64>) -> im
     }
 }
 
+/// Values accepted by `POST /api/sources/{id}/status`.
+pub const SETTABLE_SYNC_STATUSES: [&str; 4] = ["ok", "error", SKIPPED_EMPTY, "maintenance"];
+
+/// Overrides a source's recorded sync status without syncing.
+#[derive(Deserialize, ToSchema)]
+pub struct SetSyncStatus {
+    /// One of `ok`, `error`, `skipped_empty` or `maintenance`.
+    pub status: String,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/sources/{id}/status",
+    params(("id" = i64, Path, description = "Source ID")),
+    request_body = SetSyncStatus,
+    responses(
+        (status = 200, body = SourceResponse),
+        (status = 400, description = "Unknown status value", body = SourceResponse),
+        (status = 404, body = SourceResponse)
+    )
+)]
+async fn set_source_status(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(req): Json<SetSyncStatus>,
+) -> impl IntoResponse {
+    let error = |code: StatusCode, message: String| {
+        (
+            code,
+            Json(SourceResponse {
+                status: "error".into(),
+                message,
+                source: None,
+            }),
+        )
+            .into_response()
+    };
+    if !SETTABLE_SYNC_STATUSES.contains(&req.status.as_str()) {
+        return error(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Unknown status '{}'; expected one of: {}",
+                req.status,
+                SETTABLE_SYNC_STATUSES.join(", ")
+            ),
+        );
+    }
+    let db = match state.conn() {
+        Ok(db) => db,
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    match db::get_source(&db, id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return error(StatusCode::NOT_FOUND, "Source not found".into()),
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+    let message = req.error.as_deref().filter(|e| !e.trim().is_empty());
+    if let Err(e) = db::update_sync_status(&db, id, &req.status, message) {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    }
+    tracing::info!(
+        "Sync status of source {} set to '{}' via API",
+        id,
+        req.status
+    );
+    match db::get_source(&db, id) {
+        Ok(source) => (
+            StatusCode::OK,
+            Json(SourceResponse {
+                status: "success".into(),
+                message: format!("Sync status set to '{}'", req.status),
+                source,
+            }),
+        )
+            .into_response(),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sources/{id}/calendars",
+    params(("id" = i64, Path, description = "Source ID")),
+    responses((status = 200, body = CalendarListResponse))
+)]
+async fn source_calendars(State(state): State<AppState>, Path(id): Path<i64>) -> impl IntoResponse {
+    let source = match state.conn().and_then(|db| db::get_source(&db, id)) {
+        Ok(Some(s)) => s,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(CalendarListResponse {
+                    status: "error".into(),
+                    message: "Source not found".into(),
+                    calendars: vec![],
+                }),
+            )
+                .into_response();
+        }
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(CalendarListResponse {
+                    status: "error".into(),
+                    message: e.to_string(),
+                    calendars: vec![],
+                }),
+            )
+                .into_response();
+        }
+    };
+
+    let result = match CaldavClient::connect(
+        Some(&state.http),
+        &source.username,
+        &source.password,
+        source.allow_invalid_certs,
+    ) {
+        Ok(client) => crate::api::sync::list_calendars(
+            &client
+                .with_debug(source.debug_http)
+                .with_auth_scope(&source.caldav_url),
+            &source.caldav_url,
+            source.auto_discover,
+        )
+        .await
+        .map_err(crate::api::sync::explain_connect_error),
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(calendars) => (
+            StatusCode::OK,
+            Json(CalendarListResponse {
+                status: "success".into(),
+                message: format!("Found {} calendars", calendars.len()),
+                calendars,
+            }),
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("Calendar discovery failed for source {}: {}", id, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(CalendarListResponse {
+                    status: "error".into(),
+                    message: e.to_string(),
+                    calendars: vec![],
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/sources", get(list_sources).post(create_source))
+        .route("/sources/static", post(create_static_source))
+        .route("/sources/subscription", post(create_subscription_source))
+        .route("/sources/preview", post(preview_source))
         .route(
             "/sources/{id}",
             put(update_source).delete(delete_source_handler),
         )
+        .route("/sources/{id}/duplicate", post(duplicate_source))
+        .route("/sources/{id}/sign-url", post(sign_source_url))
         .route("/sources/{id}/sync", post(sync_source))
-        .route("/sources/{id}/status", get(source_status))
+        .route(
+            "/sources/{id}/ics",
+            put(replace_source_ics).delete(clear_source_ics),
+        )
+        .route(
+            "/sources/{id}/status",
+            get(source_status).post(set_source_status),
+        )
+        .route("/sources/{id}/calendars", get(source_calendars))
 }
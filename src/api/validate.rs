@@ -0,0 +1,189 @@
+use axum::{Json, Router, http::StatusCode, response::IntoResponse, routing::post};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::AppState;
+use crate::api::reverse_sync::{MAX_LINE_OCTETS, extract_events, unfold_ics};
+use crate::cron::CronSchedule;
+
+#[derive(Serialize, ToSchema)]
+pub struct IcsValidationResponse {
+    valid: bool,
+    events: usize,
+    issues: Vec<String>,
+}
+
+/// Fire times returned when the request does not ask for a count, and the
+/// most it may ask for.
+const DEFAULT_CRON_RUNS: usize = 5;
+const MAX_CRON_RUNS: usize = 50;
+
+#[derive(Deserialize, ToSchema)]
+pub struct CronValidationRequest {
+    expression: String,
+    count: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CronValidationResponse {
+    valid: bool,
+    /// Upcoming fire times in the server's local timezone (RFC 3339).
+    next_runs: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+pub fn validate_ics(text: &str) -> Vec<String> {
+    let mut issues = Vec::new();
+
+    if text.trim().is_empty() {
+        issues.push("Content is empty".to_string());
+        return issues;
+    }
+
+    let bare_lf = text
+        .match_indices('\n')
+        .filter(|(i, _)| *i == 0 || text.as_bytes()[i - 1] != b'\r')
+        .count();
+    if bare_lf > 0 {
+        issues.push(format!("{} line(s) end with LF instead of CRLF", bare_lf));
+    }
+
+    for (i, line) in text.lines().enumerate() {
+        if i == 0 && (line.starts_with(' ') || line.starts_with('\t')) {
+            issues.push("First line is a continuation line with nothing to fold onto".to_string());
+        }
+        if line.len() > MAX_LINE_OCTETS {
+            issues.push(format!(
+                "Line {} is {} octets long and should be folded at {}",
+                i + 1,
+                line.len(),
+                MAX_LINE_OCTETS
+            ));
+        }
+    }
+
+    let unfolded = unfold_ics(text);
+    let mut stack: Vec<String> = Vec::new();
+    let mut has_version = false;
+    let mut vevent_count = 0;
+    let mut has_uid = false;
+    let mut has_dtstart = false;
+
+    for line in unfolded.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let Some((name, value)) = line.split_once(':') else {
+            issues.push(format!("Line without a ':' separator: {}", line));
+            continue;
+        };
+        let prop = name.split(';').next().unwrap_or("");
+        match prop {
+            "BEGIN" => {
+                if value == "VEVENT" {
+                    vevent_count += 1;
+                    has_uid = false;
+                    has_dtstart = false;
+                }
+                stack.push(value.to_string());
+            }
+            "END" => match stack.pop() {
+                Some(open) if open == value => {
+                    if value == "VEVENT" {
+                        if !has_uid {
+                            issues.push(format!("VEVENT #{} has no UID", vevent_count));
+                        }
+                        if !has_dtstart {
+                            issues.push(format!("VEVENT #{} has no DTSTART", vevent_count));
+                        }
+                    }
+                }
+                Some(open) => {
+                    issues.push(format!("END:{} does not match BEGIN:{}", value, open));
+                }
+                None => issues.push(format!("END:{} without a matching BEGIN", value)),
+            },
+            "VERSION" if stack.len() == 1 && stack[0] == "VCALENDAR" => has_version = true,
+            "UID" if stack.last().is_some_and(|c| c == "VEVENT") => has_uid = true,
+            "DTSTART" if stack.last().is_some_and(|c| c == "VEVENT") => has_dtstart = true,
+            _ => {}
+        }
+    }
+
+    for open in stack.iter().rev() {
+        issues.push(format!("BEGIN:{} is never closed", open));
+    }
+    if !unfolded.trim_start().starts_with("BEGIN:VCALENDAR") {
+        issues.push("Content does not start with BEGIN:VCALENDAR".to_string());
+    }
+    if !has_version {
+        issues.push("VCALENDAR is missing VERSION".to_string());
+    }
+
+    issues
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/validate-ics",
+    request_body(content = String, content_type = "text/calendar"),
+    responses((status = 200, body = IcsValidationResponse))
+)]
+pub async fn validate_ics_handler(body: String) -> impl IntoResponse {
+    let issues = validate_ics(&body);
+    let events = extract_events(&body).events.values().map(Vec::len).sum();
+    (
+        StatusCode::OK,
+        Json(IcsValidationResponse {
+            valid: issues.is_empty(),
+            events,
+            issues,
+        }),
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/validate-cron",
+    request_body = CronValidationRequest,
+    responses(
+        (status = 200, body = CronValidationResponse),
+        (status = 400, body = CronValidationResponse)
+    )
+)]
+pub async fn validate_cron_handler(Json(body): Json<CronValidationRequest>) -> impl IntoResponse {
+    let schedule = match CronSchedule::parse(&body.expression) {
+        Ok(s) => s,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(CronValidationResponse {
+                    valid: false,
+                    next_runs: vec![],
+                    error: Some(e.to_string()),
+                }),
+            );
+        }
+    };
+    let count = body
+        .count
+        .unwrap_or(DEFAULT_CRON_RUNS)
+        .clamp(1, MAX_CRON_RUNS);
+    let next_runs = schedule
+        .upcoming(&chrono::Local::now(), count)
+        .iter()
+        .map(|t| t.to_rfc3339())
+        .collect();
+    (
+        StatusCode::OK,
+        Json(CronValidationResponse {
+            valid: true,
+            next_runs,
+            error: None,
+        }),
+    )
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/validate-ics", post(validate_ics_handler))
+        .route("/validate-cron", post(validate_cron_handler))
+}
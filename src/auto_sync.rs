@@ -1,20 +1,27 @@
 use std::collections::HashMap;
 use std::future::Future;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+use tokio::sync::Notify;
 use tokio::task::AbortHandle;
 use tokio_retry2::strategy::ExponentialBackoff;
 use tokio_retry2::{Retry, RetryError};
 use tracing::info;
 
 use crate::api::AppState;
+use crate::api::reverse_sync::{QuotaExceeded, ReverseSyncOptions, SuspiciousShrink};
+use crate::api::sync::{
+    SKIPPED_EMPTY, SKIPPED_EMPTY_MESSAGE, SyncOptions, SyncOutput, keeps_previous_feed,
+};
+use crate::cron::CronSchedule;
 use crate::db;
+use crate::webhooks;
 
-const RETRY_BASE_MS: u64 = 30_000;
-const RETRY_MAX_MS: u64 = 300_000;
-const MAX_RETRIES: usize = 5;
+pub const RETRY_BASE_MS: u64 = 30_000;
+pub const RETRY_MAX_MS: u64 = 300_000;
+pub const MAX_RETRIES: usize = 5;
 
 static GENERATION: AtomicU64 = AtomicU64::new(0);
 
@@ -22,6 +29,83 @@ fn next_generation() -> u64 {
     GENERATION.fetch_add(1, Ordering::Relaxed)
 }
 
+/// Counts in-flight sync runs so shutdown can wait for them to finish.
+#[derive(Clone, Default)]
+pub struct ActiveSyncs {
+    count: Arc<AtomicUsize>,
+    idle: Arc<Notify>,
+}
+
+/// Marks one sync run as in flight until dropped.
+pub struct ActiveSyncGuard(ActiveSyncs);
+
+impl ActiveSyncs {
+    pub fn start(&self) -> ActiveSyncGuard {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        ActiveSyncGuard(self.clone())
+    }
+
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    pub async fn wait_idle(&self) {
+        loop {
+            let notified = self.idle.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.count() == 0 {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+impl Drop for ActiveSyncGuard {
+    fn drop(&mut self) {
+        if self.0.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+/// Per-host locks so syncs against the same CalDAV server run one at a time.
+/// Disabled locks hand out no guard and never block.
+#[derive(Clone, Default)]
+pub struct HostLocks {
+    enabled: bool,
+    locks: Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>,
+}
+
+impl HostLocks {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            locks: Arc::default(),
+        }
+    }
+
+    /// Waits for exclusive access to `url`'s host. URLs that don't parse
+    /// are not serialized.
+    pub async fn acquire(&self, url: &str) -> Option<tokio::sync::OwnedMutexGuard<()>> {
+        if !self.enabled {
+            return None;
+        }
+        let parsed = reqwest::Url::parse(url).ok()?;
+        let key = format!(
+            "{}:{}",
+            parsed.host_str()?,
+            parsed.port_or_known_default().unwrap_or(0)
+        );
+        let lock = {
+            let mut map = self.locks.lock().ok()?;
+            Arc::clone(map.entry(key).or_default())
+        };
+        Some(lock.lock_owned().await)
+    }
+}
+
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
 pub enum AutoSyncKey {
     Source(i64),
@@ -60,15 +144,50 @@ fn try_remove(
     }
 }
 
-fn handle_sync_error(state: &AppState, key: &AutoSyncKey, msg: &str) -> bool {
-    let Ok(db) = state.db.lock() else {
-        tracing::error!("DB mutex poisoned, stopping auto-sync for {:?}", key);
-        return false;
+/// Records a failed run, with one history row however many attempts it
+/// took. `exhausted` carries the consecutive failure count when the retry
+/// budget was just used up for the first time in a row.
+fn handle_sync_error(
+    state: &AppState,
+    key: &AutoSyncKey,
+    started_at: &str,
+    msg: &str,
+    exhausted: Option<usize>,
+) -> bool {
+    let db = match state.conn() {
+        Ok(db) => db,
+        Err(e) => {
+            tracing::error!("DB pool unavailable recording error for {:?}: {}", key, e);
+            return true;
+        }
     };
     match key {
         AutoSyncKey::Source(id) => match db::get_source(&db, *id) {
-            Ok(Some(_)) => {
+            Ok(Some(source)) => {
                 let _ = db::update_sync_status(&db, *id, "error", Some(msg));
+                let _ = db::record_sync_history(&db, *id, started_at, "error", 0, Some(msg));
+                webhooks::notify_source(&state.webhooks, &state.events, &source, Err(msg));
+                if let Some(failures) = exhausted {
+                    webhooks::notify_retries_exhausted(
+                        &state.webhooks,
+                        &state.events,
+                        ("source", source.id, &source.name),
+                        failures,
+                        msg,
+                    );
+                }
+                // The row was read before this failure was counted.
+                let failed_runs = source.consecutive_failures + 1;
+                let threshold = i64::from(state.auto_disable_after_failures);
+                if threshold > 0 && failed_runs >= threshold {
+                    tracing::warn!(
+                        "Source '{}' failed {} syncs in a row, disabling it",
+                        source.name,
+                        failed_runs
+                    );
+                    let _ = db::set_source_enabled(&db, *id, false);
+                    return false;
+                }
                 true
             }
             Ok(None) => {
@@ -81,8 +200,18 @@ fn handle_sync_error(state: &AppState, key: &AutoSyncKey, msg: &str) -> bool {
             }
         },
         AutoSyncKey::Destination(id) => match db::get_destination(&db, *id) {
-            Ok(Some(_)) => {
+            Ok(Some(dest)) => {
                 let _ = db::update_destination_sync_status(&db, *id, "error", Some(msg));
+                webhooks::notify_destination(&state.webhooks, &state.events, &dest, Err(msg));
+                if let Some(failures) = exhausted {
+                    webhooks::notify_retries_exhausted(
+                        &state.webhooks,
+                        &state.events,
+                        ("destination", dest.id, &dest.name),
+                        failures,
+                        msg,
+                    );
+                }
                 true
             }
             Ok(None) => {
@@ -101,10 +230,58 @@ fn handle_sync_error(state: &AppState, key: &AutoSyncKey, msg: &str) -> bool {
     }
 }
 
+/// When an auto-sync task runs: after every fixed interval, or at each fire
+/// time of a cron expression (evaluated in the server's local time).
+#[derive(Debug, Clone)]
+enum Schedule {
+    Every(Duration),
+    Cron(CronSchedule),
+}
+
+impl Schedule {
+    /// A cron expression takes precedence over the interval. Returns `None`
+    /// when neither is set, or the stored expression no longer parses.
+    fn new(interval_secs: i64, cron: Option<&str>, name: &str) -> Option<Self> {
+        match cron.map(str::trim).filter(|c| !c.is_empty()) {
+            Some(expr) => match CronSchedule::parse(expr) {
+                Ok(c) => Some(Schedule::Cron(c)),
+                Err(e) => {
+                    tracing::error!("Auto-sync disabled for '{}': {}", name, e);
+                    None
+                }
+            },
+            None => (interval_secs > 0)
+                .then(|| Schedule::Every(Duration::from_secs(interval_secs as u64))),
+        }
+    }
+
+    /// How long to wait before the next run, or `None` if the cron
+    /// expression has no future fire time.
+    fn delay(&self) -> Option<Duration> {
+        match self {
+            Schedule::Every(d) => Some(*d),
+            Schedule::Cron(c) => {
+                let now = chrono::Local::now();
+                let next = c.next_after(&now)?;
+                Some((next - now).to_std().unwrap_or_default())
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Schedule::Every(d) => write!(f, "every {}s", d.as_secs()),
+            Schedule::Cron(_) => write!(f, "on cron schedule"),
+        }
+    }
+}
+
 fn spawn_sync_task<F, Fut>(
     registry: &AutoSyncRegistry,
     key: AutoSyncKey,
-    interval_secs: u64,
+    schedule: Schedule,
     display_name: String,
     state: AppState,
     sync_fn: F,
@@ -116,17 +293,66 @@ fn spawn_sync_task<F, Fut>(
     let registry_ref = Arc::clone(registry);
     let key_clone = key.clone();
     let log_name = display_name.clone();
+    let log_schedule = schedule.to_string();
 
     let handle = tokio::spawn(async move {
+        // Failed attempts since the last success, and whether this run of
+        // failures has already been reported as exhausting the retries.
+        let mut consecutive_failures = 0;
+        let mut exhaustion_reported = false;
         loop {
+            // Interval tasks sync immediately and then wait; cron tasks wait
+            // for their first fire time.
+            if let Schedule::Cron(_) = schedule {
+                let Some(delay) = schedule.delay() else {
+                    tracing::warn!("Cron schedule for '{}' has no upcoming run", display_name);
+                    break;
+                };
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = state.shutdown.cancelled() => break,
+                }
+            }
             let strategy = ExponentialBackoff::from_millis(RETRY_BASE_MS)
                 .max_delay(Duration::from_millis(RETRY_MAX_MS))
                 .take(MAX_RETRIES);
+            let failed_attempts = AtomicUsize::new(0);
+            let transient_failures = AtomicUsize::new(0);
+            let started_at = db::now_timestamp();
+
+            let result = Retry::spawn(strategy, || {
+                let state = state.clone();
+                let sync_fn = &sync_fn;
+                let failed_attempts = &failed_attempts;
+                let transient_failures = &transient_failures;
+                async move {
+                    if state.shutdown.is_cancelled() {
+                        return Err(RetryError::permanent(anyhow::anyhow!("Shutting down")));
+                    }
+                    let _guard = state.active_syncs.start();
+                    let result = sync_fn(state.clone()).await;
+                    if let Err(e) = &result {
+                        failed_attempts.fetch_add(1, Ordering::Relaxed);
+                        if matches!(e, RetryError::Transient { .. }) {
+                            transient_failures.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                    result
+                }
+            })
+            .await;
+            consecutive_failures += failed_attempts.into_inner();
 
-            let result = Retry::spawn(strategy, || sync_fn(state.clone())).await;
+            if state.shutdown.is_cancelled() {
+                break;
+            }
 
             match result {
-                Ok(msg) => info!("{}", msg),
+                Ok(msg) => {
+                    info!("{}", msg);
+                    consecutive_failures = 0;
+                    exhaustion_reported = false;
+                }
                 Err(e) => {
                     let msg = e.to_string();
                     tracing::error!(
@@ -135,13 +361,21 @@ fn spawn_sync_task<F, Fut>(
                         MAX_RETRIES,
                         msg
                     );
-                    if !handle_sync_error(&state, &key_clone, &msg) {
+                    let exhausted = transient_failures.into_inner() > MAX_RETRIES
+                        && !std::mem::replace(&mut exhaustion_reported, true);
+                    let exhausted = exhausted.then_some(consecutive_failures);
+                    if !handle_sync_error(&state, &key_clone, &started_at, &msg, exhausted) {
                         break;
                     }
                 }
             }
 
-            tokio::time::sleep(Duration::from_secs(interval_secs)).await;
+            if let Schedule::Every(interval) = schedule {
+                tokio::select! {
+                    _ = tokio::time::sleep(interval) => {}
+                    _ = state.shutdown.cancelled() => break,
+                }
+            }
         }
         try_remove(&registry_ref, &key_clone, generation);
     });
@@ -153,17 +387,22 @@ fn spawn_sync_task<F, Fut>(
     };
     map.insert(key, (generation, handle.abort_handle()));
     drop(map);
-    info!(
-        "Auto-sync enabled for '{}' (every {}s)",
-        log_name, interval_secs
-    );
+    info!("Auto-sync enabled for '{}' ({})", log_name, log_schedule);
 }
 
 pub fn register_source(registry: &AutoSyncRegistry, state: &AppState, source: &db::Source) {
     let key = AutoSyncKey::Source(source.id);
     cancel(registry, &key);
 
-    if source.sync_interval_secs <= 0 {
+    let Some(schedule) = Schedule::new(
+        source.sync_interval_secs,
+        source.cron_schedule.as_deref(),
+        &source.name,
+    ) else {
+        return;
+    };
+    if !source.enabled {
+        info!("Auto-sync skipped for disabled source '{}'", source.name);
         return;
     }
 
@@ -171,14 +410,14 @@ pub fn register_source(registry: &AutoSyncRegistry, state: &AppState, source: &d
     spawn_sync_task(
         registry,
         key,
-        source.sync_interval_secs as u64,
+        schedule,
         source.name.clone(),
         state.clone(),
         move |state| async move {
-            let (url, user, pass) = {
-                let db = state.db.lock().unwrap();
+            let s = {
+                let db = state.conn().map_err(RetryError::transient)?;
                 match db::get_source(&db, id) {
-                    Ok(Some(s)) => (s.caldav_url, s.username, s.password),
+                    Ok(Some(s)) => s,
                     _ => {
                         return Err(RetryError::permanent(anyhow::anyhow!(
                             "Source {} no longer exists",
@@ -187,13 +426,69 @@ pub fn register_source(registry: &AutoSyncRegistry, state: &AppState, source: &d
                     }
                 }
             };
-            let (events, calendars, ics_data) = crate::api::sync::run_sync(&url, &user, &pass)
-                .await
+            let _host = state.host_locks.acquire(&s.caldav_url).await;
+            let started_at = db::now_timestamp();
+            let sync_state = state
+                .conn()
+                .and_then(|db| db::get_calendar_sync_state(&db, id))
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Failed to load sync state for source {}: {}", id, e);
+                    Default::default()
+                });
+            let SyncOutput {
+                events,
+                calendars,
+                ics: ics_data,
+                calendar_ics,
+                trimmed,
+                sync_state,
+                resolved_url,
+                ..
+            } = match crate::api::sync::run_source_sync(
+                &s,
+                &SyncOptions::from(&s)
+                    .with_http(&state.http)
+                    .with_sync_state(sync_state),
+            )
+            .await
+            {
+                Ok(r) => r,
+                // The failed run gets one history row, in handle_sync_error.
+                Err(e) => return Err(RetryError::transient(e)),
+            };
+            let db = state.conn().map_err(RetryError::transient)?;
+            if keeps_previous_feed(&db, &s, events).map_err(RetryError::transient)? {
+                db::update_sync_status(&db, id, SKIPPED_EMPTY, Some(SKIPPED_EMPTY_MESSAGE))
+                    .map_err(RetryError::transient)?;
+                let _ = db::record_sync_history(
+                    &db,
+                    id,
+                    &started_at,
+                    SKIPPED_EMPTY,
+                    0,
+                    Some(SKIPPED_EMPTY_MESSAGE),
+                );
+                return Ok(format!(
+                    "Auto-sync source {}: {}",
+                    id, SKIPPED_EMPTY_MESSAGE
+                ));
+            }
+            db::with_transaction(&db, |tx| {
+                db::save_ics_data_with(tx, id, &ics_data, state.compress_ics_storage)?;
+                db::save_calendar_ics(tx, id, &calendar_ics)?;
+                db::update_last_synced(tx, id)?;
+                db::update_sync_status(tx, id, "ok", None)?;
+                db::update_trimmed_events(tx, id, trimmed)?;
+                if let Some(url) = &resolved_url {
+                    db::update_resolved_caldav_url(tx, id, url)?;
+                }
+                let _ = db::record_sync_history(tx, id, &started_at, "ok", events, None);
+                Ok(())
+            })
+            .map_err(RetryError::transient)?;
+            db::save_calendar_sync_state(&db, id, &sync_state, state.db_batch_size)
                 .map_err(RetryError::transient)?;
-            let db = state.db.lock().unwrap();
-            db::save_ics_data(&db, id, &ics_data).map_err(RetryError::transient)?;
-            db::update_last_synced(&db, id).map_err(RetryError::transient)?;
-            db::update_sync_status(&db, id, "ok", None).map_err(RetryError::transient)?;
+            webhooks::notify_source(&state.webhooks, &state.events, &s, Ok((events, calendars)));
             Ok(format!(
                 "Auto-sync source {}: {} events from {} calendars",
                 id, events, calendars
@@ -206,21 +501,25 @@ pub fn register_destination(registry: &AutoSyncRegistry, state: &AppState, dest:
     let key = AutoSyncKey::Destination(dest.id);
     cancel(registry, &key);
 
-    if dest.sync_interval_secs <= 0 {
+    let Some(schedule) = Schedule::new(
+        dest.sync_interval_secs,
+        dest.cron_schedule.as_deref(),
+        &dest.name,
+    ) else {
         return;
-    }
+    };
 
     let id = dest.id;
     spawn_sync_task(
         registry,
         key,
-        dest.sync_interval_secs as u64,
+        schedule,
         dest.name.clone(),
         state.clone(),
         move |state| async move {
-            let d = {
-                let db = state.db.lock().unwrap();
-                match db::get_destination(&db, id) {
+            let (d, known_hrefs) = {
+                let db = state.conn().map_err(RetryError::transient)?;
+                let d = match db::get_destination(&db, id) {
                     Ok(Some(d)) => d,
                     _ => {
                         return Err(RetryError::permanent(anyhow::anyhow!(
@@ -228,8 +527,15 @@ pub fn register_destination(registry: &AutoSyncRegistry, state: &AppState, dest:
                             id
                         )));
                     }
-                }
+                };
+                let known_hrefs = if d.uuid_hrefs {
+                    db::get_destination_hrefs(&db, id).map_err(RetryError::transient)?
+                } else {
+                    Default::default()
+                };
+                (d, known_hrefs)
             };
+            let _host = state.host_locks.acquire(&d.caldav_url).await;
             let stats = crate::api::reverse_sync::run_reverse_sync(
                 &d.ics_url,
                 &d.caldav_url,
@@ -238,40 +544,61 @@ pub fn register_destination(registry: &AutoSyncRegistry, state: &AppState, dest:
                 &d.password,
                 d.sync_all,
                 d.keep_local,
+                &ReverseSyncOptions {
+                    known_hrefs,
+                    ..ReverseSyncOptions::from(&d).with_http(&state.http)
+                },
             )
             .await
-            .map_err(RetryError::transient)?;
-            let db = state.db.lock().unwrap();
+            .map_err(|e| {
+                if e.is::<QuotaExceeded>() || e.is::<SuspiciousShrink>() {
+                    RetryError::permanent(e)
+                } else {
+                    RetryError::transient(e)
+                }
+            })?;
+            let db = state.conn().map_err(RetryError::transient)?;
             db::update_destination_sync_status(&db, id, "ok", None)
                 .map_err(RetryError::transient)?;
+            if stats.total > 0 {
+                db::update_destination_total_events(&db, id, stats.total)
+                    .map_err(RetryError::transient)?;
+            }
+            if let Some(hrefs) = &stats.hrefs {
+                db::save_destination_hrefs(&db, id, hrefs).map_err(RetryError::transient)?;
+            }
+            webhooks::notify_destination(&state.webhooks, &state.events, &d, Ok(&stats));
             Ok(format!(
-                "Auto-sync destination {}: uploaded {}, skipped {}, deleted {}, total {}",
-                id, stats.uploaded, stats.skipped, stats.deleted, stats.total
+                "Auto-sync destination {}: uploaded {}, skipped {}, deleted {}, total {}, conflicts {}",
+                id, stats.uploaded, stats.skipped, stats.deleted, stats.total, stats.conflicts
             ))
         },
     );
 }
 
 pub fn register_all(registry: &AutoSyncRegistry, state: &AppState) {
-    let sources = {
-        let db = state.db.lock().unwrap();
-        db::list_sources(&db).unwrap_or_else(|e| {
+    let sources = state
+        .conn()
+        .and_then(|db| db::list_sources(&db))
+        .unwrap_or_else(|e| {
             tracing::error!("Failed to load sources for auto-sync: {}", e);
             vec![]
-        })
-    };
+        });
     for source in &sources {
         register_source(registry, state, source);
     }
 
-    let destinations = {
-        let db = state.db.lock().unwrap();
-        db::list_destinations(&db).unwrap_or_else(|e| {
+    let destinations = state
+        .conn()
+        .and_then(|db| db::list_destinations(&db))
+        .unwrap_or_else(|e| {
             tracing::error!("Failed to load destinations for auto-sync: {}", e);
             vec![]
-        })
-    };
+        });
     for dest in &destinations {
         register_destination(registry, state, dest);
     }
+    state
+        .scheduler_ready
+        .store(true, std::sync::atomic::Ordering::Release);
 }
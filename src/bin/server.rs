@@ -1,10 +1,17 @@
 use axum::http::{HeaderName, Method, header};
 use axum::middleware;
 use caldav_ics_sync::api::AppState;
-use caldav_ics_sync::auto_sync;
-use caldav_ics_sync::config::AppConfig;
+use caldav_ics_sync::api::reverse_sync::split_ics_urls;
+use caldav_ics_sync::auto_sync::{self, HostLocks};
+use caldav_ics_sync::config::{AppConfig, EffectiveConfig};
+use caldav_ics_sync::db::SourceKind;
+use caldav_ics_sync::db::{OverlapPolicy, SqliteSettings};
+use caldav_ics_sync::events::SyncEvents;
+use caldav_ics_sync::host_allowlist::HostAllowlist;
+use caldav_ics_sync::http::{HttpClients, HttpPoolConfig, PrivateAddressGuard};
 use caldav_ics_sync::server::auth::{AuthConfig, basic_auth_middleware};
-use caldav_ics_sync::server::build_router;
+use caldav_ics_sync::server::{build_public_router, build_router};
+use caldav_ics_sync::webhooks::WebhookConfig;
 use tower_http::cors::{AllowOrigin, CorsLayer};
 use tracing::info;
 
@@ -25,21 +32,43 @@ async fn main() -> anyhow::Result<()> {
     if let Some(parent) = std::path::Path::new(&db_path).parent() {
         std::fs::create_dir_all(parent)?;
     }
-    let conn = rusqlite::Connection::open(&db_path)?;
-    conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA foreign_keys=ON;")?;
-    caldav_ics_sync::db::init_db(&conn)?;
-    info!("Database initialized at {}", db_path);
+    let sqlite = SqliteSettings::from_config(&cfg)?;
+    let pool = caldav_ics_sync::db::open_pool_with(&db_path, &sqlite)?;
+    info!(
+        "Database initialized at {} (journal_mode={})",
+        db_path, sqlite.journal_mode
+    );
 
     let proxy_url = cfg.proxy_url();
 
-    let sync_tasks = auto_sync::new_registry();
-    let app_state = AppState {
-        db: std::sync::Arc::new(std::sync::Mutex::new(conn)),
-        start_time: std::time::Instant::now(),
-        sync_tasks: sync_tasks.clone(),
-    };
+    let mut app_state = AppState::new(pool);
+    app_state.webhooks = WebhookConfig::from_config(&cfg);
+    app_state.host_locks = HostLocks::new(cfg.serialize_per_host);
+    app_state.events = SyncEvents::new(cfg.event_channel_capacity);
+    let host_allowlist = HostAllowlist::from_config(&cfg)?;
+    app_state.http = HttpClients::new(HttpPoolConfig::from_config(&cfg))
+        .with_host_allowlist(host_allowlist.clone());
+    app_state.overlap_policy = OverlapPolicy::from_strict(cfg.strict_destination_overlap);
+    app_state.manual_sync_timeout = std::time::Duration::from_secs(cfg.manual_sync_timeout_secs);
+    app_state.public_all_feed = cfg.enable_public_all_feed;
+    app_state.stale_feed_factor = cfg.stale_feed_factor;
+    app_state.stale_feed_unavailable = cfg.stale_feed_unavailable;
+    app_state.db_batch_size = cfg.db_batch_size;
+    app_state.ics_line_ending = cfg.ics_line_ending;
+    app_state.public_url_secret = cfg.public_url_secret.clone();
+    app_state.auto_disable_after_failures = cfg.auto_disable_after_failures;
+    app_state.proxy_allowed_methods = cfg.proxy_allowed_methods()?;
+    app_state.compress_ics_storage = cfg.compress_ics_storage;
+    app_state.host_allowlist = host_allowlist;
+    app_state.config = Some(std::sync::Arc::new(EffectiveConfig::from(&cfg)));
+    let sync_tasks = app_state.sync_tasks.clone();
+    let shutdown = app_state.shutdown.clone();
+    let active_syncs = app_state.active_syncs.clone();
 
     auto_sync::register_all(&sync_tasks, &app_state);
+    if !cfg.allow_private_fetch {
+        tokio::spawn(warn_private_targets(private_targets(&app_state)));
+    }
 
     let cors = CorsLayer::new()
         .allow_origin(AllowOrigin::mirror_request())
@@ -80,6 +109,23 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
+    // Optional second listener exposing only the public feeds, so the admin
+    // API can stay on an internal interface.
+    let public_server = match cfg.public_addr() {
+        Some(public_addr) => {
+            let public_app = build_public_router(app_state.clone());
+            let public_listener = tokio::net::TcpListener::bind(&public_addr).await?;
+            info!("Public feeds listening on http://{}", public_addr);
+            let stop = shutdown.clone();
+            Some(tokio::spawn(async move {
+                axum::serve(public_listener, public_app)
+                    .with_graceful_shutdown(stop.cancelled_owned())
+                    .await
+            }))
+        }
+        None => None,
+    };
+
     let app = build_router(app_state.clone(), &proxy_url)
         .await
         .layer(middleware::from_fn(basic_auth_middleware))
@@ -94,15 +140,73 @@ async fn main() -> anyhow::Result<()> {
     info!("Listening on http://{}", addr);
 
     axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
+        .with_graceful_shutdown(shutdown_signal(shutdown))
         .await?;
 
+    if let Some(handle) = public_server {
+        handle.await??;
+    }
+
+    let in_flight = active_syncs.count();
+    if in_flight > 0 {
+        info!("Waiting for {} in-flight sync(s) to finish", in_flight);
+        let timeout = std::time::Duration::from_secs(cfg.shutdown_timeout_secs);
+        match tokio::time::timeout(timeout, active_syncs.wait_idle()).await {
+            Ok(()) => info!("Drained {} in-flight sync(s)", in_flight),
+            Err(_) => tracing::warn!(
+                "Shutdown timeout after {}s with {} sync(s) still running",
+                cfg.shutdown_timeout_secs,
+                active_syncs.count()
+            ),
+        }
+    }
+
     info!("Server shutdown complete");
 
     Ok(())
 }
 
-async fn shutdown_signal() {
+/// Saved (entity, field, URL) triples, collected up front so the lookups in
+/// [`warn_private_targets`] do not hold a database connection.
+fn private_targets(state: &AppState) -> Vec<(String, &'static str, String)> {
+    let Ok(db) = state.conn() else {
+        return Vec::new();
+    };
+    let mut targets = Vec::new();
+    for source in caldav_ics_sync::db::list_sources(&db).unwrap_or_default() {
+        if source.kind != SourceKind::Static {
+            let entity = format!("Source '{}'", source.name);
+            targets.push((entity, "caldav_url", source.caldav_url));
+        }
+    }
+    for dest in caldav_ics_sync::db::list_destinations(&db).unwrap_or_default() {
+        let entity = format!("Destination '{}'", dest.name);
+        targets.push((entity.clone(), "caldav_url", dest.caldav_url));
+        if let Some(url) = dest.calendar_url_override.filter(|u| !u.trim().is_empty()) {
+            targets.push((entity.clone(), "calendar_url_override", url));
+        }
+        for url in split_ics_urls(&dest.ics_url) {
+            targets.push((entity.clone(), "ics_url", url.to_string()));
+        }
+    }
+    targets
+}
+
+/// Private addresses are blocked by default, which breaks setups saved
+/// before the guard existed; say so at startup rather than on each sync.
+async fn warn_private_targets(targets: Vec<(String, &'static str, String)>) {
+    let guard = PrivateAddressGuard::system();
+    for (entity, field, url) in targets {
+        let Ok(parsed) = reqwest::Url::parse(url.trim()) else {
+            continue;
+        };
+        if let Err(blocked) = guard.check_resolved(&parsed).await {
+            tracing::warn!("{} {}: {}", entity, field, blocked);
+        }
+    }
+}
+
+async fn shutdown_signal(shutdown: tokio_util::sync::CancellationToken) {
     let ctrl_c = async {
         tokio::signal::ctrl_c()
             .await
@@ -128,4 +232,7 @@ async fn shutdown_signal() {
             info!("Received terminate signal, initiating graceful shutdown...");
         },
     }
+
+    // Stop auto-sync loops from starting new runs while in-flight ones finish.
+    shutdown.cancel();
 }
@@ -1,33 +1,101 @@
 use anyhow::{Result, bail};
-use serde::Deserialize;
+use axum::http::Method;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::auto_sync;
+use crate::server::auth::AuthConfig;
+
+const REDACTED: &str = "[redacted]";
 
 #[derive(Debug, Deserialize)]
 pub struct AppConfig {
     pub server_host: String,
     pub server_port: u16,
+    pub public_server_host: Option<String>,
+    pub public_server_port: Option<u16>,
     pub port: u16,
     pub server_proxy_url: Option<String>,
+    pub proxy_allowed_methods: Option<String>,
     pub data_dir: String,
     pub db_path: Option<String>,
     pub auth_username: Option<String>,
     pub auth_password: Option<String>,
     pub auth_password_hash: Option<String>,
+    pub shutdown_timeout_secs: u64,
+    pub manual_sync_timeout_secs: u64,
+    pub webhook_on_success_url: Option<String>,
+    pub webhook_on_failure_url: Option<String>,
+    pub webhook_on_retries_exhausted_url: Option<String>,
+    pub serialize_per_host: bool,
+    pub strict_destination_overlap: bool,
+    pub event_channel_capacity: usize,
+    pub http_pool_idle_timeout_secs: u64,
+    pub http_pool_max_idle_per_host: usize,
+    pub http2_enable: bool,
+    pub http2_prior_knowledge: bool,
+    pub enable_public_all_feed: bool,
+    pub stale_feed_factor: u32,
+    pub stale_feed_unavailable: bool,
+    pub db_batch_size: usize,
+    pub ics_line_ending: LineEnding,
+    pub public_url_secret: Option<String>,
+    pub auto_disable_after_failures: u32,
+    pub sqlite_journal_mode: String,
+    pub sqlite_synchronous: String,
+    pub sqlite_busy_timeout_ms: u64,
+    pub compress_ics_storage: bool,
+    pub destination_host_allowlist: Option<String>,
+    pub allow_private_fetch: bool,
 }
 
 impl AppConfig {
     pub fn load() -> Result<Self> {
-        let cfg = config::Config::builder()
+        Self::build(true)
+    }
+
+    /// The built-in defaults, ignoring the environment.
+    pub fn defaults() -> Result<Self> {
+        Self::build(false)
+    }
+
+    fn build(from_env: bool) -> Result<Self> {
+        let mut builder = config::Config::builder()
             .set_default("server_host", "0.0.0.0")?
             .set_default("server_port", 6765_i64)?
             .set_default("port", 6766_i64)?
             .set_default("data_dir", "./data")?
-            .add_source(config::Environment::default())
-            .build()?
-            .try_deserialize::<Self>()?;
+            .set_default("shutdown_timeout_secs", 30_i64)?
+            .set_default("manual_sync_timeout_secs", 120_i64)?
+            .set_default("serialize_per_host", false)?
+            .set_default("strict_destination_overlap", false)?
+            .set_default("event_channel_capacity", 256_i64)?
+            .set_default("http_pool_idle_timeout_secs", 90_i64)?
+            .set_default("http_pool_max_idle_per_host", 8_i64)?
+            .set_default("http2_enable", true)?
+            .set_default("http2_prior_knowledge", false)?
+            .set_default("enable_public_all_feed", false)?
+            .set_default("stale_feed_factor", 3_i64)?
+            .set_default("stale_feed_unavailable", false)?
+            .set_default("db_batch_size", crate::db::DEFAULT_BATCH_SIZE as i64)?
+            .set_default("ics_line_ending", "crlf")?
+            .set_default("auto_disable_after_failures", 0_i64)?
+            .set_default("sqlite_journal_mode", "WAL")?
+            .set_default("sqlite_synchronous", "FULL")?
+            .set_default("sqlite_busy_timeout_ms", 5000_i64)?
+            .set_default("compress_ics_storage", false)?
+            .set_default("allow_private_fetch", false)?;
+        if from_env {
+            builder = builder.add_source(config::Environment::default());
+        }
+        let cfg = builder.build()?.try_deserialize::<Self>()?;
 
         if cfg.auth_password.is_some() && cfg.auth_password_hash.is_some() {
             bail!("AUTH_PASSWORD and AUTH_PASSWORD_HASH are mutually exclusive; set only one");
         }
+        crate::db::SqliteSettings::from_config(&cfg)?;
+        cfg.proxy_allowed_methods()?;
+        crate::host_allowlist::HostAllowlist::from_config(&cfg)?;
 
         Ok(cfg)
     }
@@ -39,10 +107,178 @@ impl AppConfig {
         }
     }
 
+    /// Bind address of the public-feeds-only listener, when enabled.
+    pub fn public_addr(&self) -> Option<String> {
+        let port = self.public_server_port?;
+        let host = self
+            .public_server_host
+            .as_deref()
+            .unwrap_or(&self.server_host);
+        Some(format!("{}:{}", host, port))
+    }
+
     pub fn proxy_url(&self) -> String {
         match &self.server_proxy_url {
             Some(url) => url.clone(),
             None => format!("http://127.0.0.1:{}", self.port),
         }
     }
+
+    /// Methods the frontend proxy forwards, from the comma-separated
+    /// `PROXY_ALLOWED_METHODS`. `None` (unset or blank) forwards every method.
+    pub fn proxy_allowed_methods(&self) -> Result<Option<Vec<Method>>> {
+        let Some(raw) = self.proxy_allowed_methods.as_deref() else {
+            return Ok(None);
+        };
+        let mut methods = Vec::new();
+        for name in raw.split(',').map(str::trim).filter(|m| !m.is_empty()) {
+            let Ok(method) = Method::from_bytes(name.to_ascii_uppercase().as_bytes()) else {
+                bail!("PROXY_ALLOWED_METHODS contains an invalid method: {}", name);
+            };
+            if !methods.contains(&method) {
+                methods.push(method);
+            }
+        }
+        Ok((!methods.is_empty()).then_some(methods))
+    }
+}
+
+/// Line ending of served ICS feeds. Feeds are stored with CRLF, as RFC 5545
+/// requires; `Lf` is for clients that choke on the `\r`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    #[default]
+    Crlf,
+    Lf,
+}
+
+impl LineEnding {
+    /// Rewrites every line break in `content`, CRLF or bare LF, to this ending.
+    pub fn apply(self, content: &str) -> String {
+        let lf = content.replace("\r\n", "\n");
+        match self {
+            Self::Crlf => lf.replace('\n', "\r\n"),
+            Self::Lf => lf,
+        }
+    }
+}
+
+/// Keeps only the origin of a URL, since webhook paths often embed tokens.
+fn redact_url(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(u) => format!("{}/{}", u.origin().ascii_serialization(), REDACTED),
+        Err(_) => REDACTED.into(),
+    }
+}
+
+/// Retry schedule of the background auto-sync.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RetrySettings {
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub max_retries: usize,
+}
+
+/// Resolved runtime settings with secrets redacted, as reported by
+/// `GET /api/admin/config`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct EffectiveConfig {
+    pub server_host: String,
+    pub server_port: u16,
+    pub public_addr: Option<String>,
+    pub port: u16,
+    pub proxy_url: String,
+    /// `None` when every method is forwarded.
+    pub proxy_allowed_methods: Option<Vec<String>>,
+    pub data_dir: String,
+    pub db_path: String,
+    pub auth_enabled: bool,
+    pub auth_username: Option<String>,
+    pub auth_password: Option<String>,
+    pub auth_password_hash: Option<String>,
+    pub shutdown_timeout_secs: u64,
+    pub manual_sync_timeout_secs: u64,
+    pub webhook_on_success_url: Option<String>,
+    pub webhook_on_failure_url: Option<String>,
+    pub webhook_on_retries_exhausted_url: Option<String>,
+    pub serialize_per_host: bool,
+    pub strict_destination_overlap: bool,
+    pub event_channel_capacity: usize,
+    pub http_pool_idle_timeout_secs: u64,
+    pub http_pool_max_idle_per_host: usize,
+    pub http2_enable: bool,
+    pub http2_prior_knowledge: bool,
+    pub enable_public_all_feed: bool,
+    pub stale_feed_factor: u32,
+    pub stale_feed_unavailable: bool,
+    pub db_batch_size: usize,
+    pub ics_line_ending: LineEnding,
+    pub public_url_secret: Option<String>,
+    pub auto_disable_after_failures: u32,
+    pub sqlite_journal_mode: String,
+    pub sqlite_synchronous: String,
+    pub sqlite_busy_timeout_ms: u64,
+    pub compress_ics_storage: bool,
+    /// `None` when every host is allowed.
+    pub destination_host_allowlist: Option<String>,
+    pub allow_private_fetch: bool,
+    pub sync_retry: RetrySettings,
+}
+
+impl From<&AppConfig> for EffectiveConfig {
+    fn from(cfg: &AppConfig) -> Self {
+        let secret = |v: &Option<String>| v.as_ref().map(|_| REDACTED.to_string());
+        Self {
+            server_host: cfg.server_host.clone(),
+            server_port: cfg.server_port,
+            public_addr: cfg.public_addr(),
+            port: cfg.port,
+            proxy_url: cfg.proxy_url(),
+            proxy_allowed_methods: cfg
+                .proxy_allowed_methods()
+                .ok()
+                .flatten()
+                .map(|m| m.iter().map(ToString::to_string).collect()),
+            data_dir: cfg.data_dir.clone(),
+            db_path: cfg.db_path(),
+            auth_enabled: !matches!(AuthConfig::from_config(cfg), AuthConfig::Disabled),
+            auth_username: cfg.auth_username.clone(),
+            auth_password: secret(&cfg.auth_password),
+            auth_password_hash: secret(&cfg.auth_password_hash),
+            shutdown_timeout_secs: cfg.shutdown_timeout_secs,
+            manual_sync_timeout_secs: cfg.manual_sync_timeout_secs,
+            webhook_on_success_url: cfg.webhook_on_success_url.as_deref().map(redact_url),
+            webhook_on_failure_url: cfg.webhook_on_failure_url.as_deref().map(redact_url),
+            webhook_on_retries_exhausted_url: cfg
+                .webhook_on_retries_exhausted_url
+                .as_deref()
+                .map(redact_url),
+            serialize_per_host: cfg.serialize_per_host,
+            strict_destination_overlap: cfg.strict_destination_overlap,
+            event_channel_capacity: cfg.event_channel_capacity,
+            http_pool_idle_timeout_secs: cfg.http_pool_idle_timeout_secs,
+            http_pool_max_idle_per_host: cfg.http_pool_max_idle_per_host,
+            http2_enable: cfg.http2_enable,
+            http2_prior_knowledge: cfg.http2_prior_knowledge,
+            enable_public_all_feed: cfg.enable_public_all_feed,
+            stale_feed_factor: cfg.stale_feed_factor,
+            stale_feed_unavailable: cfg.stale_feed_unavailable,
+            db_batch_size: cfg.db_batch_size,
+            ics_line_ending: cfg.ics_line_ending,
+            public_url_secret: secret(&cfg.public_url_secret),
+            auto_disable_after_failures: cfg.auto_disable_after_failures,
+            sqlite_journal_mode: cfg.sqlite_journal_mode.to_ascii_uppercase(),
+            sqlite_synchronous: cfg.sqlite_synchronous.to_ascii_uppercase(),
+            sqlite_busy_timeout_ms: cfg.sqlite_busy_timeout_ms,
+            compress_ics_storage: cfg.compress_ics_storage,
+            destination_host_allowlist: cfg.destination_host_allowlist.clone(),
+            allow_private_fetch: cfg.allow_private_fetch,
+            sync_retry: RetrySettings {
+                base_delay_ms: auto_sync::RETRY_BASE_MS,
+                max_delay_ms: auto_sync::RETRY_MAX_MS,
+                max_retries: auto_sync::MAX_RETRIES,
+            },
+        }
+    }
 }
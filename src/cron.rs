@@ -0,0 +1,352 @@
+use anyhow::{Context, Result, bail, ensure};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Timelike};
+
+/// How far ahead `next_after` searches. Eight years covers every leap-day
+/// schedule (2100 is not a leap year), so only impossible schedules give up.
+const SEARCH_DAYS: i64 = 366 * 8;
+
+const MONTH_NAMES: &[&str] = &[
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+const WEEKDAY_NAMES: &[&str] = &["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+/// Longest possible length of each month, counting 29 February.
+const MONTH_DAYS: [u32; 12] = [31, 29, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+
+/// A standard five-field cron expression (minute, hour, day of month, month,
+/// day of week). Each field is a bit set of the values it allows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether day of month / day of week were `*`. When both are
+    /// restricted a day matches if either does, as in classic cron.
+    any_day: bool,
+    any_weekday: bool,
+}
+
+struct Field {
+    name: &'static str,
+    min: u32,
+    max: u32,
+    names: &'static [&'static str],
+    /// First value `names` maps to (months start at 1, weekdays at 0).
+    names_start: u32,
+}
+
+const MINUTE: Field = Field {
+    name: "minute",
+    min: 0,
+    max: 59,
+    names: &[],
+    names_start: 0,
+};
+const HOUR: Field = Field {
+    name: "hour",
+    min: 0,
+    max: 23,
+    names: &[],
+    names_start: 0,
+};
+const DAY: Field = Field {
+    name: "day of month",
+    min: 1,
+    max: 31,
+    names: &[],
+    names_start: 0,
+};
+const MONTH: Field = Field {
+    name: "month",
+    min: 1,
+    max: 12,
+    names: MONTH_NAMES,
+    names_start: 1,
+};
+// 7 is accepted as an alias for Sunday and folded onto 0 after parsing.
+const WEEKDAY: Field = Field {
+    name: "day of week",
+    min: 0,
+    max: 7,
+    names: WEEKDAY_NAMES,
+    names_start: 0,
+};
+
+impl Field {
+    fn value(&self, raw: &str) -> Result<u32> {
+        let upper = raw.to_ascii_uppercase();
+        if let Some(i) = self.names.iter().position(|n| *n == upper) {
+            return Ok(self.names_start + i as u32);
+        }
+        let v: u32 = raw
+            .parse()
+            .ok()
+            .with_context(|| format!("Invalid {} value '{}'", self.name, raw))?;
+        ensure!(
+            (self.min..=self.max).contains(&v),
+            "{} value {} is out of range ({}-{})",
+            self.name,
+            v,
+            self.min,
+            self.max
+        );
+        Ok(v)
+    }
+
+    /// Parses a comma-separated list of `*`, `N`, `N-M`, each optionally
+    /// followed by `/step`, into a bit set.
+    fn parse(&self, spec: &str) -> Result<u64> {
+        let mut bits = 0u64;
+        for item in spec.split(',') {
+            let (range, step) = match item.split_once('/') {
+                Some((range, step)) => {
+                    let step: u32 = step
+                        .parse()
+                        .ok()
+                        .filter(|s| *s > 0)
+                        .with_context(|| format!("Invalid {} step '{}'", self.name, step))?;
+                    (range, step)
+                }
+                None => (item, 1),
+            };
+            let (lo, hi) = if range == "*" {
+                (self.min, self.max)
+            } else if let Some((lo, hi)) = range.split_once('-') {
+                let (lo, hi) = (self.value(lo)?, self.value(hi)?);
+                ensure!(lo <= hi, "Invalid {} range '{}'", self.name, range);
+                (lo, hi)
+            } else {
+                let lo = self.value(range)?;
+                // `N/step` runs from N to the end of the field.
+                (lo, if item.contains('/') { self.max } else { lo })
+            };
+            for v in (lo..=hi).step_by(step as usize) {
+                bits |= 1 << v;
+            }
+        }
+        Ok(bits)
+    }
+}
+
+fn has(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+impl CronSchedule {
+    /// Parses a five-field expression or one of the `@hourly`, `@daily`,
+    /// `@weekly`, `@monthly` and `@yearly` shorthands.
+    pub fn parse(expression: &str) -> Result<Self> {
+        let expression = expression.trim();
+        let expanded = match expression.to_ascii_lowercase().as_str() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            s if s.starts_with('@') => bail!("Unknown cron shorthand '{}'", expression),
+            _ => expression,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            bail!(
+                "Cron expression must have 5 fields (minute hour day month weekday), got {}",
+                fields.len()
+            );
+        };
+
+        let mut weekdays = WEEKDAY.parse(weekday)?;
+        if has(weekdays, 7) {
+            weekdays = (weekdays & !(1 << 7)) | 1;
+        }
+        let schedule = Self {
+            minutes: MINUTE.parse(minute)?,
+            hours: HOUR.parse(hour)?,
+            days: DAY.parse(day)?,
+            months: MONTH.parse(month)?,
+            weekdays,
+            any_day: day.starts_with('*'),
+            any_weekday: weekday.starts_with('*'),
+        };
+        // Catch schedules like "0 0 30 2 *" that could never fire.
+        if schedule.any_weekday {
+            let possible = (1..=12).any(|m| {
+                has(schedule.months, m)
+                    && (1..=MONTH_DAYS[m as usize - 1]).any(|d| has(schedule.days, d))
+            });
+            ensure!(possible, "Cron expression '{}' never fires", expression);
+        }
+        Ok(schedule)
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if !has(self.months, date.month()) {
+            return false;
+        }
+        let day = has(self.days, date.day());
+        let weekday = has(self.weekdays, date.weekday().num_days_from_sunday());
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (false, true) => day,
+            (true, false) => weekday,
+            (false, false) => day || weekday,
+        }
+    }
+
+    /// The first fire time strictly after `after`, evaluated in `after`'s
+    /// timezone. Wall-clock times skipped by a DST change are not fired;
+    /// repeated ones fire once, at their first occurrence.
+    pub fn next_after<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let tz = after.timezone();
+        let local = after.naive_local();
+        let start = local.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let mut date = start.date();
+        for _ in 0..SEARCH_DAYS {
+            if self.matches_date(date) {
+                for hour in (0..24).filter(|h| has(self.hours, *h)) {
+                    for minute in (0..60).filter(|m| has(self.minutes, *m)) {
+                        let candidate = date.and_time(NaiveTime::from_hms_opt(hour, minute, 0)?);
+                        if candidate < start {
+                            continue;
+                        }
+                        if let Some(t) = tz.from_local_datetime(&candidate).earliest()
+                            && t > *after
+                        {
+                            return Some(t);
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+
+    /// The next `count` fire times after `after`.
+    pub fn upcoming<Tz: TimeZone>(&self, after: &DateTime<Tz>, count: usize) -> Vec<DateTime<Tz>> {
+        let mut times = Vec::with_capacity(count);
+        let mut cursor = after.clone();
+        while times.len() < count {
+            let Some(next) = self.next_after(&cursor) else {
+                break;
+            };
+            cursor = next.clone();
+            times.push(next);
+        }
+        times
+    }
+}
+
+/// Validates an optional cron field, treating blank as unset.
+pub fn validate(expression: Option<&str>) -> Result<()> {
+    match expression.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(expr) => CronSchedule::parse(expr).map(|_| ()),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn next(expr: &str, after: &str) -> String {
+        CronSchedule::parse(expr)
+            .unwrap()
+            .next_after(&at(after))
+            .unwrap()
+            .to_rfc3339()
+    }
+
+    #[test]
+    fn parse_accepts_lists_ranges_steps_and_names() {
+        assert!(CronSchedule::parse("*/15 9-17 * * MON-FRI").is_ok());
+        assert!(CronSchedule::parse("0 0,12 1 jan,jul 7").is_ok());
+        assert!(CronSchedule::parse("5/20 * * * *").is_ok());
+        assert!(CronSchedule::parse(" @hourly ").is_ok());
+        assert_eq!(
+            CronSchedule::parse("0 0 * * 7").unwrap(),
+            CronSchedule::parse("0 0 * * SUN").unwrap()
+        );
+
+        for bad in [
+            "",
+            "* * * *",
+            "* * * * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "* * * 13 *",
+            "* * * * 8",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "a * * * *",
+            "@every5m",
+            "0 0 30 2 *",
+        ] {
+            assert!(
+                CronSchedule::parse(bad).is_err(),
+                "{bad:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn next_after_computes_the_following_fire_time() {
+        assert_eq!(
+            next("*/15 * * * *", "2026-03-10T10:07:30Z"),
+            "2026-03-10T10:15:00+00:00"
+        );
+        // Strictly after: a fire time equal to `after` is skipped.
+        assert_eq!(
+            next("0 * * * *", "2026-03-10T10:00:00Z"),
+            "2026-03-10T11:00:00+00:00"
+        );
+        assert_eq!(
+            next("30 6 * * MON-FRI", "2026-03-13T07:00:00Z"),
+            "2026-03-16T06:30:00+00:00"
+        );
+        assert_eq!(
+            next("@monthly", "2026-12-15T00:00:00Z"),
+            "2027-01-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            next("0 0 29 2 *", "2026-03-01T00:00:00Z"),
+            "2028-02-29T00:00:00+00:00"
+        );
+        // Day of month and day of week both restricted: either matches.
+        // 2026-03-11 is a Wednesday, before the 15th.
+        assert_eq!(
+            next("0 0 15 * WED", "2026-03-10T12:00:00Z"),
+            "2026-03-11T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn next_after_follows_the_timezone_and_skips_dst_gaps() {
+        let tz: chrono_tz::Tz = "Europe/London".parse().unwrap();
+        let schedule = CronSchedule::parse("30 1 * * *").unwrap();
+        // 01:30 does not exist on 2026-03-29 in London; the next run is the day after.
+        let after = tz.with_ymd_and_hms(2026, 3, 28, 12, 0, 0).unwrap();
+        let next = schedule.next_after(&after).unwrap();
+        assert_eq!(next.to_rfc3339(), "2026-03-30T01:30:00+01:00");
+
+        let runs = CronSchedule::parse("0 9 * * *")
+            .unwrap()
+            .upcoming(&after, 3);
+        let runs: Vec<String> = runs.iter().map(|t| t.to_rfc3339()).collect();
+        assert_eq!(
+            runs,
+            [
+                "2026-03-29T09:00:00+01:00",
+                "2026-03-30T09:00:00+01:00",
+                "2026-03-31T09:00:00+01:00"
+            ]
+        );
+    }
+}
@@ -1,8 +1,106 @@
-use anyhow::{Result, ensure};
+use anyhow::{Result, anyhow, ensure};
+use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::{Connection, params};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use utoipa::ToSchema;
 
+pub type DbPool = r2d2::Pool<SqliteConnectionManager>;
+pub type DbConn = r2d2::PooledConnection<SqliteConnectionManager>;
+
+/// Journal modes `SQLITE_JOURNAL_MODE` accepts.
+const JOURNAL_MODES: &[&str] = &["DELETE", "TRUNCATE", "PERSIST", "MEMORY", "WAL", "OFF"];
+
+/// `synchronous` levels `SQLITE_SYNCHRONOUS` accepts.
+const SYNCHRONOUS_LEVELS: &[&str] = &["OFF", "NORMAL", "FULL", "EXTRA"];
+
+/// Pragmas applied to every pooled connection of the database file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqliteSettings {
+    pub journal_mode: String,
+    pub synchronous: String,
+    pub busy_timeout: std::time::Duration,
+}
+
+impl Default for SqliteSettings {
+    /// WAL, SQLite's `FULL` synchronous default and rusqlite's 5 s busy timeout.
+    fn default() -> Self {
+        Self {
+            journal_mode: "WAL".into(),
+            synchronous: "FULL".into(),
+            busy_timeout: std::time::Duration::from_secs(5),
+        }
+    }
+}
+
+impl SqliteSettings {
+    /// Validates the journal mode and synchronous level, case-insensitively.
+    pub fn new(journal_mode: &str, synchronous: &str, busy_timeout_ms: u64) -> Result<Self> {
+        let journal_mode = journal_mode.trim().to_ascii_uppercase();
+        ensure!(
+            JOURNAL_MODES.contains(&journal_mode.as_str()),
+            "Unknown SQLite journal mode: {} (expected one of {})",
+            journal_mode,
+            JOURNAL_MODES.join(", ")
+        );
+        let synchronous = synchronous.trim().to_ascii_uppercase();
+        ensure!(
+            SYNCHRONOUS_LEVELS.contains(&synchronous.as_str()),
+            "Unknown SQLite synchronous level: {} (expected one of {})",
+            synchronous,
+            SYNCHRONOUS_LEVELS.join(", ")
+        );
+        Ok(Self {
+            journal_mode,
+            synchronous,
+            busy_timeout: std::time::Duration::from_millis(busy_timeout_ms),
+        })
+    }
+
+    pub fn from_config(cfg: &crate::config::AppConfig) -> Result<Self> {
+        Self::new(
+            &cfg.sqlite_journal_mode,
+            &cfg.sqlite_synchronous,
+            cfg.sqlite_busy_timeout_ms,
+        )
+    }
+
+    fn apply(&self, conn: &Connection) -> rusqlite::Result<()> {
+        conn.busy_timeout(self.busy_timeout)?;
+        conn.execute_batch(&format!(
+            "PRAGMA journal_mode={}; PRAGMA synchronous={}; PRAGMA foreign_keys=ON;",
+            self.journal_mode, self.synchronous
+        ))
+    }
+}
+
+/// Opens a WAL-mode connection pool over the database file and runs migrations.
+pub fn open_pool(path: &str) -> Result<DbPool> {
+    open_pool_with(path, &SqliteSettings::default())
+}
+
+/// Like [`open_pool`], with the given pragmas instead of the defaults.
+pub fn open_pool_with(path: &str, settings: &SqliteSettings) -> Result<DbPool> {
+    let settings = settings.clone();
+    let manager = SqliteConnectionManager::file(path).with_init(move |c| settings.apply(c));
+    let pool = r2d2::Pool::new(manager)?;
+    init_db(&*pool.get()?)?;
+    Ok(pool)
+}
+
+/// Opens a pool over a private shared-cache in-memory database, for tests.
+pub fn open_memory_pool() -> Result<DbPool> {
+    let uri = format!(
+        "file:caldav-sync-{}?mode=memory&cache=shared",
+        uuid::Uuid::new_v4()
+    );
+    let manager = SqliteConnectionManager::file(uri)
+        .with_init(|c| c.execute_batch("PRAGMA foreign_keys=ON;"));
+    let pool = r2d2::Pool::new(manager)?;
+    init_db(&*pool.get()?)?;
+    Ok(pool)
+}
+
 fn require_non_empty(field: &str, value: &str) -> Result<()> {
     ensure!(!value.trim().is_empty(), "{} cannot be empty", field);
     Ok(())
@@ -13,6 +111,120 @@ fn require_non_negative(field: &str, value: i64) -> Result<()> {
     Ok(())
 }
 
+fn validate_percent(field: &str, value: i64) -> Result<()> {
+    ensure!(
+        (0..=100).contains(&value),
+        "{} must be between 0 and 100",
+        field
+    );
+    Ok(())
+}
+
+fn non_empty(value: Option<&str>) -> Option<&str> {
+    value.filter(|v| !v.trim().is_empty())
+}
+
+/// Resolves an optional text column on update: absent keeps the existing
+/// value, an empty string clears it.
+fn updated_optional<'a>(new: Option<&'a str>, existing: Option<&'a str>) -> Option<&'a str> {
+    match new {
+        Some(v) => non_empty(Some(v)),
+        None => existing,
+    }
+}
+
+fn validate_webhook_url(value: Option<&str>) -> Result<()> {
+    match non_empty(value) {
+        Some(url) => crate::webhooks::validate_url(url),
+        None => Ok(()),
+    }
+}
+
+fn validate_calendar_url_override(value: Option<&str>) -> Result<()> {
+    if let Some(url) = non_empty(value) {
+        ensure!(
+            reqwest::Url::parse(url).is_ok_and(|u| matches!(u.scheme(), "http" | "https")),
+            "Calendar URL override must be an http or https URL"
+        );
+    }
+    Ok(())
+}
+
+/// Custom feed request headers must be sendable as-is.
+fn validate_extra_headers(headers: &BTreeMap<String, String>) -> Result<()> {
+    for (name, value) in headers {
+        ensure!(
+            reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_ok(),
+            "Invalid header name: {:?}",
+            name
+        );
+        ensure!(
+            reqwest::header::HeaderValue::from_str(value).is_ok(),
+            "Invalid value for header {}",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Extra headers are stored as a JSON object.
+fn headers_to_db(headers: &BTreeMap<String, String>) -> String {
+    serde_json::to_string(headers).unwrap_or_else(|_| "{}".into())
+}
+
+fn headers_from_db(raw: &str) -> BTreeMap<String, String> {
+    serde_json::from_str(raw).unwrap_or_default()
+}
+
+fn validate_timezone(value: Option<&str>) -> Result<()> {
+    if let Some(tz) = non_empty(value) {
+        ensure!(
+            tz.parse::<chrono_tz::Tz>().is_ok(),
+            "Unknown timezone: {} (expected an IANA name like Europe/London)",
+            tz
+        );
+    }
+    Ok(())
+}
+
+/// Parses a public window bound (RFC 3339, the stored form, or a bare date
+/// meaning midnight UTC) into the `datetime('now')` format so SQLite can compare it as text.
+fn normalize_window_time(field: &str, value: Option<&str>) -> Result<Option<String>> {
+    let Some(raw) = non_empty(value).map(str::trim) else {
+        return Ok(None);
+    };
+    let parsed = chrono::DateTime::parse_from_rfc3339(raw)
+        .map(|dt| dt.with_timezone(&chrono::Utc))
+        .or_else(|_| {
+            chrono::NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S").map(|dt| dt.and_utc())
+        })
+        .or_else(|_| {
+            chrono::NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                .map(|d| d.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc())
+        })
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "{} must be an RFC 3339 timestamp or a YYYY-MM-DD date",
+                field
+            )
+        })?;
+    Ok(Some(parsed.format("%Y-%m-%d %H:%M:%S").to_string()))
+}
+
+fn validate_public_window(from: Option<&str>, until: Option<&str>) -> Result<()> {
+    if let (Some(from), Some(until)) = (from, until) {
+        ensure!(from < until, "Public from must be before public until");
+    }
+    Ok(())
+}
+
+fn validate_summary_template(value: Option<&str>) -> Result<()> {
+    if let Some(template) = non_empty(value) {
+        crate::summary_template::validate(template)?;
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Source {
     pub id: i64,
@@ -30,6 +242,70 @@ pub struct Source {
     pub created_at: String,
     pub public_ics: bool,
     pub public_ics_path: Option<String>,
+    pub allow_invalid_certs: bool,
+    pub webhook_on_success_url: Option<String>,
+    pub webhook_on_failure_url: Option<String>,
+    pub default_timezone: Option<String>,
+    pub sort_events: bool,
+    /// Bumped on every edit or sync status change; see `list_sources_changed_since`.
+    pub updated_at: String,
+    pub summary_template: Option<String>,
+    /// Events larger than this are trimmed when the feed is assembled; 0 disables.
+    pub max_event_bytes: i64,
+    /// Events trimmed by `max_event_bytes` during the last successful sync.
+    pub last_sync_trimmed: i64,
+    /// Poll interval advertised in the feed; 0 falls back to `sync_interval_secs`.
+    pub refresh_interval_secs: i64,
+    pub kind: SourceKind,
+    pub debug_http: bool,
+    pub auto_discover: bool,
+    pub propfind_events: bool,
+    pub skip_on_empty: bool,
+    pub enabled: bool,
+    /// Public window bounds as UTC `YYYY-MM-DD HH:MM:SS`.
+    pub public_from: Option<String>,
+    pub public_until: Option<String>,
+    pub signed_public_urls: bool,
+    /// Syncs in a row that ended in `error`; reset by an `ok` sync.
+    pub consecutive_failures: i64,
+    pub cron_schedule: Option<String>,
+    /// Where `caldav_url` redirected to on the last sync; later syncs start
+    /// there. Cleared when `caldav_url` changes.
+    pub resolved_caldav_url: Option<String>,
+    /// Mark each event with the calendar it came from as `X-ORIGIN-CALENDAR`.
+    pub tag_calendar_origin: bool,
+}
+
+/// Where a source's feed comes from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    /// Pulled from a CalDAV server by `run_sync`.
+    #[default]
+    Caldav,
+    /// Uploaded ICS content, replaced only via `PUT /api/sources/{id}/ics`.
+    Static,
+    /// A plain ICS feed at `caldav_url`, fetched as-is on each sync and
+    /// re-served unchanged.
+    Subscription,
+}
+
+impl SourceKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SourceKind::Caldav => "caldav",
+            SourceKind::Static => "static",
+            SourceKind::Subscription => "subscription",
+        }
+    }
+
+    fn from_db(value: &str) -> Self {
+        match value {
+            "static" => SourceKind::Static,
+            "subscription" => SourceKind::Subscription,
+            _ => SourceKind::Caldav,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
@@ -43,9 +319,43 @@ pub struct CreateSource {
     #[serde(default)]
     pub public_ics: bool,
     pub public_ics_path: Option<String>,
+    #[serde(default)]
+    pub allow_invalid_certs: bool,
+    pub webhook_on_success_url: Option<String>,
+    pub webhook_on_failure_url: Option<String>,
+    pub default_timezone: Option<String>,
+    #[serde(default)]
+    pub sort_events: bool,
+    pub summary_template: Option<String>,
+    #[serde(default)]
+    pub max_event_bytes: i64,
+    #[serde(default)]
+    pub refresh_interval_secs: i64,
+    #[serde(default)]
+    pub debug_http: bool,
+    #[serde(default)]
+    pub auto_discover: bool,
+    #[serde(default)]
+    pub propfind_events: bool,
+    #[serde(default = "default_true")]
+    pub skip_on_empty: bool,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Optional public window: outside `[public_from, public_until)` the
+    /// source's public paths answer 404. RFC 3339 or YYYY-MM-DD (UTC).
+    pub public_from: Option<String>,
+    pub public_until: Option<String>,
+    /// Serve the public feed only through links minted by
+    /// `POST /api/sources/{id}/sign-url`.
+    #[serde(default)]
+    pub signed_public_urls: bool,
+    #[serde(default)]
+    pub cron_schedule: Option<String>,
+    #[serde(default)]
+    pub tag_calendar_origin: bool,
 }
 
-#[derive(Debug, Deserialize, ToSchema)]
+#[derive(Debug, Default, Deserialize, ToSchema)]
 pub struct UpdateSource {
     pub name: Option<String>,
     pub caldav_url: Option<String>,
@@ -55,6 +365,133 @@ pub struct UpdateSource {
     pub sync_interval_secs: Option<i64>,
     pub public_ics: Option<bool>,
     pub public_ics_path: Option<String>,
+    pub allow_invalid_certs: Option<bool>,
+    pub webhook_on_success_url: Option<String>,
+    pub webhook_on_failure_url: Option<String>,
+    pub default_timezone: Option<String>,
+    pub sort_events: Option<bool>,
+    pub summary_template: Option<String>,
+    pub max_event_bytes: Option<i64>,
+    pub refresh_interval_secs: Option<i64>,
+    pub debug_http: Option<bool>,
+    pub auto_discover: Option<bool>,
+    pub propfind_events: Option<bool>,
+    pub skip_on_empty: Option<bool>,
+    pub enabled: Option<bool>,
+    /// When `ics_path` changes, keep serving the old path as an alias for
+    /// this many seconds so existing subscribers have time to move.
+    pub ics_path_grace_secs: Option<i64>,
+    /// Empty string clears the bound.
+    pub public_from: Option<String>,
+    pub public_until: Option<String>,
+    pub signed_public_urls: Option<bool>,
+    pub cron_schedule: Option<String>,
+    pub tag_calendar_origin: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DuplicateSource {
+    pub ics_path: String,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateStaticSource {
+    pub name: String,
+    pub ics_path: String,
+    #[serde(default)]
+    pub public_ics: bool,
+    pub public_ics_path: Option<String>,
+    /// Raw `text/calendar` content to serve.
+    pub ics: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateSubscriptionSource {
+    pub name: String,
+    /// The ICS feed to mirror; credentials in the URL are sent as Basic auth.
+    pub ics_url: String,
+    pub ics_path: String,
+    #[serde(default = "default_subscription_interval")]
+    pub sync_interval_secs: i64,
+    #[serde(default)]
+    pub public_ics: bool,
+    pub public_ics_path: Option<String>,
+    #[serde(default)]
+    pub allow_invalid_certs: bool,
+}
+
+fn default_subscription_interval() -> i64 {
+    3600
+}
+
+fn table_has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})"))?;
+    let names = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    for name in names {
+        if name? == column {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Runs `step` in a transaction unless `name` is already recorded in
+/// `schema_migrations`, then records it. A failed step is rolled back and
+/// retried on the next start.
+fn apply_migration(
+    conn: &Connection,
+    name: &str,
+    step: impl FnOnce(&Connection) -> Result<()>,
+) -> Result<()> {
+    let applied: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM schema_migrations WHERE name = ?1)",
+        params![name],
+        |row| row.get(0),
+    )?;
+    if applied {
+        return Ok(());
+    }
+    let tx = conn.unchecked_transaction()?;
+    step(&tx).map_err(|e| anyhow!("Migration {} failed: {}", name, e))?;
+    tx.execute(
+        "INSERT INTO schema_migrations (name) VALUES (?1)",
+        params![name],
+    )?;
+    tx.commit()?;
+    tracing::info!("Applied migration {}", name);
+    Ok(())
+}
+
+/// Replaces the legacy `sync_interval_minutes` column with `sync_interval_secs`.
+/// Values are only converted when `sync_interval_secs` is added here; a table
+/// that already has it was converted by an older release and is left alone.
+fn migrate_interval_to_secs(conn: &Connection, table: &str) -> Result<()> {
+    if !table_has_column(conn, table, "sync_interval_minutes")? {
+        return Ok(());
+    }
+    if table_has_column(conn, table, "sync_interval_secs")? {
+        tracing::info!(
+            "{}: sync_interval_secs already present, skipping minutes conversion",
+            table
+        );
+        return Ok(());
+    }
+    conn.execute_batch(&format!(
+        "ALTER TABLE {table} ADD COLUMN sync_interval_secs INTEGER NOT NULL DEFAULT 3600;"
+    ))?;
+    let converted = conn.execute(
+        &format!(
+            "UPDATE {table} SET sync_interval_secs = sync_interval_minutes * 60 WHERE sync_interval_minutes IS NOT NULL"
+        ),
+        [],
+    )?;
+    tracing::info!(
+        "{}: converted {} sync interval(s) from minutes to seconds",
+        table,
+        converted
+    );
+    Ok(())
 }
 
 pub fn init_db(conn: &Connection) -> Result<()> {
@@ -99,16 +536,63 @@ pub fn init_db(conn: &Connection) -> Result<()> {
         "ALTER TABLE sources ADD COLUMN last_sync_status TEXT;
          ALTER TABLE sources ADD COLUMN last_sync_error TEXT;",
     );
-    // Migrate existing DBs: rename sync_interval_minutes -> sync_interval_secs
-    let _ = conn.execute_batch(
-        "ALTER TABLE sources ADD COLUMN sync_interval_secs INTEGER NOT NULL DEFAULT 3600;
-         UPDATE sources SET sync_interval_secs = sync_interval_minutes * 60 WHERE sync_interval_minutes IS NOT NULL;
-         ALTER TABLE destinations ADD COLUMN sync_interval_secs INTEGER NOT NULL DEFAULT 3600;
-         UPDATE destinations SET sync_interval_secs = sync_interval_minutes * 60 WHERE sync_interval_minutes IS NOT NULL;",
-    );
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            name TEXT PRIMARY KEY,
+            applied_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );",
+    )?;
+    apply_migration(conn, "sync_interval_secs", |tx| {
+        for table in ["sources", "destinations"] {
+            migrate_interval_to_secs(tx, table)?;
+        }
+        Ok(())
+    })?;
     let _ =
         conn.execute_batch("ALTER TABLE sources ADD COLUMN public_ics INTEGER NOT NULL DEFAULT 0;");
     let _ = conn.execute_batch("ALTER TABLE sources ADD COLUMN public_ics_path TEXT;");
+    let _ = conn.execute_batch("ALTER TABLE sources ADD COLUMN webhook_on_success_url TEXT;");
+    let _ = conn.execute_batch("ALTER TABLE sources ADD COLUMN webhook_on_failure_url TEXT;");
+    let _ = conn.execute_batch("ALTER TABLE sources ADD COLUMN default_timezone TEXT;");
+    let _ = conn
+        .execute_batch("ALTER TABLE sources ADD COLUMN sort_events INTEGER NOT NULL DEFAULT 0;");
+    let _ =
+        conn.execute_batch("ALTER TABLE sources ADD COLUMN updated_at TEXT NOT NULL DEFAULT '';");
+    conn.execute_batch(
+        "UPDATE sources SET updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', created_at) WHERE updated_at = '';",
+    )?;
+    let _ = conn.execute_batch("ALTER TABLE sources ADD COLUMN summary_template TEXT;");
+    let _ = conn.execute_batch(
+        "ALTER TABLE sources ADD COLUMN max_event_bytes INTEGER NOT NULL DEFAULT 0;",
+    );
+    let _ = conn.execute_batch(
+        "ALTER TABLE sources ADD COLUMN last_sync_trimmed INTEGER NOT NULL DEFAULT 0;",
+    );
+    let _ = conn.execute_batch(
+        "ALTER TABLE sources ADD COLUMN refresh_interval_secs INTEGER NOT NULL DEFAULT 0;",
+    );
+    let _ =
+        conn.execute_batch("ALTER TABLE sources ADD COLUMN kind TEXT NOT NULL DEFAULT 'caldav';");
+    let _ =
+        conn.execute_batch("ALTER TABLE sources ADD COLUMN debug_http INTEGER NOT NULL DEFAULT 0;");
+    let _ =
+        conn.execute_batch("ALTER TABLE sources ADD COLUMN enabled INTEGER NOT NULL DEFAULT 1;");
+    let _ = conn.execute_batch("ALTER TABLE sources ADD COLUMN public_from TEXT;");
+    let _ = conn.execute_batch("ALTER TABLE sources ADD COLUMN public_until TEXT;");
+    let _ = conn.execute_batch("ALTER TABLE sources ADD COLUMN cron_schedule TEXT;");
+    let _ = conn.execute_batch(
+        "ALTER TABLE sources ADD COLUMN signed_public_urls INTEGER NOT NULL DEFAULT 0;",
+    );
+    let _ = conn
+        .execute_batch("ALTER TABLE sources ADD COLUMN skip_on_empty INTEGER NOT NULL DEFAULT 1;");
+    let _ = conn.execute_batch(
+        "ALTER TABLE sources ADD COLUMN propfind_events INTEGER NOT NULL DEFAULT 0;",
+    );
+    let _ = conn
+        .execute_batch("ALTER TABLE sources ADD COLUMN auto_discover INTEGER NOT NULL DEFAULT 0;");
+    let _ = conn.execute_batch(
+        "ALTER TABLE sources ADD COLUMN allow_invalid_certs INTEGER NOT NULL DEFAULT 0;",
+    );
     let _ = conn.execute_batch(
         "CREATE UNIQUE INDEX IF NOT EXISTS uq_sources_public_ics_path ON sources(public_ics_path) WHERE public_ics_path IS NOT NULL;",
     );
@@ -121,12 +605,115 @@ pub fn init_db(conn: &Connection) -> Result<()> {
             created_at TEXT NOT NULL DEFAULT (datetime('now'))
         );",
     )?;
+    let _ = conn.execute_batch("ALTER TABLE source_paths ADD COLUMN expires_at TEXT;");
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS sync_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            source_id INTEGER NOT NULL REFERENCES sources(id) ON DELETE CASCADE,
+            started_at TEXT NOT NULL,
+            finished_at TEXT NOT NULL DEFAULT (datetime('now')),
+            status TEXT NOT NULL,
+            events INTEGER NOT NULL DEFAULT 0,
+            error TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_sync_history_source ON sync_history(source_id, started_at);
+        CREATE TABLE IF NOT EXISTS calendar_ics_data (
+            source_id INTEGER NOT NULL REFERENCES sources(id) ON DELETE CASCADE,
+            calendar_href TEXT NOT NULL,
+            ics_content TEXT NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+            PRIMARY KEY (source_id, calendar_href)
+        );
+        CREATE TABLE IF NOT EXISTS calendar_objects (
+            source_id INTEGER NOT NULL REFERENCES sources(id) ON DELETE CASCADE,
+            calendar_href TEXT NOT NULL,
+            href TEXT NOT NULL,
+            data TEXT NOT NULL,
+            PRIMARY KEY (source_id, calendar_href, href)
+        );",
+    )?;
+    let _ = conn.execute_batch("ALTER TABLE calendar_ics_data ADD COLUMN sync_token TEXT;");
+    let _ = conn.execute_batch(
+        "ALTER TABLE destinations ADD COLUMN allow_redirects INTEGER NOT NULL DEFAULT 1;",
+    );
+    let _ = conn.execute_batch(
+        "ALTER TABLE destinations ADD COLUMN max_redirects INTEGER NOT NULL DEFAULT 10;",
+    );
+    let _ = conn.execute_batch(
+        "ALTER TABLE destinations ADD COLUMN redirect_cross_host_headers INTEGER NOT NULL DEFAULT 0;",
+    );
+    let _ = conn.execute_batch(
+        "ALTER TABLE destinations ADD COLUMN trust_last_modified INTEGER NOT NULL DEFAULT 0;",
+    );
+    let _ = conn
+        .execute_batch("ALTER TABLE destinations ADD COLUMN match_by TEXT NOT NULL DEFAULT 'uid';");
+    let _ = conn.execute_batch(
+        "ALTER TABLE destinations ADD COLUMN allow_invalid_certs INTEGER NOT NULL DEFAULT 0;",
+    );
+    let _ = conn.execute_batch("ALTER TABLE destinations ADD COLUMN webhook_on_success_url TEXT;");
+    let _ = conn.execute_batch("ALTER TABLE destinations ADD COLUMN webhook_on_failure_url TEXT;");
+    let _ = conn.execute_batch(
+        "ALTER TABLE destinations ADD COLUMN set_display_name INTEGER NOT NULL DEFAULT 0;",
+    );
+    let _ = conn.execute_batch(
+        "ALTER TABLE destinations ADD COLUMN debug_http INTEGER NOT NULL DEFAULT 0;",
+    );
+    let _ = conn.execute_batch(
+        "ALTER TABLE destinations ADD COLUMN prefer_minimal INTEGER NOT NULL DEFAULT 0;",
+    );
+    let _ = conn.execute_batch(
+        "ALTER TABLE destinations ADD COLUMN compress_uploads INTEGER NOT NULL DEFAULT 0;",
+    );
+    let _ = conn.execute_batch(
+        "ALTER TABLE destinations ADD COLUMN extra_headers TEXT NOT NULL DEFAULT '{}';",
+    );
+    let _ = conn.execute_batch(
+        "ALTER TABLE destinations ADD COLUMN max_shrink_percent INTEGER NOT NULL DEFAULT 0;",
+    );
+    let _ = conn.execute_batch("ALTER TABLE destinations ADD COLUMN last_total_events INTEGER;");
+    let _ = conn.execute_batch(
+        "ALTER TABLE destinations ADD COLUMN uuid_hrefs INTEGER NOT NULL DEFAULT 0;",
+    );
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS destination_hrefs (
+            destination_id INTEGER NOT NULL REFERENCES destinations(id) ON DELETE CASCADE,
+            uid TEXT NOT NULL,
+            href TEXT NOT NULL,
+            PRIMARY KEY (destination_id, uid)
+        );",
+    )?;
+    let _ = conn.execute_batch(
+        "ALTER TABLE destinations ADD COLUMN skip_own_origin INTEGER NOT NULL DEFAULT 0;",
+    );
+    let _ = conn.execute_batch(
+        "ALTER TABLE destinations ADD COLUMN future_window_days INTEGER NOT NULL DEFAULT 0;",
+    );
+    let _ = conn.execute_batch(
+        "ALTER TABLE destinations ADD COLUMN ignore_transp INTEGER NOT NULL DEFAULT 0;",
+    );
+    let _ = conn.execute_batch("ALTER TABLE destinations ADD COLUMN calendar_url_override TEXT;");
+    let _ = conn.execute_batch("ALTER TABLE destinations ADD COLUMN filter_timezone TEXT;");
+    let _ = conn.execute_batch(
+        "ALTER TABLE sources ADD COLUMN consecutive_failures INTEGER NOT NULL DEFAULT 0;",
+    );
+    let _ = conn.execute_batch(
+        "ALTER TABLE destinations ADD COLUMN consecutive_failures INTEGER NOT NULL DEFAULT 0;",
+    );
+    let _ = conn.execute_batch("ALTER TABLE destinations ADD COLUMN cron_schedule TEXT;");
+    let _ = conn.execute_batch("ALTER TABLE sources ADD COLUMN resolved_caldav_url TEXT;");
+    let _ = conn.execute_batch(
+        "ALTER TABLE sources ADD COLUMN tag_calendar_origin INTEGER NOT NULL DEFAULT 0;",
+    );
+    let _ = conn.execute_batch(
+        "ALTER TABLE destinations ADD COLUMN last_success TEXT;
+         UPDATE destinations SET last_success = last_synced WHERE last_sync_status = 'ok';",
+    );
     Ok(())
 }
 
 pub fn list_sources(conn: &Connection) -> Result<Vec<Source>> {
     let mut stmt = conn.prepare(
-        "SELECT id, name, caldav_url, username, password, ics_path, sync_interval_secs, last_synced, last_sync_status, last_sync_error, created_at, public_ics, public_ics_path FROM sources ORDER BY id",
+        "SELECT id, name, caldav_url, username, password, ics_path, sync_interval_secs, last_synced, last_sync_status, last_sync_error, created_at, public_ics, public_ics_path, allow_invalid_certs, webhook_on_success_url, webhook_on_failure_url, default_timezone, sort_events, updated_at, summary_template, max_event_bytes, last_sync_trimmed, refresh_interval_secs, kind, debug_http, auto_discover, propfind_events, skip_on_empty, enabled, public_from, public_until, signed_public_urls, consecutive_failures, cron_schedule, resolved_caldav_url, tag_calendar_origin FROM sources ORDER BY id",
     )?;
     let rows = stmt.query_map([], |row| {
         Ok(Source {
@@ -139,18 +726,49 @@ pub fn list_sources(conn: &Connection) -> Result<Vec<Source>> {
             sync_interval_secs: row.get(6)?,
             last_synced: row.get(7)?,
             last_sync_status: row.get(8)?,
-            last_sync_error: row.get(9)?,
+            last_sync_error: visible_sync_error(row.get(8)?, row.get(9)?),
             created_at: row.get(10)?,
             public_ics: row.get(11)?,
             public_ics_path: row.get(12)?,
+            allow_invalid_certs: row.get(13)?,
+            webhook_on_success_url: row.get(14)?,
+            webhook_on_failure_url: row.get(15)?,
+            default_timezone: row.get(16)?,
+            sort_events: row.get(17)?,
+            updated_at: row.get(18)?,
+            summary_template: row.get(19)?,
+            max_event_bytes: row.get(20)?,
+            last_sync_trimmed: row.get(21)?,
+            refresh_interval_secs: row.get(22)?,
+            kind: SourceKind::from_db(&row.get::<_, String>(23)?),
+            debug_http: row.get(24)?,
+            auto_discover: row.get(25)?,
+            propfind_events: row.get(26)?,
+            skip_on_empty: row.get(27)?,
+            enabled: row.get(28)?,
+            public_from: row.get(29)?,
+            public_until: row.get(30)?,
+            signed_public_urls: row.get(31)?,
+            consecutive_failures: row.get(32)?,
+            cron_schedule: row.get(33)?,
+            resolved_caldav_url: row.get(34)?,
+            tag_calendar_origin: row.get(35)?,
         })
     })?;
     Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
 }
 
+/// Sources whose `updated_at` is at or after `since` (a `change_timestamp` value).
+pub fn list_sources_changed_since(conn: &Connection, since: &str) -> Result<Vec<Source>> {
+    Ok(list_sources(conn)?
+        .into_iter()
+        .filter(|s| s.updated_at.as_str() >= since)
+        .collect())
+}
+
 pub fn get_source(conn: &Connection, id: i64) -> Result<Option<Source>> {
     let mut stmt = conn.prepare(
-        "SELECT id, name, caldav_url, username, password, ics_path, sync_interval_secs, last_synced, last_sync_status, last_sync_error, created_at, public_ics, public_ics_path FROM sources WHERE id = ?1",
+        "SELECT id, name, caldav_url, username, password, ics_path, sync_interval_secs, last_synced, last_sync_status, last_sync_error, created_at, public_ics, public_ics_path, allow_invalid_certs, webhook_on_success_url, webhook_on_failure_url, default_timezone, sort_events, updated_at, summary_template, max_event_bytes, last_sync_trimmed, refresh_interval_secs, kind, debug_http, auto_discover, propfind_events, skip_on_empty, enabled, public_from, public_until, signed_public_urls, consecutive_failures, cron_schedule, resolved_caldav_url, tag_calendar_origin FROM sources WHERE id = ?1",
     )?;
     let mut rows = stmt.query_map(params![id], |row| {
         Ok(Source {
@@ -163,10 +781,33 @@ pub fn get_source(conn: &Connection, id: i64) -> Result<Option<Source>> {
             sync_interval_secs: row.get(6)?,
             last_synced: row.get(7)?,
             last_sync_status: row.get(8)?,
-            last_sync_error: row.get(9)?,
+            last_sync_error: visible_sync_error(row.get(8)?, row.get(9)?),
             created_at: row.get(10)?,
             public_ics: row.get(11)?,
             public_ics_path: row.get(12)?,
+            allow_invalid_certs: row.get(13)?,
+            webhook_on_success_url: row.get(14)?,
+            webhook_on_failure_url: row.get(15)?,
+            default_timezone: row.get(16)?,
+            sort_events: row.get(17)?,
+            updated_at: row.get(18)?,
+            summary_template: row.get(19)?,
+            max_event_bytes: row.get(20)?,
+            last_sync_trimmed: row.get(21)?,
+            refresh_interval_secs: row.get(22)?,
+            kind: SourceKind::from_db(&row.get::<_, String>(23)?),
+            debug_http: row.get(24)?,
+            auto_discover: row.get(25)?,
+            propfind_events: row.get(26)?,
+            skip_on_empty: row.get(27)?,
+            enabled: row.get(28)?,
+            public_from: row.get(29)?,
+            public_until: row.get(30)?,
+            signed_public_urls: row.get(31)?,
+            consecutive_failures: row.get(32)?,
+            cron_schedule: row.get(33)?,
+            resolved_caldav_url: row.get(34)?,
+            tag_calendar_origin: row.get(35)?,
         })
     })?;
     match rows.next() {
@@ -182,6 +823,10 @@ fn validate_ics_path(path: &str) -> Result<()> {
         trimmed != "public" && !trimmed.starts_with("public/"),
         "ICS path cannot start with 'public' — reserved for public ICS URLs"
     );
+    ensure!(
+        trimmed != "by-id" && !trimmed.starts_with("by-id/"),
+        "ICS path cannot start with 'by-id' — reserved for ICS URLs by source ID"
+    );
     Ok(())
 }
 
@@ -196,6 +841,7 @@ fn validate_public_path(
             ensure!(!p.starts_with('/'), "Public ICS path must not start with /");
             ensure!(!p.contains(".."), "Public ICS path must not contain ..");
             validate_ics_path(p)?;
+            purge_expired_source_paths(conn)?;
             let count: i64 = match exclude_id {
                 Some(id) => conn.query_row(
                     "SELECT count(*) FROM sources WHERE (ics_path = ?1 OR public_ics_path = ?1) AND id != ?2",
@@ -224,24 +870,23 @@ fn validate_public_path(
     }
 }
 
-pub fn create_source(conn: &Connection, src: &CreateSource) -> Result<i64> {
-    require_non_empty("Name", &src.name)?;
-    require_non_empty("CalDAV URL", &src.caldav_url)?;
-    require_non_empty("Username", &src.username)?;
-    require_non_empty("Password", &src.password)?;
-    require_non_empty("ICS Path", &src.ics_path)?;
-    validate_ics_path(&src.ics_path)?;
-    require_non_negative("Sync interval", src.sync_interval_secs)?;
-
+/// Checks that a new source's paths are free and returns the public path to store.
+fn validate_new_source_paths(
+    conn: &Connection,
+    ics_path: &str,
+    public_ics: bool,
+    public_ics_path: Option<&str>,
+) -> Result<Option<String>> {
+    purge_expired_source_paths(conn)?;
     let count: i64 = conn.query_row(
         "SELECT count(*) FROM sources WHERE ics_path = ?1 OR public_ics_path = ?1",
-        [&src.ics_path],
+        [ics_path],
         |row| row.get(0),
     )?;
     ensure!(count == 0, "Duplicate ICS Path is not allowed");
     let sp_count: i64 = conn.query_row(
         "SELECT count(*) FROM source_paths WHERE path = ?1",
-        params![&src.ics_path],
+        params![ics_path],
         |row| row.get(0),
     )?;
     ensure!(
@@ -249,25 +894,202 @@ pub fn create_source(conn: &Connection, src: &CreateSource) -> Result<i64> {
         "ICS path conflicts with an existing source path"
     );
 
-    let public_path = if src.public_ics {
-        validate_public_path(conn, src.public_ics_path.as_deref(), None)?
+    let public_path = if public_ics {
+        validate_public_path(conn, public_ics_path, None)?
     } else {
         None
     };
     if let Some(ref pp) = public_path {
         ensure!(
-            pp != &src.ics_path,
+            pp != ics_path,
             "Public ICS path cannot be the same as the ICS path"
         );
     }
+    Ok(public_path)
+}
+
+pub fn create_source(conn: &Connection, src: &CreateSource) -> Result<i64> {
+    require_non_empty("Name", &src.name)?;
+    require_non_empty("CalDAV URL", &src.caldav_url)?;
+    require_non_empty("Username", &src.username)?;
+    require_non_empty("Password", &src.password)?;
+    require_non_empty("ICS Path", &src.ics_path)?;
+    validate_ics_path(&src.ics_path)?;
+    require_non_negative("Sync interval", src.sync_interval_secs)?;
+    require_non_negative("Max event bytes", src.max_event_bytes)?;
+    require_non_negative("Refresh interval", src.refresh_interval_secs)?;
+    validate_webhook_url(src.webhook_on_success_url.as_deref())?;
+    validate_webhook_url(src.webhook_on_failure_url.as_deref())?;
+    validate_timezone(src.default_timezone.as_deref())?;
+    crate::cron::validate(src.cron_schedule.as_deref())?;
+    validate_summary_template(src.summary_template.as_deref())?;
+    let public_from = normalize_window_time("Public from", src.public_from.as_deref())?;
+    let public_until = normalize_window_time("Public until", src.public_until.as_deref())?;
+    validate_public_window(public_from.as_deref(), public_until.as_deref())?;
+
+    let public_path = validate_new_source_paths(
+        conn,
+        &src.ics_path,
+        src.public_ics,
+        src.public_ics_path.as_deref(),
+    )?;
 
     conn.execute(
-        "INSERT INTO sources (name, caldav_url, username, password, ics_path, sync_interval_secs, public_ics, public_ics_path) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-        params![src.name, src.caldav_url, src.username, src.password, src.ics_path, src.sync_interval_secs, src.public_ics, public_path],
+        "INSERT INTO sources (name, caldav_url, username, password, ics_path, sync_interval_secs, public_ics, public_ics_path, allow_invalid_certs, webhook_on_success_url, webhook_on_failure_url, default_timezone, sort_events, updated_at, summary_template, max_event_bytes, refresh_interval_secs, debug_http, auto_discover, propfind_events, skip_on_empty, enabled, public_from, public_until, signed_public_urls, cron_schedule, tag_calendar_origin) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27)",
+        params![src.name, src.caldav_url, src.username, src.password, src.ics_path, src.sync_interval_secs, src.public_ics, public_path, src.allow_invalid_certs, non_empty(src.webhook_on_success_url.as_deref()), non_empty(src.webhook_on_failure_url.as_deref()), non_empty(src.default_timezone.as_deref()), src.sort_events, change_timestamp(), non_empty(src.summary_template.as_deref()), src.max_event_bytes, src.refresh_interval_secs, src.debug_http, src.auto_discover, src.propfind_events, src.skip_on_empty, src.enabled, public_from, public_until, src.signed_public_urls, non_empty(src.cron_schedule.as_deref()), src.tag_calendar_origin],
     )?;
     Ok(conn.last_insert_rowid())
 }
 
+/// Copies a source into a new row under a new `ics_path`. The copy starts
+/// private: a public path must be unique, so it is not carried over.
+/// Rejects uploads that are not a single VCALENDAR object.
+fn validate_ics_upload(ics: &str) -> Result<()> {
+    let trimmed = ics.trim();
+    ensure!(
+        trimmed.starts_with("BEGIN:VCALENDAR") && trimmed.ends_with("END:VCALENDAR"),
+        "ICS content must be a VCALENDAR object (BEGIN:VCALENDAR ... END:VCALENDAR)"
+    );
+    Ok(())
+}
+
+/// Creates a source that serves uploaded ICS content. It has no CalDAV
+/// server and never auto-syncs.
+pub fn create_static_source(conn: &Connection, src: &CreateStaticSource) -> Result<i64> {
+    require_non_empty("Name", &src.name)?;
+    require_non_empty("ICS Path", &src.ics_path)?;
+    validate_ics_path(&src.ics_path)?;
+    validate_ics_upload(&src.ics)?;
+    let public_path = validate_new_source_paths(
+        conn,
+        &src.ics_path,
+        src.public_ics,
+        src.public_ics_path.as_deref(),
+    )?;
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO sources (name, caldav_url, username, password, ics_path, sync_interval_secs, public_ics, public_ics_path, updated_at, kind, last_synced) VALUES (?1, '', '', '', ?2, 0, ?3, ?4, ?5, ?6, datetime('now'))",
+        params![src.name, src.ics_path, src.public_ics, public_path, change_timestamp(), SourceKind::Static.as_str()],
+    )?;
+    let id = tx.last_insert_rowid();
+    save_ics_data(&tx, id, &src.ics)?;
+    tx.commit()?;
+    Ok(id)
+}
+
+/// Creates a source that mirrors an ICS feed. The URL is kept in
+/// `caldav_url`; there are no CalDAV credentials.
+pub fn create_subscription_source(
+    conn: &Connection,
+    src: &CreateSubscriptionSource,
+) -> Result<i64> {
+    require_non_empty("Name", &src.name)?;
+    require_non_empty("ICS URL", &src.ics_url)?;
+    ensure!(
+        reqwest::Url::parse(src.ics_url.trim())
+            .is_ok_and(|url| matches!(url.scheme(), "http" | "https")),
+        "ICS URL must be an http or https URL"
+    );
+    require_non_empty("ICS Path", &src.ics_path)?;
+    validate_ics_path(&src.ics_path)?;
+    require_non_negative("Sync interval", src.sync_interval_secs)?;
+    let public_path = validate_new_source_paths(
+        conn,
+        &src.ics_path,
+        src.public_ics,
+        src.public_ics_path.as_deref(),
+    )?;
+
+    conn.execute(
+        "INSERT INTO sources (name, caldav_url, username, password, ics_path, sync_interval_secs, public_ics, public_ics_path, allow_invalid_certs, updated_at, kind) VALUES (?1, ?2, '', '', ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![src.name, src.ics_url.trim(), src.ics_path, src.sync_interval_secs, src.public_ics, public_path, src.allow_invalid_certs, change_timestamp(), SourceKind::Subscription.as_str()],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Replaces the served content of a static source. Returns `false` when the
+/// source does not exist.
+pub fn replace_static_ics(conn: &Connection, id: i64, ics: &str) -> Result<bool> {
+    let Some(source) = get_source(conn, id)? else {
+        return Ok(false);
+    };
+    ensure!(
+        source.kind == SourceKind::Static,
+        "Only static sources accept uploaded ICS; CalDAV sources are rewritten on every sync"
+    );
+    validate_ics_upload(ics)?;
+    save_ics_data(conn, id, ics)?;
+    update_last_synced(conn, id)?;
+    Ok(true)
+}
+
+pub fn duplicate_source(conn: &Connection, id: i64, dup: &DuplicateSource) -> Result<Option<i64>> {
+    let Some(existing) = get_source(conn, id)? else {
+        return Ok(None);
+    };
+    if existing.kind == SourceKind::Static {
+        let copy = CreateStaticSource {
+            name: dup
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("Copy of {}", existing.name)),
+            ics_path: dup.ics_path.clone(),
+            public_ics: false,
+            public_ics_path: None,
+            ics: get_ics_data(conn, id)?.unwrap_or_default(),
+        };
+        return create_static_source(conn, &copy).map(Some);
+    }
+    if existing.kind == SourceKind::Subscription {
+        let copy = CreateSubscriptionSource {
+            name: dup
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("Copy of {}", existing.name)),
+            ics_url: existing.caldav_url,
+            ics_path: dup.ics_path.clone(),
+            sync_interval_secs: existing.sync_interval_secs,
+            public_ics: false,
+            public_ics_path: None,
+            allow_invalid_certs: existing.allow_invalid_certs,
+        };
+        return create_subscription_source(conn, &copy).map(Some);
+    }
+    let copy = CreateSource {
+        name: dup
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("Copy of {}", existing.name)),
+        caldav_url: existing.caldav_url,
+        username: existing.username,
+        password: existing.password,
+        ics_path: dup.ics_path.clone(),
+        sync_interval_secs: existing.sync_interval_secs,
+        public_ics: false,
+        public_ics_path: None,
+        allow_invalid_certs: existing.allow_invalid_certs,
+        webhook_on_success_url: existing.webhook_on_success_url,
+        webhook_on_failure_url: existing.webhook_on_failure_url,
+        default_timezone: existing.default_timezone,
+        sort_events: existing.sort_events,
+        summary_template: existing.summary_template,
+        max_event_bytes: existing.max_event_bytes,
+        refresh_interval_secs: existing.refresh_interval_secs,
+        debug_http: existing.debug_http,
+        auto_discover: existing.auto_discover,
+        propfind_events: existing.propfind_events,
+        skip_on_empty: existing.skip_on_empty,
+        enabled: existing.enabled,
+        public_from: existing.public_from,
+        public_until: existing.public_until,
+        signed_public_urls: existing.signed_public_urls,
+        cron_schedule: existing.cron_schedule,
+        tag_calendar_origin: existing.tag_calendar_origin,
+    };
+    create_source(conn, &copy).map(Some)
+}
+
 pub fn update_source(conn: &Connection, id: i64, upd: &UpdateSource) -> Result<bool> {
     let existing = match get_source(conn, id)? {
         Some(s) => s,
@@ -289,18 +1111,51 @@ pub fn update_source(conn: &Connection, id: i64, upd: &UpdateSource) -> Result<b
     }
     if let Some(v) = upd.sync_interval_secs {
         require_non_negative("Sync interval", v)?;
+        ensure!(
+            v == 0 || existing.kind != SourceKind::Static,
+            "Static sources do not auto-sync; sync interval must be 0"
+        );
+    }
+    if let Some(v) = upd.max_event_bytes {
+        require_non_negative("Max event bytes", v)?;
+    }
+    if let Some(v) = upd.refresh_interval_secs {
+        require_non_negative("Refresh interval", v)?;
+    }
+    if let Some(v) = upd.ics_path_grace_secs {
+        require_non_negative("ICS path grace period", v)?;
     }
+    validate_webhook_url(upd.webhook_on_success_url.as_deref())?;
+    validate_webhook_url(upd.webhook_on_failure_url.as_deref())?;
+    validate_timezone(upd.default_timezone.as_deref())?;
+    validate_summary_template(upd.summary_template.as_deref())?;
+    crate::cron::validate(upd.cron_schedule.as_deref())?;
+    ensure!(
+        existing.kind != SourceKind::Static || non_empty(upd.cron_schedule.as_deref()).is_none(),
+        "Static sources do not auto-sync; cron schedule must be empty"
+    );
+    let public_from = match upd.public_from.as_deref() {
+        Some(v) => normalize_window_time("Public from", Some(v))?,
+        None => existing.public_from.clone(),
+    };
+    let public_until = match upd.public_until.as_deref() {
+        Some(v) => normalize_window_time("Public until", Some(v))?,
+        None => existing.public_until.clone(),
+    };
+    validate_public_window(public_from.as_deref(), public_until.as_deref())?;
 
     if let Some(ref new_path) = upd.ics_path {
+        purge_expired_source_paths(conn)?;
         let count: i64 = conn.query_row(
             "SELECT count(*) FROM sources WHERE (ics_path = ?1 OR public_ics_path = ?1) AND id != ?2",
             params![new_path, id],
             |row| row.get(0),
         )?;
         ensure!(count == 0, "Duplicate ICS Path is not allowed");
+        // The source's own rename alias may be reclaimed; it is dropped below.
         let sp_count: i64 = conn.query_row(
-            "SELECT count(*) FROM source_paths WHERE path = ?1",
-            params![new_path],
+            "SELECT count(*) FROM source_paths WHERE path = ?1 AND NOT (source_id = ?2 AND expires_at IS NOT NULL)",
+            params![new_path, id],
             |row| row.get(0),
         )?;
         ensure!(
@@ -327,8 +1182,13 @@ pub fn update_source(conn: &Connection, id: i64, upd: &UpdateSource) -> Result<b
         );
     }
 
-    conn.execute(
-        "UPDATE sources SET name = ?1, caldav_url = ?2, username = ?3, password = ?4, ics_path = ?5, sync_interval_secs = ?6, public_ics = ?7, public_ics_path = ?8 WHERE id = ?9",
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "DELETE FROM source_paths WHERE source_id = ?1 AND path = ?2 AND expires_at IS NOT NULL",
+        params![id, eff_ics_path],
+    )?;
+    tx.execute(
+        "UPDATE sources SET name = ?1, caldav_url = ?2, username = ?3, password = ?4, ics_path = ?5, sync_interval_secs = ?6, public_ics = ?7, public_ics_path = ?8, allow_invalid_certs = ?9, webhook_on_success_url = ?10, webhook_on_failure_url = ?11, default_timezone = ?12, sort_events = ?13, updated_at = ?14, summary_template = ?15, max_event_bytes = ?16, refresh_interval_secs = ?17, debug_http = ?18, auto_discover = ?19, propfind_events = ?20, skip_on_empty = ?21, enabled = ?22, public_from = ?23, public_until = ?24, signed_public_urls = ?25, cron_schedule = ?26, resolved_caldav_url = CASE WHEN caldav_url = ?2 THEN resolved_caldav_url END, tag_calendar_origin = ?27 WHERE id = ?28",
         params![
             upd.name.as_deref().unwrap_or(&existing.name),
             upd.caldav_url.as_deref().unwrap_or(&existing.caldav_url),
@@ -338,12 +1198,48 @@ pub fn update_source(conn: &Connection, id: i64, upd: &UpdateSource) -> Result<b
             upd.sync_interval_secs.unwrap_or(existing.sync_interval_secs),
             eff_public_ics,
             eff_public_path,
+            upd.allow_invalid_certs.unwrap_or(existing.allow_invalid_certs),
+            updated_optional(upd.webhook_on_success_url.as_deref(), existing.webhook_on_success_url.as_deref()),
+            updated_optional(upd.webhook_on_failure_url.as_deref(), existing.webhook_on_failure_url.as_deref()),
+            updated_optional(upd.default_timezone.as_deref(), existing.default_timezone.as_deref()),
+            upd.sort_events.unwrap_or(existing.sort_events),
+            change_timestamp(),
+            updated_optional(upd.summary_template.as_deref(), existing.summary_template.as_deref()),
+            upd.max_event_bytes.unwrap_or(existing.max_event_bytes),
+            upd.refresh_interval_secs.unwrap_or(existing.refresh_interval_secs),
+            upd.debug_http.unwrap_or(existing.debug_http),
+            upd.auto_discover.unwrap_or(existing.auto_discover),
+            upd.propfind_events.unwrap_or(existing.propfind_events),
+            upd.skip_on_empty.unwrap_or(existing.skip_on_empty),
+            upd.enabled.unwrap_or(existing.enabled),
+            public_from,
+            public_until,
+            upd.signed_public_urls.unwrap_or(existing.signed_public_urls),
+            updated_optional(upd.cron_schedule.as_deref(), existing.cron_schedule.as_deref()),
+            upd.tag_calendar_origin.unwrap_or(existing.tag_calendar_origin),
             id
         ],
     )?;
+    let grace = upd.ics_path_grace_secs.unwrap_or(0);
+    if grace > 0 && eff_ics_path != existing.ics_path {
+        // Served like the standard path: public only when that one is.
+        tx.execute(
+            "INSERT INTO source_paths (source_id, path, is_public, expires_at)
+             VALUES (?1, ?2, ?3, datetime('now', '+' || ?4 || ' seconds'))",
+            params![
+                id,
+                existing.ics_path,
+                eff_public_ics && eff_public_path.is_none(),
+                grace
+            ],
+        )?;
+    }
+    tx.commit()?;
     Ok(true)
 }
 
+/// Deletes are permanent: the source's `ics_path`, public path and extra
+/// paths (removed by cascade) can be reused by a new source right away.
 pub fn delete_source(conn: &Connection, id: i64) -> Result<bool> {
     let rows = conn.execute("DELETE FROM sources WHERE id = ?1", params![id])?;
     Ok(rows > 0)
@@ -351,84 +1247,484 @@ pub fn delete_source(conn: &Connection, id: i64) -> Result<bool> {
 
 pub fn update_last_synced(conn: &Connection, id: i64) -> Result<()> {
     conn.execute(
-        "UPDATE sources SET last_synced = datetime('now') WHERE id = ?1",
-        params![id],
+        "UPDATE sources SET last_synced = datetime('now'), updated_at = ?1 WHERE id = ?2",
+        params![change_timestamp(), id],
+    )?;
+    Ok(())
+}
+
+pub fn update_trimmed_events(conn: &Connection, id: i64, trimmed: usize) -> Result<()> {
+    conn.execute(
+        "UPDATE sources SET last_sync_trimmed = ?1 WHERE id = ?2",
+        params![trimmed as i64, id],
     )?;
     Ok(())
 }
 
+/// A successful sync has no error; rows written before that was enforced
+/// may still hold one.
+fn visible_sync_error(status: Option<String>, error: Option<String>) -> Option<String> {
+    error.filter(|_| status.as_deref() != Some("ok"))
+}
+
+/// Records a sync outcome. `error` is dropped when `status` is `ok`, so an
+/// old failure never lingers next to a success.
 pub fn update_sync_status(
     conn: &Connection,
-    id: i64,
-    status: &str,
-    error: Option<&str>,
-) -> Result<()> {
-    conn.execute(
-        "UPDATE sources SET last_sync_status = ?1, last_sync_error = ?2 WHERE id = ?3",
-        params![status, error, id],
+    id: i64,
+    status: &str,
+    error: Option<&str>,
+) -> Result<()> {
+    let error = error.filter(|_| status != "ok");
+    conn.execute(
+        &format!(
+            "UPDATE sources SET last_sync_status = ?1, last_sync_error = ?2, updated_at = ?3, {} WHERE id = ?4",
+            COUNT_FAILURES
+        ),
+        params![status, error, change_timestamp(), id],
+    )?;
+    Ok(())
+}
+
+/// Assignment keeping `consecutive_failures` in step with the status in
+/// `?1`: bumped by `error`, reset by `ok`, kept for anything else.
+const COUNT_FAILURES: &str = "consecutive_failures = CASE ?1 WHEN 'ok' THEN 0 WHEN 'error' THEN consecutive_failures + 1 ELSE consecutive_failures END";
+
+/// Records where a source's `caldav_url` redirected to, or clears it when
+/// `url` is the configured URL itself.
+pub fn update_resolved_caldav_url(conn: &Connection, id: i64, url: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE sources SET resolved_caldav_url = NULLIF(?1, caldav_url) WHERE id = ?2",
+        params![url, id],
+    )?;
+    Ok(())
+}
+
+/// Turns a source on or off without touching its other settings.
+pub fn set_source_enabled(conn: &Connection, id: i64, enabled: bool) -> Result<()> {
+    conn.execute(
+        "UPDATE sources SET enabled = ?1, updated_at = ?2 WHERE id = ?3",
+        params![enabled, change_timestamp(), id],
+    )?;
+    Ok(())
+}
+
+/// Rows per transaction for `insert_in_batches` unless configured otherwise.
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// Runs `f` in a transaction, committing if it succeeds and rolling back
+/// otherwise. Inside an open transaction, `f` simply joins it.
+pub fn with_transaction<T>(
+    conn: &Connection,
+    f: impl FnOnce(&Connection) -> Result<T>,
+) -> Result<T> {
+    if !conn.is_autocommit() {
+        return f(conn);
+    }
+    let tx = conn.unchecked_transaction()?;
+    let value = f(&tx)?;
+    tx.commit()?;
+    Ok(value)
+}
+
+/// Writes `items` with `insert`, committing every `batch_size` rows so long
+/// writes don't hold the write lock throughout. A short final batch is
+/// committed too; 0 writes everything in one transaction.
+pub fn insert_in_batches<T>(
+    conn: &Connection,
+    items: &[T],
+    batch_size: usize,
+    mut insert: impl FnMut(&Connection, &T) -> Result<()>,
+) -> Result<()> {
+    let batch_size = if batch_size == 0 {
+        items.len().max(1)
+    } else {
+        batch_size
+    };
+    for batch in items.chunks(batch_size) {
+        with_transaction(conn, |tx| {
+            batch.iter().try_for_each(|item| insert(tx, item))
+        })?;
+    }
+    Ok(())
+}
+
+/// Leading bytes of a gzip stream. Rows of `ics_data` holding a blob that
+/// starts with them are compressed; text rows are stored as-is.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// A feed as stored in `ics_data`: plain text, or gzip when it was saved
+/// with `COMPRESS_ICS_STORAGE` on. Both kinds can share a database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoredIcs {
+    Plain(String),
+    Gzip(Vec<u8>),
+}
+
+impl rusqlite::types::FromSql for StoredIcs {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        use rusqlite::types::{FromSqlError, ValueRef};
+        match value {
+            ValueRef::Blob(b) if b.starts_with(&GZIP_MAGIC) => Ok(Self::Gzip(b.to_vec())),
+            ValueRef::Text(b) | ValueRef::Blob(b) => String::from_utf8(b.to_vec())
+                .map(Self::Plain)
+                .map_err(|e| FromSqlError::Other(Box::new(e))),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl StoredIcs {
+    /// Gzips a feed for storage. Line breaks are normalized to CRLF first,
+    /// the form feeds are served in, so the bytes can be sent to clients
+    /// without decompressing.
+    pub fn compress(content: &str) -> Result<Vec<u8>> {
+        use std::io::Write;
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(crate::config::LineEnding::Crlf.apply(content).as_bytes())?;
+        Ok(encoder.finish()?)
+    }
+
+    /// The feed text, decompressing if needed.
+    pub fn into_text(self) -> Result<String> {
+        match self {
+            Self::Plain(text) => Ok(text),
+            Self::Gzip(bytes) => {
+                use std::io::Read;
+                let mut text = String::new();
+                flate2::read::GzDecoder::new(bytes.as_slice()).read_to_string(&mut text)?;
+                Ok(text)
+            }
+        }
+    }
+}
+
+pub fn save_ics_data(conn: &Connection, source_id: i64, content: &str) -> Result<()> {
+    save_ics_data_with(conn, source_id, content, false)
+}
+
+/// Like [`save_ics_data`], gzipping the feed when `compress` is set.
+pub fn save_ics_data_with(
+    conn: &Connection,
+    source_id: i64,
+    content: &str,
+    compress: bool,
+) -> Result<()> {
+    let compressed;
+    let value: &dyn rusqlite::ToSql = if compress {
+        compressed = StoredIcs::compress(content)?;
+        &compressed
+    } else {
+        &content
+    };
+    conn.execute(
+        "INSERT INTO ics_data (source_id, ics_content, updated_at) VALUES (?1, ?2, datetime('now'))
+         ON CONFLICT(source_id) DO UPDATE SET ics_content = ?2, updated_at = datetime('now')",
+        params![source_id, value],
+    )?;
+    Ok(())
+}
+
+/// Drops the served feed of a source; it 404s until the next sync.
+pub fn clear_ics_data(conn: &Connection, source_id: i64) -> Result<()> {
+    conn.execute(
+        "DELETE FROM ics_data WHERE source_id = ?1",
+        params![source_id],
+    )?;
+    Ok(())
+}
+
+pub fn get_ics_data(conn: &Connection, source_id: i64) -> Result<Option<String>> {
+    let mut stmt = conn.prepare("SELECT ics_content FROM ics_data WHERE source_id = ?1")?;
+    let mut rows = stmt.query_map(params![source_id], |row| row.get::<_, StoredIcs>(0))?;
+    match rows.next() {
+        Some(Ok(s)) => s.into_text().map(Some),
+        Some(Err(e)) => Err(e.into()),
+        None => Ok(None),
+    }
+}
+
+/// Replaces the stored per-calendar ICS for a source; calendars no longer
+/// present are removed.
+pub fn save_calendar_ics(
+    conn: &Connection,
+    source_id: i64,
+    calendars: &[(String, String)],
+) -> Result<()> {
+    with_transaction(conn, |tx| {
+        tx.execute(
+            "DELETE FROM calendar_ics_data WHERE source_id = ?1",
+            params![source_id],
+        )?;
+        for (href, content) in calendars {
+            tx.execute(
+                "INSERT INTO calendar_ics_data (source_id, calendar_href, ics_content) VALUES (?1, ?2, ?3)",
+                params![source_id, href, content],
+            )?;
+        }
+        Ok(())
+    })
+}
+
+pub fn get_calendar_ics(conn: &Connection, source_id: i64, href: &str) -> Result<Option<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT ics_content FROM calendar_ics_data WHERE source_id = ?1 AND calendar_href = ?2",
+    )?;
+    let mut rows = stmt.query_map(params![source_id, href], |row| row.get::<_, String>(0))?;
+    match rows.next() {
+        Some(Ok(s)) => Ok(Some(s)),
+        Some(Err(e)) => Err(e.into()),
+        None => Ok(None),
+    }
+}
+
+/// Incremental-sync bookkeeping for one calendar of a source.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CalendarSyncState {
+    pub href: String,
+    /// Token from the last `sync-collection` REPORT; `None` forces a full fetch.
+    pub sync_token: Option<String>,
+    /// Calendar objects as (object href, calendar-data), in server order.
+    pub objects: Vec<(String, String)>,
+}
+
+/// Stores each calendar's sync-token and objects, committing objects
+/// `batch_size` at a time. Call after `save_calendar_ics`, which recreates
+/// the calendar rows and clears their tokens. Tokens are stored last, so a
+/// failed write leaves a full fetch for next time rather than partial objects.
+pub fn save_calendar_sync_state(
+    conn: &Connection,
+    source_id: i64,
+    calendars: &[CalendarSyncState],
+    batch_size: usize,
+) -> Result<()> {
+    with_transaction(conn, |tx| {
+        tx.execute(
+            "UPDATE calendar_ics_data SET sync_token = NULL WHERE source_id = ?1",
+            params![source_id],
+        )?;
+        tx.execute(
+            "DELETE FROM calendar_objects WHERE source_id = ?1",
+            params![source_id],
+        )?;
+        Ok(())
+    })?;
+    let objects: Vec<(&str, &str, &str)> = calendars
+        .iter()
+        .flat_map(|cal| {
+            cal.objects
+                .iter()
+                .map(|(href, data)| (cal.href.as_str(), href.as_str(), data.as_str()))
+        })
+        .collect();
+    insert_in_batches(conn, &objects, batch_size, |tx, (calendar, href, data)| {
+        tx.execute(
+            "INSERT OR REPLACE INTO calendar_objects (source_id, calendar_href, href, data) VALUES (?1, ?2, ?3, ?4)",
+            params![source_id, calendar, href, data],
+        )?;
+        Ok(())
+    })?;
+    with_transaction(conn, |tx| {
+        for cal in calendars {
+            tx.execute(
+                "UPDATE calendar_ics_data SET sync_token = ?3 WHERE source_id = ?1 AND calendar_href = ?2",
+                params![source_id, cal.href, cal.sync_token],
+            )?;
+        }
+        Ok(())
+    })
+}
+
+/// Loads the state saved by `save_calendar_sync_state`, keyed by calendar href.
+pub fn get_calendar_sync_state(
+    conn: &Connection,
+    source_id: i64,
+) -> Result<HashMap<String, CalendarSyncState>> {
+    let mut stmt = conn
+        .prepare("SELECT calendar_href, sync_token FROM calendar_ics_data WHERE source_id = ?1")?;
+    let mut calendars = stmt
+        .query_map(params![source_id], |row| {
+            Ok(CalendarSyncState {
+                href: row.get(0)?,
+                sync_token: row.get(1)?,
+                objects: Vec::new(),
+            })
+        })?
+        .map(|cal| cal.map(|cal| (cal.href.clone(), cal)))
+        .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT calendar_href, href, data FROM calendar_objects WHERE source_id = ?1 ORDER BY rowid",
     )?;
-    Ok(())
+    let rows = stmt.query_map(params![source_id], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+        ))
+    })?;
+    for row in rows {
+        let (calendar_href, href, data) = row?;
+        if let Some(cal) = calendars.get_mut(&calendar_href) {
+            cal.objects.push((href, data));
+        }
+    }
+    Ok(calendars)
 }
 
-pub fn save_ics_data(conn: &Connection, source_id: i64, content: &str) -> Result<()> {
-    conn.execute(
-        "INSERT INTO ics_data (source_id, ics_content, updated_at) VALUES (?1, ?2, datetime('now'))
-         ON CONFLICT(source_id) DO UPDATE SET ics_content = ?2, updated_at = datetime('now')",
-        params![source_id, content],
-    )?;
-    Ok(())
+/// A stored feed as served, with whether its source is overdue for a sync.
+#[derive(Debug, Clone)]
+pub struct ServedFeed {
+    pub content: StoredIcs,
+    pub stale: bool,
+    /// The source's public links must carry a valid signature.
+    pub signed: bool,
 }
 
-pub fn get_ics_data(conn: &Connection, source_id: i64) -> Result<Option<String>> {
-    let mut stmt = conn.prepare("SELECT ics_content FROM ics_data WHERE source_id = ?1")?;
-    let mut rows = stmt.query_map(params![source_id], |row| row.get::<_, String>(0))?;
+/// SQL flagging a source `s` whose last successful sync is older than `?2`
+/// sync intervals; never set when `?2` is 0 or the source does not auto-sync.
+const STALE_SQL: &str = "(?2 > 0 AND s.sync_interval_secs > 0 AND s.last_synced IS NOT NULL
+     AND s.last_synced < datetime('now', '-' || (?2 * s.sync_interval_secs) || ' seconds'))";
+
+/// Runs a feed query whose `?1` is the path or source ID in `key`.
+fn query_served_feed(
+    conn: &Connection,
+    sql: &str,
+    key: impl rusqlite::ToSql,
+    stale_factor: i64,
+) -> Result<Option<ServedFeed>> {
+    let mut stmt = conn.prepare(&sql.replace("{stale}", STALE_SQL))?;
+    let mut rows = stmt.query_map(params![key, stale_factor], |row| {
+        Ok(ServedFeed {
+            content: row.get(0)?,
+            stale: row.get(1)?,
+            signed: row.get(2)?,
+        })
+    })?;
     match rows.next() {
-        Some(Ok(s)) => Ok(Some(s)),
+        Some(Ok(feed)) => Ok(Some(feed)),
         Some(Err(e)) => Err(e.into()),
         None => Ok(None),
     }
 }
 
 pub fn get_ics_data_by_path(conn: &Connection, path: &str) -> Result<Option<String>> {
-    let mut stmt = conn.prepare(
-        "SELECT d.ics_content FROM ics_data d JOIN sources s ON d.source_id = s.id
+    get_feed_by_path(conn, path, 0)?
+        .map(|feed| feed.content.into_text())
+        .transpose()
+}
+
+/// Like [`get_ics_data_by_path`], also flagging a feed not synced within
+/// `stale_factor` sync intervals (0 disables the check).
+pub fn get_feed_by_path(
+    conn: &Connection,
+    path: &str,
+    stale_factor: i64,
+) -> Result<Option<ServedFeed>> {
+    query_served_feed(
+        conn,
+        "SELECT d.ics_content, {stale}, s.signed_public_urls FROM ics_data d JOIN sources s ON d.source_id = s.id
          WHERE s.ics_path = ?1
          UNION ALL
-         SELECT d.ics_content FROM ics_data d JOIN source_paths sp ON d.source_id = sp.source_id
-         WHERE sp.path = ?1
+         SELECT d.ics_content, {stale}, s.signed_public_urls FROM ics_data d
+         JOIN source_paths sp ON d.source_id = sp.source_id
+         JOIN sources s ON s.id = sp.source_id
+         WHERE sp.path = ?1 AND (sp.expires_at IS NULL OR sp.expires_at > datetime('now'))
          LIMIT 1",
-    )?;
-    let mut rows = stmt.query_map(params![path], |row| row.get::<_, String>(0))?;
-    match rows.next() {
-        Some(Ok(s)) => Ok(Some(s)),
-        Some(Err(e)) => Err(e.into()),
-        None => Ok(None),
-    }
+        path,
+        stale_factor,
+    )
+}
+
+/// The feed of source `id`, whatever its paths; see `/ics/by-id/{id}`.
+pub fn get_feed_by_id(conn: &Connection, id: i64, stale_factor: i64) -> Result<Option<ServedFeed>> {
+    query_served_feed(
+        conn,
+        "SELECT d.ics_content, {stale}, s.signed_public_urls FROM ics_data d JOIN sources s ON d.source_id = s.id
+         WHERE s.id = ?1",
+        id,
+        stale_factor,
+    )
+}
+
+/// Public counterpart of [`get_feed_by_id`]: only sources that are public
+/// and inside their public window.
+pub fn get_feed_by_public_id(
+    conn: &Connection,
+    id: i64,
+    stale_factor: i64,
+) -> Result<Option<ServedFeed>> {
+    query_served_feed(
+        conn,
+        "SELECT d.ics_content, {stale}, s.signed_public_urls FROM ics_data d JOIN sources s ON d.source_id = s.id
+         WHERE s.id = ?1 AND s.public_ics = 1
+           AND (s.public_from IS NULL OR s.public_from <= datetime('now'))
+           AND (s.public_until IS NULL OR s.public_until > datetime('now'))",
+        id,
+        stale_factor,
+    )
 }
 
 pub fn get_ics_data_by_public_path(conn: &Connection, path: &str) -> Result<Option<String>> {
-    let mut stmt = conn.prepare(
-        "SELECT d.ics_content FROM ics_data d JOIN sources s ON d.source_id = s.id
+    get_feed_by_public_path(conn, path, 0)?
+        .map(|feed| feed.content.into_text())
+        .transpose()
+}
+
+/// Public counterpart of [`get_feed_by_path`].
+pub fn get_feed_by_public_path(
+    conn: &Connection,
+    path: &str,
+    stale_factor: i64,
+) -> Result<Option<ServedFeed>> {
+    query_served_feed(
+        conn,
+        "SELECT d.ics_content, {stale}, s.signed_public_urls FROM ics_data d JOIN sources s ON d.source_id = s.id
          WHERE s.public_ics_path = ?1 AND s.public_ics = 1
+           AND (s.public_from IS NULL OR s.public_from <= datetime('now'))
+           AND (s.public_until IS NULL OR s.public_until > datetime('now'))
          UNION ALL
-         SELECT d.ics_content FROM ics_data d JOIN source_paths sp ON d.source_id = sp.source_id
+         SELECT d.ics_content, {stale}, s.signed_public_urls FROM ics_data d
+         JOIN source_paths sp ON d.source_id = sp.source_id
+         JOIN sources s ON s.id = sp.source_id
          WHERE sp.path = ?1 AND sp.is_public = 1
+           AND (sp.expires_at IS NULL OR sp.expires_at > datetime('now'))
+           AND (s.public_from IS NULL OR s.public_from <= datetime('now'))
+           AND (s.public_until IS NULL OR s.public_until > datetime('now'))
          LIMIT 1",
+        path,
+        stale_factor,
+    )
+}
+
+/// Stored feeds of every source currently public (`public_ics` set and inside
+/// its public window), in source order. Sources behind signed links are left
+/// out.
+pub fn list_public_ics(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT d.ics_content FROM ics_data d JOIN sources s ON d.source_id = s.id
+         WHERE s.public_ics = 1 AND s.signed_public_urls = 0
+           AND (s.public_from IS NULL OR s.public_from <= datetime('now'))
+           AND (s.public_until IS NULL OR s.public_until > datetime('now'))
+         ORDER BY s.id",
     )?;
-    let mut rows = stmt.query_map(params![path], |row| row.get::<_, String>(0))?;
-    match rows.next() {
-        Some(Ok(s)) => Ok(Some(s)),
-        Some(Err(e)) => Err(e.into()),
-        None => Ok(None),
-    }
+    let rows = stmt.query_map([], |row| row.get::<_, StoredIcs>(0))?;
+    rows.map(|row| row?.into_text()).collect()
 }
 
 pub fn is_public_standard_ics(conn: &Connection, ics_path: &str) -> Result<bool> {
     let count: i64 = conn.query_row(
         "SELECT count(*) FROM (
-            SELECT 1 FROM sources WHERE ics_path = ?1 AND public_ics = 1 AND (public_ics_path IS NULL OR public_ics_path = '')
+            SELECT 1 FROM sources WHERE ics_path = ?1 AND public_ics = 1 AND signed_public_urls = 0
+              AND (public_ics_path IS NULL OR public_ics_path = '')
+              AND (public_from IS NULL OR public_from <= datetime('now'))
+              AND (public_until IS NULL OR public_until > datetime('now'))
             UNION ALL
-            SELECT 1 FROM source_paths WHERE path = ?1 AND is_public = 1
+            SELECT 1 FROM source_paths sp JOIN sources s ON s.id = sp.source_id
+            WHERE sp.path = ?1 AND sp.is_public = 1 AND s.signed_public_urls = 0
+              AND (sp.expires_at IS NULL OR sp.expires_at > datetime('now'))
+              AND (s.public_from IS NULL OR s.public_from <= datetime('now'))
+              AND (s.public_until IS NULL OR s.public_until > datetime('now'))
          ) t",
         params![ics_path],
         |row| row.get(0),
@@ -445,6 +1741,9 @@ pub struct SourcePath {
     pub path: String,
     pub is_public: bool,
     pub created_at: String,
+    /// Set on aliases left behind by an `ics_path` rename; the path stops
+    /// being served at this UTC time and is then freed.
+    pub expires_at: Option<String>,
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
@@ -454,13 +1753,23 @@ pub struct CreateSourcePath {
     pub is_public: bool,
 }
 
-#[derive(Debug, Deserialize, ToSchema)]
+#[derive(Debug, Default, Deserialize, ToSchema)]
 pub struct UpdateSourcePath {
     pub path: Option<String>,
     pub is_public: Option<bool>,
 }
 
+/// Drops rename aliases past their expiry so their paths can be reused.
+fn purge_expired_source_paths(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "DELETE FROM source_paths WHERE expires_at IS NOT NULL AND expires_at <= datetime('now')",
+        [],
+    )?;
+    Ok(())
+}
+
 fn validate_source_path(conn: &Connection, path: &str, exclude_id: Option<i64>) -> Result<String> {
+    purge_expired_source_paths(conn)?;
     let trimmed = path.trim();
     require_non_empty("Path", trimmed)?;
     validate_ics_path(trimmed)?;
@@ -496,7 +1805,8 @@ fn validate_source_path(conn: &Connection, path: &str, exclude_id: Option<i64>)
 
 pub fn list_source_paths(conn: &Connection, source_id: i64) -> Result<Vec<SourcePath>> {
     let mut stmt = conn.prepare(
-        "SELECT id, source_id, path, is_public, created_at FROM source_paths WHERE source_id = ?1 ORDER BY id",
+        "SELECT id, source_id, path, is_public, created_at, expires_at FROM source_paths
+         WHERE source_id = ?1 AND (expires_at IS NULL OR expires_at > datetime('now')) ORDER BY id",
     )?;
     let rows = stmt.query_map(params![source_id], |row| {
         Ok(SourcePath {
@@ -505,6 +1815,7 @@ pub fn list_source_paths(conn: &Connection, source_id: i64) -> Result<Vec<Source
             path: row.get(2)?,
             is_public: row.get(3)?,
             created_at: row.get(4)?,
+            expires_at: row.get(5)?,
         })
     })?;
     Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
@@ -512,7 +1823,8 @@ pub fn list_source_paths(conn: &Connection, source_id: i64) -> Result<Vec<Source
 
 pub fn get_source_path(conn: &Connection, id: i64) -> Result<Option<SourcePath>> {
     let mut stmt = conn.prepare(
-        "SELECT id, source_id, path, is_public, created_at FROM source_paths WHERE id = ?1",
+        "SELECT id, source_id, path, is_public, created_at, expires_at FROM source_paths
+         WHERE id = ?1 AND (expires_at IS NULL OR expires_at > datetime('now'))",
     )?;
     let mut rows = stmt.query_map(params![id], |row| {
         Ok(SourcePath {
@@ -521,6 +1833,7 @@ pub fn get_source_path(conn: &Connection, id: i64) -> Result<Option<SourcePath>>
             path: row.get(2)?,
             is_public: row.get(3)?,
             created_at: row.get(4)?,
+            expires_at: row.get(5)?,
         })
     })?;
     match rows.next() {
@@ -544,6 +1857,147 @@ pub fn create_source_path(
     Ok(conn.last_insert_rowid())
 }
 
+/// Outcome of one entry of `create_source_paths_batch`.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SourcePathBatchItem {
+    pub path: String,
+    /// Set when the path was inserted and the batch committed.
+    pub id: Option<i64>,
+    pub error: Option<String>,
+}
+
+/// Result of `create_source_paths_batch`.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SourcePathBatch {
+    /// False when an invalid entry rolled the whole batch back.
+    pub committed: bool,
+    pub results: Vec<SourcePathBatchItem>,
+}
+
+/// Adds several paths to a source in one transaction. Entries are validated
+/// in order, so a path repeated within the batch is rejected as a duplicate.
+/// Any failure rolls everything back unless `partial` is set, in which case
+/// the valid entries are kept.
+pub fn create_source_paths_batch(
+    conn: &Connection,
+    source_id: i64,
+    paths: &[CreateSourcePath],
+    partial: bool,
+) -> Result<SourcePathBatch> {
+    ensure!(!paths.is_empty(), "No paths given");
+    ensure!(get_source(conn, source_id)?.is_some(), "Source not found");
+
+    let tx = conn.unchecked_transaction()?;
+    let mut results = Vec::with_capacity(paths.len());
+    for body in paths {
+        let inserted = validate_source_path(&tx, &body.path, None).and_then(|path| {
+            tx.execute(
+                "INSERT INTO source_paths (source_id, path, is_public) VALUES (?1, ?2, ?3)",
+                params![source_id, path, body.is_public],
+            )?;
+            Ok(tx.last_insert_rowid())
+        });
+        results.push(match inserted {
+            Ok(id) => SourcePathBatchItem {
+                path: body.path.clone(),
+                id: Some(id),
+                error: None,
+            },
+            Err(e) => SourcePathBatchItem {
+                path: body.path.clone(),
+                id: None,
+                error: Some(e.to_string()),
+            },
+        });
+    }
+
+    let failed = results.iter().any(|r| r.error.is_some());
+    if failed && !partial {
+        // Dropping the transaction rolls it back.
+        for r in &mut results {
+            r.id = None;
+        }
+        return Ok(SourcePathBatch {
+            committed: false,
+            results,
+        });
+    }
+    tx.commit()?;
+    Ok(SourcePathBatch {
+        committed: true,
+        results,
+    })
+}
+
+/// One entry of the desired set passed to `replace_source_paths`.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DesiredSourcePath {
+    /// Existing path to keep under a new name; omit to match by `path`.
+    pub id: Option<i64>,
+    pub path: String,
+    #[serde(default)]
+    pub is_public: bool,
+}
+
+/// Makes a source's paths exactly `desired` in one transaction. Entries
+/// match existing rows by `id`, else by path; matched rows keep their id
+/// and `created_at`, rows left unmatched are deleted and new entries are
+/// created. Any invalid entry rolls the whole change back.
+pub fn replace_source_paths(
+    conn: &Connection,
+    source_id: i64,
+    desired: &[DesiredSourcePath],
+) -> Result<Vec<SourcePath>> {
+    ensure!(get_source(conn, source_id)?.is_some(), "Source not found");
+
+    let tx = conn.unchecked_transaction()?;
+    let existing = list_source_paths(&tx, source_id)?;
+    let mut kept: Vec<Option<&SourcePath>> = Vec::with_capacity(desired.len());
+    for entry in desired {
+        let matched = match entry.id {
+            Some(id) => Some(
+                existing
+                    .iter()
+                    .find(|sp| sp.id == id)
+                    .ok_or_else(|| anyhow!("Path id {} does not belong to this source", id))?,
+            ),
+            None => existing.iter().find(|sp| sp.path == entry.path.trim()),
+        };
+        if let Some(sp) = matched {
+            ensure!(
+                !kept.iter().flatten().any(|k| k.id == sp.id),
+                "Path id {} is listed more than once",
+                sp.id
+            );
+        }
+        kept.push(matched);
+    }
+
+    // Clearing first lets entries swap or reuse names freed in this call;
+    // kept rows are reinserted under their old id.
+    tx.execute(
+        "DELETE FROM source_paths WHERE source_id = ?1",
+        params![source_id],
+    )?;
+    for (entry, matched) in desired.iter().zip(kept) {
+        let path = validate_source_path(&tx, &entry.path, None)
+            .map_err(|e| anyhow!("Invalid path '{}': {}", entry.path, e))?;
+        match matched {
+            Some(sp) => tx.execute(
+                "INSERT INTO source_paths (id, source_id, path, is_public, created_at, expires_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![sp.id, source_id, path, entry.is_public, sp.created_at, sp.expires_at],
+            )?,
+            None => tx.execute(
+                "INSERT INTO source_paths (source_id, path, is_public) VALUES (?1, ?2, ?3)",
+                params![source_id, path, entry.is_public],
+            )?,
+        };
+    }
+    let paths = list_source_paths(&tx, source_id)?;
+    tx.commit()?;
+    Ok(paths)
+}
+
 pub fn update_source_path(conn: &Connection, id: i64, upd: &UpdateSourcePath) -> Result<bool> {
     let existing = match get_source_path(conn, id)? {
         Some(sp) => sp,
@@ -588,6 +2042,76 @@ pub struct Destination {
     pub last_sync_status: Option<String>,
     pub last_sync_error: Option<String>,
     pub created_at: String,
+    pub allow_redirects: bool,
+    pub max_redirects: i64,
+    pub redirect_cross_host_headers: bool,
+    pub trust_last_modified: bool,
+    pub match_by: MatchBy,
+    pub allow_invalid_certs: bool,
+    pub webhook_on_success_url: Option<String>,
+    pub webhook_on_failure_url: Option<String>,
+    pub set_display_name: bool,
+    pub debug_http: bool,
+    pub prefer_minimal: bool,
+    pub compress_uploads: bool,
+    /// Sent with every ICS feed request, e.g. a `Referer` the feed host insists on.
+    pub extra_headers: BTreeMap<String, String>,
+    /// Abort a sync whose feed has this many percent fewer events than
+    /// `last_total_events`; 0 disables the check.
+    pub max_shrink_percent: i64,
+    /// Feed events in the last successful sync; `None` before the first.
+    pub last_total_events: Option<i64>,
+    /// Syncs in a row that ended in `error`; reset by an `ok` sync.
+    pub consecutive_failures: i64,
+    pub uuid_hrefs: bool,
+    pub skip_own_origin: bool,
+    pub future_window_days: i64,
+    pub ignore_transp: bool,
+    /// Full URL of the event collection, used as-is instead of deriving it
+    /// from `caldav_url` and `calendar_name`.
+    pub calendar_url_override: Option<String>,
+    /// IANA timezone whose current date decides whether an all-day event
+    /// is in the future; `None` uses the server's local date.
+    pub filter_timezone: Option<String>,
+    pub cron_schedule: Option<String>,
+    /// When the last `ok` sync finished; `last_synced` also moves on errors.
+    pub last_success: Option<String>,
+}
+
+/// Key used to decide whether an incoming event already exists on the destination.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchBy {
+    /// Match on UID only.
+    #[default]
+    Uid,
+    /// Match on UID, falling back to an event with the same DTSTART and
+    /// otherwise identical content under a different UID.
+    UidAndDtstart,
+}
+
+impl MatchBy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MatchBy::Uid => "uid",
+            MatchBy::UidAndDtstart => "uid_and_dtstart",
+        }
+    }
+
+    fn from_db(value: &str) -> Self {
+        match value {
+            "uid_and_dtstart" => MatchBy::UidAndDtstart,
+            _ => MatchBy::Uid,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_redirects() -> i64 {
+    10
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
@@ -603,9 +2127,58 @@ pub struct CreateDestination {
     pub sync_all: bool,
     #[serde(default)]
     pub keep_local: bool,
+    #[serde(default = "default_true")]
+    pub allow_redirects: bool,
+    #[serde(default = "default_max_redirects")]
+    pub max_redirects: i64,
+    #[serde(default)]
+    pub redirect_cross_host_headers: bool,
+    #[serde(default)]
+    pub trust_last_modified: bool,
+    #[serde(default)]
+    pub match_by: MatchBy,
+    #[serde(default)]
+    pub allow_invalid_certs: bool,
+    pub webhook_on_success_url: Option<String>,
+    pub webhook_on_failure_url: Option<String>,
+    #[serde(default)]
+    pub set_display_name: bool,
+    #[serde(default)]
+    pub debug_http: bool,
+    #[serde(default)]
+    pub prefer_minimal: bool,
+    #[serde(default)]
+    pub compress_uploads: bool,
+    #[serde(default)]
+    pub extra_headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub max_shrink_percent: i64,
+    /// Store events under generated UUID names instead of their UID, for
+    /// servers that reject UID-based hrefs.
+    #[serde(default)]
+    pub uuid_hrefs: bool,
+    /// Ignore feed events this service produced: calendars carrying our
+    /// PRODID and events with an `X-SYNC-ORIGIN` marker.
+    #[serde(default)]
+    pub skip_own_origin: bool,
+    /// Only sync events starting within this many days; 0 syncs every
+    /// future event. Ignored with `sync_all`.
+    #[serde(default)]
+    pub future_window_days: i64,
+    /// Don't re-upload events whose only change is TRANSP (free/busy).
+    #[serde(default)]
+    pub ignore_transp: bool,
+    /// Full URL of the event collection, used as-is instead of deriving it
+    /// from `caldav_url` and `calendar_name`.
+    pub calendar_url_override: Option<String>,
+    /// IANA timezone whose current date decides whether an all-day event
+    /// is in the future; `None` uses the server's local date.
+    pub filter_timezone: Option<String>,
+    #[serde(default)]
+    pub cron_schedule: Option<String>,
 }
 
-#[derive(Debug, Deserialize, ToSchema)]
+#[derive(Debug, Default, Deserialize, ToSchema)]
 pub struct UpdateDestination {
     pub name: Option<String>,
     pub ics_url: Option<String>,
@@ -616,8 +2189,36 @@ pub struct UpdateDestination {
     pub sync_interval_secs: Option<i64>,
     pub sync_all: Option<bool>,
     pub keep_local: Option<bool>,
+    pub allow_redirects: Option<bool>,
+    pub max_redirects: Option<i64>,
+    pub redirect_cross_host_headers: Option<bool>,
+    pub trust_last_modified: Option<bool>,
+    pub match_by: Option<MatchBy>,
+    pub allow_invalid_certs: Option<bool>,
+    pub webhook_on_success_url: Option<String>,
+    pub webhook_on_failure_url: Option<String>,
+    pub set_display_name: Option<bool>,
+    pub debug_http: Option<bool>,
+    pub prefer_minimal: Option<bool>,
+    pub compress_uploads: Option<bool>,
+    pub extra_headers: Option<BTreeMap<String, String>>,
+    pub max_shrink_percent: Option<i64>,
+    pub uuid_hrefs: Option<bool>,
+    pub skip_own_origin: Option<bool>,
+    pub future_window_days: Option<i64>,
+    pub ignore_transp: Option<bool>,
+    pub calendar_url_override: Option<String>,
+    pub filter_timezone: Option<String>,
+    pub cron_schedule: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct DuplicateDestination {
+    pub name: Option<String>,
 }
 
+const DESTINATION_COLUMNS: &str = "id, name, ics_url, caldav_url, calendar_name, username, password, sync_interval_secs, sync_all, keep_local, last_synced, last_sync_status, last_sync_error, created_at, allow_redirects, max_redirects, redirect_cross_host_headers, trust_last_modified, match_by, allow_invalid_certs, webhook_on_success_url, webhook_on_failure_url, set_display_name, debug_http, prefer_minimal, compress_uploads, extra_headers, max_shrink_percent, last_total_events, uuid_hrefs, skip_own_origin, future_window_days, ignore_transp, calendar_url_override, filter_timezone, consecutive_failures, cron_schedule, last_success";
+
 fn map_destination_row(row: &rusqlite::Row) -> rusqlite::Result<Destination> {
     Ok(Destination {
         id: row.get(0)?,
@@ -632,23 +2233,49 @@ fn map_destination_row(row: &rusqlite::Row) -> rusqlite::Result<Destination> {
         keep_local: row.get(9)?,
         last_synced: row.get(10)?,
         last_sync_status: row.get(11)?,
-        last_sync_error: row.get(12)?,
+        last_sync_error: visible_sync_error(row.get(11)?, row.get(12)?),
         created_at: row.get(13)?,
+        allow_redirects: row.get(14)?,
+        max_redirects: row.get(15)?,
+        redirect_cross_host_headers: row.get(16)?,
+        trust_last_modified: row.get(17)?,
+        match_by: MatchBy::from_db(&row.get::<_, String>(18)?),
+        allow_invalid_certs: row.get(19)?,
+        webhook_on_success_url: row.get(20)?,
+        webhook_on_failure_url: row.get(21)?,
+        set_display_name: row.get(22)?,
+        debug_http: row.get(23)?,
+        prefer_minimal: row.get(24)?,
+        compress_uploads: row.get(25)?,
+        extra_headers: headers_from_db(&row.get::<_, String>(26)?),
+        max_shrink_percent: row.get(27)?,
+        last_total_events: row.get(28)?,
+        uuid_hrefs: row.get(29)?,
+        skip_own_origin: row.get(30)?,
+        future_window_days: row.get(31)?,
+        ignore_transp: row.get(32)?,
+        calendar_url_override: row.get(33)?,
+        filter_timezone: row.get(34)?,
+        consecutive_failures: row.get(35)?,
+        cron_schedule: row.get(36)?,
+        last_success: row.get(37)?,
     })
 }
 
 pub fn list_destinations(conn: &Connection) -> Result<Vec<Destination>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, name, ics_url, caldav_url, calendar_name, username, password, sync_interval_secs, sync_all, keep_local, last_synced, last_sync_status, last_sync_error, created_at FROM destinations ORDER BY id",
-    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM destinations ORDER BY id",
+        DESTINATION_COLUMNS
+    ))?;
     let rows = stmt.query_map([], map_destination_row)?;
     Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
 }
 
 pub fn get_destination(conn: &Connection, id: i64) -> Result<Option<Destination>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, name, ics_url, caldav_url, calendar_name, username, password, sync_interval_secs, sync_all, keep_local, last_synced, last_sync_status, last_sync_error, created_at FROM destinations WHERE id = ?1",
-    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM destinations WHERE id = ?1",
+        DESTINATION_COLUMNS
+    ))?;
     let mut rows = stmt.query_map(params![id], map_destination_row)?;
     match rows.next() {
         Some(Ok(d)) => Ok(Some(d)),
@@ -663,7 +2290,10 @@ pub fn find_overlapping_destinations(
     calendar_name: &str,
     exclude_id: Option<i64>,
 ) -> Result<Vec<Destination>> {
-    let base_sql = "SELECT id, name, ics_url, caldav_url, calendar_name, username, password, sync_interval_secs, sync_all, keep_local, last_synced, last_sync_status, last_sync_error, created_at FROM destinations WHERE caldav_url = ?1 AND calendar_name = ?2";
+    let base_sql = format!(
+        "SELECT {} FROM destinations WHERE caldav_url = ?1 AND calendar_name = ?2",
+        DESTINATION_COLUMNS
+    );
 
     match exclude_id {
         Some(id) => {
@@ -674,14 +2304,78 @@ pub fn find_overlapping_destinations(
             Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
         }
         None => {
-            let mut stmt = conn.prepare(base_sql)?;
+            let mut stmt = conn.prepare(&base_sql)?;
             let rows = stmt.query_map(params![caldav_url, calendar_name], map_destination_row)?;
             Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
         }
     }
 }
 
+/// Whether saving a destination that shares `(caldav_url, calendar_name)` with
+/// another one is only reported (`check-overlap`) or refused outright.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverlapPolicy {
+    #[default]
+    Warn,
+    Reject,
+}
+
+impl OverlapPolicy {
+    pub fn from_strict(strict: bool) -> Self {
+        if strict { Self::Reject } else { Self::Warn }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "Calendar '{calendar_name}' is already synced by destination(s) {}; enable keep_local on all of them to share it",
+    conflicting.join(", ")
+)]
+pub struct DestinationOverlap {
+    pub calendar_name: String,
+    /// Names of the destinations the save would collide with.
+    pub conflicting: Vec<String>,
+}
+
+/// Under `OverlapPolicy::Reject`, fails with `DestinationOverlap` when another
+/// destination targets the same calendar, unless both sides keep local events.
+fn enforce_overlap_policy(
+    conn: &Connection,
+    policy: OverlapPolicy,
+    caldav_url: &str,
+    calendar_name: &str,
+    keep_local: bool,
+    exclude_id: Option<i64>,
+) -> Result<()> {
+    if policy == OverlapPolicy::Warn {
+        return Ok(());
+    }
+    let conflicting: Vec<String> =
+        find_overlapping_destinations(conn, caldav_url, calendar_name, exclude_id)?
+            .into_iter()
+            .filter(|d| !(keep_local && d.keep_local))
+            .map(|d| format!("'{}' (id {})", d.name, d.id))
+            .collect();
+    if conflicting.is_empty() {
+        Ok(())
+    } else {
+        Err(DestinationOverlap {
+            calendar_name: calendar_name.to_string(),
+            conflicting,
+        }
+        .into())
+    }
+}
+
 pub fn create_destination(conn: &Connection, dest: &CreateDestination) -> Result<i64> {
+    create_destination_with_policy(conn, dest, OverlapPolicy::Warn)
+}
+
+pub fn create_destination_with_policy(
+    conn: &Connection,
+    dest: &CreateDestination,
+    policy: OverlapPolicy,
+) -> Result<i64> {
     require_non_empty("Name", &dest.name)?;
     require_non_empty("ICS URL", &dest.ics_url)?;
     require_non_empty("CalDAV URL", &dest.caldav_url)?;
@@ -689,15 +2383,98 @@ pub fn create_destination(conn: &Connection, dest: &CreateDestination) -> Result
     require_non_empty("Username", &dest.username)?;
     require_non_empty("Password", &dest.password)?;
     require_non_negative("Sync interval", dest.sync_interval_secs)?;
+    require_non_negative("Max redirects", dest.max_redirects)?;
+    validate_webhook_url(dest.webhook_on_success_url.as_deref())?;
+    validate_webhook_url(dest.webhook_on_failure_url.as_deref())?;
+    validate_extra_headers(&dest.extra_headers)?;
+    validate_percent("Max shrink percent", dest.max_shrink_percent)?;
+    require_non_negative("Future window days", dest.future_window_days)?;
+    validate_calendar_url_override(dest.calendar_url_override.as_deref())?;
+    validate_timezone(dest.filter_timezone.as_deref())?;
+    crate::cron::validate(dest.cron_schedule.as_deref())?;
+    enforce_overlap_policy(
+        conn,
+        policy,
+        &dest.caldav_url,
+        &dest.calendar_name,
+        dest.keep_local,
+        None,
+    )?;
 
     conn.execute(
-        "INSERT INTO destinations (name, ics_url, caldav_url, calendar_name, username, password, sync_interval_secs, sync_all, keep_local) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-        params![dest.name, dest.ics_url, dest.caldav_url, dest.calendar_name, dest.username, dest.password, dest.sync_interval_secs, dest.sync_all, dest.keep_local],
+        "INSERT INTO destinations (name, ics_url, caldav_url, calendar_name, username, password, sync_interval_secs, sync_all, keep_local, allow_redirects, max_redirects, redirect_cross_host_headers, trust_last_modified, match_by, allow_invalid_certs, webhook_on_success_url, webhook_on_failure_url, set_display_name, debug_http, prefer_minimal, compress_uploads, extra_headers, max_shrink_percent, uuid_hrefs, skip_own_origin, future_window_days, ignore_transp, calendar_url_override, filter_timezone, cron_schedule) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30)",
+        params![dest.name, dest.ics_url, dest.caldav_url, dest.calendar_name, dest.username, dest.password, dest.sync_interval_secs, dest.sync_all, dest.keep_local, dest.allow_redirects, dest.max_redirects, dest.redirect_cross_host_headers, dest.trust_last_modified, dest.match_by.as_str(), dest.allow_invalid_certs, non_empty(dest.webhook_on_success_url.as_deref()), non_empty(dest.webhook_on_failure_url.as_deref()), dest.set_display_name, dest.debug_http, dest.prefer_minimal, dest.compress_uploads, headers_to_db(&dest.extra_headers), dest.max_shrink_percent, dest.uuid_hrefs, dest.skip_own_origin, dest.future_window_days, dest.ignore_transp, non_empty(dest.calendar_url_override.as_deref()), non_empty(dest.filter_timezone.as_deref()), non_empty(dest.cron_schedule.as_deref())],
     )?;
     Ok(conn.last_insert_rowid())
 }
 
+pub fn duplicate_destination(
+    conn: &Connection,
+    id: i64,
+    dup: &DuplicateDestination,
+) -> Result<Option<i64>> {
+    duplicate_destination_with_policy(conn, id, dup, OverlapPolicy::Warn)
+}
+
+/// Copies a destination under `policy`: the copy writes to the same
+/// calendar, so strict mode rejects it unless both keep local events.
+pub fn duplicate_destination_with_policy(
+    conn: &Connection,
+    id: i64,
+    dup: &DuplicateDestination,
+    policy: OverlapPolicy,
+) -> Result<Option<i64>> {
+    let Some(existing) = get_destination(conn, id)? else {
+        return Ok(None);
+    };
+    let copy = CreateDestination {
+        name: dup
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("Copy of {}", existing.name)),
+        ics_url: existing.ics_url,
+        caldav_url: existing.caldav_url,
+        calendar_name: existing.calendar_name,
+        username: existing.username,
+        password: existing.password,
+        sync_interval_secs: existing.sync_interval_secs,
+        sync_all: existing.sync_all,
+        keep_local: existing.keep_local,
+        allow_redirects: existing.allow_redirects,
+        max_redirects: existing.max_redirects,
+        redirect_cross_host_headers: existing.redirect_cross_host_headers,
+        trust_last_modified: existing.trust_last_modified,
+        match_by: existing.match_by,
+        allow_invalid_certs: existing.allow_invalid_certs,
+        webhook_on_success_url: existing.webhook_on_success_url,
+        webhook_on_failure_url: existing.webhook_on_failure_url,
+        set_display_name: existing.set_display_name,
+        debug_http: existing.debug_http,
+        prefer_minimal: existing.prefer_minimal,
+        compress_uploads: existing.compress_uploads,
+        extra_headers: existing.extra_headers,
+        max_shrink_percent: existing.max_shrink_percent,
+        uuid_hrefs: existing.uuid_hrefs,
+        skip_own_origin: existing.skip_own_origin,
+        future_window_days: existing.future_window_days,
+        ignore_transp: existing.ignore_transp,
+        calendar_url_override: existing.calendar_url_override,
+        filter_timezone: existing.filter_timezone,
+        cron_schedule: existing.cron_schedule,
+    };
+    create_destination_with_policy(conn, &copy, policy).map(Some)
+}
+
 pub fn update_destination(conn: &Connection, id: i64, upd: &UpdateDestination) -> Result<bool> {
+    update_destination_with_policy(conn, id, upd, OverlapPolicy::Warn)
+}
+
+pub fn update_destination_with_policy(
+    conn: &Connection,
+    id: i64,
+    upd: &UpdateDestination,
+    policy: OverlapPolicy,
+) -> Result<bool> {
     let existing = match get_destination(conn, id)? {
         Some(d) => d,
         None => return Ok(false),
@@ -721,15 +2498,41 @@ pub fn update_destination(conn: &Connection, id: i64, upd: &UpdateDestination) -
     if let Some(v) = upd.sync_interval_secs {
         require_non_negative("Sync interval", v)?;
     }
+    if let Some(v) = upd.max_redirects {
+        require_non_negative("Max redirects", v)?;
+    }
+    validate_webhook_url(upd.webhook_on_success_url.as_deref())?;
+    validate_webhook_url(upd.webhook_on_failure_url.as_deref())?;
+    if let Some(ref v) = upd.extra_headers {
+        validate_extra_headers(v)?;
+    }
+    if let Some(v) = upd.max_shrink_percent {
+        validate_percent("Max shrink percent", v)?;
+    }
+    if let Some(v) = upd.future_window_days {
+        require_non_negative("Future window days", v)?;
+    }
+    validate_calendar_url_override(upd.calendar_url_override.as_deref())?;
+    validate_timezone(upd.filter_timezone.as_deref())?;
+    crate::cron::validate(upd.cron_schedule.as_deref())?;
 
     let eff_caldav_url = upd.caldav_url.as_deref().unwrap_or(&existing.caldav_url);
     let eff_calendar_name = upd
         .calendar_name
         .as_deref()
         .unwrap_or(&existing.calendar_name);
+    let eff_keep_local = upd.keep_local.unwrap_or(existing.keep_local);
+    enforce_overlap_policy(
+        conn,
+        policy,
+        eff_caldav_url,
+        eff_calendar_name,
+        eff_keep_local,
+        Some(id),
+    )?;
 
     conn.execute(
-        "UPDATE destinations SET name = ?1, ics_url = ?2, caldav_url = ?3, calendar_name = ?4, username = ?5, password = ?6, sync_interval_secs = ?7, sync_all = ?8, keep_local = ?9 WHERE id = ?10",
+        "UPDATE destinations SET name = ?1, ics_url = ?2, caldav_url = ?3, calendar_name = ?4, username = ?5, password = ?6, sync_interval_secs = ?7, sync_all = ?8, keep_local = ?9, allow_redirects = ?10, max_redirects = ?11, redirect_cross_host_headers = ?12, trust_last_modified = ?13, match_by = ?14, allow_invalid_certs = ?15, webhook_on_success_url = ?16, webhook_on_failure_url = ?17, set_display_name = ?18, debug_http = ?19, prefer_minimal = ?20, compress_uploads = ?21, extra_headers = ?22, max_shrink_percent = ?23, uuid_hrefs = ?24, skip_own_origin = ?25, future_window_days = ?26, ignore_transp = ?27, calendar_url_override = ?28, filter_timezone = ?29, cron_schedule = ?30 WHERE id = ?31",
         params![
             upd.name.as_deref().unwrap_or(&existing.name),
             upd.ics_url.as_deref().unwrap_or(&existing.ics_url),
@@ -739,7 +2542,29 @@ pub fn update_destination(conn: &Connection, id: i64, upd: &UpdateDestination) -
             upd.password.as_deref().filter(|s| !s.trim().is_empty()).unwrap_or(&existing.password),
             upd.sync_interval_secs.unwrap_or(existing.sync_interval_secs),
             upd.sync_all.unwrap_or(existing.sync_all),
-            upd.keep_local.unwrap_or(existing.keep_local),
+            eff_keep_local,
+            upd.allow_redirects.unwrap_or(existing.allow_redirects),
+            upd.max_redirects.unwrap_or(existing.max_redirects),
+            upd.redirect_cross_host_headers
+                .unwrap_or(existing.redirect_cross_host_headers),
+            upd.trust_last_modified.unwrap_or(existing.trust_last_modified),
+            upd.match_by.unwrap_or(existing.match_by).as_str(),
+            upd.allow_invalid_certs.unwrap_or(existing.allow_invalid_certs),
+            updated_optional(upd.webhook_on_success_url.as_deref(), existing.webhook_on_success_url.as_deref()),
+            updated_optional(upd.webhook_on_failure_url.as_deref(), existing.webhook_on_failure_url.as_deref()),
+            upd.set_display_name.unwrap_or(existing.set_display_name),
+            upd.debug_http.unwrap_or(existing.debug_http),
+            upd.prefer_minimal.unwrap_or(existing.prefer_minimal),
+            upd.compress_uploads.unwrap_or(existing.compress_uploads),
+            headers_to_db(upd.extra_headers.as_ref().unwrap_or(&existing.extra_headers)),
+            upd.max_shrink_percent.unwrap_or(existing.max_shrink_percent),
+            upd.uuid_hrefs.unwrap_or(existing.uuid_hrefs),
+            upd.skip_own_origin.unwrap_or(existing.skip_own_origin),
+            upd.future_window_days.unwrap_or(existing.future_window_days),
+            upd.ignore_transp.unwrap_or(existing.ignore_transp),
+            updated_optional(upd.calendar_url_override.as_deref(), existing.calendar_url_override.as_deref()),
+            updated_optional(upd.filter_timezone.as_deref(), existing.filter_timezone.as_deref()),
+            updated_optional(upd.cron_schedule.as_deref(), existing.cron_schedule.as_deref()),
             id
         ],
     )?;
@@ -757,9 +2582,207 @@ pub fn update_destination_sync_status(
     status: &str,
     error: Option<&str>,
 ) -> Result<()> {
+    let error = error.filter(|_| status != "ok");
     conn.execute(
-        "UPDATE destinations SET last_sync_status = ?1, last_sync_error = ?2, last_synced = datetime('now') WHERE id = ?3",
+        &format!(
+            "UPDATE destinations SET last_sync_status = ?1, last_sync_error = ?2, last_synced = datetime('now'), last_success = CASE ?1 WHEN 'ok' THEN datetime('now') ELSE last_success END, {} WHERE id = ?3",
+            COUNT_FAILURES
+        ),
         params![status, error, id],
     )?;
     Ok(())
 }
+
+pub fn update_destination_total_events(conn: &Connection, id: i64, total: usize) -> Result<()> {
+    conn.execute(
+        "UPDATE destinations SET last_total_events = ?1 WHERE id = ?2",
+        params![total as i64, id],
+    )?;
+    Ok(())
+}
+
+/// Object names (the last href segment) a `uuid_hrefs` destination stored
+/// its events under, keyed by UID.
+pub fn get_destination_hrefs(conn: &Connection, id: i64) -> Result<HashMap<String, String>> {
+    let mut stmt =
+        conn.prepare("SELECT uid, href FROM destination_hrefs WHERE destination_id = ?1")?;
+    let rows = stmt.query_map(params![id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<std::result::Result<HashMap<_, _>, _>>()?)
+}
+
+/// Replaces a destination's UID to href map with the one left by a sync.
+pub fn save_destination_hrefs(
+    conn: &Connection,
+    id: i64,
+    hrefs: &BTreeMap<String, String>,
+) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "DELETE FROM destination_hrefs WHERE destination_id = ?1",
+        params![id],
+    )?;
+    for (uid, href) in hrefs {
+        tx.execute(
+            "INSERT INTO destination_hrefs (destination_id, uid, href) VALUES (?1, ?2, ?3)",
+            params![id, uid, href],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+// --- Sync history (per-run outcomes for sources) ---
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SyncHistoryEntry {
+    pub id: i64,
+    pub source_id: i64,
+    pub started_at: String,
+    pub finished_at: String,
+    pub status: String,
+    pub events: i64,
+    pub error: Option<String>,
+}
+
+/// Millisecond-precision UTC time used for `updated_at`, so changes in the
+/// same second as a client's last poll still sort after it.
+pub fn change_timestamp() -> String {
+    chrono::Utc::now()
+        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        .to_string()
+}
+
+/// Current UTC time in the same format as SQLite's `datetime('now')`.
+pub fn now_timestamp() -> String {
+    chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+pub fn record_sync_history(
+    conn: &Connection,
+    source_id: i64,
+    started_at: &str,
+    status: &str,
+    events: usize,
+    error: Option<&str>,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO sync_history (source_id, started_at, status, events, error) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![source_id, started_at, status, events as i64, error],
+    )?;
+    Ok(())
+}
+
+/// Newest-first history for a source, optionally limited to runs started at or after `since`.
+pub fn list_sync_history(
+    conn: &Connection,
+    source_id: i64,
+    since: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<SyncHistoryEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, source_id, started_at, finished_at, status, events, error FROM sync_history
+         WHERE source_id = ?1 AND (?2 IS NULL OR started_at >= ?2)
+         ORDER BY started_at DESC, id DESC LIMIT ?3 OFFSET ?4",
+    )?;
+    let rows = stmt.query_map(params![source_id, since, limit, offset], |row| {
+        Ok(SyncHistoryEntry {
+            id: row.get(0)?,
+            source_id: row.get(1)?,
+            started_at: row.get(2)?,
+            finished_at: row.get(3)?,
+            status: row.get(4)?,
+            events: row.get(5)?,
+            error: row.get(6)?,
+        })
+    })?;
+    Ok(rows.collect::<std::result::Result<Vec<_>, _>>()?)
+}
+
+pub fn count_sync_history(conn: &Connection, source_id: i64, since: Option<&str>) -> Result<i64> {
+    Ok(conn.query_row(
+        "SELECT count(*) FROM sync_history WHERE source_id = ?1 AND (?2 IS NULL OR started_at >= ?2)",
+        params![source_id, since],
+        |row| row.get(0),
+    )?)
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DbSize {
+    pub page_count: i64,
+    pub page_size: i64,
+    /// Unused pages that `VACUUM` would reclaim.
+    pub freelist_count: i64,
+    /// Main database file plus its WAL, or `None` for in-memory databases.
+    pub file_size_bytes: Option<u64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TableRowCount {
+    pub table: String,
+    pub rows: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DbStats {
+    pub size: DbSize,
+    pub tables: Vec<TableRowCount>,
+}
+
+fn pragma_i64(conn: &Connection, name: &str) -> Result<i64> {
+    Ok(conn.query_row(&format!("PRAGMA {}", name), [], |row| row.get(0))?)
+}
+
+fn db_file_size(conn: &Connection) -> Result<Option<u64>> {
+    let path: String = conn.query_row("PRAGMA database_list", [], |row| row.get(2))?;
+    if path.is_empty() {
+        return Ok(None);
+    }
+    let main = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    let wal = std::fs::metadata(format!("{}-wal", path))
+        .map(|m| m.len())
+        .unwrap_or(0);
+    Ok(Some(main + wal))
+}
+
+pub fn db_size(conn: &Connection) -> Result<DbSize> {
+    Ok(DbSize {
+        page_count: pragma_i64(conn, "page_count")?,
+        page_size: pragma_i64(conn, "page_size")?,
+        freelist_count: pragma_i64(conn, "freelist_count")?,
+        file_size_bytes: db_file_size(conn)?,
+    })
+}
+
+pub fn db_stats(conn: &Connection) -> Result<DbStats> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let mut tables = Vec::with_capacity(names.len());
+    for table in names {
+        let rows = conn.query_row(
+            &format!("SELECT count(*) FROM \"{}\"", table.replace('"', "\"\"")),
+            [],
+            |row| row.get(0),
+        )?;
+        tables.push(TableRowCount { table, rows });
+    }
+    Ok(DbStats {
+        size: db_size(conn)?,
+        tables,
+    })
+}
+
+/// Rebuilds the database file and truncates the WAL so the freed space is
+/// returned to the filesystem. Returns the size before and after.
+pub fn vacuum(conn: &Connection) -> Result<(DbSize, DbSize)> {
+    let before = db_size(conn)?;
+    conn.execute_batch("VACUUM;")?;
+    if before.file_size_bytes.is_some() {
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    }
+    Ok((before, db_size(conn)?))
+}
@@ -0,0 +1,98 @@
+use crate::api::reverse_sync::{fold_line, unfold_ics};
+
+/// Appended to a DESCRIPTION that had to be cut short.
+pub const TRUNCATION_NOTE: &str = "… [truncated]";
+
+/// Continuation overhead of folding: CRLF plus the leading space.
+const FOLD_OVERHEAD: usize = 3;
+
+fn property_name(line: &str) -> &str {
+    line.split([':', ';']).next().unwrap_or_default()
+}
+
+/// Splits a content line at the first ':' outside a quoted parameter value,
+/// e.g. `DESCRIPTION;ALTREP="cid:part1":Text`.
+fn split_value(line: &str) -> (&str, &str) {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ':' if !quoted => return (&line[..i], &line[i + 1..]),
+            _ => {}
+        }
+    }
+    (line, "")
+}
+
+/// Longest content line that still fits in `budget` octets once folded.
+fn unfolded_budget(budget: usize) -> usize {
+    budget * 74 / (74 + FOLD_OVERHEAD)
+}
+
+/// Cuts an escaped TEXT value to at most `max` bytes without splitting a
+/// character or leaving a dangling backslash escape.
+fn truncate_value(value: &str, max: usize) -> &str {
+    let mut end = max.min(value.len());
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    let cut = &value[..end];
+    let trailing = cut.len() - cut.trim_end_matches('\\').len();
+    if trailing % 2 == 1 {
+        &cut[..cut.len() - 1]
+    } else {
+        cut
+    }
+}
+
+/// Shrinks one VEVENT block (CRLF-terminated lines) that exceeds `max_bytes`:
+/// ATTACH properties are dropped and the event's DESCRIPTION is truncated with
+/// a note. Returns `None` when the event fits or has nothing to trim.
+pub fn trim_event(vevent: &str, max_bytes: usize) -> Option<String> {
+    if vevent.len() <= max_bytes {
+        return None;
+    }
+
+    let unfolded = unfold_ics(vevent);
+    let mut out: Vec<String> = Vec::new();
+    let mut description: Option<(usize, &str)> = None;
+    let mut changed = false;
+    let mut depth = 0usize;
+    for line in unfolded.lines() {
+        let name = property_name(line);
+        if line.starts_with("BEGIN:") {
+            depth += 1;
+        } else if line.starts_with("END:") {
+            depth = depth.saturating_sub(1);
+        } else if name == "ATTACH" {
+            changed = true;
+            continue;
+        } else if depth == 1 && name == "DESCRIPTION" && description.is_none() {
+            description = Some((out.len(), line));
+            out.push(String::new());
+            continue;
+        }
+        out.push(fold_line(line));
+    }
+
+    if let Some((slot, line)) = description {
+        let fixed: usize = out.iter().map(String::len).sum();
+        let budget = unfolded_budget(max_bytes.saturating_sub(fixed));
+        let (head, value) = split_value(line);
+        let line = if head.len() + 1 + value.len() + 2 <= budget {
+            line.to_string()
+        } else {
+            changed = true;
+            let room = budget.saturating_sub(head.len() + 1 + TRUNCATION_NOTE.len() + 2);
+            format!(
+                "{}:{}{}",
+                head,
+                truncate_value(value, room),
+                TRUNCATION_NOTE
+            )
+        };
+        out[slot] = fold_line(&line);
+    }
+
+    changed.then(|| out.concat())
+}
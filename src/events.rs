@@ -0,0 +1,35 @@
+use tokio::sync::broadcast;
+
+use crate::webhooks::WebhookPayload;
+
+pub const DEFAULT_EVENT_CAPACITY: usize = 256;
+
+/// Broadcasts sync results to `/api/events` subscribers. The channel is
+/// bounded: a subscriber that falls more than `capacity` events behind skips
+/// ahead and is told how many it missed, so it never stalls the syncers.
+#[derive(Clone)]
+pub struct SyncEvents {
+    tx: broadcast::Sender<WebhookPayload>,
+}
+
+impl SyncEvents {
+    pub fn new(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(capacity.max(1));
+        Self { tx }
+    }
+
+    /// Sending with no subscribers is not an error; the event is dropped.
+    pub fn publish(&self, payload: WebhookPayload) {
+        let _ = self.tx.send(payload);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<WebhookPayload> {
+        self.tx.subscribe()
+    }
+}
+
+impl Default for SyncEvents {
+    fn default() -> Self {
+        Self::new(DEFAULT_EVENT_CAPACITY)
+    }
+}
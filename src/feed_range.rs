@@ -0,0 +1,61 @@
+use chrono::NaiveDateTime;
+
+use crate::api::reverse_sync::{self, EventEnd};
+
+fn is_recurring(vevent: &str) -> bool {
+    reverse_sync::unfold_ics(vevent).lines().any(|line| {
+        let name = line.split([':', ';']).next().unwrap_or_default();
+        name == "RRULE" || name == "RDATE"
+    })
+}
+
+fn overlaps(vevent: &str, from: Option<NaiveDateTime>, to: Option<NaiveDateTime>) -> bool {
+    let start = reverse_sync::event_start_parsed(vevent);
+    if let (Some(start), Some(to)) = (start, to)
+        && start >= to
+    {
+        return false;
+    }
+    // Recurrences are not expanded, so a series that began earlier may still
+    // have occurrences in range.
+    if is_recurring(vevent) {
+        return true;
+    }
+    let end = reverse_sync::event_end_parsed(vevent)
+        .ok()
+        .map(|end| match end {
+            EventEnd::Date(d) => d.and_time(chrono::NaiveTime::MIN),
+            EventEnd::DateTime(dt) => dt,
+        });
+    !matches!((end, from), (Some(end), Some(from)) if end < from)
+}
+
+/// Drops the VEVENTs of a feed that fall outside `[from, to)`. Everything
+/// else, VTIMEZONEs included, is passed through byte for byte, as are events
+/// without a parseable start.
+pub fn filter_feed(ics: &str, from: Option<NaiveDateTime>, to: Option<NaiveDateTime>) -> String {
+    let mut out = String::with_capacity(ics.len());
+    let mut vevent = String::new();
+    let mut in_vevent = false;
+    for line in ics.split_inclusive('\n') {
+        let content = line.trim_end();
+        if !in_vevent && content == "BEGIN:VEVENT" {
+            in_vevent = true;
+        }
+        if !in_vevent {
+            out.push_str(line);
+            continue;
+        }
+        vevent.push_str(line);
+        if content == "END:VEVENT" {
+            in_vevent = false;
+            if overlaps(&vevent, from, to) {
+                out.push_str(&vevent);
+            }
+            vevent.clear();
+        }
+    }
+    // A truncated trailing event is kept rather than silently lost.
+    out.push_str(&vevent);
+    out
+}
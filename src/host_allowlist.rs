@@ -0,0 +1,326 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
+
+use anyhow::{Context, Result, bail, ensure};
+use reqwest::Url;
+
+use crate::config::AppConfig;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Entry {
+    /// `*`: any public host.
+    Any,
+    /// `*.example.com`: public hosts under the domain (stored as `.example.com`).
+    Suffix(String),
+    /// `caldav.example.com`: exactly that host.
+    Host(String),
+    /// `10.0.0.5` or `10.0.0.0/8`: addresses in the range.
+    Net(IpAddr, u8),
+}
+
+impl Entry {
+    fn parse(raw: &str) -> Result<Self> {
+        let entry = raw.strip_suffix('.').unwrap_or(raw).to_ascii_lowercase();
+        if entry == "*" {
+            return Ok(Self::Any);
+        }
+        if let Some(domain) = entry.strip_prefix("*.") {
+            ensure!(
+                is_hostname(domain),
+                "DESTINATION_HOST_ALLOWLIST contains an invalid wildcard: {}",
+                raw
+            );
+            return Ok(Self::Suffix(format!(".{}", domain)));
+        }
+        let (addr, prefix) = match entry.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (entry.as_str(), None),
+        };
+        let addr = addr.trim_start_matches('[').trim_end_matches(']');
+        if let Ok(ip) = addr.parse::<IpAddr>() {
+            let max = if ip.is_ipv4() { 32 } else { 128 };
+            let prefix = match prefix {
+                Some(p) => p
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|p| *p <= max)
+                    .with_context(|| {
+                        format!(
+                            "DESTINATION_HOST_ALLOWLIST contains an invalid range: {}",
+                            raw
+                        )
+                    })?,
+                None => max,
+            };
+            return Ok(Self::Net(ip, prefix));
+        }
+        if prefix.is_some() || !is_hostname(&entry) {
+            bail!(
+                "DESTINATION_HOST_ALLOWLIST contains an invalid host: {}",
+                raw
+            );
+        }
+        Ok(Self::Host(entry))
+    }
+
+    /// Whether the entry names this host explicitly, which is the only way
+    /// a private or loopback host is let through.
+    fn matches_explicitly(&self, host: &HostName) -> bool {
+        match (self, host) {
+            (Self::Host(h), HostName::Domain(d)) => h == d,
+            (Self::Net(net, prefix), HostName::Ip(ip)) => in_range(*ip, *net, *prefix),
+            _ => false,
+        }
+    }
+
+    fn matches(&self, host: &HostName) -> bool {
+        match (self, host) {
+            (Self::Any, _) => true,
+            (Self::Suffix(suffix), HostName::Domain(d)) => d.ends_with(suffix.as_str()),
+            _ => self.matches_explicitly(host),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum HostName {
+    Domain(String),
+    Ip(IpAddr),
+}
+
+impl HostName {
+    fn of(url: &Url) -> Option<Self> {
+        let host = url.host_str()?;
+        let bare = host.trim_start_matches('[').trim_end_matches(']');
+        Some(match bare.parse::<IpAddr>() {
+            Ok(ip) => Self::Ip(ip),
+            Err(_) => Self::Domain(host.trim_end_matches('.').to_ascii_lowercase()),
+        })
+    }
+
+    fn is_internal(&self) -> bool {
+        match self {
+            Self::Domain(d) => d == "localhost" || d.ends_with(".localhost"),
+            Self::Ip(ip) => is_internal_ip(*ip),
+        }
+    }
+}
+
+fn is_hostname(s: &str) -> bool {
+    !s.is_empty()
+        && s.split('.').all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+fn in_range(ip: IpAddr, net: IpAddr, prefix: u8) -> bool {
+    let (ip, net, bits) = match (ip, net) {
+        (IpAddr::V4(ip), IpAddr::V4(net)) => {
+            (u128::from(u32::from(ip)), u128::from(u32::from(net)), 32)
+        }
+        (IpAddr::V6(ip), IpAddr::V6(net)) => (u128::from(ip), u128::from(net), 128),
+        _ => return false,
+    };
+    let shift = bits - u32::from(prefix);
+    shift >= bits || ip >> shift == net >> shift
+}
+
+fn is_internal_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        // Carrier-grade NAT, 100.64.0.0/10.
+        || (a == 100 && b & 0xc0 == 64)
+}
+
+fn is_internal_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        // Unique local (fc00::/7) and link-local (fe80::/10).
+        || first & 0xfe00 == 0xfc00
+        || first & 0xffc0 == 0xfe80
+        || ip.to_ipv4_mapped().is_some_and(is_internal_v4)
+}
+
+/// Private, loopback, link-local and other addresses that are not on the
+/// public internet.
+pub(crate) fn is_internal_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_internal_v4(ip),
+        IpAddr::V6(ip) => is_internal_v6(ip),
+    }
+}
+
+/// A URL a source or destination was saved with, or a redirect it led to,
+/// points at a host `DESTINATION_HOST_ALLOWLIST` does not allow.
+#[derive(Debug, thiserror::Error)]
+pub enum HostNotAllowed {
+    #[error("{field} host '{host}' is not in DESTINATION_HOST_ALLOWLIST")]
+    NotListed { field: &'static str, host: String },
+    #[error(
+        "{field} host '{host}' is a private or loopback address; list it explicitly in DESTINATION_HOST_ALLOWLIST to allow it"
+    )]
+    Internal { field: &'static str, host: String },
+}
+
+/// Hosts sources and destinations may be saved with, from the
+/// comma-separated `DESTINATION_HOST_ALLOWLIST`. Entries are hostnames,
+/// `*.domain` wildcards, `*` for any public host, IP addresses and CIDR
+/// ranges. Private, loopback and link-local addresses and `localhost` only
+/// pass when a hostname, address or range entry names them; wildcards never
+/// reach them.
+///
+/// Saved URLs are checked, and so is every redirect target CalDAV requests
+/// and feed fetches follow. Hostnames are not resolved, so a public name
+/// pointing at an internal address is not caught.
+#[derive(Debug, Clone, Default)]
+pub struct HostAllowlist {
+    /// `None` (unset or blank) allows every host.
+    entries: Option<Arc<[Entry]>>,
+}
+
+impl HostAllowlist {
+    pub fn parse(raw: Option<&str>) -> Result<Self> {
+        let entries = raw
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+            .map(Entry::parse)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            entries: (!entries.is_empty()).then(|| entries.into()),
+        })
+    }
+
+    pub fn from_config(cfg: &AppConfig) -> Result<Self> {
+        Self::parse(cfg.destination_host_allowlist.as_deref())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.entries.is_some()
+    }
+
+    /// Checks the host of `url`, saved as `field`. URLs that do not parse
+    /// are left to the field's own validation.
+    pub fn check(&self, field: &'static str, url: &str) -> Result<(), HostNotAllowed> {
+        let Some(entries) = &self.entries else {
+            return Ok(());
+        };
+        let Some(host) = Url::parse(url.trim()).ok().as_ref().and_then(HostName::of) else {
+            return Ok(());
+        };
+        let display = || match &host {
+            HostName::Domain(d) => d.clone(),
+            HostName::Ip(ip) => ip.to_string(),
+        };
+        if host.is_internal() {
+            if entries.iter().any(|e| e.matches_explicitly(&host)) {
+                return Ok(());
+            }
+            return Err(HostNotAllowed::Internal {
+                field,
+                host: display(),
+            });
+        }
+        if entries.iter().any(|e| e.matches(&host)) {
+            Ok(())
+        } else {
+            Err(HostNotAllowed::NotListed {
+                field,
+                host: display(),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowlist(raw: &str) -> HostAllowlist {
+        HostAllowlist::parse(Some(raw)).unwrap()
+    }
+
+    #[test]
+    fn parse_accepts_hosts_wildcards_and_ranges() {
+        assert!(!allowlist(" , ").is_enabled());
+        assert!(!HostAllowlist::parse(None).unwrap().is_enabled());
+        assert!(allowlist("caldav.example.com, *.fastmail.com, 10.0.0.0/8, ::1, *").is_enabled());
+
+        for bad in [
+            "*..",
+            "*.a_b",
+            "a/b",
+            "10.0.0.0/33",
+            "::/129",
+            "host:8443",
+            "bad host",
+        ] {
+            assert!(
+                HostAllowlist::parse(Some(bad)).is_err(),
+                "{bad:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn check_matches_hosts_and_wildcards() {
+        let list = allowlist("caldav.example.com, *.fastmail.com");
+        assert!(
+            list.check("caldav_url", "https://CalDAV.Example.com./dav")
+                .is_ok()
+        );
+        assert!(
+            list.check("caldav_url", "https://dav.fastmail.com/")
+                .is_ok()
+        );
+        assert!(matches!(
+            list.check("caldav_url", "https://fastmail.com/"),
+            Err(HostNotAllowed::NotListed { .. })
+        ));
+        assert!(matches!(
+            list.check("caldav_url", "https://evil-example.com/"),
+            Err(HostNotAllowed::NotListed { .. })
+        ));
+        // Unparseable URLs are left to the field's own validation.
+        assert!(list.check("caldav_url", "not a url").is_ok());
+    }
+
+    #[test]
+    fn internal_hosts_need_an_explicit_entry() {
+        let list = allowlist("*, *.localhost");
+        for url in [
+            "http://127.0.0.1:8080/",
+            "http://localhost/",
+            "http://dav.localhost/",
+            "http://10.1.2.3/",
+            "http://192.168.0.1/",
+            "http://169.254.169.254/latest/meta-data",
+            "http://100.64.0.1/",
+            "http://[::1]/",
+            "http://[fd00::1]/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://2130706433/",
+        ] {
+            assert!(
+                matches!(
+                    list.check("caldav_url", url),
+                    Err(HostNotAllowed::Internal { .. })
+                ),
+                "{url} should be blocked"
+            );
+        }
+        assert!(list.check("caldav_url", "https://dav.example.org/").is_ok());
+
+        let list = allowlist("localhost, 10.0.0.0/8, ::1");
+        assert!(list.check("caldav_url", "http://localhost:5232/").is_ok());
+        assert!(list.check("caldav_url", "http://10.20.30.40/").is_ok());
+        assert!(list.check("caldav_url", "http://[::1]:5232/").is_ok());
+        assert!(list.check("caldav_url", "http://192.168.1.1/").is_err());
+    }
+}
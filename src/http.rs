@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::{Client, Url};
+
+use crate::config::AppConfig;
+use crate::host_allowlist::{HostAllowlist, is_internal_ip};
+
+/// Which HTTP versions outbound clients may use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Http2Mode {
+    /// reqwest's default: HTTP/2 when TLS ALPN offers it, otherwise HTTP/1.1.
+    #[default]
+    Negotiate,
+    /// Never use HTTP/2.
+    Http1Only,
+    /// Speak HTTP/2 from the first byte, even over plain HTTP. Servers that
+    /// reject it are retried over HTTP/1.1; see [`HttpClients::http1_fallback`].
+    PriorKnowledge,
+}
+
+impl Http2Mode {
+    pub fn from_config(cfg: &AppConfig) -> Self {
+        if cfg.http2_prior_knowledge {
+            Self::PriorKnowledge
+        } else if cfg.http2_enable {
+            Self::Negotiate
+        } else {
+            Self::Http1Only
+        }
+    }
+}
+
+/// A connection refused because its host is a private, loopback or
+/// link-local address and `ALLOW_PRIVATE_FETCH` is off.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error(
+    "Refusing to connect to {host} ({addr}): private, loopback and link-local addresses are blocked unless ALLOW_PRIVATE_FETCH=true"
+)]
+pub struct PrivateAddressBlocked {
+    pub host: String,
+    pub addr: IpAddr,
+}
+
+/// Resolver that drops private, loopback and link-local addresses, failing
+/// with [`PrivateAddressBlocked`] when nothing else is left. Checking what a
+/// name resolves to, rather than the name, also catches public names that
+/// point inside the network.
+#[derive(Clone)]
+pub struct PrivateAddressGuard {
+    inner: Arc<dyn Resolve>,
+}
+
+impl std::fmt::Debug for PrivateAddressGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrivateAddressGuard")
+            .finish_non_exhaustive()
+    }
+}
+
+impl PrivateAddressGuard {
+    /// Guards the system resolver.
+    pub fn system() -> Self {
+        Self::new(Arc::new(SystemResolver))
+    }
+
+    /// Guards `inner`, e.g. a fixed resolver in tests.
+    pub fn new(inner: Arc<dyn Resolve>) -> Self {
+        Self { inner }
+    }
+
+    /// Refuses a URL whose host is a private IP literal. reqwest connects to
+    /// those without asking the resolver, so callers check each request.
+    pub fn check_url(url: &Url) -> Result<(), PrivateAddressBlocked> {
+        let Some(host) = url.host_str() else {
+            return Ok(());
+        };
+        match host.trim_start_matches('[').trim_end_matches(']').parse() {
+            Ok(addr) if is_internal_ip(addr) => Err(PrivateAddressBlocked {
+                host: host.to_string(),
+                addr,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks a URL the way a request to it would be checked: the IP literal,
+    /// then every address its name resolves to. Lookup failures are not
+    /// reported, since the request would fail on them anyway.
+    pub async fn check_resolved(&self, url: &Url) -> Result<(), PrivateAddressBlocked> {
+        Self::check_url(url)?;
+        let Some(host) = url.host_str().filter(|h| !h.starts_with('[')) else {
+            return Ok(());
+        };
+        if host.parse::<IpAddr>().is_ok() {
+            return Ok(());
+        }
+        let Ok(name) = host.parse::<Name>() else {
+            return Ok(());
+        };
+        match self.resolve(name).await {
+            Err(e) => match e.downcast::<PrivateAddressBlocked>() {
+                Ok(blocked) => Err(*blocked),
+                Err(_) => Ok(()),
+            },
+            Ok(_) => Ok(()),
+        }
+    }
+}
+
+impl Resolve for PrivateAddressGuard {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        let resolving = self.inner.resolve(name);
+        Box::pin(async move {
+            let mut blocked = None;
+            let allowed: Vec<SocketAddr> = resolving
+                .await?
+                .filter(|addr| {
+                    let internal = is_internal_ip(addr.ip());
+                    if internal {
+                        blocked.get_or_insert(addr.ip());
+                    }
+                    !internal
+                })
+                .collect();
+            match blocked {
+                Some(addr) if allowed.is_empty() => {
+                    Err(Box::new(PrivateAddressBlocked { host, addr }) as _)
+                }
+                _ => Ok(Box::new(allowed.into_iter()) as Addrs),
+            }
+        })
+    }
+}
+
+/// getaddrinfo on the blocking pool, as reqwest's default resolver does.
+struct SystemResolver;
+
+impl Resolve for SystemResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Connection pool settings shared by every outbound sync client.
+#[derive(Debug, Clone)]
+pub struct HttpPoolConfig {
+    pub idle_timeout: Duration,
+    pub max_idle_per_host: usize,
+    pub http2: Http2Mode,
+    /// Refuses connections to private, loopback and link-local addresses;
+    /// `None` allows them. The server sets it unless `ALLOW_PRIVATE_FETCH` is on.
+    pub private_address_guard: Option<PrivateAddressGuard>,
+}
+
+impl Default for HttpPoolConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Duration::from_secs(90),
+            max_idle_per_host: 8,
+            http2: Http2Mode::Negotiate,
+            private_address_guard: None,
+        }
+    }
+}
+
+impl HttpPoolConfig {
+    pub fn from_config(cfg: &AppConfig) -> Self {
+        Self {
+            idle_timeout: Duration::from_secs(cfg.http_pool_idle_timeout_secs),
+            max_idle_per_host: cfg.http_pool_max_idle_per_host,
+            http2: Http2Mode::from_config(cfg),
+            private_address_guard: (!cfg.allow_private_fetch).then(PrivateAddressGuard::system),
+        }
+    }
+}
+
+/// Builds a client without credentials; callers attach auth per request.
+/// Without `follow_redirects` the caller handles 3xx responses itself.
+pub fn build_client(
+    pool: &HttpPoolConfig,
+    allow_invalid_certs: bool,
+    follow_redirects: bool,
+) -> Result<Client> {
+    let mut builder = Client::builder()
+        .pool_idle_timeout(pool.idle_timeout)
+        .pool_max_idle_per_host(pool.max_idle_per_host)
+        .danger_accept_invalid_certs(allow_invalid_certs);
+    match pool.http2 {
+        Http2Mode::Negotiate => {}
+        Http2Mode::Http1Only => builder = builder.http1_only(),
+        Http2Mode::PriorKnowledge => builder = builder.http2_prior_knowledge(),
+    }
+    if !follow_redirects {
+        builder = builder.redirect(reqwest::redirect::Policy::none());
+    }
+    if let Some(guard) = &pool.private_address_guard {
+        builder = builder.dns_resolver(guard.clone());
+    }
+    Ok(builder.build()?)
+}
+
+/// (allow_invalid_certs, follow_redirects, HTTP version) of a cached client.
+type ClientKey = (bool, bool, Http2Mode);
+
+/// Lazily built clients reused across sync runs so keep-alive connections
+/// (and their TLS sessions) survive between cycles. One client exists per
+/// (allow_invalid_certs, follow_redirects, HTTP version) combination.
+#[derive(Debug, Clone, Default)]
+pub struct HttpClients {
+    pool: HttpPoolConfig,
+    clients: Arc<Mutex<HashMap<ClientKey, Client>>>,
+    /// Hosts redirects may lead to; callers following redirects by hand
+    /// check each target against it.
+    host_allowlist: HostAllowlist,
+}
+
+impl HttpClients {
+    pub fn new(pool: HttpPoolConfig) -> Self {
+        Self {
+            pool,
+            clients: Arc::default(),
+            host_allowlist: HostAllowlist::default(),
+        }
+    }
+
+    pub fn with_host_allowlist(mut self, host_allowlist: HostAllowlist) -> Self {
+        self.host_allowlist = host_allowlist;
+        self
+    }
+
+    pub fn host_allowlist(&self) -> &HostAllowlist {
+        &self.host_allowlist
+    }
+
+    /// Whether clients refuse private addresses; IP literals must then be
+    /// checked with [`PrivateAddressGuard::check_url`] before each request.
+    pub fn blocks_private_addresses(&self) -> bool {
+        self.pool.private_address_guard.is_some()
+    }
+
+    pub fn get(&self, allow_invalid_certs: bool, follow_redirects: bool) -> Result<Client> {
+        self.client(allow_invalid_certs, follow_redirects, self.pool.http2)
+    }
+
+    /// An HTTP/1.1 client to retry with when prior-knowledge HTTP/2 fails,
+    /// e.g. against a server that only speaks HTTP/1.1. `None` in other modes.
+    pub fn http1_fallback(
+        &self,
+        allow_invalid_certs: bool,
+        follow_redirects: bool,
+    ) -> Result<Option<Client>> {
+        if self.pool.http2 != Http2Mode::PriorKnowledge {
+            return Ok(None);
+        }
+        self.client(allow_invalid_certs, follow_redirects, Http2Mode::Http1Only)
+            .map(Some)
+    }
+
+    fn client(
+        &self,
+        allow_invalid_certs: bool,
+        follow_redirects: bool,
+        http2: Http2Mode,
+    ) -> Result<Client> {
+        let mut clients = self
+            .clients
+            .lock()
+            .map_err(|_| anyhow::anyhow!("HTTP client cache poisoned"))?;
+        let key = (allow_invalid_certs, follow_redirects, http2);
+        if let Some(client) = clients.get(&key) {
+            return Ok(client.clone());
+        }
+        let pool = HttpPoolConfig {
+            http2,
+            ..self.pool.clone()
+        };
+        let client = build_client(&pool, allow_invalid_certs, follow_redirects)?;
+        clients.insert(key, client.clone());
+        Ok(client)
+    }
+}
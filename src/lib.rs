@@ -1,5 +1,14 @@
 pub mod api;
 pub mod auto_sync;
 pub mod config;
+pub mod cron;
 pub mod db;
+pub mod event_trim;
+pub mod events;
+pub mod feed_range;
+pub mod host_allowlist;
+pub mod http;
 pub mod server;
+pub mod signed_url;
+pub mod summary_template;
+pub mod webhooks;
@@ -14,7 +14,7 @@ use subtle::ConstantTimeEq;
 
 use crate::config::AppConfig;
 
-const AUTH_EXEMPT_PATHS: &[&str] = &["/api/health"];
+const AUTH_EXEMPT_PATHS: &[&str] = &["/api/health", "/api/health/live", "/api/health/ready"];
 
 #[derive(Clone)]
 pub enum AuthConfig {
@@ -96,10 +96,10 @@ pub async fn basic_auth_middleware(
         let Some(state) = req.extensions().get::<crate::api::AppState>() else {
             return false;
         };
-        let db = match state.db.lock() {
-            Ok(g) => g,
+        let db = match state.conn() {
+            Ok(c) => c,
             Err(e) => {
-                tracing::error!("DB lock poisoned in auth middleware: {}", e);
+                tracing::error!("DB pool unavailable in auth middleware: {}", e);
                 return false;
             }
         };
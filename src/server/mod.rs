@@ -6,3 +6,7 @@ pub mod route_builder;
 pub async fn build_router(state: crate::api::AppState, proxy_url: &str) -> Router {
     route_builder::register_routes(state, proxy_url).await
 }
+
+pub fn build_public_router(state: crate::api::AppState) -> Router {
+    route_builder::register_public_routes(state)
+}
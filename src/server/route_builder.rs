@@ -1,20 +1,51 @@
+use std::collections::{BTreeMap, HashSet};
 use std::sync::Arc;
 
 use axum::{
     Router,
-    extract::{Request, State},
-    http::StatusCode,
+    extract::{Query, Request, State},
+    http::{HeaderMap, Method, StatusCode, header},
     response::{IntoResponse, Response},
     routing::get,
 };
 use hyper_util::client::legacy::Client;
 use hyper_util::rt::TokioExecutor;
+use serde::Deserialize;
+
+use crate::api::reverse_sync;
+use crate::api::sync::redact_url;
+use crate::config::LineEnding;
+use crate::db::{ServedFeed, StoredIcs};
+use crate::feed_range;
+
+/// Where the fallback proxy forwards to, and which methods it lets through.
+struct ProxyTarget {
+    url: String,
+    allowed_methods: Option<Vec<Method>>,
+}
+
+async fn proxy_to_nextjs(State(target): State<Arc<ProxyTarget>>, mut req: Request) -> Response {
+    if let Some(allowed) = &target.allowed_methods
+        && !allowed.contains(req.method())
+    {
+        let allow = allowed
+            .iter()
+            .map(Method::as_str)
+            .collect::<Vec<_>>()
+            .join(", ");
+        return (
+            StatusCode::METHOD_NOT_ALLOWED,
+            [(header::ALLOW, allow)],
+            "Method not allowed",
+        )
+            .into_response();
+    }
+    let proxy_url = target.url.as_str();
 
-async fn proxy_to_nextjs(State(proxy_url): State<Arc<String>>, mut req: Request) -> Response {
     let proxy_uri = match proxy_url.parse::<hyper::Uri>() {
         Ok(uri) => uri,
         Err(e) => {
-            tracing::error!("Invalid proxy URL {}: {}", proxy_url, e);
+            tracing::error!("Invalid proxy URL {}: {}", redact_url(proxy_url), e);
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Invalid proxy configuration",
@@ -34,7 +65,7 @@ async fn proxy_to_nextjs(State(proxy_url): State<Arc<String>>, mut req: Request)
     match new_uri.parse() {
         Ok(uri) => *req.uri_mut() = uri,
         Err(e) => {
-            tracing::error!("Failed to parse URI {}: {}", new_uri, e);
+            tracing::error!("Failed to parse URI {}: {}", redact_url(&new_uri), e);
             return (StatusCode::INTERNAL_SERVER_ERROR, "Invalid URI").into_response();
         }
     }
@@ -61,12 +92,12 @@ async fn proxy_to_nextjs(State(proxy_url): State<Arc<String>>, mut req: Request)
     }
 }
 
-fn ics_response(result: anyhow::Result<Option<String>>) -> Response {
+fn ics_response(result: anyhow::Result<Option<String>>, line_ending: LineEnding) -> Response {
     match result {
         Ok(Some(content)) => Response::builder()
             .status(StatusCode::OK)
             .header("Content-Type", "text/calendar")
-            .body(axum::body::Body::from(content))
+            .body(axum::body::Body::from(line_ending.apply(&content)))
             .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response()),
         Ok(None) => (StatusCode::NOT_FOUND, "ICS not found").into_response(),
         Err(e) => {
@@ -76,43 +107,338 @@ fn ics_response(result: anyhow::Result<Option<String>>) -> Response {
     }
 }
 
+/// Optional `?from=&to=` window (date or RFC 3339) narrowing a served feed.
+#[derive(Deserialize)]
+struct FeedRange {
+    from: Option<String>,
+    to: Option<String>,
+}
+
+/// Serves a stored feed, filtered to the requested window when one is given.
+fn feed_response(
+    result: anyhow::Result<Option<String>>,
+    range: &FeedRange,
+    line_ending: LineEnding,
+) -> Response {
+    if range.from.is_none() && range.to.is_none() {
+        return ics_response(result, line_ending);
+    }
+    let mut bounds = [None, None];
+    for (slot, raw) in bounds.iter_mut().zip([&range.from, &range.to]) {
+        if let Some(raw) = raw {
+            match crate::api::source_events::parse_bound(raw) {
+                Some(dt) => *slot = Some(dt),
+                None => {
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        format!("Invalid date or timestamp: {}", raw),
+                    )
+                        .into_response();
+                }
+            }
+        }
+    }
+    let [from, to] = bounds;
+    ics_response(
+        result.map(|ics| ics.map(|ics| feed_range::filter_feed(&ics, from, to))),
+        line_ending,
+    )
+}
+
+/// Whether `Accept-Encoding` lists gzip with a non-zero quality.
+fn accepts_gzip(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|coding| {
+            let mut parts = coding.split(';').map(str::trim);
+            let name = parts.next().unwrap_or_default();
+            let q = parts
+                .find_map(|p| p.strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (name.eq_ignore_ascii_case("gzip") || name.eq_ignore_ascii_case("x-gzip")) && q > 0.0
+        })
+}
+
+/// Marks a feed whose source has missed several sync intervals.
+const STALE_HEADER: &str = "x-stale";
+
+/// Serves a feed looked up with a staleness check: stale content is either
+/// flagged with `X-Stale: true` or refused with 503, per configuration.
+fn served_feed_response(
+    state: &crate::api::AppState,
+    result: anyhow::Result<Option<ServedFeed>>,
+    range: &FeedRange,
+    accepts_gzip: bool,
+) -> Response {
+    let stale = matches!(&result, Ok(Some(feed)) if feed.stale);
+    if stale && state.stale_feed_unavailable {
+        return (StatusCode::SERVICE_UNAVAILABLE, "ICS feed is stale").into_response();
+    }
+    let compressed =
+        matches!(&result, Ok(Some(feed)) if matches!(feed.content, StoredIcs::Gzip(_)));
+    let unfiltered = range.from.is_none() && range.to.is_none();
+    let mut response = match result {
+        // Compressed feeds are stored with CRLF, so they can go out as-is.
+        Ok(Some(ServedFeed {
+            content: StoredIcs::Gzip(bytes),
+            ..
+        })) if accepts_gzip && unfiltered && state.ics_line_ending == LineEnding::Crlf => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .header(header::CONTENT_TYPE, "text/calendar")
+                .header(header::CONTENT_ENCODING, "gzip")
+                .body(axum::body::Body::from(bytes))
+                .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+        }
+        result => feed_response(
+            result.and_then(|feed| feed.map(|f| f.content.into_text()).transpose()),
+            range,
+            state.ics_line_ending,
+        ),
+    };
+    if compressed {
+        response.headers_mut().insert(
+            header::VARY,
+            header::HeaderValue::from_static("accept-encoding"),
+        );
+    }
+    if stale && response.status() == StatusCode::OK {
+        response.headers_mut().insert(
+            header::HeaderName::from_static(STALE_HEADER),
+            header::HeaderValue::from_static("true"),
+        );
+    }
+    response
+}
+
+/// Feeds are read-only; answer write methods here instead of letting them
+/// fall through to the frontend proxy.
+async fn ics_method_not_allowed() -> Response {
+    (
+        StatusCode::METHOD_NOT_ALLOWED,
+        [(header::ALLOW, "GET, HEAD")],
+        "Method not allowed",
+    )
+        .into_response()
+}
+
 async fn serve_ics(
     State(state): State<crate::api::AppState>,
     axum::extract::Path(path): axum::extract::Path<String>,
+    Query(range): Query<FeedRange>,
+    headers: HeaderMap,
 ) -> Response {
-    let Ok(db) = state.db.lock() else {
-        tracing::error!("DB lock poisoned serving ICS /{}", path);
-        return (StatusCode::INTERNAL_SERVER_ERROR, "Internal error").into_response();
+    let db = match state.conn() {
+        Ok(db) => db,
+        Err(e) => {
+            tracing::error!("DB pool unavailable serving ICS /{}: {}", path, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Internal error").into_response();
+        }
+    };
+    let feed = crate::db::get_feed_by_path(&db, &path, state.stale_feed_factor.into());
+    served_feed_response(&state, feed, &range, accepts_gzip(&headers))
+}
+
+/// Serves a source's feed by ID, a URL that survives `ics_path` renames.
+async fn serve_ics_by_id(
+    State(state): State<crate::api::AppState>,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+    Query(range): Query<FeedRange>,
+    headers: HeaderMap,
+) -> Response {
+    let db = match state.conn() {
+        Ok(db) => db,
+        Err(e) => {
+            tracing::error!("DB pool unavailable serving ICS of source {}: {}", id, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Internal error").into_response();
+        }
     };
-    ics_response(crate::db::get_ics_data_by_path(&db, &path))
+    let feed = crate::db::get_feed_by_id(&db, id, state.stale_feed_factor.into());
+    served_feed_response(&state, feed, &range, accepts_gzip(&headers))
+}
+
+/// `?sig=&exp=` of a signed public link; see `crate::signed_url`.
+#[derive(Deserialize)]
+struct SignedLink {
+    sig: Option<String>,
+    exp: Option<i64>,
+}
+
+impl SignedLink {
+    fn is_valid(&self, secret: Option<&str>, path: &str) -> bool {
+        match (secret, &self.sig) {
+            (Some(secret), Some(sig)) => crate::signed_url::verify(
+                secret,
+                path,
+                sig,
+                self.exp,
+                chrono::Utc::now().timestamp(),
+            ),
+            _ => false,
+        }
+    }
+}
+
+/// Public path of the merged feed; it shadows a source published as `all`.
+const PUBLIC_ALL_PATH: &str = "all";
+
+/// Lets clients and proxies reuse the merged feed for five minutes.
+const PUBLIC_ALL_CACHE_CONTROL: &str = "public, max-age=300";
+
+/// Merges stored feeds into one VCALENDAR. Each TZID and each UID is kept
+/// once, from the first feed that has it; events are ordered by UID.
+fn merge_feeds(feeds: &[String]) -> String {
+    let mut seen_tzids = HashSet::new();
+    let mut vtimezones = Vec::new();
+    let mut events = BTreeMap::new();
+    for feed in feeds {
+        let extracted = reverse_sync::extract_events(feed);
+        for tz in extracted.vtimezones {
+            let tzid = tz
+                .lines()
+                .find_map(|line| line.strip_prefix("TZID:"))
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            if seen_tzids.insert(tzid) {
+                vtimezones.push(tz);
+            }
+        }
+        for (uid, vevents) in extracted.events {
+            events.entry(uid).or_insert(vevents);
+        }
+    }
+
+    let mut output = String::from(
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//CalDAV/ICS Sync//EN\r\nCALSCALE:GREGORIAN\r\nMETHOD:PUBLISH\r\n",
+    );
+    for tz in vtimezones {
+        output.push_str(&tz);
+    }
+    for vevent in events.into_values().flatten() {
+        output.push_str(&vevent);
+    }
+    output.push_str("END:VCALENDAR\r\n");
+    reverse_sync::fold_long_lines(&output)
 }
 
 async fn serve_public_ics(
     State(state): State<crate::api::AppState>,
     axum::extract::Path(path): axum::extract::Path<String>,
+    Query(range): Query<FeedRange>,
+    Query(link): Query<SignedLink>,
+    headers: HeaderMap,
 ) -> Response {
     if path.contains("..") || path.starts_with('/') {
         return (StatusCode::BAD_REQUEST, "Invalid path").into_response();
     }
-    let Ok(db) = state.db.lock() else {
-        tracing::error!("DB lock poisoned serving public ICS /{}", path);
-        return (StatusCode::INTERNAL_SERVER_ERROR, "Internal error").into_response();
+    let db = match state.conn() {
+        Ok(db) => db,
+        Err(e) => {
+            tracing::error!("DB pool unavailable serving public ICS /{}: {}", path, e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Internal error").into_response();
+        }
     };
-    ics_response(crate::db::get_ics_data_by_public_path(&db, &path))
+    if state.public_all_feed && path == PUBLIC_ALL_PATH {
+        let merged = crate::db::list_public_ics(&db).map(|feeds| Some(merge_feeds(&feeds)));
+        let mut response = feed_response(merged, &range, state.ics_line_ending);
+        if response.status() == StatusCode::OK {
+            response.headers_mut().insert(
+                header::CACHE_CONTROL,
+                header::HeaderValue::from_static(PUBLIC_ALL_CACHE_CONTROL),
+            );
+        }
+        return response;
+    }
+    let feed = crate::db::get_feed_by_public_path(&db, &path, state.stale_feed_factor.into());
+    // A bad or missing signature looks the same as an unknown path.
+    let feed = match feed {
+        Ok(Some(f)) if f.signed && !link.is_valid(state.public_url_secret.as_deref(), &path) => {
+            Ok(None)
+        }
+        other => other,
+    };
+    served_feed_response(&state, feed, &range, accepts_gzip(&headers))
+}
+
+/// Public counterpart of [`serve_ics_by_id`]. Sources behind signed links
+/// need a link signed for the path `by-id/{id}`.
+async fn serve_public_ics_by_id(
+    State(state): State<crate::api::AppState>,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+    Query(range): Query<FeedRange>,
+    Query(link): Query<SignedLink>,
+    headers: HeaderMap,
+) -> Response {
+    let db = match state.conn() {
+        Ok(db) => db,
+        Err(e) => {
+            tracing::error!(
+                "DB pool unavailable serving public ICS of source {}: {}",
+                id,
+                e
+            );
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Internal error").into_response();
+        }
+    };
+    let feed = crate::db::get_feed_by_public_id(&db, id, state.stale_feed_factor.into());
+    let signed_path = format!("by-id/{}", id);
+    let feed = match feed {
+        Ok(Some(f))
+            if f.signed && !link.is_valid(state.public_url_secret.as_deref(), &signed_path) =>
+        {
+            Ok(None)
+        }
+        other => other,
+    };
+    served_feed_response(&state, feed, &range, accepts_gzip(&headers))
+}
+
+/// Only the unauthenticated `/ics/public/*` feeds; everything else is 404.
+pub fn register_public_routes(state: crate::api::AppState) -> Router {
+    Router::new()
+        .route(
+            "/ics/public/by-id/{id}",
+            get(serve_public_ics_by_id).fallback(ics_method_not_allowed),
+        )
+        .route(
+            "/ics/public/{*path}",
+            get(serve_public_ics).fallback(ics_method_not_allowed),
+        )
+        .with_state(state)
 }
 
 pub async fn register_routes(state: crate::api::AppState, proxy_url: &str) -> Router {
     let api_routes = crate::api::routes();
-    let proxy_url = Arc::new(proxy_url.to_owned());
+    let target = Arc::new(ProxyTarget {
+        url: proxy_url.to_owned(),
+        allowed_methods: state.proxy_allowed_methods.clone(),
+    });
 
-    let fallback_router = Router::new()
-        .fallback(proxy_to_nextjs)
-        .with_state(proxy_url);
+    let fallback_router = Router::new().fallback(proxy_to_nextjs).with_state(target);
 
     Router::new()
         .nest("/api", api_routes)
-        .route("/ics/public/{*path}", get(serve_public_ics))
-        .route("/ics/{*path}", get(serve_ics))
+        .route(
+            "/ics/public/by-id/{id}",
+            get(serve_public_ics_by_id).fallback(ics_method_not_allowed),
+        )
+        .route(
+            "/ics/by-id/{id}",
+            get(serve_ics_by_id).fallback(ics_method_not_allowed),
+        )
+        .route(
+            "/ics/public/{*path}",
+            get(serve_public_ics).fallback(ics_method_not_allowed),
+        )
+        .route(
+            "/ics/{*path}",
+            get(serve_ics).fallback(ics_method_not_allowed),
+        )
         .merge(fallback_router)
         .with_state(state)
 }
@@ -0,0 +1,32 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use ring::hmac;
+
+/// What a signature covers: the public path and the expiry, if any, so
+/// neither can be changed without invalidating the link.
+fn message(path: &str, expires: Option<i64>) -> String {
+    match expires {
+        Some(exp) => format!("{}\n{}", path, exp),
+        None => format!("{}\n", path),
+    }
+}
+
+/// Base64url HMAC-SHA256 of `path` and `expires` (Unix seconds) under `secret`.
+pub fn sign(secret: &str, path: &str, expires: Option<i64>) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = hmac::sign(&key, message(path, expires).as_bytes());
+    URL_SAFE_NO_PAD.encode(tag.as_ref())
+}
+
+/// Checks `sig` against `path` and `expires` in constant time, and that the
+/// link has not expired at `now` (Unix seconds).
+pub fn verify(secret: &str, path: &str, sig: &str, expires: Option<i64>, now: i64) -> bool {
+    if expires.is_some_and(|exp| exp <= now) {
+        return false;
+    }
+    let Ok(tag) = URL_SAFE_NO_PAD.decode(sig) else {
+        return false;
+    };
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    hmac::verify(&key, message(path, expires).as_bytes(), &tag).is_ok()
+}
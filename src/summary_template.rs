@@ -0,0 +1,124 @@
+use anyhow::{Result, bail};
+
+use crate::api::reverse_sync::fold_line;
+
+/// Placeholders a `summary_template` may use. Templates are plain text plus
+/// these substitutions; there is deliberately no expression language.
+pub const PLACEHOLDERS: &[&str] = &["summary", "calendar"];
+
+#[derive(Debug)]
+enum Segment<'a> {
+    Text(&'a str),
+    Summary,
+    Calendar,
+}
+
+fn parse(template: &str) -> Result<Vec<Segment<'_>>> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+    while !rest.is_empty() {
+        let Some(open) = rest.find(['{', '}']) else {
+            segments.push(Segment::Text(rest));
+            break;
+        };
+        if rest[open..].starts_with('}') {
+            bail!("Unmatched '}}' in summary template");
+        }
+        if open > 0 {
+            segments.push(Segment::Text(&rest[..open]));
+        }
+        let after = &rest[open + 1..];
+        let Some(close) = after.find('}') else {
+            bail!("Unclosed '{{' in summary template");
+        };
+        let name = &after[..close];
+        segments.push(match name {
+            "summary" => Segment::Summary,
+            "calendar" => Segment::Calendar,
+            _ => bail!(
+                "Unknown placeholder {{{}}} in summary template (expected one of: {})",
+                name,
+                PLACEHOLDERS
+                    .iter()
+                    .map(|p| format!("{{{}}}", p))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        });
+        rest = &after[close + 1..];
+    }
+    Ok(segments)
+}
+
+pub fn validate(template: &str) -> Result<()> {
+    parse(template).map(|_| ())
+}
+
+/// Escapes an iCalendar TEXT value.
+pub(crate) fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Renders the template into an escaped SUMMARY value. `summary` is taken as
+/// already escaped, straight from the event.
+fn render(segments: &[Segment], summary: &str, calendar: &str) -> String {
+    segments
+        .iter()
+        .map(|seg| match seg {
+            Segment::Text(t) => escape_text(t),
+            Segment::Summary => summary.to_string(),
+            Segment::Calendar => escape_text(calendar),
+        })
+        .collect()
+}
+
+/// Rewrites the SUMMARY of one VEVENT block (CRLF-terminated lines). Nested
+/// components such as VALARM keep their own SUMMARY. An event without one
+/// gets a SUMMARY when the template renders to something non-blank.
+pub fn apply(vevent: &str, template: &str, calendar: &str) -> String {
+    let Ok(segments) = parse(template) else {
+        return vevent.to_string();
+    };
+
+    let lines: Vec<&str> = vevent.lines().collect();
+    let mut out = String::with_capacity(vevent.len() + template.len());
+    let mut depth = 0usize;
+    let mut replaced = false;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+        if line.starts_with("BEGIN:") {
+            depth += 1;
+        } else if line.starts_with("END:") {
+            if line.starts_with("END:VEVENT") && depth == 1 && !replaced {
+                let value = render(&segments, "", calendar);
+                if !value.trim().is_empty() {
+                    out.push_str(&fold_line(&format!("SUMMARY:{}", value.trim())));
+                }
+            }
+            depth = depth.saturating_sub(1);
+        } else if depth == 1
+            && !replaced
+            && let Some((name, value)) = line.split_once(':')
+            && name.split(';').next() == Some("SUMMARY")
+        {
+            let mut value = value.to_string();
+            while i < lines.len() && lines[i].starts_with([' ', '\t']) {
+                value.push_str(&lines[i][1..]);
+                i += 1;
+            }
+            let rendered = render(&segments, &value, calendar);
+            out.push_str(&fold_line(&format!("{}:{}", name, rendered)));
+            replaced = true;
+            continue;
+        }
+        out.push_str(line);
+        out.push_str("\r\n");
+    }
+    out
+}
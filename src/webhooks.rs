@@ -0,0 +1,238 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use anyhow::{Result, ensure};
+use reqwest::{Client, StatusCode};
+use serde::Serialize;
+
+use crate::api::reverse_sync::ReverseSyncStats;
+use crate::config::AppConfig;
+use crate::db;
+use crate::events::SyncEvents;
+use crate::http::PrivateAddressGuard;
+
+/// Webhook delivery is fire-and-forget, so a slow receiver only costs a
+/// background task this long.
+pub const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Redirects followed per delivery, as with reqwest's default policy.
+const MAX_REDIRECTS: usize = 10;
+
+/// Status of the payload sent when a scheduled sync exhausts its retries.
+pub const RETRIES_EXHAUSTED: &str = "retries_exhausted";
+
+/// Global webhook targets; per-entity URLs take precedence when set.
+#[derive(Debug, Clone, Default)]
+pub struct WebhookConfig {
+    pub on_success_url: Option<String>,
+    pub on_failure_url: Option<String>,
+    /// Fired once per run of failures, when a scheduled sync first uses up
+    /// its whole retry budget.
+    pub on_retries_exhausted_url: Option<String>,
+    /// Keeps deliveries off private addresses, as for sync requests; `None`
+    /// when `ALLOW_PRIVATE_FETCH` is on.
+    pub private_address_guard: Option<PrivateAddressGuard>,
+}
+
+impl WebhookConfig {
+    pub fn from_config(cfg: &AppConfig) -> Self {
+        Self {
+            on_success_url: cfg.webhook_on_success_url.clone(),
+            on_failure_url: cfg.webhook_on_failure_url.clone(),
+            on_retries_exhausted_url: cfg.webhook_on_retries_exhausted_url.clone(),
+            private_address_guard: (!cfg.allow_private_fetch).then(PrivateAddressGuard::system),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookPayload {
+    pub kind: String,
+    pub id: i64,
+    pub name: String,
+    pub status: String,
+    pub counts: BTreeMap<String, usize>,
+    pub error: Option<String>,
+}
+
+impl WebhookPayload {
+    fn source(source: &db::Source, result: Result<(usize, usize), &str>) -> Self {
+        let (counts, error) = match result {
+            Ok((events, calendars)) => (vec![("events", events), ("calendars", calendars)], None),
+            Err(e) => (vec![], Some(e.to_string())),
+        };
+        Self::new("source", source.id, &source.name, counts, error)
+    }
+
+    fn destination(dest: &db::Destination, result: Result<&ReverseSyncStats, &str>) -> Self {
+        let (counts, error) = match result {
+            Ok(stats) => (
+                vec![
+                    ("uploaded", stats.uploaded),
+                    ("skipped", stats.skipped),
+                    ("deleted", stats.deleted),
+                    ("total", stats.total),
+                    ("conflicts", stats.conflicts),
+                ],
+                None,
+            ),
+            Err(e) => (vec![], Some(e.to_string())),
+        };
+        Self::new("destination", dest.id, &dest.name, counts, error)
+    }
+
+    /// Placeholder payload used to check that a receiver is reachable.
+    pub fn sample() -> Self {
+        Self::new("test", 0, "Webhook test", vec![("events", 0)], None)
+    }
+
+    fn new(
+        kind: &str,
+        id: i64,
+        name: &str,
+        counts: Vec<(&str, usize)>,
+        error: Option<String>,
+    ) -> Self {
+        Self {
+            kind: kind.into(),
+            id,
+            name: name.into(),
+            status: if error.is_some() { "error" } else { "ok" }.into(),
+            counts: counts.into_iter().map(|(k, v)| (k.into(), v)).collect(),
+            error,
+        }
+    }
+}
+
+pub fn validate_url(url: &str) -> Result<()> {
+    ensure!(
+        url.starts_with("http://") || url.starts_with("https://"),
+        "Webhook URL must use http or https"
+    );
+    Ok(())
+}
+
+/// POSTs the payload and returns the receiver's status code. With a
+/// `guard`, private addresses are refused whether written as IP literals or
+/// reached through a hostname.
+pub async fn deliver(
+    url: &str,
+    payload: &WebhookPayload,
+    guard: Option<&PrivateAddressGuard>,
+) -> Result<StatusCode> {
+    validate_url(url)?;
+    let mut builder = Client::builder().timeout(WEBHOOK_TIMEOUT);
+    if let Some(guard) = guard {
+        PrivateAddressGuard::check_url(&reqwest::Url::parse(url)?)?;
+        // IP literals skip the resolver, so every redirect hop is checked too.
+        builder = builder
+            .dns_resolver(guard.clone())
+            .redirect(reqwest::redirect::Policy::custom(|attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS {
+                    attempt.error("too many redirects")
+                } else if let Err(e) = PrivateAddressGuard::check_url(attempt.url()) {
+                    attempt.error(e)
+                } else {
+                    attempt.follow()
+                }
+            }));
+    }
+    let res = builder.build()?.post(url).json(payload).send().await?;
+    Ok(res.status())
+}
+
+/// Sends the payload to the matching webhook in the background. `overrides`
+/// are the entity's own (success, failure) URLs.
+pub fn dispatch(
+    config: &WebhookConfig,
+    overrides: (Option<&str>, Option<&str>),
+    payload: WebhookPayload,
+) {
+    let (entity_url, global_url) = if payload.error.is_none() {
+        (overrides.0, config.on_success_url.as_deref())
+    } else {
+        (overrides.1, config.on_failure_url.as_deref())
+    };
+    if let Some(url) = entity_url.or(global_url) {
+        spawn_delivery(
+            url.to_owned(),
+            payload,
+            config.private_address_guard.clone(),
+        );
+    }
+}
+
+fn spawn_delivery(url: String, payload: WebhookPayload, guard: Option<PrivateAddressGuard>) {
+    tokio::spawn(async move {
+        match deliver(&url, &payload, guard.as_ref()).await {
+            Ok(status) if status.is_success() => {}
+            Ok(status) => tracing::warn!(
+                "Webhook for {} {} returned {}",
+                payload.kind,
+                payload.id,
+                status
+            ),
+            Err(e) => tracing::warn!("Webhook for {} {} failed: {}", payload.kind, payload.id, e),
+        }
+    });
+}
+
+/// Publishes the result to event subscribers and fires the matching webhook.
+pub fn notify_source(
+    config: &WebhookConfig,
+    events: &SyncEvents,
+    source: &db::Source,
+    result: Result<(usize, usize), &str>,
+) {
+    let payload = WebhookPayload::source(source, result);
+    events.publish(payload.clone());
+    dispatch(
+        config,
+        (
+            source.webhook_on_success_url.as_deref(),
+            source.webhook_on_failure_url.as_deref(),
+        ),
+        payload,
+    );
+}
+
+pub fn notify_destination(
+    config: &WebhookConfig,
+    events: &SyncEvents,
+    dest: &db::Destination,
+    result: Result<&ReverseSyncStats, &str>,
+) {
+    let payload = WebhookPayload::destination(dest, result);
+    events.publish(payload.clone());
+    dispatch(
+        config,
+        (
+            dest.webhook_on_success_url.as_deref(),
+            dest.webhook_on_failure_url.as_deref(),
+        ),
+        payload,
+    );
+}
+
+/// Publishes a `retries_exhausted` payload and POSTs it to
+/// `on_retries_exhausted_url`. `kind` is `source` or `destination`.
+pub fn notify_retries_exhausted(
+    config: &WebhookConfig,
+    events: &SyncEvents,
+    (kind, id, name): (&str, i64, &str),
+    consecutive_failures: usize,
+    error: &str,
+) {
+    let mut payload = WebhookPayload::new(
+        kind,
+        id,
+        name,
+        vec![("consecutive_failures", consecutive_failures)],
+        Some(error.to_string()),
+    );
+    payload.status = RETRIES_EXHAUSTED.into();
+    events.publish(payload.clone());
+    if let Some(url) = &config.on_retries_exhausted_url {
+        spawn_delivery(url.clone(), payload, config.private_address_guard.clone());
+    }
+}
@@ -1,28 +1,19 @@
-use std::sync::{Arc, Mutex};
-use std::time::Instant;
-
 use axum::Router;
 use axum::body::Body;
 use axum::http::{Request, StatusCode};
 use http_body_util::BodyExt;
-use rusqlite::Connection;
 use serde_json::Value;
 use tower::ServiceExt;
 
 use caldav_ics_sync::api::AppState;
-use caldav_ics_sync::auto_sync;
 use caldav_ics_sync::db;
+use caldav_ics_sync::events::SyncEvents;
+use caldav_ics_sync::host_allowlist::HostAllowlist;
+use caldav_ics_sync::http::PrivateAddressGuard;
+use caldav_ics_sync::webhooks::WebhookPayload;
 
 fn test_state() -> AppState {
-    let conn = Connection::open_in_memory().expect("in-memory DB");
-    conn.execute_batch("PRAGMA foreign_keys=ON;")
-        .expect("enable FK");
-    db::init_db(&conn).expect("init_db");
-    AppState {
-        db: Arc::new(Mutex::new(conn)),
-        start_time: Instant::now(),
-        sync_tasks: auto_sync::new_registry(),
-    }
+    AppState::new(db::open_memory_pool().expect("in-memory DB pool"))
 }
 
 fn app(state: AppState) -> Router {
@@ -142,7 +133,7 @@ async fn list_sources_returns_created() {
     let state = test_state();
 
     {
-        let db = state.db.lock().unwrap();
+        let db = state.db.get().unwrap();
         db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap();
     }
 
@@ -163,6 +154,62 @@ async fn list_sources_returns_created() {
     assert_eq!(json["sources"][0]["name"], "Test Source");
 }
 
+async fn get_json(state: AppState, uri: &str) -> (StatusCode, Value) {
+    let resp = app(state)
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = resp.status();
+    (status, body_json(resp.into_body()).await)
+}
+
+#[tokio::test]
+async fn list_sources_changed_since_returns_only_updated() {
+    let state = test_state();
+    let (first, second) = {
+        let db = state.db.get().unwrap();
+        let first =
+            db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap();
+        let mut other = source_json();
+        other["ics_path"] = "other.ics".into();
+        let second = db::create_source(&db, &serde_json::from_value(other).unwrap()).unwrap();
+        (first, second)
+    };
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+    let (_, json) = get_json(state.clone(), "/api/sources").await;
+    assert_eq!(json["sources"].as_array().unwrap().len(), 2);
+    let poll = json["server_time"].as_str().unwrap().to_string();
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+    {
+        let db = state.db.get().unwrap();
+        db::update_sync_status(&db, second, "ok", None).unwrap();
+    }
+
+    let (status, json) = get_json(
+        state.clone(),
+        &format!("/api/sources?changed_since={}", poll),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let sources = json["sources"].as_array().unwrap();
+    assert_eq!(sources.len(), 1);
+    assert_eq!(sources[0]["id"], second);
+    assert_ne!(sources[0]["id"], first);
+
+    let next = json["server_time"].as_str().unwrap().to_string();
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    let (_, json) = get_json(state, &format!("/api/sources?changed_since={}", next)).await;
+    assert!(json["sources"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn list_sources_rejects_invalid_changed_since() {
+    let (status, _) = get_json(test_state(), "/api/sources?changed_since=yesterday").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
 // ---------- Sources: update ----------
 
 #[tokio::test]
@@ -170,7 +217,7 @@ async fn update_source_returns_200() {
     let state = test_state();
 
     let id = {
-        let db = state.db.lock().unwrap();
+        let db = state.db.get().unwrap();
         db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap()
     };
 
@@ -221,7 +268,7 @@ async fn delete_source_returns_200() {
     let state = test_state();
 
     let id = {
-        let db = state.db.lock().unwrap();
+        let db = state.db.get().unwrap();
         db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap()
     };
 
@@ -266,7 +313,7 @@ async fn create_source_path_returns_201() {
     let state = test_state();
 
     let source_id = {
-        let db = state.db.lock().unwrap();
+        let db = state.db.get().unwrap();
         db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap()
     };
 
@@ -297,7 +344,7 @@ async fn list_source_paths_returns_200() {
     let state = test_state();
 
     let source_id = {
-        let db = state.db.lock().unwrap();
+        let db = state.db.get().unwrap();
         let sid = db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap();
         db::create_source_path(
             &db,
@@ -324,6 +371,237 @@ async fn list_source_paths_returns_200() {
     assert_eq!(json["paths"].as_array().unwrap().len(), 1);
 }
 
+#[tokio::test]
+async fn all_source_paths_lists_every_url() {
+    let state = test_state();
+    let source_id = {
+        let db = state.db.get().unwrap();
+        let mut source = source_json();
+        source["public_ics"] = true.into();
+        source["public_ics_path"] = "open.ics".into();
+        let sid = db::create_source(&db, &serde_json::from_value(source).unwrap()).unwrap();
+        db::create_source_path(
+            &db,
+            sid,
+            &serde_json::from_value(serde_json::json!({"path": "extra.ics", "is_public": true}))
+                .unwrap(),
+        )
+        .unwrap();
+        sid
+    };
+
+    let resp = app(state.clone())
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/sources/{}/all-paths", source_id))
+                .header("host", "cal.example.com")
+                .header("x-forwarded-proto", "https")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let json = body_json(resp.into_body()).await;
+    let paths = json["paths"].as_array().unwrap();
+    assert_eq!(paths.len(), 3);
+    assert_eq!(paths[0]["kind"], "primary");
+    assert_eq!(paths[0]["url"], "https://cal.example.com/ics/test.ics");
+    assert_eq!(paths[0]["is_public"], false);
+    assert_eq!(paths[1]["kind"], "public");
+    assert_eq!(
+        paths[1]["url"],
+        "https://cal.example.com/ics/public/open.ics"
+    );
+    assert_eq!(paths[1]["is_public"], true);
+    assert_eq!(paths[2]["kind"], "source_path");
+    assert!(paths[2]["id"].is_i64());
+    assert_eq!(paths[2]["url"], "https://cal.example.com/ics/extra.ics");
+    assert_eq!(
+        paths[2]["public_url"],
+        "https://cal.example.com/ics/public/extra.ics"
+    );
+
+    let (status, _) = get_json(state, "/api/sources/999/all-paths").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+async fn post_path_batch(state: AppState, uri: String, body: Value) -> (StatusCode, Value) {
+    let resp = app(state)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = resp.status();
+    (status, body_json(resp.into_body()).await)
+}
+
+#[tokio::test]
+async fn batch_source_paths_is_atomic_unless_partial() {
+    let state = test_state();
+    let source_id = {
+        let db = state.db.get().unwrap();
+        db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap()
+    };
+    let body = serde_json::json!([
+        {"path": "one.ics"},
+        {"path": "../escape"},
+        {"path": "two.ics", "is_public": true}
+    ]);
+    let uri = format!("/api/sources/{}/paths/batch", source_id);
+
+    let (status, json) = post_path_batch(state.clone(), uri.clone(), body.clone()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(json["batch"]["committed"], false);
+    assert!(json["batch"]["results"][1]["error"].is_string());
+    let (_, listed) = get_json(state.clone(), &format!("/api/sources/{}/paths", source_id)).await;
+    assert!(listed["paths"].as_array().unwrap().is_empty());
+
+    let (status, json) =
+        post_path_batch(state.clone(), format!("{}?partial=true", uri), body).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(json["batch"]["committed"], true);
+    assert!(json["batch"]["results"][0]["id"].is_i64());
+    assert!(json["batch"]["results"][1]["id"].is_null());
+    assert!(json["batch"]["results"][2]["id"].is_i64());
+    let (_, listed) = get_json(state.clone(), &format!("/api/sources/{}/paths", source_id)).await;
+    assert_eq!(listed["paths"].as_array().unwrap().len(), 2);
+
+    let (status, json) = post_path_batch(
+        state.clone(),
+        "/api/sources/999/paths/batch".into(),
+        serde_json::json!([{"path": "x.ics"}]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(json["message"], "Source not found");
+
+    let (status, _) = post_path_batch(state, uri, serde_json::json!([])).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+async fn put_paths(state: AppState, source_id: i64, body: Value) -> (StatusCode, Value) {
+    let resp = app(state)
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/sources/{}/paths", source_id))
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = resp.status();
+    (status, body_json(resp.into_body()).await)
+}
+
+#[tokio::test]
+async fn replace_source_paths_adds_removes_and_renames() {
+    let state = test_state();
+    let source_id = {
+        let db = state.db.get().unwrap();
+        db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap()
+    };
+    let (_, json) = put_paths(
+        state.clone(),
+        source_id,
+        serde_json::json!([{"path": "keep.ics"}, {"path": "old.ics"}, {"path": "drop.ics"}]),
+    )
+    .await;
+    let ids: Vec<i64> = json["paths"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["id"].as_i64().unwrap())
+        .collect();
+
+    let (status, json) = put_paths(
+        state.clone(),
+        source_id,
+        serde_json::json!([
+            {"path": "keep.ics", "is_public": true},
+            {"id": ids[1], "path": "renamed.ics"},
+            {"path": "new.ics"}
+        ]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let paths = json["paths"].as_array().unwrap();
+    let summary: Vec<(&str, bool)> = paths
+        .iter()
+        .map(|p| {
+            (
+                p["path"].as_str().unwrap(),
+                p["is_public"].as_bool().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("keep.ics", true),
+            ("renamed.ics", false),
+            ("new.ics", false)
+        ]
+    );
+    assert_eq!(paths[0]["id"], ids[0]);
+    assert_eq!(paths[1]["id"], ids[1]);
+
+    let (_, listed) = get_json(state, &format!("/api/sources/{}/paths", source_id)).await;
+    assert_eq!(listed["paths"], json["paths"]);
+}
+
+#[tokio::test]
+async fn replace_source_paths_swaps_names_and_rolls_back_on_error() {
+    let state = test_state();
+    let source_id = {
+        let db = state.db.get().unwrap();
+        db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap()
+    };
+    let (_, json) = put_paths(
+        state.clone(),
+        source_id,
+        serde_json::json!([{"path": "a.ics"}, {"path": "b.ics"}]),
+    )
+    .await;
+    let (a, b) = (
+        json["paths"][0]["id"].clone(),
+        json["paths"][1]["id"].clone(),
+    );
+
+    let (status, json) = put_paths(
+        state.clone(),
+        source_id,
+        serde_json::json!([{"id": a, "path": "b.ics"}, {"id": b, "path": "a.ics"}]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["paths"][0]["path"], "b.ics");
+    assert_eq!(json["paths"][1]["path"], "a.ics");
+
+    let (status, json) = put_paths(
+        state.clone(),
+        source_id,
+        serde_json::json!([{"path": "fresh.ics"}, {"path": "../escape"}]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(json["message"].as_str().unwrap().contains("../escape"));
+    let (_, listed) = get_json(state.clone(), &format!("/api/sources/{}/paths", source_id)).await;
+    assert_eq!(listed["paths"].as_array().unwrap().len(), 2);
+    assert_eq!(listed["paths"][0]["path"], "b.ics");
+
+    let (status, _) = put_paths(state, 999, serde_json::json!([])).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
 // ---------- Source Paths: update ----------
 
 #[tokio::test]
@@ -331,7 +609,7 @@ async fn update_source_path_returns_200() {
     let state = test_state();
 
     let (source_id, path_id) = {
-        let db = state.db.lock().unwrap();
+        let db = state.db.get().unwrap();
         let sid = db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap();
         let pid = db::create_source_path(
             &db,
@@ -367,7 +645,7 @@ async fn update_source_path_wrong_source_returns_404() {
     let state = test_state();
 
     let (_source_id, path_id) = {
-        let db = state.db.lock().unwrap();
+        let db = state.db.get().unwrap();
         let sid = db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap();
         let pid = db::create_source_path(
             &db,
@@ -401,7 +679,7 @@ async fn delete_source_path_returns_200() {
     let state = test_state();
 
     let (source_id, path_id) = {
-        let db = state.db.lock().unwrap();
+        let db = state.db.get().unwrap();
         let sid = db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap();
         let pid = db::create_source_path(
             &db,
@@ -452,6 +730,216 @@ async fn create_destination_returns_201() {
     assert!(json["destination"]["id"].as_i64().is_some());
 }
 
+#[tokio::test]
+async fn create_overlapping_destination_returns_409_when_strict() {
+    let mut state = test_state();
+    state.overlap_policy = db::OverlapPolicy::Reject;
+    {
+        let db = state.db.get().unwrap();
+        db::create_destination(&db, &serde_json::from_value(destination_json()).unwrap()).unwrap();
+    }
+
+    let mut body = destination_json();
+    body["name"] = "Second".into();
+    let resp = app(state)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/destinations")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    let json = body_json(resp.into_body()).await;
+    assert_eq!(json["status"], "error");
+    assert!(json["message"].as_str().unwrap().contains("TestCal"));
+}
+
+async fn send_json(state: AppState, method: &str, uri: &str, body: Value) -> (StatusCode, Value) {
+    let resp = app(state)
+        .oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = resp.status();
+    (status, body_json(resp.into_body()).await)
+}
+
+fn allowlisted_state(allowlist: &str) -> AppState {
+    let mut state = test_state();
+    state.host_allowlist = HostAllowlist::parse(Some(allowlist)).unwrap();
+    state.http = state.http.with_host_allowlist(state.host_allowlist.clone());
+    state
+}
+
+#[tokio::test]
+async fn destination_hosts_are_checked_against_allowlist() {
+    let state = allowlisted_state("example.com, *.example.com, 10.1.0.0/16");
+
+    let (status, json) = send_json(
+        state.clone(),
+        "POST",
+        "/api/destinations",
+        destination_json(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{json}");
+    let id = json["destination"]["id"].as_i64().unwrap();
+
+    let mut body = destination_json();
+    body["name"] = "Elsewhere".into();
+    body["caldav_url"] = "https://caldav.other.org/dav".into();
+    let (status, json) = send_json(state.clone(), "POST", "/api/destinations", body).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert!(
+        json["message"]
+            .as_str()
+            .unwrap()
+            .contains("caldav.other.org")
+    );
+
+    // The wildcard covers public hosts only; internal ones must be listed.
+    for url in [
+        "http://127.0.0.1:5232/",
+        "http://localhost/",
+        "http://192.168.1.10/",
+    ] {
+        let body = serde_json::json!({ "caldav_url": url });
+        let (status, json) = send_json(
+            state.clone(),
+            "PUT",
+            &format!("/api/destinations/{id}"),
+            body,
+        )
+        .await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{url}");
+        assert!(
+            json["message"]
+                .as_str()
+                .unwrap()
+                .contains("private or loopback")
+        );
+    }
+    let body = serde_json::json!({ "calendar_url_override": "http://[::1]/cal/" });
+    let (status, _) = send_json(
+        state.clone(),
+        "PUT",
+        &format!("/api/destinations/{id}"),
+        body,
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let body = serde_json::json!({ "caldav_url": "http://10.1.2.3/dav" });
+    let (status, json) = send_json(
+        state.clone(),
+        "PUT",
+        &format!("/api/destinations/{id}"),
+        body,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["destination"]["caldav_url"], "http://10.1.2.3/dav");
+}
+
+#[tokio::test]
+async fn destination_ics_url_is_checked_against_allowlist() {
+    let state = allowlisted_state("example.com, *.example.com");
+
+    let mut body = destination_json();
+    body["ics_url"] = "https://example.com/a.ics\nhttp://169.254.169.254/latest".into();
+    let (status, json) = send_json(state.clone(), "POST", "/api/destinations", body).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert!(json["message"].as_str().unwrap().contains("ics_url"));
+
+    let (status, json) = send_json(
+        state.clone(),
+        "POST",
+        "/api/destinations",
+        destination_json(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{json}");
+    let id = json["destination"]["id"].as_i64().unwrap();
+
+    let body = serde_json::json!({ "ics_url": "https://feeds.other.org/cal.ics" });
+    let (status, json) = send_json(
+        state.clone(),
+        "PUT",
+        &format!("/api/destinations/{id}"),
+        body,
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert!(
+        json["message"]
+            .as_str()
+            .unwrap()
+            .contains("feeds.other.org")
+    );
+}
+
+#[tokio::test]
+async fn source_hosts_are_checked_against_allowlist() {
+    let state = allowlisted_state("caldav.example.com, feeds.example.com");
+
+    let (status, json) = send_json(state.clone(), "POST", "/api/sources", source_json()).await;
+    assert_eq!(status, StatusCode::CREATED, "{json}");
+    let id = json["source"]["id"].as_i64().unwrap();
+
+    let mut body = source_json();
+    body["ics_path"] = "other.ics".into();
+    body["caldav_url"] = "http://127.0.0.1/dav".into();
+    let (status, _) = send_json(state.clone(), "POST", "/api/sources", body).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let body = serde_json::json!({ "caldav_url": "https://attacker.example.net/" });
+    let (status, _) = send_json(state.clone(), "PUT", &format!("/api/sources/{id}"), body).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let sub = |ics_url: &str| {
+        serde_json::json!({
+            "name": "Feed",
+            "ics_url": ics_url,
+            "ics_path": "feed.ics"
+        })
+    };
+    let (status, _) = send_json(
+        state.clone(),
+        "POST",
+        "/api/sources/subscription",
+        sub("http://169.254.169.254/latest/meta-data"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, json) = send_json(
+        state.clone(),
+        "POST",
+        "/api/sources/subscription",
+        sub("https://feeds.example.com/team.ics"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{json}");
+
+    let body = serde_json::json!({
+        "caldav_url": "http://localhost:5232/",
+        "username": "u",
+        "password": "p"
+    });
+    let (status, _) = send_json(state, "POST", "/api/sources/preview", body).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
 // ---------- Destinations: list ----------
 
 #[tokio::test]
@@ -459,7 +947,7 @@ async fn list_destinations_returns_created() {
     let state = test_state();
 
     {
-        let db = state.db.lock().unwrap();
+        let db = state.db.get().unwrap();
         db::create_destination(&db, &serde_json::from_value(destination_json()).unwrap()).unwrap();
     }
 
@@ -486,7 +974,7 @@ async fn update_destination_returns_200() {
     let state = test_state();
 
     let id = {
-        let db = state.db.lock().unwrap();
+        let db = state.db.get().unwrap();
         db::create_destination(&db, &serde_json::from_value(destination_json()).unwrap()).unwrap()
     };
 
@@ -537,7 +1025,7 @@ async fn delete_destination_returns_200() {
     let state = test_state();
 
     let id = {
-        let db = state.db.lock().unwrap();
+        let db = state.db.get().unwrap();
         db::create_destination(&db, &serde_json::from_value(destination_json()).unwrap()).unwrap()
     };
 
@@ -575,17 +1063,49 @@ async fn delete_destination_nonexistent_returns_404() {
     assert_eq!(resp.status(), StatusCode::NOT_FOUND);
 }
 
-// ---------- Health ----------
+// ---------- Sources: calendars ----------
+
+async fn start_propfind_server() -> std::net::SocketAddr {
+    let body = r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>/dav/work/</d:href>
+    <d:propstat>
+      <d:prop>
+        <d:resourcetype><d:collection/><c:calendar/></d:resourcetype>
+        <d:displayname>Work</d:displayname>
+      </d:prop>
+    </d:propstat>
+  </d:response>
+</d:multistatus>"#;
+    let mock = Router::new().fallback(axum::routing::any(move || async move {
+        (StatusCode::MULTI_STATUS, body)
+    }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, mock).await.unwrap();
+    });
+    addr
+}
 
 #[tokio::test]
-async fn health_returns_200() {
+async fn source_calendars_lists_stored_source_calendars() {
     let state = test_state();
-    let router = app(state);
+    let addr = start_propfind_server().await;
+
+    let id = {
+        let db = state.db.get().unwrap();
+        let mut src = source_json();
+        src["caldav_url"] = format!("http://{}/dav/", addr).into();
+        db::create_source(&db, &serde_json::from_value(src).unwrap()).unwrap()
+    };
 
+    let router = app(state);
     let resp = router
         .oneshot(
             Request::builder()
-                .uri("/api/health")
+                .uri(format!("/api/sources/{}/calendars", id))
                 .body(Body::empty())
                 .unwrap(),
         )
@@ -594,145 +1114,1904 @@ async fn health_returns_200() {
 
     assert_eq!(resp.status(), StatusCode::OK);
     let json = body_json(resp.into_body()).await;
-    assert_eq!(json["status"], "ok");
+    assert_eq!(json["calendars"][0]["href"], "/dav/work/");
+    assert_eq!(json["calendars"][0]["display_name"], "Work");
+    assert!(!json.to_string().contains("pass"));
 }
 
 #[tokio::test]
-async fn health_detailed_returns_200() {
+async fn source_calendars_nonexistent_returns_404() {
     let state = test_state();
     let router = app(state);
 
     let resp = router
         .oneshot(
             Request::builder()
-                .uri("/api/health/detailed")
+                .uri("/api/sources/999/calendars")
                 .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
 
-    assert_eq!(resp.status(), StatusCode::OK);
-    let json = body_json(resp.into_body()).await;
-    assert!(json["db_ok"].as_bool().unwrap());
-    assert!(json["uptime_seconds"].as_u64().is_some());
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
 }
 
-// ---------- OpenAPI ----------
-
-#[tokio::test]
-async fn openapi_json_returns_200_with_paths() {
-    let state = test_state();
-    let router = app(state);
+// ---------- Validate ICS ----------
 
+async fn validate(body: &str) -> Value {
+    let router = app(test_state());
     let resp = router
         .oneshot(
             Request::builder()
-                .uri("/api/openapi.json")
-                .body(Body::empty())
+                .method("POST")
+                .uri("/api/validate-ics")
+                .header("content-type", "text/calendar")
+                .body(Body::from(body.to_owned()))
                 .unwrap(),
         )
         .await
         .unwrap();
-
     assert_eq!(resp.status(), StatusCode::OK);
-    let json = body_json(resp.into_body()).await;
-    assert!(json["paths"].as_object().is_some());
-    assert!(!json["paths"].as_object().unwrap().is_empty());
+    body_json(resp.into_body()).await
 }
 
-// ---------- Validation ----------
+#[tokio::test]
+async fn validate_ics_accepts_well_formed_calendar() {
+    let json = validate(
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nUID:1\r\nDTSTART:20260101T100000Z\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+    )
+    .await;
+    assert_eq!(json["valid"], true);
+    assert_eq!(json["events"], 1);
+    assert!(json["issues"].as_array().unwrap().is_empty());
+}
 
 #[tokio::test]
-async fn create_source_with_public_ics_path_returns_400() {
-    let state = test_state();
-    let router = app(state);
+async fn validate_ics_reports_structural_issues() {
+    let json = validate("BEGIN:VCALENDAR\nBEGIN:VEVENT\nSUMMARY:No uid\nEND:VCALENDAR\n").await;
+    assert_eq!(json["valid"], false);
+    let issues: Vec<String> = json["issues"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v.as_str().unwrap().to_owned())
+        .collect();
+    assert!(issues.iter().any(|i| i.contains("CRLF")));
+    assert!(issues.iter().any(|i| i.contains("missing VERSION")));
+    assert!(
+        issues
+            .iter()
+            .any(|i| i.contains("does not match BEGIN:VEVENT"))
+    );
+}
+
+#[tokio::test]
+async fn validate_ics_flags_vevent_without_uid_or_dtstart() {
+    let json = validate(
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nSUMMARY:x\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+    )
+    .await;
+    let issues = json["issues"].to_string();
+    assert!(issues.contains("VEVENT #1 has no UID"));
+    assert!(issues.contains("VEVENT #1 has no DTSTART"));
+}
 
+#[tokio::test]
+async fn validate_cron_lists_next_runs_or_rejects() {
+    let router = app(test_state());
     let resp = router
+        .clone()
         .oneshot(
             Request::builder()
                 .method("POST")
-                .uri("/api/sources")
+                .uri("/api/validate-cron")
                 .header("content-type", "application/json")
-                .body(Body::from(
-                    serde_json::json!({
-                        "name": "Bad",
-                        "caldav_url": "https://example.com",
-                        "username": "u",
-                        "password": "p",
-                        "ics_path": "public",
-                        "sync_interval_secs": 0
-                    })
-                    .to_string(),
-                ))
+                .body(Body::from(r#"{"expression":"30 6 * * *","count":3}"#))
                 .unwrap(),
         )
         .await
         .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let json = body_json(resp.into_body()).await;
+    assert_eq!(json["valid"], true);
+    let runs = json["next_runs"].as_array().unwrap();
+    assert_eq!(runs.len(), 3);
+    let times: Vec<chrono::DateTime<chrono::FixedOffset>> = runs
+        .iter()
+        .map(|r| chrono::DateTime::parse_from_rfc3339(r.as_str().unwrap()).unwrap())
+        .collect();
+    assert!(times.windows(2).all(|w| w[0] < w[1]));
+    assert!(times.iter().all(|t| t.format("%M").to_string() == "30"));
 
+    let resp = router
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/validate-cron")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"expression":"61 * * * *"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
     assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
     let json = body_json(resp.into_body()).await;
-    assert!(json["message"].as_str().unwrap().contains("public"));
+    assert_eq!(json["valid"], false);
+    assert!(json["error"].as_str().unwrap().contains("out of range"));
 }
+// ---------- Health ----------
 
 #[tokio::test]
-async fn create_source_duplicate_ics_path_returns_400() {
+async fn health_returns_200() {
     let state = test_state();
-
-    {
-        let db = state.db.lock().unwrap();
-        db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap();
-    }
-
     let router = app(state);
+
     let resp = router
         .oneshot(
             Request::builder()
-                .method("POST")
-                .uri("/api/sources")
-                .header("content-type", "application/json")
-                .body(Body::from(source_json().to_string()))
+                .uri("/api/health")
+                .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
 
-    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(resp.status(), StatusCode::OK);
     let json = body_json(resp.into_body()).await;
-    assert!(
-        json["message"]
-            .as_str()
-            .unwrap()
-            .to_lowercase()
-            .contains("duplicate")
-    );
+    assert_eq!(json["status"], "ok");
 }
 
 #[tokio::test]
-async fn create_source_path_with_public_prefix_returns_400() {
+async fn health_detailed_returns_200() {
     let state = test_state();
-
-    let source_id = {
-        let db = state.db.lock().unwrap();
-        db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap()
-    };
-
     let router = app(state);
+
     let resp = router
         .oneshot(
             Request::builder()
-                .method("POST")
-                .uri(format!("/api/sources/{}/paths", source_id))
-                .header("content-type", "application/json")
-                .body(Body::from(
-                    serde_json::json!({"path": "public/foo"}).to_string(),
-                ))
+                .uri("/api/health/detailed")
+                .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
 
-    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(resp.status(), StatusCode::OK);
     let json = body_json(resp.into_body()).await;
-    assert!(json["message"].as_str().unwrap().contains("public"));
+    assert!(json["db_ok"].as_bool().unwrap());
+    assert!(json["uptime_seconds"].as_u64().is_some());
+}
+
+#[tokio::test]
+async fn health_live_returns_200() {
+    let (status, json) = get_json(test_state(), "/api/health/live").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["status"], "ok");
+}
+
+#[tokio::test]
+async fn health_ready_waits_for_scheduler() {
+    let state = test_state();
+
+    let (status, json) = get_json(state.clone(), "/api/health/ready").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(json["db_ok"], true);
+    assert_eq!(json["scheduler_ready"], false);
+
+    caldav_ics_sync::auto_sync::register_all(&state.sync_tasks, &state);
+
+    let (status, json) = get_json(state, "/api/health/ready").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["status"], "ok");
+    assert_eq!(json["scheduler_ready"], true);
+}
+
+// ---------- OpenAPI ----------
+
+#[tokio::test]
+async fn openapi_json_returns_200_with_paths() {
+    let state = test_state();
+    let router = app(state);
+
+    let resp = router
+        .oneshot(
+            Request::builder()
+                .uri("/api/openapi.json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let json = body_json(resp.into_body()).await;
+    assert!(json["paths"].as_object().is_some());
+    assert!(!json["paths"].as_object().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn openapi_paths_lists_every_documented_operation() {
+    let (status, json) = get_json(test_state(), "/api/openapi/paths").await;
+    assert_eq!(status, StatusCode::OK);
+    let routes = json.as_array().unwrap();
+    let has = |method: &str, path: &str| {
+        routes
+            .iter()
+            .any(|r| r["method"] == method && r["path"] == path)
+    };
+    assert!(has("GET", "/api/sources"));
+    assert!(has("POST", "/api/sources"));
+    assert!(has("DELETE", "/api/sources/{id}/ics"));
+    assert!(has("POST", "/api/sources/{source_id}/paths/batch"));
+    assert!(has("GET", "/api/admin/config"));
+    let paths: Vec<&str> = routes.iter().map(|r| r["path"].as_str().unwrap()).collect();
+    assert!(paths.is_sorted());
+}
+
+#[tokio::test]
+async fn openapi_paths_are_all_routed() {
+    for route in caldav_ics_sync::api::openapi::route_inventory() {
+        let uri = route
+            .path
+            .replace("{id}", "999")
+            .replace("{source_id}", "999")
+            .replace("{path_id}", "999");
+        let resp = app(test_state())
+            .oneshot(
+                Request::builder()
+                    .method(route.method.as_str())
+                    .uri(&uri)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = resp.status();
+        assert_ne!(
+            status,
+            StatusCode::METHOD_NOT_ALLOWED,
+            "{} {}",
+            route.method,
+            uri
+        );
+        if status == StatusCode::NOT_FOUND {
+            // Handlers explain their 404s; the router's fallback has no body.
+            let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+            assert!(!bytes.is_empty(), "{} {} is not routed", route.method, uri);
+        }
+    }
+}
+
+#[tokio::test]
+async fn schema_export_returns_standalone_request_schemas() {
+    let (status, json) = get_json(test_state(), "/api/schema/source").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["title"], "CreateSource");
+    assert!(json["properties"]["ics_path"].is_object());
+    assert!(
+        json["required"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|r| r == "ics_path")
+    );
+
+    // References to other components resolve within the document.
+    let (_, json) = get_json(test_state(), "/api/schema/destination").await;
+    let text = json.to_string();
+    assert!(!text.contains("#/components/"));
+    assert!(json["$defs"]["MatchBy"].is_object());
+
+    let (status, json) = get_json(test_state(), "/api/schema/source_path").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(json["properties"]["path"].is_object());
+}
+
+#[tokio::test]
+async fn schema_export_rejects_unknown_types() {
+    let (status, json) = get_json(test_state(), "/api/schema/widget").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(json["message"].as_str().unwrap().contains("source_path"));
+}
+
+// ---------- Validation ----------
+
+#[tokio::test]
+async fn create_source_with_public_ics_path_returns_400() {
+    let state = test_state();
+    let router = app(state);
+
+    let resp = router
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/sources")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "name": "Bad",
+                        "caldav_url": "https://example.com",
+                        "username": "u",
+                        "password": "p",
+                        "ics_path": "public",
+                        "sync_interval_secs": 0
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let json = body_json(resp.into_body()).await;
+    assert!(json["message"].as_str().unwrap().contains("public"));
+}
+
+#[tokio::test]
+async fn create_source_duplicate_ics_path_returns_400() {
+    let state = test_state();
+
+    {
+        let db = state.db.get().unwrap();
+        db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap();
+    }
+
+    let router = app(state);
+    let resp = router
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/sources")
+                .header("content-type", "application/json")
+                .body(Body::from(source_json().to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let json = body_json(resp.into_body()).await;
+    assert!(
+        json["message"]
+            .as_str()
+            .unwrap()
+            .to_lowercase()
+            .contains("duplicate")
+    );
+}
+
+#[tokio::test]
+async fn create_source_path_with_public_prefix_returns_400() {
+    let state = test_state();
+
+    let source_id = {
+        let db = state.db.get().unwrap();
+        db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap()
+    };
+
+    let router = app(state);
+    let resp = router
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/sources/{}/paths", source_id))
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({"path": "public/foo"}).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let json = body_json(resp.into_body()).await;
+    assert!(json["message"].as_str().unwrap().contains("public"));
+}
+
+// ---------- Sync history ----------
+
+fn seed_history(state: &AppState) -> i64 {
+    let db = state.db.get().unwrap();
+    let id = db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap();
+    db::record_sync_history(&db, id, "2026-03-01 08:00:00", "ok", 4, None).unwrap();
+    db::record_sync_history(
+        &db,
+        id,
+        "2026-03-02 08:00:00",
+        "error",
+        0,
+        Some("timeout, \"502\""),
+    )
+    .unwrap();
+    db::record_sync_history(&db, id, "2026-03-03 08:00:00", "ok", 5, None).unwrap();
+    id
+}
+
+#[tokio::test]
+async fn source_history_paginates() {
+    let state = test_state();
+    let id = seed_history(&state);
+
+    let resp = app(state)
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/sources/{}/history?limit=2&offset=1", id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let json = body_json(resp.into_body()).await;
+    assert_eq!(json["total"], 3);
+    let entries = json["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["started_at"], "2026-03-02 08:00:00");
+}
+
+#[tokio::test]
+async fn source_history_missing_source_returns_404() {
+    let resp = app(test_state())
+        .oneshot(
+            Request::builder()
+                .uri("/api/sources/999/history")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn source_history_csv_exports_rows() {
+    let state = test_state();
+    let id = seed_history(&state);
+
+    let resp = app(state)
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/sources/{}/history.csv?since=2026-03-02", id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(
+        resp.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/csv")
+    );
+    assert!(
+        resp.headers()["content-disposition"]
+            .to_str()
+            .unwrap()
+            .contains("attachment")
+    );
+    let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+    let csv = String::from_utf8(bytes.to_vec()).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "started_at,finished_at,status,events,error");
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].starts_with("2026-03-03 08:00:00,"));
+    assert!(lines[2].ends_with(",error,0,\"timeout, \"\"502\"\"\""));
+}
+
+#[tokio::test]
+async fn source_history_csv_rejects_bad_since() {
+    let state = test_state();
+    let id = seed_history(&state);
+
+    let resp = app(state)
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/sources/{}/history.csv?since=yesterday", id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+// ---------- Event preview ----------
+
+fn seed_events(state: &AppState) -> i64 {
+    let db = state.db.get().unwrap();
+    let id = db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap();
+    let ics = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n\
+        BEGIN:VEVENT\r\nUID:late\r\nSUMMARY:Review\\, part 2\r\nDTSTART:20260312T150000Z\r\nDTEND:20260312T160000Z\r\nEND:VEVENT\r\n\
+        BEGIN:VEVENT\r\nUID:holiday\r\nSUMMARY:Holiday\r\nDTSTART;VALUE=DATE:20260310\r\nDTEND;VALUE=DATE:20260311\r\nEND:VEVENT\r\n\
+        BEGIN:VEVENT\r\nUID:early\r\nSUMMARY:Kickoff\r\nDTSTART:20260301T090000Z\r\nDTEND:20260301T100000Z\r\nEND:VEVENT\r\n\
+        END:VCALENDAR\r\n";
+    db::save_ics_data(&db, id, ics).unwrap();
+    id
+}
+
+async fn get_events(state: AppState, query: String) -> (StatusCode, Value) {
+    let resp = app(state)
+        .oneshot(Request::builder().uri(query).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = resp.status();
+    (status, body_json(resp.into_body()).await)
+}
+
+#[tokio::test]
+async fn source_events_lists_sorted_events() {
+    let state = test_state();
+    let id = seed_events(&state);
+
+    let (status, json) = get_events(state, format!("/api/sources/{}/events", id)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    let events = json["events"].as_array().unwrap();
+    let uids: Vec<_> = events.iter().map(|e| e["uid"].as_str().unwrap()).collect();
+    assert_eq!(uids, ["early", "holiday", "late"]);
+    assert_eq!(events[1]["all_day"], true);
+    assert_eq!(events[1]["start"], "2026-03-10");
+    assert_eq!(events[1]["end"], "2026-03-11");
+    assert_eq!(events[2]["all_day"], false);
+    assert_eq!(events[2]["start"], "2026-03-12T15:00:00Z");
+    assert_eq!(events[2]["summary"], "Review, part 2");
+}
+
+#[tokio::test]
+async fn source_events_filters_by_window() {
+    let state = test_state();
+    let id = seed_events(&state);
+
+    let (status, json) = get_events(
+        state,
+        format!("/api/sources/{}/events?start=2026-03-10&end=2026-03-12", id),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    let events = json["events"].as_array().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["uid"], "holiday");
+}
+
+#[tokio::test]
+async fn source_events_window_includes_timed_overlap() {
+    let state = test_state();
+    let id = seed_events(&state);
+
+    let (_, json) = get_events(
+        state,
+        format!(
+            "/api/sources/{}/events?start=2026-03-12T15:30:00Z&end=2026-03-13",
+            id
+        ),
+    )
+    .await;
+
+    let events = json["events"].as_array().unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["uid"], "late");
+}
+
+#[tokio::test]
+async fn source_events_rejects_bad_bounds_and_unknown_source() {
+    let state = test_state();
+    let id = seed_events(&state);
+
+    let (status, _) = get_events(
+        state.clone(),
+        format!("/api/sources/{}/events?start=soon", id),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = get_events(state, "/api/sources/999/events".into()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn source_timezones_lists_vtimezone_blocks() {
+    let state = test_state();
+    let id = {
+        let db = state.db.get().unwrap();
+        let id = db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap();
+        let ics = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n\
+            BEGIN:VTIMEZONE\r\nTZID:Europe/Berlin\r\nBEGIN:STANDARD\r\nTZOFFSETFROM:+0200\r\nTZOFFSETTO:+0100\r\nDTSTART:19701025T030000\r\nEND:STANDARD\r\nEND:VTIMEZONE\r\n\
+            BEGIN:VEVENT\r\nUID:a\r\nDTSTART;TZID=Europe/Berlin:20260312T150000\r\nEND:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+        db::save_ics_data(&db, id, ics).unwrap();
+        id
+    };
+
+    let (status, json) = get_events(state, format!("/api/sources/{}/timezones", id)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    let timezones = json["timezones"].as_array().unwrap();
+    assert_eq!(timezones.len(), 1);
+    assert_eq!(timezones[0]["tzid"], "Europe/Berlin");
+    let raw = timezones[0]["raw"].as_str().unwrap();
+    assert!(raw.starts_with("BEGIN:VTIMEZONE\r\n"));
+    assert!(raw.contains("TZOFFSETTO:+0100"));
+    assert!(raw.ends_with("END:VTIMEZONE\r\n"));
+}
+
+#[tokio::test]
+async fn source_timezones_empty_without_vtimezones() {
+    let state = test_state();
+    let id = seed_events(&state);
+
+    let (status, json) = get_events(state.clone(), format!("/api/sources/{}/timezones", id)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["timezones"], serde_json::json!([]));
+
+    let (status, _) = get_events(state, "/api/sources/999/timezones".into()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+// ---------- Duplicating ----------
+
+#[tokio::test]
+async fn duplicate_source_returns_201_with_copy() {
+    let state = test_state();
+    let id = {
+        let db = state.db.get().unwrap();
+        db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap()
+    };
+
+    let resp = app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/sources/{}/duplicate", id))
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({"ics_path": "copy.ics"}).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let json = body_json(resp.into_body()).await;
+    assert_eq!(json["source"]["name"], "Copy of Test Source");
+    assert_eq!(json["source"]["ics_path"], "copy.ics");
+    assert_ne!(json["source"]["id"], id);
+
+    let db = state.db.get().unwrap();
+    assert_eq!(db::list_sources(&db).unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn duplicate_source_with_taken_path_returns_400() {
+    let state = test_state();
+    let id = {
+        let db = state.db.get().unwrap();
+        db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap()
+    };
+
+    let resp = app(state)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/sources/{}/duplicate", id))
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({"ics_path": "test.ics"}).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn duplicate_destination_returns_201_with_copy() {
+    let state = test_state();
+    let id = {
+        let db = state.db.get().unwrap();
+        db::create_destination(&db, &serde_json::from_value(destination_json()).unwrap()).unwrap()
+    };
+
+    let resp = app(state)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/destinations/{}/duplicate", id))
+                .header("content-type", "application/json")
+                .body(Body::from("{}"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let json = body_json(resp.into_body()).await;
+    assert_eq!(json["destination"]["name"], "Copy of Test Dest");
+    assert_eq!(json["destination"]["calendar_name"], "TestCal");
+}
+
+#[tokio::test]
+async fn duplicate_destination_returns_409_when_strict() {
+    let mut state = test_state();
+    state.overlap_policy = db::OverlapPolicy::Reject;
+    let id = {
+        let db = state.db.get().unwrap();
+        db::create_destination(&db, &serde_json::from_value(destination_json()).unwrap()).unwrap()
+    };
+
+    let (status, json) = send_json(
+        state.clone(),
+        "POST",
+        &format!("/api/destinations/{}/duplicate", id),
+        serde_json::json!({}),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert!(json["message"].as_str().unwrap().contains("TestCal"));
+    let db = state.db.get().unwrap();
+    assert_eq!(db::list_destinations(&db).unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn duplicate_destination_checks_hosts_against_allowlist() {
+    let state = allowlisted_state("*.example.com");
+    let id = {
+        let db = state.db.get().unwrap();
+        let mut dest = destination_json();
+        dest["caldav_url"] = "https://caldav.elsewhere.org/dav".into();
+        db::create_destination(&db, &serde_json::from_value(dest).unwrap()).unwrap()
+    };
+
+    let (status, _) = send_json(
+        state,
+        "POST",
+        &format!("/api/destinations/{}/duplicate", id),
+        serde_json::json!({}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn duplicate_destination_nonexistent_returns_404() {
+    let resp = app(test_state())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/destinations/999/duplicate")
+                .header("content-type", "application/json")
+                .body(Body::from("{}"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+// ---------- Webhooks ----------
+
+async fn start_webhook_receiver() -> (std::net::SocketAddr, tokio::sync::mpsc::Receiver<Value>) {
+    let (tx, rx) = tokio::sync::mpsc::channel(4);
+    let mock = Router::new().fallback(axum::routing::post(
+        move |axum::Json(payload): axum::Json<Value>| {
+            let tx = tx.clone();
+            async move {
+                let _ = tx.send(payload).await;
+                StatusCode::OK
+            }
+        },
+    ));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, mock).await.unwrap();
+    });
+    (addr, rx)
+}
+
+#[tokio::test]
+async fn failed_sync_posts_failure_webhook() {
+    let (addr, mut rx) = start_webhook_receiver().await;
+    let mut state = test_state();
+    state.webhooks.on_failure_url = Some(format!("http://{}/hook", addr));
+
+    let id = {
+        let db = state.db.get().unwrap();
+        let mut source = source_json();
+        source["caldav_url"] = "http://127.0.0.1:1/dav/".into();
+        db::create_source(&db, &serde_json::from_value(source).unwrap()).unwrap()
+    };
+
+    let resp = app(state)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/sources/{}/sync", id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    let payload = tokio::time::timeout(std::time::Duration::from_secs(5), rx.recv())
+        .await
+        .expect("webhook not delivered")
+        .unwrap();
+    assert_eq!(payload["kind"], "source");
+    assert_eq!(payload["id"], id);
+    assert_eq!(payload["name"], "Test Source");
+    assert_eq!(payload["status"], "error");
+    assert!(!payload["error"].as_str().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn slow_manual_sync_returns_504() {
+    let mock = Router::new().fallback(axum::routing::any(|| async {
+        tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+        StatusCode::MULTI_STATUS
+    }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, mock).await.unwrap();
+    });
+
+    let mut state = test_state();
+    state.manual_sync_timeout = std::time::Duration::from_millis(200);
+    let id = {
+        let db = state.db.get().unwrap();
+        let mut source = source_json();
+        source["caldav_url"] = format!("http://{}/dav/", addr).into();
+        db::create_source(&db, &serde_json::from_value(source).unwrap()).unwrap()
+    };
+
+    let resp = app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/sources/{}/sync", id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::GATEWAY_TIMEOUT);
+    let json = body_json(resp.into_body()).await;
+    assert_eq!(json["status"], "error");
+    assert!(json["message"].as_str().unwrap().contains("timed out"));
+
+    let db = state.db.get().unwrap();
+    let source = db::get_source(&db, id).unwrap().unwrap();
+    assert_eq!(source.last_sync_status.as_deref(), Some("error"));
+    assert!(source.last_sync_error.unwrap().contains("timed out"));
+}
+
+#[tokio::test]
+async fn per_source_webhook_overrides_global() {
+    let (global_addr, mut global_rx) = start_webhook_receiver().await;
+    let (own_addr, mut own_rx) = start_webhook_receiver().await;
+    let mut state = test_state();
+    state.webhooks.on_failure_url = Some(format!("http://{}/hook", global_addr));
+
+    let id = {
+        let db = state.db.get().unwrap();
+        let mut source = source_json();
+        source["caldav_url"] = "http://127.0.0.1:1/dav/".into();
+        source["webhook_on_failure_url"] = format!("http://{}/hook", own_addr).into();
+        db::create_source(&db, &serde_json::from_value(source).unwrap()).unwrap()
+    };
+
+    app(state)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/sources/{}/sync", id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let payload = tokio::time::timeout(std::time::Duration::from_secs(5), own_rx.recv())
+        .await
+        .expect("webhook not delivered")
+        .unwrap();
+    assert_eq!(payload["status"], "error");
+    assert!(global_rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn create_source_rejects_non_http_webhook() {
+    let mut source = source_json();
+    source["webhook_on_failure_url"] = "ftp://example.com/hook".into();
+
+    let resp = app(test_state())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/sources")
+                .header("content-type", "application/json")
+                .body(Body::from(source.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_webhook_reports_receiver_status() {
+    let (addr, mut rx) = start_webhook_receiver().await;
+
+    let resp = app(test_state())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/test-webhook")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({"url": format!("http://{}/hook", addr)}).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let json = body_json(resp.into_body()).await;
+    assert_eq!(json["status"], "success");
+    assert_eq!(json["http_status"], 200);
+    assert!(json["latency_ms"].is_u64());
+
+    let payload = rx.recv().await.unwrap();
+    assert_eq!(payload["kind"], "test");
+}
+
+#[tokio::test]
+async fn test_webhook_rejects_non_http_url() {
+    let resp = app(test_state())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/test-webhook")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({"url": "file:///etc/passwd"}).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_webhook_refuses_private_addresses() {
+    let mut state = test_state();
+    state.webhooks.private_address_guard = Some(PrivateAddressGuard::system());
+
+    for url in [
+        "http://169.254.169.254/latest/meta-data",
+        "http://localhost:1/hook",
+    ] {
+        let (status, json) = send_json(
+            state.clone(),
+            "POST",
+            "/api/admin/test-webhook",
+            serde_json::json!({ "url": url }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY, "{url}");
+        let message = json["message"].as_str().unwrap();
+        assert!(message.contains("ALLOW_PRIVATE_FETCH"), "{url}: {message}");
+        assert!(json.get("http_status").is_none());
+    }
+}
+
+#[tokio::test]
+async fn db_stats_reports_row_counts() {
+    let state = test_state();
+    {
+        let db = state.db.get().unwrap();
+        db::create_source(&db, &serde_json::from_value(source_json()).unwrap()).unwrap();
+    }
+
+    let (status, json) = get_json(state, "/api/admin/db-stats").await;
+    assert_eq!(status, StatusCode::OK);
+    let tables = json["stats"]["tables"].as_array().unwrap();
+    let sources = tables.iter().find(|t| t["table"] == "sources").unwrap();
+    assert_eq!(sources["rows"], 1);
+    assert!(tables.iter().any(|t| t["table"] == "destinations"));
+    assert!(json["stats"]["size"]["page_count"].as_i64().unwrap() > 0);
+}
+
+#[tokio::test]
+async fn vacuum_returns_before_and_after_sizes() {
+    let resp = app(test_state())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/vacuum")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let json = body_json(resp.into_body()).await;
+    assert_eq!(json["status"], "success");
+    assert!(json["before"]["page_count"].is_i64());
+    assert!(json["after"]["page_size"].as_i64().unwrap() > 0);
+    assert_eq!(json["after"]["freelist_count"], 0);
+}
+
+// ---------- Event stream ----------
+
+/// Reads SSE frames until `n` events (blank-line terminated) have arrived.
+async fn read_sse_events(body: &mut Body, n: usize) -> Vec<String> {
+    let mut buf = String::new();
+    while buf.matches("\n\n").count() < n {
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(5), body.frame())
+            .await
+            .expect("timed out waiting for SSE frame")
+            .expect("stream ended")
+            .unwrap();
+        if let Ok(data) = frame.into_data() {
+            buf.push_str(std::str::from_utf8(&data).unwrap());
+        }
+    }
+    buf.split_terminator("\n\n").map(str::to_owned).collect()
+}
+
+#[tokio::test]
+async fn event_stream_reports_lag_and_recovers() {
+    let mut state = test_state();
+    state.events = SyncEvents::new(2);
+    let events = state.events.clone();
+
+    let resp = app(state)
+        .oneshot(
+            Request::builder()
+                .uri("/api/events")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let mut body = resp.into_body();
+
+    for _ in 0..5 {
+        events.publish(WebhookPayload::sample());
+    }
+    let received = read_sse_events(&mut body, 3).await;
+    assert!(received[0].contains("event: lagged"));
+    assert!(received[0].contains("data: missed 3 events"));
+    assert!(received[1].contains("event: sync"));
+    assert!(received[2].contains("event: sync"));
+
+    events.publish(WebhookPayload::sample());
+    let received = read_sse_events(&mut body, 1).await;
+    assert!(received[0].contains("event: sync"));
+    assert!(received[0].contains("\"kind\":\"test\""));
+}
+
+// ---------- Admin config ----------
+
+#[tokio::test]
+async fn admin_config_reports_defaults_and_redacts_secrets() {
+    let mut cfg = caldav_ics_sync::config::AppConfig::defaults().unwrap();
+    cfg.auth_username = Some("admin".into());
+    cfg.auth_password = Some("hunter2".into());
+    cfg.webhook_on_failure_url = Some("https://hooks.example.com/T0/secret-token".into());
+
+    let mut state = test_state();
+    state.config = Some(std::sync::Arc::new(
+        caldav_ics_sync::config::EffectiveConfig::from(&cfg),
+    ));
+    let (status, json) = get_json(state, "/api/admin/config").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let config = &json["config"];
+    assert_eq!(config["data_dir"], "./data");
+    assert_eq!(config["db_path"], "./data/caldav-sync.db");
+    assert_eq!(config["manual_sync_timeout_secs"], 120);
+    assert_eq!(config["sync_retry"]["max_retries"], 5);
+    assert_eq!(config["auth_enabled"], true);
+    assert_eq!(config["auth_username"], "admin");
+    assert_eq!(config["auth_password"], "[redacted]");
+    assert!(config["auth_password_hash"].is_null());
+    assert_eq!(
+        config["webhook_on_failure_url"],
+        "https://hooks.example.com/[redacted]"
+    );
+
+    let raw = json.to_string();
+    assert!(!raw.contains("hunter2"));
+    assert!(!raw.contains("secret-token"));
+}
+
+#[tokio::test]
+async fn admin_config_without_loaded_config_returns_503() {
+    let (status, json) = get_json(test_state(), "/api/admin/config").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(json["status"], "error");
+}
+
+// ---------- Sources: preview ----------
+
+/// Two calendars, `/dav/work/` and `/dav/home/`, each with one event named after it.
+async fn start_two_calendar_server() -> std::net::SocketAddr {
+    let mock = Router::new().fallback(axum::routing::any(|req: Request<Body>| async move {
+        let path = req.uri().path().to_string();
+        let body = match req.method().as_str() {
+            "PROPFIND" => r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>/dav/work/</d:href>
+    <d:propstat><d:prop><d:resourcetype><c:calendar/></d:resourcetype></d:prop></d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/dav/home/</d:href>
+    <d:propstat><d:prop><d:resourcetype><c:calendar/></d:resourcetype></d:prop></d:propstat>
+  </d:response>
+</d:multistatus>"#
+                .to_string(),
+            _ => {
+                let name = path
+                    .trim_matches('/')
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or_default();
+                format!(
+                    r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>{path}{name}.ics</d:href>
+    <d:propstat><d:prop><c:calendar-data>BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:{name}
+SUMMARY:{name} event
+DTSTART:20250101T100000Z
+END:VEVENT
+END:VCALENDAR</c:calendar-data></d:prop></d:propstat>
+  </d:response>
+</d:multistatus>"#
+                )
+            }
+        };
+        (StatusCode::MULTI_STATUS, body)
+    }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, mock).await.unwrap();
+    });
+    addr
+}
+
+async fn post_preview(state: AppState, body: Value) -> axum::response::Response {
+    app(state)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/sources/preview")
+                .header("Content-Type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn preview_returns_filtered_feed_without_saving() {
+    let addr = start_two_calendar_server().await;
+    let state = test_state();
+
+    let resp = post_preview(
+        state.clone(),
+        serde_json::json!({
+            "caldav_url": format!("http://{}/dav/", addr),
+            "username": "user",
+            "password": "pass",
+            "calendar_filter": "WORK",
+        }),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["content-type"], "text/calendar");
+    let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+    let ics = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(ics.starts_with("BEGIN:VCALENDAR"));
+    assert!(ics.contains("SUMMARY:work event"));
+    assert!(!ics.contains("SUMMARY:home event"));
+
+    let db = state.db.get().unwrap();
+    assert!(db::list_sources(&db).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn preview_requires_caldav_url() {
+    let resp = post_preview(
+        test_state(),
+        serde_json::json!({ "caldav_url": " ", "username": "u", "password": "p" }),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let json = body_json(resp.into_body()).await;
+    assert_eq!(json["status"], "error");
+}
+
+// ---------- Loop check ----------
+
+async fn create_via_api(state: AppState, uri: &str, body: Value) -> i64 {
+    let resp = app(state)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let json = body_json(resp.into_body()).await;
+    json["source"]["id"]
+        .as_i64()
+        .or_else(|| json["destination"]["id"].as_i64())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn loop_check_reports_destination_writing_back_to_source() {
+    let state = test_state();
+    let source_id = create_via_api(state.clone(), "/api/sources", source_json()).await;
+
+    let mut looping = destination_json();
+    looping["name"] = "Loop".into();
+    looping["ics_url"] = "http://localhost:6765/ics/test.ics".into();
+    let loop_id = create_via_api(state.clone(), "/api/destinations", looping).await;
+
+    let mut elsewhere = destination_json();
+    elsewhere["name"] = "Elsewhere".into();
+    elsewhere["ics_url"] = "http://127.0.0.1:6765/ics/test.ics".into();
+    elsewhere["caldav_url"] = "https://other.example.com/dav".into();
+    create_via_api(state.clone(), "/api/destinations", elsewhere).await;
+
+    // Same path on a foreign host is someone else's feed.
+    let mut external = destination_json();
+    external["name"] = "External".into();
+    external["ics_url"] = "https://feeds.example.org/ics/test.ics".into();
+    external["calendar_name"] = "Other".into();
+    create_via_api(state.clone(), "/api/destinations", external).await;
+
+    let (status, json) = get_json(state, &format!("/api/sources/{}/loop-check", source_id)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["status"], "success");
+    let loops = json["loops"].as_array().unwrap();
+    assert_eq!(loops.len(), 2);
+
+    let closing: Vec<_> = loops.iter().filter(|l| l["closes_loop"] == true).collect();
+    assert_eq!(closing.len(), 1);
+    assert_eq!(closing[0]["destination_id"], loop_id);
+    assert_eq!(
+        closing[0]["calendar_url"],
+        "https://caldav.example.com/dav/TestCal/"
+    );
+    let chain = closing[0]["chain"].as_array().unwrap();
+    assert_eq!(chain.first(), chain.last());
+    assert!(
+        json["message"]
+            .as_str()
+            .unwrap()
+            .contains("Possible sync loop")
+    );
+}
+
+#[tokio::test]
+async fn loop_check_without_consumers_is_clean() {
+    let state = test_state();
+    let source_id = create_via_api(state.clone(), "/api/sources", source_json()).await;
+    create_via_api(state.clone(), "/api/destinations", destination_json()).await;
+
+    let (status, json) = get_json(state, &format!("/api/sources/{}/loop-check", source_id)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(json["loops"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn loop_check_nonexistent_source_returns_404() {
+    let (status, json) = get_json(test_state(), "/api/sources/999/loop-check").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(json["status"], "error");
+}
+
+// ---------- Sources: empty sync results ----------
+
+/// A CalDAV server that lists no calendars, so every sync yields 0 events.
+async fn start_empty_caldav_server() -> std::net::SocketAddr {
+    let mock = Router::new().fallback(axum::routing::any(|| async {
+        (
+            StatusCode::MULTI_STATUS,
+            r#"<?xml version="1.0" encoding="utf-8" ?><d:multistatus xmlns:d="DAV:"/>"#,
+        )
+    }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, mock).await.unwrap();
+    });
+    addr
+}
+
+async fn sync_with_previous_feed(skip_on_empty: bool) -> (Value, db::Source, Option<String>) {
+    let addr = start_empty_caldav_server().await;
+    let state = test_state();
+    let id = {
+        let db = state.db.get().unwrap();
+        let mut source = source_json();
+        source["caldav_url"] = format!("http://{}/dav/", addr).into();
+        // Left out when true to exercise the default.
+        if !skip_on_empty {
+            source["skip_on_empty"] = false.into();
+        }
+        let id = db::create_source(&db, &serde_json::from_value(source).unwrap()).unwrap();
+        db::save_ics_data(&db, id, "BEGIN:VCALENDAR\r\nPREVIOUS\r\nEND:VCALENDAR\r\n").unwrap();
+        id
+    };
+
+    let resp = app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/sources/{}/sync", id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let json = body_json(resp.into_body()).await;
+
+    let db = state.db.get().unwrap();
+    let source = db::get_source(&db, id).unwrap().unwrap();
+    let ics = db::get_ics_data(&db, id).unwrap();
+    (json, source, ics)
+}
+
+#[tokio::test]
+async fn empty_sync_keeps_previous_feed_by_default() {
+    let (json, source, ics) = sync_with_previous_feed(true).await;
+    assert_eq!(json["status"], "success");
+    assert_eq!(json["events"], 0);
+    assert!(
+        json["message"]
+            .as_str()
+            .unwrap()
+            .contains("kept the previous feed")
+    );
+    assert!(ics.unwrap().contains("PREVIOUS"));
+    assert_eq!(source.last_sync_status.as_deref(), Some("skipped_empty"));
+    assert!(source.last_synced.is_none());
+}
+
+#[tokio::test]
+async fn empty_sync_overwrites_feed_when_skip_disabled() {
+    let (_, source, ics) = sync_with_previous_feed(false).await;
+    let ics = ics.unwrap();
+    assert!(!ics.contains("PREVIOUS"));
+    assert!(ics.starts_with("BEGIN:VCALENDAR"));
+    assert_eq!(source.last_sync_status.as_deref(), Some("ok"));
+}
+
+// ---------- Sources: manual status ----------
+
+async fn post_status(state: AppState, id: i64, body: Value) -> (StatusCode, Value) {
+    let resp = app(state)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/sources/{}/status", id))
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = resp.status();
+    (status, body_json(resp.into_body()).await)
+}
+
+#[tokio::test]
+async fn set_source_status_round_trips() {
+    let state = test_state();
+    let id = create_via_api(state.clone(), "/api/sources", source_json()).await;
+
+    let (status, json) = post_status(
+        state.clone(),
+        id,
+        serde_json::json!({ "status": "maintenance", "error": "server upgrade" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["source"]["last_sync_status"], "maintenance");
+
+    let (_, json) = get_json(state.clone(), &format!("/api/sources/{}/status", id)).await;
+    assert_eq!(json["source"]["last_sync_status"], "maintenance");
+    assert_eq!(json["source"]["last_sync_error"], "server upgrade");
+
+    let (status, json) =
+        post_status(state.clone(), id, serde_json::json!({ "status": "ok" })).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["source"]["last_sync_status"], "ok");
+    assert!(json["source"]["last_sync_error"].is_null());
+}
+
+#[tokio::test]
+async fn set_source_status_rejects_unknown_values() {
+    let state = test_state();
+    let id = create_via_api(state.clone(), "/api/sources", source_json()).await;
+
+    let (status, json) =
+        post_status(state.clone(), id, serde_json::json!({ "status": "bogus" })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(json["message"].as_str().unwrap().contains("maintenance"));
+
+    let (status, _) = post_status(state, 999, serde_json::json!({ "status": "ok" })).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+// ---------- Sources: enabled flag ----------
+
+#[tokio::test]
+async fn disabled_source_is_not_scheduled_and_rejects_manual_sync() {
+    use caldav_ics_sync::auto_sync::AutoSyncKey;
+
+    let state = test_state();
+    let mut source = source_json();
+    source["sync_interval_secs"] = 3600.into();
+    source["enabled"] = false.into();
+    let id = create_via_api(state.clone(), "/api/sources", source).await;
+
+    let (_, json) = get_json(state.clone(), &format!("/api/sources/{}/status", id)).await;
+    assert_eq!(json["source"]["enabled"], false);
+    assert_eq!(json["source"]["sync_interval_secs"], 3600);
+    let scheduled = |state: &AppState| {
+        state
+            .sync_tasks
+            .lock()
+            .unwrap()
+            .contains_key(&AutoSyncKey::Source(id))
+    };
+    assert!(!scheduled(&state));
+
+    let resp = app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/sources/{}/sync", id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    let json = body_json(resp.into_body()).await;
+    assert_eq!(json["status"], "error");
+
+    let resp = app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/api/sources/{}", id))
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"enabled": true}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(scheduled(&state));
+}
+
+// ---------- Destinations: sync details ----------
+
+/// Creates a destination whose feed has `keep` and `new`, syncing into a
+/// calendar that already holds an unchanged `keep` and a stale `orphan`.
+async fn destination_with_mixed_calendar(state: &AppState) -> i64 {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut dest = destination_json();
+    dest["ics_url"] = format!("http://{}/feed.ics", addr).into();
+    dest["caldav_url"] = format!("http://{}/dav/", addr).into();
+    dest["calendar_name"] = "cal".into();
+    dest["sync_all"] = true.into();
+    let id = create_via_api(state.clone(), "/api/destinations", dest).await;
+
+    let vevent = |uid: &str| {
+        format!(
+            "BEGIN:VEVENT\r\nUID:{uid}\r\nSUMMARY:{uid}\r\nDTSTART:20270601T080000Z\r\nDTEND:20270601T090000Z\r\nEND:VEVENT\r\n"
+        )
+    };
+    let feed = format!(
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{}{}END:VCALENDAR\r\n",
+        vevent("keep"),
+        vevent("new")
+    );
+    let stored: String = ["keep", "orphan"]
+        .iter()
+        .map(|uid| {
+            let marked =
+                vevent(uid).replace("END:VEVENT", &format!("X-SYNC-ORIGIN:{id}\r\nEND:VEVENT"));
+            format!(
+                "<d:response><d:href>/dav/cal/{uid}.ics</d:href><d:propstat><d:prop>\
+                 <c:calendar-data>BEGIN:VCALENDAR\r\n{marked}END:VCALENDAR</c:calendar-data>\
+                 </d:prop></d:propstat></d:response>"
+            )
+        })
+        .collect();
+    let report = format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?><d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">{stored}</d:multistatus>"#
+    );
+    let mock = Router::new().fallback(axum::routing::any(move |req: Request<Body>| {
+        let (feed, report) = (feed.clone(), report.clone());
+        async move {
+            match req.method().as_str() {
+                "GET" => (StatusCode::OK, feed),
+                "REPORT" => (StatusCode::MULTI_STATUS, report),
+                "PUT" => (StatusCode::CREATED, String::new()),
+                _ => (StatusCode::NO_CONTENT, String::new()),
+            }
+        }
+    }));
+    tokio::spawn(async move {
+        axum::serve(listener, mock).await.unwrap();
+    });
+    id
+}
+
+async fn sync_destination(state: AppState, uri: String) -> Value {
+    let resp = app(state)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    body_json(resp.into_body()).await
+}
+
+#[tokio::test]
+async fn destination_sync_details_list_uids_matching_counts() {
+    let state = test_state();
+    let id = destination_with_mixed_calendar(&state).await;
+
+    let json = sync_destination(state, format!("/api/destinations/{}/sync?details=true", id)).await;
+
+    assert_eq!(json["uploaded_uids"], serde_json::json!(["new"]));
+    assert_eq!(json["skipped_uids"], serde_json::json!(["keep"]));
+    assert_eq!(json["deleted_uids"], serde_json::json!(["orphan"]));
+    for kind in ["uploaded", "skipped", "deleted"] {
+        assert_eq!(
+            json[format!("{kind}_uids")].as_array().unwrap().len() as u64,
+            json[kind].as_u64().unwrap(),
+            "{kind}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn destination_sync_omits_uids_by_default() {
+    let state = test_state();
+    let id = destination_with_mixed_calendar(&state).await;
+
+    let json = sync_destination(state, format!("/api/destinations/{}/sync", id)).await;
+
+    assert_eq!(json["uploaded"], 1);
+    assert_eq!(json["deleted"], 1);
+    assert!(json.get("uploaded_uids").is_none());
+    assert!(json.get("skipped_uids").is_none());
+    assert!(json.get("deleted_uids").is_none());
+}
+
+#[tokio::test]
+async fn destination_sync_stream_reports_progress_then_result() {
+    let state = test_state();
+    let id = destination_with_mixed_calendar(&state).await;
+
+    let resp = app(state)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/destinations/{}/sync/stream", id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()["content-type"], "application/x-ndjson");
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let lines: Vec<Value> = String::from_utf8(bytes.to_vec())
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    let (result, progress) = lines.split_last().unwrap();
+    assert_eq!(progress.len(), 3, "initial line plus one per event");
+    let processed: Vec<u64> = progress
+        .iter()
+        .map(|p| p["processed"].as_u64().unwrap())
+        .collect();
+    assert_eq!(processed, [0, 1, 2]);
+    let last = progress.last().unwrap();
+    assert_eq!(last["total"], 2);
+    assert_eq!(last["uploaded"], 1);
+    assert_eq!(last["skipped"], 1);
+
+    assert_eq!(result["status"], "success");
+    assert_eq!(result["total"], 2);
+    assert_eq!(result["uploaded"], 1);
+    assert_eq!(result["skipped"], 1);
+    assert_eq!(result["deleted"], 1);
+}
+
+#[tokio::test]
+async fn destination_sync_stream_unknown_destination_is_404() {
+    let resp = app(test_state())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/destinations/999/sync/stream")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn destination_sync_guards_against_feed_shrink_unless_forced() {
+    let state = test_state();
+    let id = destination_with_mixed_calendar(&state).await;
+    {
+        let db = state.db.get().unwrap();
+        let upd = db::UpdateDestination {
+            max_shrink_percent: Some(50),
+            ..Default::default()
+        };
+        db::update_destination(&db, id, &upd).unwrap();
+        db::update_destination_total_events(&db, id, 10).unwrap();
+    }
+
+    let resp = app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/api/destinations/{}/sync", id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    let json = body_json(resp.into_body()).await;
+    assert!(
+        json["message"]
+            .as_str()
+            .unwrap()
+            .contains("Suspicious feed shrink")
+    );
+
+    let json = sync_destination(
+        state.clone(),
+        format!("/api/destinations/{}/sync?force=true", id),
+    )
+    .await;
+    assert_eq!(json["uploaded"], 1);
+    let dest = db::get_destination(&state.db.get().unwrap(), id)
+        .unwrap()
+        .unwrap();
+    assert_eq!(dest.last_total_events, Some(2));
+}
+
+/// Answers every request with an empty multistatus after `delay_ms`.
+async fn start_slow_caldav_server(delay_ms: u64) -> std::net::SocketAddr {
+    let mock = Router::new().fallback(axum::routing::any(move || async move {
+        tokio::time::sleep(std::time::Duration::from_millis(delay_ms)).await;
+        (
+            StatusCode::MULTI_STATUS,
+            r#"<?xml version="1.0" encoding="utf-8" ?><d:multistatus xmlns:d="DAV:"/>"#,
+        )
+    }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, mock).await.unwrap();
+    });
+    addr
+}
+
+#[tokio::test]
+async fn source_sync_reports_timings_only_when_asked() {
+    let addr = start_slow_caldav_server(30).await;
+    let state = test_state();
+    let mut source = source_json();
+    source["caldav_url"] = format!("http://{}/dav/", addr).into();
+    let id = create_via_api(state.clone(), "/api/sources", source).await;
+
+    let sync = |uri: String| {
+        let state = state.clone();
+        async move {
+            let resp = app(state)
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri(uri)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            body_json(resp.into_body()).await
+        }
+    };
+
+    let plain = sync(format!("/api/sources/{}/sync", id)).await;
+    assert!(plain.get("timings").is_none());
+
+    let json = sync(format!("/api/sources/{}/sync?timings=true", id)).await;
+    let timings = &json["timings"];
+    let phase = |name: &str| {
+        timings[name]
+            .as_u64()
+            .unwrap_or_else(|| panic!("missing {}", name))
+    };
+    let total = phase("total_ms");
+    let sum: u64 = [
+        "setup_ms",
+        "propfind_ms",
+        "report_ms",
+        "assembly_ms",
+        "db_write_ms",
+    ]
+    .into_iter()
+    .map(phase)
+    .sum();
+    assert!(phase("propfind_ms") >= 30, "{}", timings);
+    // Each phase is rounded down to whole milliseconds.
+    assert!(sum <= total && total - sum <= 5, "{}", timings);
+}
+
+// ---------- Sync freshness ----------
+
+#[tokio::test]
+async fn sync_freshness_flags_overdue_entities() {
+    let state = test_state();
+    let (stale, fresh) = {
+        let db = state.db.get().unwrap();
+        let mut source = source_json();
+        source["sync_interval_secs"] = 3600.into();
+        let stale =
+            db::create_source(&db, &serde_json::from_value(source.clone()).unwrap()).unwrap();
+        source["ics_path"] = "fresh.ics".into();
+        let fresh = db::create_source(&db, &serde_json::from_value(source).unwrap()).unwrap();
+        db.execute(
+            "UPDATE sources SET last_synced = '2020-01-01 00:00:00' WHERE id = ?1",
+            [stale],
+        )
+        .unwrap();
+        db::update_last_synced(&db, fresh).unwrap();
+        db::create_destination(&db, &serde_json::from_value(destination_json()).unwrap()).unwrap();
+        (stale, fresh)
+    };
+
+    let (status, json) = get_json(state, "/api/sync/freshness").await;
+    assert_eq!(status, StatusCode::OK);
+    let entities = json.as_array().unwrap();
+    assert_eq!(entities.len(), 3);
+    let source = |id: i64| {
+        entities
+            .iter()
+            .find(|e| e["kind"] == "source" && e["id"] == id)
+            .unwrap()
+    };
+
+    let stale = source(stale);
+    assert_eq!(stale["overdue"], true);
+    assert_eq!(stale["last_synced"], "2020-01-01 00:00:00");
+    assert!(stale["seconds_since_sync"].as_i64().unwrap() > 365 * 86_400);
+
+    let fresh = source(fresh);
+    assert_eq!(fresh["overdue"], false);
+    assert!(fresh["seconds_since_sync"].as_i64().unwrap() < 60);
+
+    // Never synced, and without an interval it is never overdue.
+    let dest = entities
+        .iter()
+        .find(|e| e["kind"] == "destination")
+        .unwrap();
+    assert_eq!(dest["name"], "Test Dest");
+    assert!(dest["seconds_since_sync"].is_null());
+    assert_eq!(dest["overdue"], false);
+}
+
+#[tokio::test]
+async fn sync_freshness_ignores_failed_destination_syncs() {
+    let state = test_state();
+    let (failing, never_ok) = {
+        let db = state.db.get().unwrap();
+        let mut dest = destination_json();
+        dest["sync_interval_secs"] = 3600.into();
+        let failing =
+            db::create_destination(&db, &serde_json::from_value(dest.clone()).unwrap()).unwrap();
+        dest["name"] = "Never ok".into();
+        let never_ok = db::create_destination(&db, &serde_json::from_value(dest).unwrap()).unwrap();
+        db::update_destination_sync_status(&db, failing, "ok", None).unwrap();
+        db.execute(
+            "UPDATE destinations SET last_success = '2020-01-01 00:00:00' WHERE id = ?1",
+            [failing],
+        )
+        .unwrap();
+        db.execute(
+            "UPDATE destinations SET created_at = '2020-01-01 00:00:00' WHERE id = ?1",
+            [never_ok],
+        )
+        .unwrap();
+        for id in [failing, never_ok] {
+            db::update_destination_sync_status(&db, id, "error", Some("boom")).unwrap();
+        }
+        (failing, never_ok)
+    };
+
+    let (status, json) = get_json(state, "/api/sync/freshness").await;
+    assert_eq!(status, StatusCode::OK);
+    let dest = |id: i64| {
+        json.as_array()
+            .unwrap()
+            .iter()
+            .find(|e| e["kind"] == "destination" && e["id"] == id)
+            .unwrap()
+            .clone()
+    };
+
+    // Erroring just now does not make a destination fresh.
+    let failing = dest(failing);
+    assert_eq!(failing["last_sync_status"], "error");
+    assert_eq!(failing["last_synced"], "2020-01-01 00:00:00");
+    assert!(failing["seconds_since_sync"].as_i64().unwrap() > 365 * 86_400);
+    assert_eq!(failing["overdue"], true);
+
+    let never_ok = dest(never_ok);
+    assert!(never_ok["seconds_since_sync"].is_null());
+    assert_eq!(never_ok["overdue"], true);
 }
@@ -1,3 +1,4 @@
+use caldav_ics_sync::api::sync::{SKIPPED_EMPTY, SyncOptions};
 use caldav_ics_sync::db::*;
 use rusqlite::Connection;
 
@@ -18,6 +19,24 @@ fn valid_source() -> CreateSource {
         sync_interval_secs: 3600,
         public_ics: false,
         public_ics_path: None,
+        allow_invalid_certs: false,
+        webhook_on_success_url: None,
+        webhook_on_failure_url: None,
+        default_timezone: None,
+        sort_events: false,
+        summary_template: None,
+        max_event_bytes: 0,
+        refresh_interval_secs: 0,
+        debug_http: false,
+        auto_discover: false,
+        propfind_events: false,
+        skip_on_empty: true,
+        enabled: true,
+        public_from: None,
+        public_until: None,
+        signed_public_urls: false,
+        cron_schedule: None,
+        tag_calendar_origin: false,
     }
 }
 
@@ -32,6 +51,27 @@ fn valid_destination() -> CreateDestination {
         sync_interval_secs: 3600,
         sync_all: false,
         keep_local: false,
+        allow_redirects: true,
+        max_redirects: 10,
+        redirect_cross_host_headers: false,
+        trust_last_modified: false,
+        match_by: MatchBy::Uid,
+        allow_invalid_certs: false,
+        webhook_on_success_url: None,
+        webhook_on_failure_url: None,
+        set_display_name: false,
+        debug_http: false,
+        prefer_minimal: false,
+        compress_uploads: false,
+        extra_headers: Default::default(),
+        max_shrink_percent: 0,
+        uuid_hrefs: false,
+        skip_own_origin: false,
+        future_window_days: 0,
+        ignore_transp: false,
+        calendar_url_override: None,
+        filter_timezone: None,
+        cron_schedule: None,
     }
 }
 
@@ -117,6 +157,16 @@ fn create_source_rejects_public_slash_prefix() {
     assert!(create_source(&conn, &s).is_err());
 }
 
+#[test]
+fn create_source_rejects_by_id_prefix() {
+    let conn = setup();
+    let mut s = valid_source();
+    s.ics_path = "by-id/1".into();
+    assert!(create_source(&conn, &s).is_err());
+    s.ics_path = "by-identity.ics".into();
+    assert!(create_source(&conn, &s).is_ok());
+}
+
 #[test]
 fn list_sources_returns_created() {
     let conn = setup();
@@ -132,6 +182,107 @@ fn list_sources_returns_created() {
     assert_eq!(sources[1].ics_path, "other.ics");
 }
 
+#[test]
+fn update_source_bumps_updated_at() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    let before = get_source(&conn, id).unwrap().unwrap().updated_at;
+    std::thread::sleep(std::time::Duration::from_millis(5));
+
+    let upd = UpdateSource {
+        name: Some("Renamed".into()),
+        ..Default::default()
+    };
+    update_source(&conn, id, &upd).unwrap();
+
+    let after = get_source(&conn, id).unwrap().unwrap().updated_at;
+    assert!(after > before, "{} should be after {}", after, before);
+    assert_eq!(list_sources_changed_since(&conn, &after).unwrap().len(), 1);
+}
+
+#[test]
+fn refresh_interval_override_takes_precedence_over_sync_interval() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    let source = get_source(&conn, id).unwrap().unwrap();
+    assert_eq!(SyncOptions::from(&source).refresh_interval_secs, Some(3600));
+
+    let upd = UpdateSource {
+        refresh_interval_secs: Some(900),
+        ..Default::default()
+    };
+    update_source(&conn, id, &upd).unwrap();
+    let source = get_source(&conn, id).unwrap().unwrap();
+    assert_eq!(SyncOptions::from(&source).refresh_interval_secs, Some(900));
+
+    let bad = UpdateSource {
+        refresh_interval_secs: Some(-5),
+        ..Default::default()
+    };
+    assert!(update_source(&conn, id, &bad).is_err());
+}
+
+#[test]
+fn max_event_bytes_rejects_negative_and_records_trimmed() {
+    let conn = setup();
+    let bad = CreateSource {
+        max_event_bytes: -1,
+        ..valid_source()
+    };
+    assert!(create_source(&conn, &bad).is_err());
+
+    let src = CreateSource {
+        max_event_bytes: 4096,
+        ..valid_source()
+    };
+    let id = create_source(&conn, &src).unwrap();
+    update_trimmed_events(&conn, id, 3).unwrap();
+    let saved = get_source(&conn, id).unwrap().unwrap();
+    assert_eq!(saved.max_event_bytes, 4096);
+    assert_eq!(saved.last_sync_trimmed, 3);
+}
+
+#[test]
+fn create_source_validates_summary_template() {
+    let conn = setup();
+    for bad in ["{summary", "summary}", "{title}"] {
+        let src = CreateSource {
+            summary_template: Some(bad.into()),
+            ..valid_source()
+        };
+        assert!(
+            create_source(&conn, &src).is_err(),
+            "{} should be rejected",
+            bad
+        );
+    }
+
+    let src = CreateSource {
+        summary_template: Some("[Work] {summary} / {calendar}".into()),
+        ..valid_source()
+    };
+    let id = create_source(&conn, &src).unwrap();
+    assert_eq!(
+        get_source(&conn, id)
+            .unwrap()
+            .unwrap()
+            .summary_template
+            .as_deref(),
+        Some("[Work] {summary} / {calendar}")
+    );
+}
+
+#[test]
+fn update_source_rejects_invalid_summary_template() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    let upd = UpdateSource {
+        summary_template: Some("{nope}".into()),
+        ..Default::default()
+    };
+    assert!(update_source(&conn, id, &upd).is_err());
+}
+
 #[test]
 fn get_source_by_id() {
     let conn = setup();
@@ -160,6 +311,25 @@ fn update_source_preserves_password_on_empty() {
         sync_interval_secs: None,
         public_ics: None,
         public_ics_path: None,
+        allow_invalid_certs: None,
+        webhook_on_success_url: None,
+        webhook_on_failure_url: None,
+        default_timezone: None,
+        sort_events: None,
+        summary_template: None,
+        max_event_bytes: None,
+        refresh_interval_secs: None,
+        debug_http: None,
+        auto_discover: None,
+        propfind_events: None,
+        skip_on_empty: None,
+        enabled: None,
+        ics_path_grace_secs: None,
+        public_from: None,
+        public_until: None,
+        signed_public_urls: None,
+        cron_schedule: None,
+        tag_calendar_origin: None,
     };
     update_source(&conn, id, &upd).unwrap();
     let src = get_source(&conn, id).unwrap().unwrap();
@@ -167,6 +337,33 @@ fn update_source_preserves_password_on_empty() {
     assert_eq!(src.password, "pass");
 }
 
+#[test]
+fn source_enabled_defaults_true_and_toggles() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    assert!(get_source(&conn, id).unwrap().unwrap().enabled);
+
+    let upd = UpdateSource {
+        enabled: Some(false),
+        ..Default::default()
+    };
+    update_source(&conn, id, &upd).unwrap();
+    let src = get_source(&conn, id).unwrap().unwrap();
+    assert!(!src.enabled);
+    assert_eq!(src.sync_interval_secs, valid_source().sync_interval_secs);
+
+    let src: CreateSource = serde_json::from_value(serde_json::json!({
+        "name": "From JSON",
+        "caldav_url": "https://caldav.example.com/dav",
+        "username": "u",
+        "password": "p",
+        "ics_path": "json.ics",
+        "sync_interval_secs": 0
+    }))
+    .unwrap();
+    assert!(src.enabled);
+}
+
 #[test]
 fn update_source_rejects_duplicate_ics_path() {
     let conn = setup();
@@ -185,10 +382,123 @@ fn update_source_rejects_duplicate_ics_path() {
         sync_interval_secs: None,
         public_ics: None,
         public_ics_path: None,
+        allow_invalid_certs: None,
+        webhook_on_success_url: None,
+        webhook_on_failure_url: None,
+        default_timezone: None,
+        sort_events: None,
+        summary_template: None,
+        max_event_bytes: None,
+        refresh_interval_secs: None,
+        debug_http: None,
+        auto_discover: None,
+        propfind_events: None,
+        skip_on_empty: None,
+        enabled: None,
+        ics_path_grace_secs: None,
+        public_from: None,
+        public_until: None,
+        signed_public_urls: None,
+        cron_schedule: None,
+        tag_calendar_origin: None,
     };
     assert!(update_source(&conn, id1, &upd).is_err());
 }
 
+#[test]
+fn update_source_toggles_allow_invalid_certs() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    assert!(!get_source(&conn, id).unwrap().unwrap().allow_invalid_certs);
+
+    let upd = UpdateSource {
+        allow_invalid_certs: Some(true),
+        ..Default::default()
+    };
+    update_source(&conn, id, &upd).unwrap();
+    assert!(get_source(&conn, id).unwrap().unwrap().allow_invalid_certs);
+}
+
+#[test]
+fn create_source_rejects_unknown_timezone() {
+    let conn = setup();
+    let mut s = valid_source();
+    s.default_timezone = Some("Mars/Olympus_Mons".into());
+    assert!(create_source(&conn, &s).is_err());
+
+    s.default_timezone = Some("America/New_York".into());
+    let id = create_source(&conn, &s).unwrap();
+    let src = get_source(&conn, id).unwrap().unwrap();
+    assert_eq!(src.default_timezone.as_deref(), Some("America/New_York"));
+}
+
+#[test]
+fn update_source_clears_timezone_with_empty_string() {
+    let conn = setup();
+    let mut s = valid_source();
+    s.default_timezone = Some("Asia/Tokyo".into());
+    let id = create_source(&conn, &s).unwrap();
+
+    let upd = UpdateSource {
+        default_timezone: Some("".into()),
+        ..Default::default()
+    };
+    update_source(&conn, id, &upd).unwrap();
+    assert!(
+        get_source(&conn, id)
+            .unwrap()
+            .unwrap()
+            .default_timezone
+            .is_none()
+    );
+}
+
+#[test]
+fn duplicate_source_copies_into_independent_row() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    let dup = DuplicateSource {
+        ics_path: "copy.ics".into(),
+        name: None,
+    };
+    let copy_id = duplicate_source(&conn, id, &dup).unwrap().unwrap();
+    assert_ne!(copy_id, id);
+
+    let copy = get_source(&conn, copy_id).unwrap().unwrap();
+    assert_eq!(copy.name, "Copy of Test");
+    assert_eq!(copy.ics_path, "copy.ics");
+    assert_eq!(copy.password, "pass");
+
+    let upd = UpdateSource {
+        caldav_url: Some("https://other.example.com".into()),
+        ..Default::default()
+    };
+    update_source(&conn, copy_id, &upd).unwrap();
+    let original = get_source(&conn, id).unwrap().unwrap();
+    assert_eq!(original.caldav_url, "https://cal.example.com");
+}
+
+#[test]
+fn duplicate_source_rejects_taken_ics_path() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    let dup = DuplicateSource {
+        ics_path: "cal.ics".into(),
+        name: None,
+    };
+    assert!(duplicate_source(&conn, id, &dup).is_err());
+}
+
+#[test]
+fn duplicate_source_nonexistent() {
+    let conn = setup();
+    let dup = DuplicateSource {
+        ics_path: "copy.ics".into(),
+        name: None,
+    };
+    assert!(duplicate_source(&conn, 999, &dup).unwrap().is_none());
+}
+
 #[test]
 fn delete_source_removes_it() {
     let conn = setup();
@@ -197,12 +507,227 @@ fn delete_source_removes_it() {
     assert!(get_source(&conn, id).unwrap().is_none());
 }
 
+#[test]
+fn deleted_source_paths_are_reusable_immediately() {
+    let conn = setup();
+    let mut src = valid_source();
+    src.public_ics = true;
+    src.public_ics_path = Some("shared".into());
+    let id = create_source(&conn, &src).unwrap();
+    let alias = CreateSourcePath {
+        path: "alias.ics".into(),
+        is_public: true,
+    };
+    create_source_path(&conn, id, &alias).unwrap();
+    assert!(create_source(&conn, &src).is_err());
+
+    assert!(delete_source(&conn, id).unwrap());
+    let recreated = create_source(&conn, &src).unwrap();
+    create_source_path(&conn, recreated, &alias).unwrap();
+    let saved = get_source(&conn, recreated).unwrap().unwrap();
+    assert_eq!(saved.ics_path, src.ics_path);
+    assert_eq!(saved.public_ics_path.as_deref(), Some("shared"));
+}
+
+fn rename_with_grace(conn: &Connection, id: i64, new_path: &str, grace: i64) {
+    let upd = UpdateSource {
+        ics_path: Some(new_path.into()),
+        ics_path_grace_secs: Some(grace),
+        ..Default::default()
+    };
+    assert!(update_source(conn, id, &upd).unwrap());
+}
+
+fn expire_aliases(conn: &Connection) {
+    conn.execute(
+        "UPDATE source_paths SET expires_at = datetime('now', '-1 second') WHERE expires_at IS NOT NULL",
+        [],
+    )
+    .unwrap();
+}
+
+#[test]
+fn renamed_ics_path_stays_served_during_grace_period() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    let old_path = valid_source().ics_path;
+    save_ics_data(&conn, id, "BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n").unwrap();
+
+    rename_with_grace(&conn, id, "renamed.ics", 3600);
+
+    assert!(
+        get_ics_data_by_path(&conn, "renamed.ics")
+            .unwrap()
+            .is_some()
+    );
+    assert!(get_ics_data_by_path(&conn, &old_path).unwrap().is_some());
+    let paths = list_source_paths(&conn, id).unwrap();
+    assert_eq!(paths.len(), 1);
+    assert_eq!(paths[0].path, old_path);
+    assert!(paths[0].expires_at.is_some());
+
+    expire_aliases(&conn);
+    assert!(get_ics_data_by_path(&conn, &old_path).unwrap().is_none());
+    assert!(list_source_paths(&conn, id).unwrap().is_empty());
+    // The expired alias no longer blocks the name.
+    let mut other = valid_source();
+    other.name = "Other".into();
+    create_source(&conn, &other).unwrap();
+}
+
+#[test]
+fn rename_without_grace_frees_old_path_immediately() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    save_ics_data(&conn, id, "BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n").unwrap();
+
+    rename_with_grace(&conn, id, "renamed.ics", 0);
+
+    assert!(
+        get_ics_data_by_path(&conn, &valid_source().ics_path)
+            .unwrap()
+            .is_none()
+    );
+    assert!(list_source_paths(&conn, id).unwrap().is_empty());
+}
+
+#[test]
+fn rename_back_reclaims_own_alias() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    let old_path = valid_source().ics_path;
+
+    rename_with_grace(&conn, id, "renamed.ics", 3600);
+    rename_with_grace(&conn, id, &old_path, 3600);
+
+    let source = get_source(&conn, id).unwrap().unwrap();
+    assert_eq!(source.ics_path, old_path);
+    let paths = list_source_paths(&conn, id).unwrap();
+    assert_eq!(paths.len(), 1);
+    assert_eq!(paths[0].path, "renamed.ics");
+}
+
 #[test]
 fn delete_source_nonexistent() {
     let conn = setup();
     assert!(!delete_source(&conn, 999).unwrap());
 }
 
+#[test]
+fn successful_sync_clears_previous_error() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    update_sync_status(&conn, id, "error", Some("401 Unauthorized")).unwrap();
+    assert_eq!(
+        get_source(&conn, id)
+            .unwrap()
+            .unwrap()
+            .last_sync_error
+            .as_deref(),
+        Some("401 Unauthorized")
+    );
+
+    update_sync_status(&conn, id, "ok", None).unwrap();
+    let src = get_source(&conn, id).unwrap().unwrap();
+    assert_eq!(src.last_sync_status.as_deref(), Some("ok"));
+    assert_eq!(src.last_sync_error, None);
+    let stored: Option<String> = conn
+        .query_row(
+            "SELECT last_sync_error FROM sources WHERE id = ?1",
+            [id],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(stored, None);
+}
+
+#[test]
+fn ok_status_never_shows_an_error() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    update_sync_status(&conn, id, "ok", Some("ignored")).unwrap();
+    assert_eq!(
+        get_source(&conn, id).unwrap().unwrap().last_sync_error,
+        None
+    );
+
+    // A row left inconsistent by an older release.
+    conn.execute(
+        "UPDATE sources SET last_sync_status = 'ok', last_sync_error = 'old' WHERE id = ?1",
+        [id],
+    )
+    .unwrap();
+    assert_eq!(
+        get_source(&conn, id).unwrap().unwrap().last_sync_error,
+        None
+    );
+    assert_eq!(list_sources(&conn).unwrap()[0].last_sync_error, None);
+
+    let dest_id = create_destination(&conn, &valid_destination()).unwrap();
+    update_destination_sync_status(&conn, dest_id, "error", Some("timeout")).unwrap();
+    update_destination_sync_status(&conn, dest_id, "ok", None).unwrap();
+    assert_eq!(
+        get_destination(&conn, dest_id)
+            .unwrap()
+            .unwrap()
+            .last_sync_error,
+        None
+    );
+}
+
+#[test]
+fn failures_are_counted_until_a_successful_sync() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    let failures = |conn: &Connection| get_source(conn, id).unwrap().unwrap().consecutive_failures;
+    assert_eq!(failures(&conn), 0);
+    update_sync_status(&conn, id, "error", Some("timeout")).unwrap();
+    update_sync_status(&conn, id, "error", Some("timeout")).unwrap();
+    assert_eq!(failures(&conn), 2);
+    update_sync_status(&conn, id, SKIPPED_EMPTY, None).unwrap();
+    assert_eq!(failures(&conn), 2);
+    update_sync_status(&conn, id, "error", Some("timeout")).unwrap();
+    assert_eq!(failures(&conn), 3);
+    update_sync_status(&conn, id, "ok", None).unwrap();
+    assert_eq!(failures(&conn), 0);
+}
+
+#[test]
+fn destination_failures_reset_on_success() {
+    let conn = setup();
+    let id = create_destination(&conn, &valid_destination()).unwrap();
+    let failures = |conn: &Connection| {
+        get_destination(conn, id)
+            .unwrap()
+            .unwrap()
+            .consecutive_failures
+    };
+    update_destination_sync_status(&conn, id, "error", Some("timeout")).unwrap();
+    update_destination_sync_status(&conn, id, "error", Some("timeout")).unwrap();
+    assert_eq!(failures(&conn), 2);
+    update_destination_sync_status(&conn, id, "ok", None).unwrap();
+    assert_eq!(failures(&conn), 0);
+}
+
+#[test]
+fn destination_last_success_only_moves_on_ok() {
+    let conn = setup();
+    let id = create_destination(&conn, &valid_destination()).unwrap();
+    let last_success = |conn: &Connection| get_destination(conn, id).unwrap().unwrap().last_success;
+    update_destination_sync_status(&conn, id, "error", Some("timeout")).unwrap();
+    assert_eq!(last_success(&conn), None);
+    update_destination_sync_status(&conn, id, "ok", None).unwrap();
+    let ok = last_success(&conn);
+    assert!(ok.is_some());
+    conn.execute(
+        "UPDATE destinations SET last_success = '2020-01-01 00:00:00' WHERE id = ?1",
+        [id],
+    )
+    .unwrap();
+    update_destination_sync_status(&conn, id, "error", Some("timeout")).unwrap();
+    assert_eq!(last_success(&conn).as_deref(), Some("2020-01-01 00:00:00"));
+}
+
 // ---- Public ICS ----
 
 #[test]
@@ -298,6 +823,25 @@ fn update_public_ics_false_clears_public_path() {
         sync_interval_secs: None,
         public_ics: Some(false),
         public_ics_path: None,
+        allow_invalid_certs: None,
+        webhook_on_success_url: None,
+        webhook_on_failure_url: None,
+        default_timezone: None,
+        sort_events: None,
+        summary_template: None,
+        max_event_bytes: None,
+        refresh_interval_secs: None,
+        debug_http: None,
+        auto_discover: None,
+        propfind_events: None,
+        skip_on_empty: None,
+        enabled: None,
+        ics_path_grace_secs: None,
+        public_from: None,
+        public_until: None,
+        signed_public_urls: None,
+        cron_schedule: None,
+        tag_calendar_origin: None,
     };
     update_source(&conn, id, &upd).unwrap();
     let src = get_source(&conn, id).unwrap().unwrap();
@@ -326,6 +870,25 @@ fn get_ics_data_by_public_path_only_when_public() {
         sync_interval_secs: None,
         public_ics: Some(false),
         public_ics_path: None,
+        allow_invalid_certs: None,
+        webhook_on_success_url: None,
+        webhook_on_failure_url: None,
+        default_timezone: None,
+        sort_events: None,
+        summary_template: None,
+        max_event_bytes: None,
+        refresh_interval_secs: None,
+        debug_http: None,
+        auto_discover: None,
+        propfind_events: None,
+        skip_on_empty: None,
+        enabled: None,
+        ics_path_grace_secs: None,
+        public_from: None,
+        public_until: None,
+        signed_public_urls: None,
+        cron_schedule: None,
+        tag_calendar_origin: None,
     };
     update_source(&conn, id, &upd).unwrap();
     let data = get_ics_data_by_public_path(&conn, "shared.ics").unwrap();
@@ -362,51 +925,134 @@ fn is_public_standard_ics_false_when_not_public() {
     assert!(!is_public_standard_ics(&conn, "cal.ics").unwrap());
 }
 
-// ---- Source Paths ----
+fn windowed_public_source(conn: &Connection, from: &str, until: &str) -> i64 {
+    let mut s = valid_source();
+    s.public_ics = true;
+    s.public_ics_path = Some("shared.ics".into());
+    s.public_from = Some(from.into());
+    s.public_until = Some(until.into());
+    let id = create_source(conn, &s).unwrap();
+    save_ics_data(conn, id, "BEGIN:VCALENDAR\nEND:VCALENDAR").unwrap();
+    id
+}
 
 #[test]
-fn create_source_path_succeeds() {
+fn public_window_hides_source_before_it_opens() {
     let conn = setup();
-    let src_id = create_source(&conn, &valid_source()).unwrap();
-    let body = CreateSourcePath {
-        path: "alias.ics".into(),
-        is_public: false,
-    };
-    let sp_id = create_source_path(&conn, src_id, &body).unwrap();
-    assert!(sp_id > 0);
+    windowed_public_source(&conn, "2999-01-01", "2999-12-31T00:00:00Z");
+    assert!(
+        get_ics_data_by_public_path(&conn, "shared.ics")
+            .unwrap()
+            .is_none()
+    );
 }
 
 #[test]
-fn create_source_path_rejects_duplicate() {
+fn public_window_serves_source_while_open() {
     let conn = setup();
-    let src_id = create_source(&conn, &valid_source()).unwrap();
-    let body = CreateSourcePath {
-        path: "alias.ics".into(),
-        is_public: false,
-    };
-    create_source_path(&conn, src_id, &body).unwrap();
-    assert!(create_source_path(&conn, src_id, &body).is_err());
+    let id = windowed_public_source(&conn, "2000-01-01T00:00:00+02:00", "2999-01-01");
+    assert!(
+        get_ics_data_by_public_path(&conn, "shared.ics")
+            .unwrap()
+            .is_some()
+    );
+
+    let src = get_source(&conn, id).unwrap().unwrap();
+    assert_eq!(src.public_from.as_deref(), Some("1999-12-31 22:00:00"));
+    assert_eq!(src.public_until.as_deref(), Some("2999-01-01 00:00:00"));
 }
 
 #[test]
-fn create_source_path_rejects_match_with_sources_ics_path() {
+fn public_window_hides_source_after_it_closes() {
     let conn = setup();
-    let src_id = create_source(&conn, &valid_source()).unwrap();
-    let body = CreateSourcePath {
-        path: "cal.ics".into(),
-        is_public: false,
+    let id = windowed_public_source(&conn, "2000-01-01", "2001-01-01");
+    assert!(
+        get_ics_data_by_public_path(&conn, "shared.ics")
+            .unwrap()
+            .is_none()
+    );
+
+    // Clearing the bound re-opens the source.
+    let upd = UpdateSource {
+        public_until: Some(String::new()),
+        ..Default::default()
     };
-    assert!(create_source_path(&conn, src_id, &body).is_err());
+    update_source(&conn, id, &upd).unwrap();
+    assert!(
+        get_ics_data_by_public_path(&conn, "shared.ics")
+            .unwrap()
+            .is_some()
+    );
 }
 
 #[test]
-fn create_source_path_rejects_match_with_sources_public_path() {
+fn public_window_applies_to_standard_path() {
     let conn = setup();
     let mut s = valid_source();
     s.public_ics = true;
-    s.public_ics_path = Some("shared.ics".into());
-    let src_id = create_source(&conn, &s).unwrap();
-    let body = CreateSourcePath {
+    s.public_from = Some("2999-01-01".into());
+    create_source(&conn, &s).unwrap();
+    assert!(!is_public_standard_ics(&conn, "cal.ics").unwrap());
+}
+
+#[test]
+fn public_window_rejects_invalid_bounds() {
+    let conn = setup();
+    let mut s = valid_source();
+    s.public_from = Some("next tuesday".into());
+    assert!(create_source(&conn, &s).is_err());
+
+    let mut s = valid_source();
+    s.public_from = Some("2030-01-01".into());
+    s.public_until = Some("2020-01-01".into());
+    assert!(create_source(&conn, &s).is_err());
+}
+
+// ---- Source Paths ----
+
+#[test]
+fn create_source_path_succeeds() {
+    let conn = setup();
+    let src_id = create_source(&conn, &valid_source()).unwrap();
+    let body = CreateSourcePath {
+        path: "alias.ics".into(),
+        is_public: false,
+    };
+    let sp_id = create_source_path(&conn, src_id, &body).unwrap();
+    assert!(sp_id > 0);
+}
+
+#[test]
+fn create_source_path_rejects_duplicate() {
+    let conn = setup();
+    let src_id = create_source(&conn, &valid_source()).unwrap();
+    let body = CreateSourcePath {
+        path: "alias.ics".into(),
+        is_public: false,
+    };
+    create_source_path(&conn, src_id, &body).unwrap();
+    assert!(create_source_path(&conn, src_id, &body).is_err());
+}
+
+#[test]
+fn create_source_path_rejects_match_with_sources_ics_path() {
+    let conn = setup();
+    let src_id = create_source(&conn, &valid_source()).unwrap();
+    let body = CreateSourcePath {
+        path: "cal.ics".into(),
+        is_public: false,
+    };
+    assert!(create_source_path(&conn, src_id, &body).is_err());
+}
+
+#[test]
+fn create_source_path_rejects_match_with_sources_public_path() {
+    let conn = setup();
+    let mut s = valid_source();
+    s.public_ics = true;
+    s.public_ics_path = Some("shared.ics".into());
+    let src_id = create_source(&conn, &s).unwrap();
+    let body = CreateSourcePath {
         path: "shared.ics".into(),
         is_public: false,
     };
@@ -486,6 +1132,86 @@ fn list_source_paths_for_source() {
     assert_eq!(paths[1].path, "b.ics");
 }
 
+fn path_batch(paths: &[&str]) -> Vec<CreateSourcePath> {
+    paths
+        .iter()
+        .map(|p| CreateSourcePath {
+            path: (*p).into(),
+            is_public: false,
+        })
+        .collect()
+}
+
+#[test]
+fn create_source_paths_batch_inserts_all() {
+    let conn = setup();
+    let src_id = create_source(&conn, &valid_source()).unwrap();
+    let batch =
+        create_source_paths_batch(&conn, src_id, &path_batch(&["a.ics", "b.ics"]), false).unwrap();
+    assert!(batch.committed);
+    assert!(
+        batch
+            .results
+            .iter()
+            .all(|r| r.id.is_some() && r.error.is_none())
+    );
+    assert_eq!(list_source_paths(&conn, src_id).unwrap().len(), 2);
+}
+
+#[test]
+fn create_source_paths_batch_rolls_back_on_failure() {
+    let conn = setup();
+    let src_id = create_source(&conn, &valid_source()).unwrap();
+    // "a.ics" twice: the second copy is a duplicate of the first.
+    let batch = create_source_paths_batch(
+        &conn,
+        src_id,
+        &path_batch(&["a.ics", "../x", "a.ics"]),
+        false,
+    )
+    .unwrap();
+    assert!(!batch.committed);
+    assert!(batch.results.iter().all(|r| r.id.is_none()));
+    assert!(batch.results[0].error.is_none());
+    assert!(batch.results[1].error.is_some());
+    assert!(
+        batch.results[2]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("Duplicate")
+    );
+    assert!(list_source_paths(&conn, src_id).unwrap().is_empty());
+}
+
+#[test]
+fn create_source_paths_batch_partial_keeps_valid_entries() {
+    let conn = setup();
+    let src_id = create_source(&conn, &valid_source()).unwrap();
+    let batch = create_source_paths_batch(
+        &conn,
+        src_id,
+        &path_batch(&["a.ics", "/bad", "b.ics"]),
+        true,
+    )
+    .unwrap();
+    assert!(batch.committed);
+    assert!(batch.results[0].id.is_some());
+    assert!(batch.results[1].error.is_some());
+    assert!(batch.results[2].id.is_some());
+    let paths = list_source_paths(&conn, src_id).unwrap();
+    assert_eq!(paths.len(), 2);
+    assert_eq!(paths[1].path, "b.ics");
+}
+
+#[test]
+fn create_source_paths_batch_requires_source_and_paths() {
+    let conn = setup();
+    assert!(create_source_paths_batch(&conn, 999, &path_batch(&["a.ics"]), false).is_err());
+    let src_id = create_source(&conn, &valid_source()).unwrap();
+    assert!(create_source_paths_batch(&conn, src_id, &[], false).is_err());
+}
+
 #[test]
 fn update_source_path_changes_path() {
     let conn = setup();
@@ -640,6 +1366,29 @@ fn create_destination_valid() {
     assert!(id > 0);
 }
 
+#[test]
+fn destination_hrefs_round_trip_and_cascade() {
+    let conn = setup();
+    let id = create_destination(&conn, &valid_destination()).unwrap();
+    assert!(get_destination_hrefs(&conn, id).unwrap().is_empty());
+
+    let hrefs = std::collections::BTreeMap::from([
+        ("a/b@c".to_string(), "1111.ics".to_string()),
+        ("d".to_string(), "2222.ics".to_string()),
+    ]);
+    save_destination_hrefs(&conn, id, &hrefs).unwrap();
+    let loaded = get_destination_hrefs(&conn, id).unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded["a/b@c"], "1111.ics");
+
+    let fewer = std::collections::BTreeMap::from([("d".to_string(), "2222.ics".to_string())]);
+    save_destination_hrefs(&conn, id, &fewer).unwrap();
+    assert_eq!(get_destination_hrefs(&conn, id).unwrap().len(), 1);
+
+    delete_destination(&conn, id).unwrap();
+    assert!(get_destination_hrefs(&conn, id).unwrap().is_empty());
+}
+
 #[test]
 fn create_destination_rejects_empty_name() {
     let conn = setup();
@@ -648,6 +1397,105 @@ fn create_destination_rejects_empty_name() {
     assert!(create_destination(&conn, &d).is_err());
 }
 
+#[test]
+fn destination_filter_timezone_is_validated() {
+    let conn = setup();
+    let mut d = valid_destination();
+    d.filter_timezone = Some("Mars/Olympus".into());
+    assert!(create_destination(&conn, &d).is_err());
+    d.filter_timezone = Some("Asia/Tokyo".into());
+    let id = create_destination(&conn, &d).unwrap();
+    assert_eq!(
+        get_destination(&conn, id)
+            .unwrap()
+            .unwrap()
+            .filter_timezone
+            .as_deref(),
+        Some("Asia/Tokyo")
+    );
+}
+
+#[test]
+fn resolved_caldav_url_is_cleared_when_caldav_url_changes() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    let caldav_url = valid_source().caldav_url;
+
+    update_resolved_caldav_url(&conn, id, "https://moved.example.com/dav/").unwrap();
+    let resolved = |conn: &Connection| get_source(conn, id).unwrap().unwrap().resolved_caldav_url;
+    assert_eq!(
+        resolved(&conn).as_deref(),
+        Some("https://moved.example.com/dav/")
+    );
+
+    // Unrelated edits keep it.
+    let rename = UpdateSource {
+        name: Some("Renamed".into()),
+        ..Default::default()
+    };
+    assert!(update_source(&conn, id, &rename).unwrap());
+    assert!(resolved(&conn).is_some());
+
+    // Landing on the configured URL itself stores nothing.
+    update_resolved_caldav_url(&conn, id, &caldav_url).unwrap();
+    assert!(resolved(&conn).is_none());
+
+    update_resolved_caldav_url(&conn, id, "https://moved.example.com/dav/").unwrap();
+    let moved = UpdateSource {
+        caldav_url: Some("https://other.example.com/dav/".into()),
+        ..Default::default()
+    };
+    assert!(update_source(&conn, id, &moved).unwrap());
+    assert!(resolved(&conn).is_none());
+}
+
+#[test]
+fn cron_schedule_is_validated_and_stored() {
+    let conn = setup();
+    let mut src = valid_source();
+    src.cron_schedule = Some("0 25 * * *".into());
+    assert!(create_source(&conn, &src).is_err());
+    src.cron_schedule = Some("*/15 9-17 * * MON-FRI".into());
+    let id = create_source(&conn, &src).unwrap();
+    let stored = get_source(&conn, id).unwrap().unwrap();
+    assert_eq!(
+        stored.cron_schedule.as_deref(),
+        Some("*/15 9-17 * * MON-FRI")
+    );
+
+    let bad = UpdateSource {
+        cron_schedule: Some("every day".into()),
+        ..Default::default()
+    };
+    assert!(update_source(&conn, id, &bad).is_err());
+    let clear = UpdateSource {
+        cron_schedule: Some(String::new()),
+        ..Default::default()
+    };
+    assert!(update_source(&conn, id, &clear).unwrap());
+    assert!(
+        get_source(&conn, id)
+            .unwrap()
+            .unwrap()
+            .cron_schedule
+            .is_none()
+    );
+
+    let mut d = valid_destination();
+    d.cron_schedule = Some("0 0 31 2 *".into());
+    assert!(create_destination(&conn, &d).is_err());
+    d.cron_schedule = Some("@daily".into());
+    let id = create_destination(&conn, &d).unwrap();
+    assert_eq!(
+        get_destination(&conn, id)
+            .unwrap()
+            .unwrap()
+            .cron_schedule
+            .as_deref(),
+        Some("@daily")
+    );
+}
+
 #[test]
 fn create_destination_rejects_empty_ics_url() {
     let conn = setup();
@@ -688,20 +1536,43 @@ fn create_destination_rejects_empty_password() {
     assert!(create_destination(&conn, &d).is_err());
 }
 
+#[test]
+fn destination_extra_headers_round_trip() {
+    let conn = setup();
+    let mut d = valid_destination();
+    d.extra_headers = [("Referer".into(), "https://provider.example/".into())].into();
+    let id = create_destination(&conn, &d).unwrap();
+    let saved = get_destination(&conn, id).unwrap().unwrap();
+    assert_eq!(saved.extra_headers, d.extra_headers);
+}
+
+#[test]
+fn destination_rejects_invalid_extra_headers() {
+    let conn = setup();
+    let mut d = valid_destination();
+    d.extra_headers = [("Bad Header".into(), "x".into())].into();
+    let err = create_destination(&conn, &d).unwrap_err();
+    assert!(err.to_string().contains("Invalid header name"));
+
+    d.extra_headers = [("X-Token".into(), "line\nbreak".into())].into();
+    assert!(create_destination(&conn, &d).is_err());
+
+    let id = create_destination(&conn, &valid_destination()).unwrap();
+    let upd = UpdateDestination {
+        extra_headers: Some([("Bad:Name".into(), "x".into())].into()),
+        ..Default::default()
+    };
+    assert!(update_destination(&conn, id, &upd).is_err());
+}
+
 #[test]
 fn update_destination_preserves_password_on_empty() {
     let conn = setup();
     let id = create_destination(&conn, &valid_destination()).unwrap();
     let upd = UpdateDestination {
         name: Some("Renamed".into()),
-        ics_url: None,
-        caldav_url: None,
-        calendar_name: None,
-        username: None,
         password: Some("".into()),
-        sync_interval_secs: None,
-        sync_all: None,
-        keep_local: None,
+        ..Default::default()
     };
     update_destination(&conn, id, &upd).unwrap();
     let dest = get_destination(&conn, id).unwrap().unwrap();
@@ -768,6 +1639,69 @@ fn find_overlapping_destinations_no_match() {
     assert!(overlaps.is_empty());
 }
 
+#[test]
+fn strict_overlap_rejects_create_on_same_calendar() {
+    let conn = setup();
+    create_destination(&conn, &valid_destination()).unwrap();
+    let mut d2 = valid_destination();
+    d2.name = "Dest2".into();
+
+    let err = create_destination_with_policy(&conn, &d2, OverlapPolicy::Reject).unwrap_err();
+    let overlap = err.downcast_ref::<DestinationOverlap>().unwrap();
+    assert_eq!(overlap.calendar_name, "main");
+    assert_eq!(overlap.conflicting.len(), 1);
+    assert_eq!(list_destinations(&conn).unwrap().len(), 1);
+
+    // Advisory mode still allows it.
+    create_destination_with_policy(&conn, &d2, OverlapPolicy::Warn).unwrap();
+}
+
+#[test]
+fn strict_overlap_allows_when_both_keep_local() {
+    let conn = setup();
+    let mut d1 = valid_destination();
+    d1.keep_local = true;
+    create_destination(&conn, &d1).unwrap();
+
+    let mut d2 = valid_destination();
+    d2.name = "Dest2".into();
+    d2.keep_local = true;
+    create_destination_with_policy(&conn, &d2, OverlapPolicy::Reject).unwrap();
+
+    // Only one side keeping local events is not enough.
+    let mut d3 = valid_destination();
+    d3.name = "Dest3".into();
+    assert!(create_destination_with_policy(&conn, &d3, OverlapPolicy::Reject).is_err());
+}
+
+#[test]
+fn strict_overlap_rejects_update_into_used_calendar() {
+    let conn = setup();
+    create_destination(&conn, &valid_destination()).unwrap();
+    let mut d2 = valid_destination();
+    d2.name = "Dest2".into();
+    d2.calendar_name = "other".into();
+    let id2 = create_destination(&conn, &d2).unwrap();
+
+    let upd = UpdateDestination {
+        calendar_name: Some("main".into()),
+        ..Default::default()
+    };
+    let err = update_destination_with_policy(&conn, id2, &upd, OverlapPolicy::Reject).unwrap_err();
+    assert!(err.is::<DestinationOverlap>());
+    assert_eq!(
+        get_destination(&conn, id2).unwrap().unwrap().calendar_name,
+        "other"
+    );
+
+    // Saving a destination without moving it does not conflict with itself.
+    let rename = UpdateDestination {
+        name: Some("Renamed".into()),
+        ..Default::default()
+    };
+    assert!(update_destination_with_policy(&conn, id2, &rename, OverlapPolicy::Reject).unwrap());
+}
+
 // ---- ICS Data ----
 
 #[test]
@@ -791,6 +1725,95 @@ fn save_ics_data_upserts() {
     assert_eq!(data.as_deref(), Some("second"));
 }
 
+#[test]
+fn compressed_ics_data_round_trips() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    let feed = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nEND:VCALENDAR\r\n".repeat(50);
+    save_ics_data_with(&conn, id, &feed, true).unwrap();
+
+    let (kind, size): (String, i64) = conn
+        .query_row(
+            "SELECT typeof(ics_content), length(ics_content) FROM ics_data WHERE source_id = ?1",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!(kind, "blob");
+    assert!((size as usize) < feed.len());
+    assert_eq!(get_ics_data(&conn, id).unwrap().as_deref(), Some(&*feed));
+    assert_eq!(
+        get_ics_data_by_path(&conn, &valid_source().ics_path)
+            .unwrap()
+            .as_deref(),
+        Some(&*feed)
+    );
+    let served = get_feed_by_id(&conn, id, 0).unwrap().unwrap();
+    assert!(matches!(served.content, StoredIcs::Gzip(_)));
+    assert_eq!(served.content.into_text().unwrap(), feed);
+
+    // Compressed feeds are stored with CRLF line breaks.
+    save_ics_data_with(&conn, id, "BEGIN:VCALENDAR\nEND:VCALENDAR\n", true).unwrap();
+    assert_eq!(
+        get_ics_data(&conn, id).unwrap().as_deref(),
+        Some("BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n")
+    );
+    // Turning compression off writes plain text again.
+    save_ics_data(&conn, id, "plain").unwrap();
+    assert_eq!(get_ics_data(&conn, id).unwrap().as_deref(), Some("plain"));
+}
+
+#[test]
+fn legacy_and_compressed_ics_rows_are_read_side_by_side() {
+    let conn = setup();
+    let legacy = create_source(
+        &conn,
+        &CreateSource {
+            ics_path: "legacy.ics".into(),
+            public_ics: true,
+            ..valid_source()
+        },
+    )
+    .unwrap();
+    let packed = create_source(
+        &conn,
+        &CreateSource {
+            ics_path: "packed.ics".into(),
+            public_ics: true,
+            ..valid_source()
+        },
+    )
+    .unwrap();
+    save_ics_data(&conn, legacy, "LEGACY\r\n").unwrap();
+    save_ics_data_with(&conn, packed, "PACKED\r\n", true).unwrap();
+
+    assert_eq!(
+        get_ics_data(&conn, legacy).unwrap().as_deref(),
+        Some("LEGACY\r\n")
+    );
+    assert_eq!(
+        get_ics_data(&conn, packed).unwrap().as_deref(),
+        Some("PACKED\r\n")
+    );
+    assert_eq!(
+        get_ics_data_by_path(&conn, "legacy.ics")
+            .unwrap()
+            .as_deref(),
+        Some("LEGACY\r\n")
+    );
+    assert!(matches!(
+        get_feed_by_path(&conn, "legacy.ics", 0)
+            .unwrap()
+            .unwrap()
+            .content,
+        StoredIcs::Plain(_)
+    ));
+    assert_eq!(
+        list_public_ics(&conn).unwrap(),
+        ["LEGACY\r\n", "PACKED\r\n"]
+    );
+}
+
 #[test]
 fn get_ics_data_by_path_not_found() {
     let conn = setup();
@@ -842,3 +1865,480 @@ fn create_source_rejects_public_path_matching_existing_source_path() {
     s2.public_ics_path = Some("taken.ics".into());
     assert!(create_source(&conn, &s2).is_err());
 }
+
+// ---- Connection pool ----
+
+#[test]
+fn memory_pool_connections_share_database() {
+    let pool = open_memory_pool().unwrap();
+    let a = pool.get().unwrap();
+    let b = pool.get().unwrap();
+    let id = create_source(&a, &valid_source()).unwrap();
+    assert!(get_source(&b, id).unwrap().is_some());
+}
+
+#[test]
+fn memory_pools_are_isolated() {
+    let first = open_memory_pool().unwrap();
+    let second = open_memory_pool().unwrap();
+    create_source(&first.get().unwrap(), &valid_source()).unwrap();
+    assert!(list_sources(&second.get().unwrap()).unwrap().is_empty());
+}
+
+#[test]
+fn memory_pool_enforces_foreign_keys() {
+    let pool = open_memory_pool().unwrap();
+    let conn = pool.get().unwrap();
+    let result = conn.execute(
+        "INSERT INTO source_paths (source_id, path) VALUES (999, 'x.ics')",
+        [],
+    );
+    assert!(result.is_err());
+}
+
+// ---- Sync history ----
+
+#[test]
+fn sync_history_lists_newest_first() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    record_sync_history(&conn, id, "2026-01-01 10:00:00", "ok", 3, None).unwrap();
+    record_sync_history(&conn, id, "2026-01-02 10:00:00", "error", 0, Some("boom")).unwrap();
+
+    let entries = list_sync_history(&conn, id, None, 10, 0).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].status, "error");
+    assert_eq!(entries[0].error.as_deref(), Some("boom"));
+    assert_eq!(entries[1].events, 3);
+}
+
+#[test]
+fn sync_history_filters_by_since_and_paginates() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    for day in 1..=5 {
+        let started = format!("2026-01-0{} 10:00:00", day);
+        record_sync_history(&conn, id, &started, "ok", day, None).unwrap();
+    }
+
+    assert_eq!(count_sync_history(&conn, id, None).unwrap(), 5);
+    assert_eq!(
+        count_sync_history(&conn, id, Some("2026-01-03 00:00:00")).unwrap(),
+        3
+    );
+
+    let page = list_sync_history(&conn, id, None, 2, 2).unwrap();
+    assert_eq!(page.len(), 2);
+    assert_eq!(page[0].started_at, "2026-01-03 10:00:00");
+}
+
+#[test]
+fn sync_history_removed_with_source() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    record_sync_history(&conn, id, "2026-01-01 10:00:00", "ok", 1, None).unwrap();
+    delete_source(&conn, id).unwrap();
+    assert_eq!(count_sync_history(&conn, id, None).unwrap(), 0);
+}
+
+// ---- Duplicating destinations ----
+
+#[test]
+fn duplicate_destination_copies_into_independent_row() {
+    let conn = setup();
+    let id = create_destination(&conn, &valid_destination()).unwrap();
+    let dup = DuplicateDestination {
+        name: Some("Second".into()),
+    };
+    let copy_id = duplicate_destination(&conn, id, &dup).unwrap().unwrap();
+
+    let copy = get_destination(&conn, copy_id).unwrap().unwrap();
+    assert_eq!(copy.name, "Second");
+    assert_eq!(copy.password, "pass");
+
+    delete_destination(&conn, copy_id).unwrap();
+    assert!(get_destination(&conn, id).unwrap().is_some());
+}
+
+// ---- Per-calendar ICS ----
+
+#[test]
+fn calendar_ics_round_trips_and_replaces_stale_calendars() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    save_calendar_ics(
+        &conn,
+        id,
+        &[
+            (
+                "/cal/a/".into(),
+                "BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n".into(),
+            ),
+            ("/cal/b/".into(), "B".into()),
+        ],
+    )
+    .unwrap();
+    assert_eq!(
+        get_calendar_ics(&conn, id, "/cal/a/").unwrap().as_deref(),
+        Some("BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n")
+    );
+
+    save_calendar_ics(&conn, id, &[("/cal/a/".into(), "A2".into())]).unwrap();
+    assert_eq!(
+        get_calendar_ics(&conn, id, "/cal/a/").unwrap().as_deref(),
+        Some("A2")
+    );
+    assert!(get_calendar_ics(&conn, id, "/cal/b/").unwrap().is_none());
+}
+
+#[test]
+fn calendar_ics_removed_with_source() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    save_calendar_ics(&conn, id, &[("/cal/a/".into(), "A".into())]).unwrap();
+    delete_source(&conn, id).unwrap();
+    assert!(get_calendar_ics(&conn, id, "/cal/a/").unwrap().is_none());
+}
+
+#[test]
+fn calendar_sync_state_round_trips_with_token_and_objects() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    let state = CalendarSyncState {
+        href: "/cal/a/".into(),
+        sync_token: Some("tok-1".into()),
+        objects: vec![
+            ("/cal/a/2.ics".into(), "TWO".into()),
+            ("/cal/a/1.ics".into(), "ONE".into()),
+        ],
+    };
+    save_calendar_ics(&conn, id, &[("/cal/a/".into(), "TWO\r\nONE\r\n".into())]).unwrap();
+    save_calendar_sync_state(&conn, id, std::slice::from_ref(&state), DEFAULT_BATCH_SIZE).unwrap();
+    assert_eq!(
+        get_calendar_sync_state(&conn, id).unwrap()["/cal/a/"],
+        state
+    );
+
+    // Rewriting the calendars without sync state drops the token.
+    save_calendar_ics(&conn, id, &[("/cal/a/".into(), "X".into())]).unwrap();
+    assert_eq!(
+        get_calendar_sync_state(&conn, id).unwrap()["/cal/a/"].sync_token,
+        None
+    );
+
+    delete_source(&conn, id).unwrap();
+    let objects: i64 = conn
+        .query_row("SELECT count(*) FROM calendar_objects", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(objects, 0);
+}
+
+#[test]
+fn calendar_sync_state_commits_partial_final_batch() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    let state = CalendarSyncState {
+        href: "/cal/a/".into(),
+        sync_token: Some("tok-1".into()),
+        objects: (1..=5)
+            .map(|n| (format!("/cal/a/{n}.ics"), format!("EVENT {n}")))
+            .collect(),
+    };
+    save_calendar_ics(&conn, id, &[("/cal/a/".into(), "ALL".into())]).unwrap();
+    // Two full batches of two, then one object on its own.
+    save_calendar_sync_state(&conn, id, std::slice::from_ref(&state), 2).unwrap();
+
+    assert!(conn.is_autocommit());
+    assert_eq!(
+        get_calendar_sync_state(&conn, id).unwrap()["/cal/a/"],
+        state
+    );
+}
+
+#[test]
+fn insert_in_batches_keeps_committed_batches_on_failure() {
+    let conn = setup();
+    conn.execute_batch("CREATE TABLE numbers (n INTEGER NOT NULL)")
+        .unwrap();
+    let items = [1, 2, 3, 4, 5];
+    let result = insert_in_batches(&conn, &items, 2, |tx, &n| {
+        anyhow::ensure!(n != 4, "no fours");
+        tx.execute("INSERT INTO numbers (n) VALUES (?1)", [n])?;
+        Ok(())
+    });
+
+    assert!(result.is_err());
+    let stored: Vec<i64> = conn
+        .prepare("SELECT n FROM numbers ORDER BY n")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    // The batch holding 3 and 4 was rolled back.
+    assert_eq!(stored, vec![1, 2]);
+}
+
+#[test]
+fn with_transaction_rolls_back_on_error() {
+    let conn = setup();
+    let id = create_source(&conn, &valid_source()).unwrap();
+    let result: anyhow::Result<()> = with_transaction(&conn, |tx| {
+        save_ics_data(tx, id, "BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n")?;
+        anyhow::bail!("write failed")
+    });
+
+    assert!(result.is_err());
+    assert_eq!(get_ics_data(&conn, id).unwrap(), None);
+}
+
+// ---- Maintenance ----
+
+#[test]
+fn vacuum_reclaims_space_from_deleted_rows() {
+    let path = std::env::temp_dir().join(format!("caldav-vacuum-{}.db", std::process::id()));
+    let path_str = path.to_str().unwrap().to_string();
+    {
+        let pool = open_pool(&path_str).unwrap();
+        let conn = pool.get().unwrap();
+        let id = create_source(&conn, &valid_source()).unwrap();
+        save_ics_data(&conn, id, &"X".repeat(512 * 1024)).unwrap();
+        save_ics_data(&conn, id, "small").unwrap();
+
+        let stats = db_stats(&conn).unwrap();
+        assert!(stats.size.freelist_count > 0);
+        assert!(stats.size.file_size_bytes.is_some());
+        let ics_rows = stats.tables.iter().find(|t| t.table == "ics_data").unwrap();
+        assert_eq!(ics_rows.rows, 1);
+
+        let (before, after) = vacuum(&conn).unwrap();
+        assert!(after.page_count < before.page_count);
+        assert_eq!(after.freelist_count, 0);
+        assert!(after.file_size_bytes.unwrap() < before.file_size_bytes.unwrap());
+    }
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", path_str, suffix));
+    }
+}
+
+#[test]
+fn sqlite_settings_validate_and_apply_pragmas() {
+    assert!(SqliteSettings::new("wal2", "full", 0).is_err());
+    assert!(SqliteSettings::new("wal", "sometimes", 0).is_err());
+    let settings = SqliteSettings::new("delete", "normal", 250).unwrap();
+    assert_eq!(settings.journal_mode, "DELETE");
+
+    let path = std::env::temp_dir().join(format!("caldav-pragmas-{}.db", std::process::id()));
+    let path_str = path.to_str().unwrap().to_string();
+    {
+        let pool = open_pool_with(&path_str, &settings).unwrap();
+        let conn = pool.get().unwrap();
+        let mode: String = conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "delete");
+        let sync: i64 = conn
+            .query_row("PRAGMA synchronous", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(sync, 1);
+        let timeout: i64 = conn
+            .query_row("PRAGMA busy_timeout", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(timeout, 250);
+    }
+    let _ = std::fs::remove_file(&path_str);
+}
+
+/// Holds a write lock on `path` for `hold`, then tries a write on a second
+/// connection from a pool with `busy_timeout_ms`.
+fn write_while_locked(
+    path: &str,
+    busy_timeout_ms: u64,
+    hold: std::time::Duration,
+) -> Result<(), String> {
+    let settings = SqliteSettings::new("delete", "full", busy_timeout_ms).unwrap();
+    let pool = open_pool_with(path, &settings).unwrap();
+    let holder = pool.get().unwrap();
+    holder.execute_batch("BEGIN IMMEDIATE").unwrap();
+    create_source(&holder, &valid_source()).unwrap();
+    let release = std::thread::spawn(move || {
+        std::thread::sleep(hold);
+        holder.execute_batch("COMMIT").unwrap();
+    });
+    let writer = pool.get().unwrap();
+    let mut other = valid_source();
+    other.ics_path = "other.ics".into();
+    let result = create_source(&writer, &other)
+        .map(|_| ())
+        .map_err(|e| e.to_string());
+    release.join().unwrap();
+    result
+}
+
+#[test]
+fn busy_timeout_waits_for_concurrent_writer() {
+    let path = std::env::temp_dir().join(format!("caldav-busy-{}.db", std::process::id()));
+    let path_str = path.to_str().unwrap().to_string();
+    let hold = std::time::Duration::from_millis(300);
+
+    let started = std::time::Instant::now();
+    write_while_locked(&path_str, 5_000, hold).unwrap();
+    assert!(started.elapsed() >= hold);
+
+    let _ = std::fs::remove_file(&path_str);
+    let err = write_while_locked(&path_str, 0, hold).unwrap_err();
+    assert!(err.contains("locked"), "{}", err);
+    let _ = std::fs::remove_file(&path_str);
+}
+
+#[test]
+fn db_size_has_no_file_for_in_memory_database() {
+    let conn = setup();
+    assert!(db_size(&conn).unwrap().file_size_bytes.is_none());
+}
+
+// ---- Static sources ----
+
+fn static_source(ics_path: &str) -> CreateStaticSource {
+    CreateStaticSource {
+        name: "Holidays".into(),
+        ics_path: ics_path.into(),
+        public_ics: false,
+        public_ics_path: None,
+        ics: "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nEND:VCALENDAR\r\n".into(),
+    }
+}
+
+#[test]
+fn static_source_cannot_enable_auto_sync() {
+    let conn = setup();
+    let id = create_static_source(&conn, &static_source("holidays")).unwrap();
+    let upd = UpdateSource {
+        sync_interval_secs: Some(3600),
+        ..Default::default()
+    };
+    assert!(update_source(&conn, id, &upd).is_err());
+    let cron = UpdateSource {
+        cron_schedule: Some("0 * * * *".into()),
+        ..Default::default()
+    };
+    assert!(update_source(&conn, id, &cron).is_err());
+
+    let rename = UpdateSource {
+        name: Some("Public holidays".into()),
+        ..Default::default()
+    };
+    assert!(update_source(&conn, id, &rename).unwrap());
+}
+
+#[test]
+fn static_source_paths_must_be_unique() {
+    let conn = setup();
+    create_source(&conn, &valid_source()).unwrap();
+    assert!(create_static_source(&conn, &static_source("cal.ics")).is_err());
+}
+
+#[test]
+fn duplicate_static_source_copies_content() {
+    let conn = setup();
+    let id = create_static_source(&conn, &static_source("holidays")).unwrap();
+    let dup = DuplicateSource {
+        ics_path: "holidays-copy".into(),
+        name: None,
+    };
+    let copy_id = duplicate_source(&conn, id, &dup).unwrap().unwrap();
+    let copy = get_source(&conn, copy_id).unwrap().unwrap();
+    assert_eq!(copy.kind, SourceKind::Static);
+    assert_eq!(
+        get_ics_data(&conn, copy_id).unwrap(),
+        get_ics_data(&conn, id).unwrap()
+    );
+}
+
+// ---- Schema migrations ----
+
+/// A database from before intervals were stored in seconds.
+fn legacy_conn(with_secs_column: bool) -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    let secs = if with_secs_column {
+        "sync_interval_secs INTEGER NOT NULL DEFAULT 3600,"
+    } else {
+        ""
+    };
+    conn.execute_batch(&format!(
+        "CREATE TABLE sources (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            caldav_url TEXT NOT NULL,
+            username TEXT NOT NULL,
+            password TEXT NOT NULL,
+            ics_path TEXT NOT NULL UNIQUE,
+            sync_interval_minutes INTEGER NOT NULL DEFAULT 60,
+            {secs}
+            last_synced TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        INSERT INTO sources (name, caldav_url, username, password, ics_path, sync_interval_minutes)
+        VALUES ('Legacy', 'https://example.com', 'u', 'p', 'legacy', 5);"
+    ))
+    .unwrap();
+    conn
+}
+
+fn legacy_interval(conn: &Connection) -> i64 {
+    conn.query_row(
+        "SELECT sync_interval_secs FROM sources WHERE ics_path = 'legacy'",
+        [],
+        |row| row.get(0),
+    )
+    .unwrap()
+}
+
+#[test]
+fn init_db_converts_legacy_minutes_once() {
+    let conn = legacy_conn(false);
+    init_db(&conn).unwrap();
+    assert_eq!(legacy_interval(&conn), 300);
+
+    conn.execute(
+        "UPDATE sources SET sync_interval_secs = 900 WHERE ics_path = 'legacy'",
+        [],
+    )
+    .unwrap();
+    init_db(&conn).unwrap();
+    assert_eq!(legacy_interval(&conn), 900);
+
+    let recorded: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM schema_migrations WHERE name = 'sync_interval_secs'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(recorded, 1);
+}
+
+#[test]
+fn init_db_leaves_already_converted_intervals() {
+    let conn = legacy_conn(true);
+    conn.execute(
+        "UPDATE sources SET sync_interval_secs = 900 WHERE ics_path = 'legacy'",
+        [],
+    )
+    .unwrap();
+    init_db(&conn).unwrap();
+    assert_eq!(legacy_interval(&conn), 900);
+}
+
+#[test]
+fn init_db_records_migrations_on_fresh_db() {
+    let conn = setup();
+    let names: Vec<String> = conn
+        .prepare("SELECT name FROM schema_migrations")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(names, vec!["sync_interval_secs"]);
+}
@@ -1,13 +1,11 @@
-use std::sync::{Arc, Mutex};
-
 use axum::http::{Request, StatusCode, header};
 use axum::middleware;
 use base64::Engine;
 use caldav_ics_sync::api::AppState;
-use caldav_ics_sync::auto_sync;
+use caldav_ics_sync::config::LineEnding;
 use caldav_ics_sync::db::{self, CreateSource, CreateSourcePath};
 use caldav_ics_sync::server::auth::{AuthConfig, basic_auth_middleware};
-use caldav_ics_sync::server::build_router;
+use caldav_ics_sync::server::{build_public_router, build_router};
 use http_body_util::BodyExt;
 use tower::ServiceExt;
 
@@ -15,14 +13,7 @@ const VCALENDAR: &str = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nEND:VCALENDAR";
 const PROXY_URL: &str = "http://127.0.0.1:19999";
 
 fn test_state() -> AppState {
-    let conn = rusqlite::Connection::open_in_memory().unwrap();
-    conn.execute_batch("PRAGMA foreign_keys=ON;").unwrap();
-    db::init_db(&conn).unwrap();
-    AppState {
-        db: Arc::new(Mutex::new(conn)),
-        start_time: std::time::Instant::now(),
-        sync_tasks: auto_sync::new_registry(),
-    }
+    AppState::new(db::open_memory_pool().unwrap())
 }
 
 fn insert_source(
@@ -31,7 +22,7 @@ fn insert_source(
     public_ics: bool,
     public_ics_path: Option<&str>,
 ) -> i64 {
-    let db = state.db.lock().unwrap();
+    let db = state.db.get().unwrap();
     db::create_source(
         &db,
         &CreateSource {
@@ -43,18 +34,36 @@ fn insert_source(
             sync_interval_secs: 0,
             public_ics,
             public_ics_path: public_ics_path.map(str::to_owned),
+            allow_invalid_certs: false,
+            webhook_on_success_url: None,
+            webhook_on_failure_url: None,
+            default_timezone: None,
+            sort_events: false,
+            summary_template: None,
+            max_event_bytes: 0,
+            refresh_interval_secs: 0,
+            debug_http: false,
+            auto_discover: false,
+            propfind_events: false,
+            skip_on_empty: true,
+            enabled: true,
+            public_from: None,
+            public_until: None,
+            signed_public_urls: false,
+            cron_schedule: None,
+            tag_calendar_origin: false,
         },
     )
     .unwrap()
 }
 
 fn save_ics(state: &AppState, source_id: i64, content: &str) {
-    let db = state.db.lock().unwrap();
+    let db = state.db.get().unwrap();
     db::save_ics_data(&db, source_id, content).unwrap();
 }
 
 fn insert_source_path(state: &AppState, source_id: i64, path: &str, is_public: bool) -> i64 {
-    let db = state.db.lock().unwrap();
+    let db = state.db.get().unwrap();
     db::create_source_path(
         &db,
         source_id,
@@ -118,6 +127,76 @@ async fn ics_existing_path_returns_200() {
     assert!(body.contains("BEGIN:VCALENDAR"));
 }
 
+#[tokio::test]
+async fn ics_disabled_source_is_still_served() {
+    let state = test_state();
+    let id = insert_source(&state, "paused", false, None);
+    save_ics(&state, id, VCALENDAR);
+    {
+        let db = state.db.get().unwrap();
+        let upd = db::UpdateSource {
+            enabled: Some(false),
+            ..Default::default()
+        };
+        db::update_source(&db, id, &upd).unwrap();
+    }
+    let app = router_no_auth(state).await;
+
+    let resp = app
+        .oneshot(
+            Request::get("/ics/paused")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(body_string(resp).await.contains("BEGIN:VCALENDAR"));
+}
+
+#[tokio::test]
+async fn renamed_ics_path_serves_until_grace_period_ends() {
+    let state = test_state();
+    let id = insert_source(&state, "old-path", false, None);
+    save_ics(&state, id, VCALENDAR);
+    {
+        let db = state.db.get().unwrap();
+        let upd = db::UpdateSource {
+            ics_path: Some("new-path".into()),
+            ics_path_grace_secs: Some(3600),
+            ..Default::default()
+        };
+        db::update_source(&db, id, &upd).unwrap();
+    }
+    let status = |uri: &'static str| {
+        let state = state.clone();
+        async move {
+            router_no_auth(state)
+                .await
+                .oneshot(Request::get(uri).body(axum::body::Body::empty()).unwrap())
+                .await
+                .unwrap()
+                .status()
+        }
+    };
+
+    assert_eq!(status("/ics/new-path").await, StatusCode::OK);
+    assert_eq!(status("/ics/old-path").await, StatusCode::OK);
+
+    state
+        .db
+        .get()
+        .unwrap()
+        .execute(
+            "UPDATE source_paths SET expires_at = datetime('now', '-1 second')",
+            [],
+        )
+        .unwrap();
+    assert_eq!(status("/ics/old-path").await, StatusCode::NOT_FOUND);
+    assert_eq!(status("/ics/new-path").await, StatusCode::OK);
+}
+
 #[tokio::test]
 async fn ics_nonexistent_returns_404() {
     let state = test_state();
@@ -277,6 +356,21 @@ async fn auth_health_exempt_returns_200() {
     assert_eq!(resp.status(), StatusCode::OK);
 }
 
+#[tokio::test]
+async fn auth_health_probes_exempt() {
+    let state = test_state();
+    let app = router_with_auth(state).await;
+
+    for uri in ["/api/health/live", "/api/health/ready"] {
+        let resp = app
+            .clone()
+            .oneshot(Request::get(uri).body(axum::body::Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_ne!(resp.status(), StatusCode::UNAUTHORIZED, "{}", uri);
+    }
+}
+
 #[tokio::test]
 async fn auth_sources_without_credentials_returns_401() {
     let state = test_state();
@@ -410,3 +504,861 @@ async fn auth_public_standard_ics_no_custom_path_bypasses_auth() {
     let body = body_string(resp).await;
     assert!(body.contains("BEGIN:VCALENDAR"));
 }
+
+// ---------------------------------------------------------------------------
+// Method handling
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn ics_post_returns_405_with_allow_header() {
+    let state = test_state();
+    let id = insert_source(&state, "test-path", false, None);
+    save_ics(&state, id, VCALENDAR);
+    let app = router_no_auth(state).await;
+
+    let resp = app
+        .oneshot(
+            Request::post("/ics/test-path")
+                .body(axum::body::Body::from("BEGIN:VCALENDAR"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(resp.headers().get(header::ALLOW).unwrap(), "GET, HEAD");
+}
+
+#[tokio::test]
+async fn public_ics_delete_returns_405_with_allow_header() {
+    let state = test_state();
+    let id = insert_source(&state, "private", true, Some("pub-feed"));
+    save_ics(&state, id, VCALENDAR);
+    let app = router_no_auth(state).await;
+
+    let resp = app
+        .oneshot(
+            Request::delete("/ics/public/pub-feed")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(resp.headers().get(header::ALLOW).unwrap(), "GET, HEAD");
+}
+
+/// An upstream that answers every request with `200 ok` and counts the
+/// connections it accepts.
+async fn start_counting_upstream() -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let connections = std::sync::Arc::new(AtomicUsize::new(0));
+    let counter = connections.clone();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf).await;
+            let _ = stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok")
+                .await;
+        }
+    });
+    (url, connections)
+}
+
+#[tokio::test]
+async fn proxy_rejects_disallowed_methods_without_contacting_upstream() {
+    use std::sync::atomic::Ordering;
+
+    let (upstream, connections) = start_counting_upstream().await;
+    let mut state = test_state();
+    state.proxy_allowed_methods = Some(vec![axum::http::Method::GET, axum::http::Method::POST]);
+    let app = build_router(state, &upstream).await;
+
+    let resp = app
+        .clone()
+        .oneshot(
+            Request::delete("/settings")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(resp.headers().get(header::ALLOW).unwrap(), "GET, POST");
+    assert_eq!(connections.load(Ordering::SeqCst), 0);
+
+    let (status, body) = send(&app, get("/settings")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "ok");
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn ics_head_returns_200() {
+    let state = test_state();
+    let id = insert_source(&state, "test-path", false, None);
+    save_ics(&state, id, VCALENDAR);
+    let app = router_no_auth(state).await;
+
+    let resp = app
+        .oneshot(
+            Request::head("/ics/test-path")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("content-type").unwrap(), "text/calendar");
+}
+
+// ---------------------------------------------------------------------------
+// Public-only listener
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn public_router_serves_public_feed() {
+    let state = test_state();
+    let id = insert_source(&state, "private", true, Some("pub-feed"));
+    save_ics(&state, id, VCALENDAR);
+    let app = build_public_router(state);
+
+    let resp = app
+        .oneshot(
+            Request::get("/ics/public/pub-feed")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = body_string(resp).await;
+    assert!(body.contains("BEGIN:VCALENDAR"));
+}
+
+#[tokio::test]
+async fn public_router_does_not_expose_api_or_private_feeds() {
+    let state = test_state();
+    let id = insert_source(&state, "private", true, Some("pub-feed"));
+    save_ics(&state, id, VCALENDAR);
+    let app = build_public_router(state);
+
+    for uri in ["/api/sources", "/api/health", "/ics/private", "/"] {
+        let resp = app
+            .clone()
+            .oneshot(Request::get(uri).body(axum::body::Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{}", uri);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Static sources
+// ---------------------------------------------------------------------------
+
+const STATIC_ICS: &str = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nUID:one\r\nSUMMARY:First\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+
+async fn send(app: &axum::Router, req: Request<axum::body::Body>) -> (StatusCode, String) {
+    let resp = app.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    (status, body_string(resp).await)
+}
+
+#[tokio::test]
+async fn static_source_create_serve_and_replace() {
+    let app = router_no_auth(test_state()).await;
+
+    let (status, body) = send(
+        &app,
+        Request::post("/api/sources/static")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(axum::body::Body::from(
+                serde_json::json!({"name": "Holidays", "ics_path": "holidays", "ics": STATIC_ICS})
+                    .to_string(),
+            ))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", body);
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["source"]["kind"], "static");
+    assert_eq!(json["source"]["sync_interval_secs"], 0);
+    let id = json["source"]["id"].as_i64().unwrap();
+
+    let get = || {
+        Request::get("/ics/holidays")
+            .body(axum::body::Body::empty())
+            .unwrap()
+    };
+    let (status, body) = send(&app, get()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, STATIC_ICS);
+
+    let replacement = STATIC_ICS.replace("First", "Second");
+    let (status, _) = send(
+        &app,
+        Request::put(format!("/api/sources/{}/ics", id))
+            .header(header::CONTENT_TYPE, "text/calendar")
+            .body(axum::body::Body::from(replacement.clone()))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = send(&app, get()).await;
+    assert_eq!(body, replacement);
+
+    let (status, body) = send(
+        &app,
+        Request::post(format!("/api/sources/{}/sync", id))
+            .body(axum::body::Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("Static sources"));
+}
+
+#[tokio::test]
+async fn replace_ics_rejects_caldav_sources_and_invalid_content() {
+    let state = test_state();
+    let caldav_id = insert_source(&state, "caldav-feed", false, None);
+    let app = router_no_auth(state).await;
+
+    let put = |id: i64, body: &str| {
+        Request::put(format!("/api/sources/{}/ics", id))
+            .header(header::CONTENT_TYPE, "text/calendar")
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap()
+    };
+    let (status, _) = send(&app, put(caldav_id, STATIC_ICS)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send(&app, put(999, STATIC_ICS)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = send(
+        &app,
+        Request::post("/api/sources/static")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(axum::body::Body::from(
+                serde_json::json!({"name": "Bad", "ics_path": "bad", "ics": "not a calendar"})
+                    .to_string(),
+            ))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+/// Minimal CalDAV server with one calendar holding one event.
+async fn start_caldav_mock() -> std::net::SocketAddr {
+    let propfind = r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>/dav/cal/</d:href>
+    <d:propstat><d:prop><d:resourcetype><d:collection/><c:calendar/></d:resourcetype></d:prop></d:propstat>
+  </d:response>
+</d:multistatus>"#;
+    let report = r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>/dav/cal/one.ics</d:href>
+    <d:propstat><d:prop><c:calendar-data>BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:one
+DTSTART:20250601T100000Z
+END:VEVENT
+END:VCALENDAR</c:calendar-data></d:prop></d:propstat>
+  </d:response>
+</d:multistatus>"#;
+    let mock = axum::Router::new().fallback(axum::routing::any(
+        move |method: axum::http::Method| async move {
+            let body = if method.as_str() == "PROPFIND" {
+                propfind
+            } else {
+                report
+            };
+            (StatusCode::MULTI_STATUS, body)
+        },
+    ));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, mock).await.unwrap();
+    });
+    addr
+}
+
+#[tokio::test]
+async fn cleared_feed_returns_404_until_resynced() {
+    let addr = start_caldav_mock().await;
+    let state = test_state();
+    let id = insert_source(&state, "work", false, None);
+    {
+        let db = state.db.get().unwrap();
+        db.execute(
+            "UPDATE sources SET caldav_url = ?1 WHERE id = ?2",
+            rusqlite::params![format!("http://{}/dav/", addr), id],
+        )
+        .unwrap();
+    }
+    let app = router_no_auth(state).await;
+    let get = || {
+        Request::get("/ics/work")
+            .body(axum::body::Body::empty())
+            .unwrap()
+    };
+    let sync = || {
+        Request::post(format!("/api/sources/{}/sync", id))
+            .body(axum::body::Body::empty())
+            .unwrap()
+    };
+
+    let (status, body) = send(&app, sync()).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (status, body) = send(&app, get()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("UID:one"));
+
+    let (status, _) = send(
+        &app,
+        Request::delete(format!("/api/sources/{}/ics", id))
+            .body(axum::body::Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(&app, get()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = send(&app, sync()).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = send(&app, get()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("UID:one"));
+
+    let (status, _) = send(
+        &app,
+        Request::delete("/api/sources/999/ics")
+            .body(axum::body::Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+// ---------------------------------------------------------------------------
+// Date-range filtering
+// ---------------------------------------------------------------------------
+
+const RANGE_FEED: &str = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n\
+BEGIN:VTIMEZONE\r\nTZID:Europe/Berlin\r\nEND:VTIMEZONE\r\n\
+BEGIN:VEVENT\r\nUID:jan\r\nDTSTART:20250115T100000Z\r\nDTEND:20250115T110000Z\r\nEND:VEVENT\r\n\
+BEGIN:VEVENT\r\nUID:apr\r\nDTSTART;VALUE=DATE:20250410\r\nEND:VEVENT\r\n\
+BEGIN:VEVENT\r\nUID:weekly\r\nDTSTART:20241001T090000Z\r\nRRULE:FREQ=WEEKLY\r\nEND:VEVENT\r\n\
+END:VCALENDAR\r\n";
+
+async fn range_app() -> axum::Router {
+    let state = test_state();
+    let id = insert_source(&state, "ranged", true, None);
+    save_ics(&state, id, RANGE_FEED);
+    router_no_auth(state).await
+}
+
+fn get_feed(uri: &str) -> Request<axum::body::Body> {
+    Request::get(uri).body(axum::body::Body::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn ics_range_keeps_overlapping_events_and_timezones() {
+    let app = range_app().await;
+    let (status, body) = send(&app, get_feed("/ics/ranged?from=2025-01-01&to=2025-03-01")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("UID:jan"));
+    assert!(!body.contains("UID:apr"));
+    // Recurring series that started earlier may still occur in range.
+    assert!(body.contains("UID:weekly"));
+    assert!(body.contains("BEGIN:VTIMEZONE\r\nTZID:Europe/Berlin\r\nEND:VTIMEZONE\r\n"));
+    assert!(body.starts_with("BEGIN:VCALENDAR\r\n"));
+    assert!(body.ends_with("END:VCALENDAR\r\n"));
+
+    // Either bound may be omitted.
+    let (_, later) = send(&app, get_feed("/ics/ranged?from=2025-04-01")).await;
+    assert!(!later.contains("UID:jan"));
+    assert!(later.contains("UID:apr"));
+}
+
+#[tokio::test]
+async fn ics_range_outside_all_events_returns_empty_calendar() {
+    let app = range_app().await;
+    let (status, body) = send(&app, get_feed("/ics/ranged?from=2023-01-01&to=2023-02-01")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!body.contains("BEGIN:VEVENT"));
+    assert!(body.contains("BEGIN:VTIMEZONE"));
+    assert!(body.contains("END:VCALENDAR"));
+}
+
+#[tokio::test]
+async fn ics_without_range_serves_full_feed() {
+    let app = range_app().await;
+    let (status, body) = send(&app, get_feed("/ics/ranged")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, RANGE_FEED);
+
+    let (status, _) = send(&app, get_feed("/ics/ranged?from=yesterday")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+// ---------------------------------------------------------------------------
+// Merged public feed
+// ---------------------------------------------------------------------------
+
+fn feed_with_event(uid: &str) -> String {
+    format!(
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n\
+         BEGIN:VTIMEZONE\r\nTZID:Europe/Berlin\r\nEND:VTIMEZONE\r\n\
+         BEGIN:VEVENT\r\nUID:{uid}\r\nDTSTART;TZID=Europe/Berlin:20260310T090000\r\nEND:VEVENT\r\n\
+         END:VCALENDAR\r\n"
+    )
+}
+
+fn state_with_public_and_private_sources() -> AppState {
+    let state = test_state();
+    for (path, public, uid) in [
+        ("one", true, "public-one"),
+        ("two", true, "public-two"),
+        ("three", false, "private"),
+    ] {
+        let id = insert_source(&state, path, public, None);
+        save_ics(&state, id, &feed_with_event(uid));
+    }
+    state
+}
+
+#[tokio::test]
+async fn public_all_feed_merges_only_public_sources() {
+    let mut state = state_with_public_and_private_sources();
+    state.public_all_feed = true;
+    let app = router_with_auth(state).await;
+
+    let resp = app
+        .oneshot(
+            Request::get("/ics/public/all")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers()[header::CACHE_CONTROL], "public, max-age=300");
+    let body = body_string(resp).await;
+    assert!(body.starts_with("BEGIN:VCALENDAR\r\n"));
+    assert!(body.ends_with("END:VCALENDAR\r\n"));
+    assert_eq!(body.matches("BEGIN:VCALENDAR").count(), 1);
+    assert_eq!(body.matches("TZID:Europe/Berlin\r\n").count(), 1);
+    assert!(body.contains("UID:public-one"));
+    assert!(body.contains("UID:public-two"));
+    assert!(!body.contains("UID:private"));
+}
+
+#[tokio::test]
+async fn public_all_feed_is_off_by_default() {
+    let state = state_with_public_and_private_sources();
+    let app = router_no_auth(state).await;
+
+    let resp = app
+        .oneshot(
+            Request::get("/ics/public/all")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+/// A source on a one-minute interval whose last sync was an hour ago.
+fn state_with_stale_source() -> AppState {
+    let state = test_state();
+    let id = insert_source(&state, "stale.ics", true, Some("stale-public.ics"));
+    save_ics(&state, id, VCALENDAR);
+    let db = state.db.get().unwrap();
+    db.execute(
+        "UPDATE sources SET sync_interval_secs = 60, last_synced = datetime('now', '-1 hour') WHERE id = ?1",
+        [id],
+    )
+    .unwrap();
+    state
+}
+
+#[tokio::test]
+async fn stale_feed_is_served_with_header() {
+    let app = router_no_auth(state_with_stale_source()).await;
+
+    for uri in ["/ics/stale.ics", "/ics/public/stale-public.ics"] {
+        let resp = app
+            .clone()
+            .oneshot(Request::get(uri).body(axum::body::Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK, "{}", uri);
+        assert_eq!(resp.headers()["x-stale"], "true", "{}", uri);
+        assert_eq!(body_string(resp).await, VCALENDAR);
+    }
+}
+
+#[tokio::test]
+async fn fresh_feed_has_no_stale_header() {
+    let state = test_state();
+    let id = insert_source(&state, "fresh.ics", false, None);
+    save_ics(&state, id, VCALENDAR);
+    state
+        .db
+        .get()
+        .unwrap()
+        .execute(
+            "UPDATE sources SET sync_interval_secs = 60, last_synced = datetime('now') WHERE id = ?1",
+            [id],
+        )
+        .unwrap();
+    let app = router_no_auth(state).await;
+
+    let resp = app
+        .oneshot(
+            Request::get("/ics/fresh.ics")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp.headers().get("x-stale").is_none());
+}
+
+#[tokio::test]
+async fn stale_feed_can_answer_unavailable() {
+    let mut state = state_with_stale_source();
+    state.stale_feed_unavailable = true;
+    let app = router_no_auth(state).await;
+
+    let resp = app
+        .oneshot(
+            Request::get("/ics/stale.ics")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+// ---------------------------------------------------------------------------
+// Line endings
+// ---------------------------------------------------------------------------
+
+async fn served_with_line_ending(line_ending: LineEnding) -> String {
+    let mut state = test_state();
+    state.ics_line_ending = line_ending;
+    let id = insert_source(&state, "endings.ics", false, None);
+    // Stored content with a stray bare LF, as a verbatim subscription might have.
+    save_ics(
+        &state,
+        id,
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\nUID:1\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+    );
+    let app = router_no_auth(state).await;
+
+    let resp = app
+        .oneshot(
+            Request::get("/ics/endings.ics")
+                .body(axum::body::Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    body_string(resp).await
+}
+
+#[tokio::test]
+async fn lf_line_ending_strips_carriage_returns() {
+    let body = served_with_line_ending(LineEnding::Lf).await;
+
+    assert!(!body.contains('\r'));
+    assert_eq!(
+        body,
+        "BEGIN:VCALENDAR\nVERSION:2.0\nBEGIN:VEVENT\nUID:1\nEND:VEVENT\nEND:VCALENDAR\n"
+    );
+}
+
+#[tokio::test]
+async fn crlf_line_ending_pairs_every_line_feed() {
+    let body = served_with_line_ending(LineEnding::Crlf).await;
+
+    assert_eq!(body.matches('\n').count(), 6);
+    assert_eq!(body.matches("\r\n").count(), 6);
+    assert_eq!(body.matches('\r').count(), 6);
+}
+
+// ---------------------------------------------------------------------------
+// Compressed storage
+// ---------------------------------------------------------------------------
+
+const EVENT_FEED: &str = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nUID:1\r\nDTSTART:20260105T100000Z\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+
+async fn compressed_feed_app(line_ending: LineEnding) -> axum::Router {
+    let mut state = test_state();
+    state.ics_line_ending = line_ending;
+    let id = insert_source(&state, "packed.ics", false, None);
+    db::save_ics_data_with(&state.db.get().unwrap(), id, EVENT_FEED, true).unwrap();
+    router_no_auth(state).await
+}
+
+fn get_gzip(uri: &str) -> Request<axum::body::Body> {
+    Request::get(uri)
+        .header(header::ACCEPT_ENCODING, "br, gzip;q=0.8")
+        .body(axum::body::Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn compressed_feed_is_sent_as_stored_to_gzip_clients() {
+    use std::io::Read;
+
+    let app = compressed_feed_app(LineEnding::Crlf).await;
+    let resp = app
+        .clone()
+        .oneshot(get_gzip("/ics/packed.ics"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get(header::CONTENT_ENCODING).unwrap(),
+        "gzip"
+    );
+    assert_eq!(resp.headers().get(header::VARY).unwrap(), "accept-encoding");
+    let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+    let mut body = String::new();
+    flate2::read::GzDecoder::new(&bytes[..])
+        .read_to_string(&mut body)
+        .unwrap();
+    assert_eq!(body, EVENT_FEED);
+
+    // Clients without gzip get the decompressed text.
+    let resp = app.oneshot(get("/ics/packed.ics")).await.unwrap();
+    assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+    assert_eq!(body_string(resp).await, EVENT_FEED);
+}
+
+#[tokio::test]
+async fn compressed_feed_is_decompressed_when_it_must_be_rewritten() {
+    let app = compressed_feed_app(LineEnding::Lf).await;
+    let resp = app.oneshot(get_gzip("/ics/packed.ics")).await.unwrap();
+    assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+    assert_eq!(body_string(resp).await, EVENT_FEED.replace("\r\n", "\n"));
+
+    let app = compressed_feed_app(LineEnding::Crlf).await;
+    let resp = app
+        .oneshot(get_gzip("/ics/packed.ics?from=2026-02-01"))
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp.headers().get(header::CONTENT_ENCODING).is_none());
+    assert!(!body_string(resp).await.contains("BEGIN:VEVENT"));
+}
+
+// ---------------------------------------------------------------------------
+// Signed public links
+// ---------------------------------------------------------------------------
+
+const URL_SECRET: &str = "link-secret";
+
+/// A source published at `/ics/public/shared.ics` behind signed links.
+async fn signed_source_app() -> (axum::Router, i64) {
+    let mut state = test_state();
+    state.public_url_secret = Some(URL_SECRET.into());
+    let id = insert_source(&state, "signed", true, Some("shared.ics"));
+    {
+        let db = state.db.get().unwrap();
+        let upd = db::UpdateSource {
+            signed_public_urls: Some(true),
+            ..Default::default()
+        };
+        db::update_source(&db, id, &upd).unwrap();
+    }
+    save_ics(&state, id, VCALENDAR);
+    (router_no_auth(state).await, id)
+}
+
+async fn mint_url(app: &axum::Router, id: i64, body: serde_json::Value) -> String {
+    let (status, body) = send(
+        app,
+        Request::post(format!("/api/sources/{}/sign-url", id))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    json["url"].as_str().unwrap().to_string()
+}
+
+fn get(uri: &str) -> Request<axum::body::Body> {
+    Request::get(uri).body(axum::body::Body::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn signed_link_serves_feed() {
+    let (app, id) = signed_source_app().await;
+    let url = mint_url(&app, id, serde_json::json!({"expires_in_secs": 3600})).await;
+    assert!(url.starts_with("/ics/public/shared.ics?sig="));
+
+    let (status, body) = send(&app, get(&url)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, VCALENDAR);
+
+    let forever = mint_url(&app, id, serde_json::json!({})).await;
+    assert!(!forever.contains("exp="));
+    assert_eq!(send(&app, get(&forever)).await.0, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn unsigned_or_tampered_links_are_not_found() {
+    let (app, id) = signed_source_app().await;
+    let url = mint_url(&app, id, serde_json::json!({"expires_in_secs": 3600})).await;
+
+    let (status, _) = send(&app, get("/ics/public/shared.ics")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // A different signature, and the real one with a pushed-back expiry.
+    let (sig, exp) = url
+        .split_once("sig=")
+        .unwrap()
+        .1
+        .split_once("&exp=")
+        .unwrap();
+    let exp: i64 = exp.parse().unwrap();
+    let bad_sig = format!(
+        "/ics/public/shared.ics?sig={}&exp={}",
+        sig.replace(|c: char| c != 'A', "A"),
+        exp
+    );
+    let later = format!("/ics/public/shared.ics?sig={}&exp={}", sig, exp + 3600);
+    for uri in [bad_sig, later] {
+        assert_eq!(
+            send(&app, get(&uri)).await.0,
+            StatusCode::NOT_FOUND,
+            "{uri}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn expired_signed_link_is_not_found() {
+    let (app, _) = signed_source_app().await;
+    let exp = chrono::Utc::now().timestamp() - 60;
+    let sig = caldav_ics_sync::signed_url::sign(URL_SECRET, "shared.ics", Some(exp));
+
+    let (status, _) = send(
+        &app,
+        get(&format!("/ics/public/shared.ics?sig={}&exp={}", sig, exp)),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn sign_url_requires_secret() {
+    let state = test_state();
+    let id = insert_source(&state, "signed", true, Some("shared.ics"));
+    let app = router_no_auth(state).await;
+
+    let (status, _) = send(
+        &app,
+        Request::post(format!("/api/sources/{}/sign-url", id))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(axum::body::Body::from("{}"))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+}
+
+// ---------------------------------------------------------------------------
+// Serving by source ID
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn feed_by_id_survives_ics_path_rename() {
+    let state = test_state();
+    let id = insert_source(&state, "before", false, None);
+    save_ics(&state, id, VCALENDAR);
+    {
+        let db = state.db.get().unwrap();
+        let upd = db::UpdateSource {
+            ics_path: Some("after".into()),
+            ..Default::default()
+        };
+        db::update_source(&db, id, &upd).unwrap();
+    }
+    let app = router_no_auth(state).await;
+
+    let (status, body) = send(&app, get(&format!("/ics/by-id/{}", id))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, VCALENDAR);
+    let (status, _) = send(&app, get("/ics/by-id/999")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    // Private sources have no public ID URL.
+    let (status, _) = send(&app, get(&format!("/ics/public/by-id/{}", id))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn feed_by_id_requires_auth_even_for_public_sources() {
+    let state = test_state();
+    let id = insert_source(&state, "open", true, None);
+    save_ics(&state, id, VCALENDAR);
+    let app = router_with_auth(state).await;
+
+    let (status, _) = send(&app, get(&format!("/ics/by-id/{}", id))).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send(
+        &app,
+        Request::get(format!("/ics/by-id/{}", id))
+            .header(header::AUTHORIZATION, basic_auth_header("test", "test"))
+            .body(axum::body::Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, body) = send(&app, get(&format!("/ics/public/by-id/{}", id))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, VCALENDAR);
+}
+
+#[tokio::test]
+async fn public_feed_by_id_honours_signed_links() {
+    let (app, id) = signed_source_app().await;
+    let path = format!("/ics/public/by-id/{}", id);
+
+    let (status, _) = send(&app, get(&path)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let sig = caldav_ics_sync::signed_url::sign(URL_SECRET, &format!("by-id/{}", id), None);
+    let (status, body) = send(&app, get(&format!("{}?sig={}", path, sig))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, VCALENDAR);
+}
@@ -1,16 +1,33 @@
+use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
 
 use axum::{
     Router,
     body::Body,
-    extract::Request,
+    extract::{ConnectInfo, Request},
     http::StatusCode,
     response::{IntoResponse, Response},
     routing::any,
 };
-use caldav_ics_sync::api::reverse_sync::run_reverse_sync;
-use caldav_ics_sync::api::sync::{fetch_calendars, fetch_events, run_sync, toggle_slash};
-use reqwest::{Client, header};
+use caldav_ics_sync::api::AppState;
+use caldav_ics_sync::api::reverse_sync::{
+    QuotaExceeded, ReverseSyncOptions, SuspiciousShrink, run_reverse_sync, split_ics_urls,
+};
+use caldav_ics_sync::api::sync::{
+    CaldavClient, SyncOptions, build_caldav_client, discover_calendar_list, explain_tls_error,
+    fetch_calendar_list, fetch_calendars, fetch_events, ics_duration, run_source_sync, run_sync,
+    tag_origin, toggle_slash,
+};
+use caldav_ics_sync::auto_sync::{self, ActiveSyncs, HostLocks};
+use caldav_ics_sync::db;
+use caldav_ics_sync::db::MatchBy;
+use caldav_ics_sync::event_trim::{self, TRUNCATION_NOTE};
+use caldav_ics_sync::host_allowlist::HostAllowlist;
+use caldav_ics_sync::http::{Http2Mode, HttpClients, HttpPoolConfig, PrivateAddressGuard};
+use caldav_ics_sync::summary_template;
+use caldav_ics_sync::webhooks;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::header;
 use tokio::net::TcpListener;
 
 // ---------------------------------------------------------------------------
@@ -122,15 +139,8 @@ async fn start_mock_server(state: std::sync::Arc<MockState>) -> SocketAddr {
     addr
 }
 
-fn build_client(username: &str, password: &str) -> Client {
-    let auth = format!("{}:{}", username, password);
-    let encoded = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &auth);
-    let mut headers = header::HeaderMap::new();
-    headers.insert(
-        header::AUTHORIZATION,
-        header::HeaderValue::from_str(&format!("Basic {}", encoded)).unwrap(),
-    );
-    Client::builder().default_headers(headers).build().unwrap()
+fn build_client(username: &str, password: &str) -> CaldavClient {
+    build_caldav_client(username, password, false).unwrap()
 }
 
 // ---------------------------------------------------------------------------
@@ -232,6 +242,527 @@ async fn fetch_calendars_returns_empty_when_no_calendars() {
     assert!(cals.is_empty());
 }
 
+#[tokio::test]
+async fn fetch_calendar_list_includes_display_names() {
+    let state = std::sync::Arc::new(MockState {
+        propfind_body: mock_propfind_response(&["/dav/calendars/personal/"]),
+        report_body: String::new(),
+        put_status: StatusCode::CREATED,
+    });
+    let addr = start_mock_server(state).await;
+    let client = build_client("user", "pass");
+
+    let cals = fetch_calendar_list(&client, &format!("http://{}/dav/", addr))
+        .await
+        .unwrap();
+
+    assert_eq!(cals.len(), 1);
+    assert_eq!(cals[0].href, "/dav/calendars/personal/");
+    assert_eq!(cals[0].display_name.as_deref(), Some("cal"));
+}
+
+// ---------------------------------------------------------------------------
+// Well-known discovery tests
+// ---------------------------------------------------------------------------
+
+fn mock_href_property(href: &str, ns: &str, prop: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>{href}</d:href>
+    <d:propstat>
+      <d:prop><{ns}:{prop}><d:href>{href}</d:href></{ns}:{prop}></d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+</d:multistatus>"#,
+    )
+}
+
+/// Server whose root lists nothing; calendars are only reachable via
+/// `/.well-known/caldav` -> principal -> calendar home. Records each request.
+async fn start_discovery_server(log: std::sync::Arc<std::sync::Mutex<Vec<String>>>) -> SocketAddr {
+    let app = Router::new().fallback(any(move |req: Request<Body>| {
+        let log = log.clone();
+        async move {
+            let (method, path) = (req.method().to_string(), req.uri().path().to_string());
+            log.lock().unwrap().push(format!("{} {}", method, path));
+            match (method.as_str(), path.as_str()) {
+                (_, "/.well-known/caldav") => {
+                    (StatusCode::SEE_OTHER, [(header::LOCATION, "/dav/")], "").into_response()
+                }
+                ("PROPFIND", "/dav/") => (
+                    StatusCode::MULTI_STATUS,
+                    mock_href_property("/dav/principals/alice/", "d", "current-user-principal"),
+                )
+                    .into_response(),
+                ("PROPFIND", "/dav/principals/alice/") => (
+                    StatusCode::MULTI_STATUS,
+                    mock_href_property("/dav/calendars/alice/", "c", "calendar-home-set"),
+                )
+                    .into_response(),
+                ("PROPFIND", "/dav/calendars/alice/") => (
+                    StatusCode::MULTI_STATUS,
+                    mock_propfind_response(&["/dav/calendars/alice/home/"]),
+                )
+                    .into_response(),
+                ("REPORT", "/dav/calendars/alice/home/") => (
+                    StatusCode::MULTI_STATUS,
+                    mock_report_response(&[(
+                        "found",
+                        "Discovered",
+                        "20250101T100000Z",
+                        "20250101T110000Z",
+                    )]),
+                )
+                    .into_response(),
+                ("PROPFIND", "/") => {
+                    (StatusCode::MULTI_STATUS, mock_propfind_response(&[])).into_response()
+                }
+                _ => (StatusCode::NOT_FOUND, "").into_response(),
+            }
+        }
+    }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    addr
+}
+
+#[tokio::test]
+async fn discover_calendar_list_walks_well_known_chain() {
+    let log = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let addr = start_discovery_server(log.clone()).await;
+    let client = build_client("user", "pass");
+
+    let cals = discover_calendar_list(&client, &format!("http://{}/", addr))
+        .await
+        .unwrap();
+
+    assert_eq!(cals.len(), 1);
+    assert_eq!(cals[0].href, "/dav/calendars/alice/home/");
+    let log = log.lock().unwrap().clone();
+    assert_eq!(
+        log,
+        [
+            "PROPFIND /",
+            "PROPFIND /",
+            "PROPFIND /.well-known/caldav",
+            "PROPFIND /dav/",
+            "PROPFIND /dav/principals/alice/",
+            "PROPFIND /dav/calendars/alice/",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn run_sync_discovers_calendars_only_when_enabled() {
+    let log = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let addr = start_discovery_server(log).await;
+    let url = format!("http://{}/", addr);
+
+    let plain = run_sync(&url, "user", "pass", &SyncOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(plain.calendars, 0);
+
+    let opts = SyncOptions {
+        auto_discover: true,
+        ..Default::default()
+    };
+    let discovered = run_sync(&url, "user", "pass", &opts).await.unwrap();
+    assert_eq!(discovered.calendars, 1);
+    assert_eq!(discovered.events, 1);
+    assert!(discovered.ics.contains("SUMMARY:Discovered"));
+}
+
+#[tokio::test]
+async fn discover_calendar_list_errors_without_principal() {
+    let state = std::sync::Arc::new(MockState {
+        propfind_body: mock_propfind_response(&[]),
+        report_body: String::new(),
+        put_status: StatusCode::CREATED,
+    });
+    let addr = start_mock_server(state).await;
+    let client = build_client("user", "pass");
+
+    let err = discover_calendar_list(&client, &format!("http://{}/dav/", addr))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("current-user-principal"));
+}
+
+// ---------------------------------------------------------------------------
+// CalDAV redirect tests
+// ---------------------------------------------------------------------------
+
+/// CalDAV server that moved from `/dav/` to `/moved/dav/`: the old URL
+/// answers every request with a 301. Records each request.
+async fn start_moved_server(log: std::sync::Arc<std::sync::Mutex<Vec<String>>>) -> SocketAddr {
+    let app = Router::new().fallback(any(move |req: Request<Body>| {
+        let log = log.clone();
+        async move {
+            let (method, path) = (req.method().to_string(), req.uri().path().to_string());
+            let authorized = req.headers().contains_key(header::AUTHORIZATION);
+            log.lock().unwrap().push(format!("{} {}", method, path));
+            if !authorized {
+                return (StatusCode::UNAUTHORIZED, "").into_response();
+            }
+            match (method.as_str(), path.as_str()) {
+                (_, p) if p.starts_with("/dav/") => (
+                    StatusCode::MOVED_PERMANENTLY,
+                    [(header::LOCATION, format!("/moved{}", p))],
+                    "",
+                )
+                    .into_response(),
+                ("PROPFIND", "/moved/dav/") => (
+                    StatusCode::MULTI_STATUS,
+                    mock_propfind_response(&["/moved/dav/work/"]),
+                )
+                    .into_response(),
+                ("REPORT", "/moved/dav/work/") => (
+                    StatusCode::MULTI_STATUS,
+                    mock_report_response(&[(
+                        "moved-1",
+                        "Moved",
+                        "20250101T100000Z",
+                        "20250101T110000Z",
+                    )]),
+                )
+                    .into_response(),
+                _ => (StatusCode::NOT_FOUND, "").into_response(),
+            }
+        }
+    }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    addr
+}
+
+#[tokio::test]
+async fn run_sync_follows_redirected_propfind_with_the_same_method() {
+    let log = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let addr = start_moved_server(log.clone()).await;
+
+    let output = run_sync(
+        &format!("http://{}/dav/", addr),
+        "user",
+        "pass",
+        &SyncOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(output.events, 1);
+    assert!(output.ics.contains("SUMMARY:Moved"));
+    assert_eq!(
+        output.resolved_url,
+        Some(format!("http://{}/moved/dav/", addr))
+    );
+    // Calendar REPORTs go straight to the new location.
+    let log = log.lock().unwrap().clone();
+    assert_eq!(log[..2], ["PROPFIND /dav/", "PROPFIND /moved/dav/"]);
+    assert!(log[2..].iter().all(|l| l == "REPORT /moved/dav/work/"));
+}
+
+#[tokio::test]
+async fn source_sync_reuses_the_resolved_caldav_url() {
+    let log = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let addr = start_moved_server(log.clone()).await;
+    let conn = db::open_memory_pool().unwrap().get().unwrap();
+    let source: db::CreateSource = serde_json::from_value(serde_json::json!({
+        "name": "Moved",
+        "caldav_url": format!("http://{}/dav/", addr),
+        "username": "user",
+        "password": "pass",
+        "ics_path": "moved.ics",
+        "sync_interval_secs": 0
+    }))
+    .unwrap();
+    let id = db::create_source(&conn, &source).unwrap();
+
+    let source = db::get_source(&conn, id).unwrap().unwrap();
+    let output = run_source_sync(&source, &SyncOptions::from(&source))
+        .await
+        .unwrap();
+    db::update_resolved_caldav_url(&conn, id, output.resolved_url.as_deref().unwrap()).unwrap();
+    let source = db::get_source(&conn, id).unwrap().unwrap();
+    assert_eq!(
+        source.resolved_caldav_url,
+        Some(format!("http://{}/moved/dav/", addr))
+    );
+
+    log.lock().unwrap().clear();
+    let output = run_source_sync(&source, &SyncOptions::from(&source))
+        .await
+        .unwrap();
+    assert_eq!(output.events, 1);
+    let log = log.lock().unwrap().clone();
+    assert_eq!(log[0], "PROPFIND /moved/dav/");
+    assert!(!log.iter().any(|l| l.contains(" /dav/")), "{:?}", log);
+}
+
+/// CalDAV server on another host that answers without checking credentials
+/// and records whether each request carried any.
+async fn start_open_dav_server(log: std::sync::Arc<std::sync::Mutex<Vec<String>>>) -> SocketAddr {
+    let app = Router::new().fallback(any(move |req: Request<Body>| {
+        let log = log.clone();
+        async move {
+            let (method, path) = (req.method().to_string(), req.uri().path().to_string());
+            let authorized = req.headers().contains_key(header::AUTHORIZATION);
+            log.lock()
+                .unwrap()
+                .push(format!("{} {} auth={}", method, path, authorized));
+            match (method.as_str(), path.as_str()) {
+                ("PROPFIND", "/dav/") => (
+                    StatusCode::MULTI_STATUS,
+                    mock_propfind_response(&["/dav/work/"]),
+                )
+                    .into_response(),
+                ("REPORT", "/dav/work/") => (
+                    StatusCode::MULTI_STATUS,
+                    mock_report_response(&[(
+                        "foreign-1",
+                        "Foreign",
+                        "20250101T100000Z",
+                        "20250101T110000Z",
+                    )]),
+                )
+                    .into_response(),
+                _ => (StatusCode::NOT_FOUND, "").into_response(),
+            }
+        }
+    }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    addr
+}
+
+/// Answers every request with a 301 to `target`.
+async fn start_permanent_redirect_server(target: String) -> SocketAddr {
+    let app = Router::new().fallback(any(move || {
+        let target = target.clone();
+        async move { (StatusCode::MOVED_PERMANENTLY, [(header::LOCATION, target)]).into_response() }
+    }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    addr
+}
+
+#[tokio::test]
+async fn run_sync_never_sends_credentials_to_a_cross_host_redirect() {
+    let log = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let foreign = start_open_dav_server(log.clone()).await;
+    let addr =
+        start_permanent_redirect_server(format!("http://localhost:{}/dav/", foreign.port())).await;
+
+    let output = run_sync(
+        &format!("http://{}/dav/", addr),
+        "user",
+        "pass",
+        &SyncOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(output.events, 1);
+    // Another host is never remembered as the source's CalDAV URL.
+    assert_eq!(output.resolved_url, None);
+    let log = log.lock().unwrap().clone();
+    assert_eq!(log[0], "PROPFIND /dav/ auth=false");
+    assert!(log.iter().all(|l| l.ends_with("auth=false")), "{:?}", log);
+}
+
+#[tokio::test]
+async fn run_sync_refuses_redirects_outside_the_allowlist() {
+    let log = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let foreign = start_open_dav_server(log.clone()).await;
+    let addr =
+        start_permanent_redirect_server(format!("http://localhost:{}/dav/", foreign.port())).await;
+    let allowlist = HostAllowlist::parse(Some("127.0.0.1")).unwrap();
+    let http = HttpClients::default().with_host_allowlist(allowlist);
+
+    let err = run_sync(
+        &format!("http://{}/dav/", addr),
+        "user",
+        "pass",
+        &SyncOptions::default().with_http(&http),
+    )
+    .await
+    .unwrap_err();
+
+    assert!(
+        err.to_string().contains("CalDAV redirect host 'localhost'"),
+        "got: {err}"
+    );
+    assert!(log.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn reverse_sync_refuses_feed_redirects_outside_the_allowlist() {
+    let events = [("uid-out", "Out", "20270601T080000Z", "20270601T090000Z")];
+    let (ics_addr, caldav_addr) = start_reverse_sync_mocks(&events, StatusCode::CREATED).await;
+    let redirect_addr =
+        start_redirect_server(format!("http://localhost:{}/feed.ics", ics_addr.port())).await;
+    let allowlist = HostAllowlist::parse(Some("127.0.0.1")).unwrap();
+
+    let err = run_reverse_sync(
+        &format!("http://{}/start", redirect_addr),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
+        "user",
+        "pass",
+        false,
+        false,
+        &ReverseSyncOptions::default()
+            .with_http(&HttpClients::default().with_host_allowlist(allowlist)),
+    )
+    .await
+    .unwrap_err();
+
+    assert!(
+        err.to_string()
+            .contains("ICS feed redirect host 'localhost'"),
+        "got: {err}"
+    );
+}
+
+#[tokio::test]
+async fn source_sync_ignores_a_stored_cross_host_resolved_url() {
+    let log = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let foreign = start_open_dav_server(log.clone()).await;
+    let moved_log = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let addr = start_moved_server(moved_log.clone()).await;
+    let conn = db::open_memory_pool().unwrap().get().unwrap();
+    let source: db::CreateSource = serde_json::from_value(serde_json::json!({
+        "name": "Moved",
+        "caldav_url": format!("http://{}/dav/", addr),
+        "username": "user",
+        "password": "pass",
+        "ics_path": "moved.ics",
+        "sync_interval_secs": 0
+    }))
+    .unwrap();
+    let id = db::create_source(&conn, &source).unwrap();
+    // Saved by an earlier version that recorded any redirect target.
+    db::update_resolved_caldav_url(
+        &conn,
+        id,
+        &format!("http://localhost:{}/dav/", foreign.port()),
+    )
+    .unwrap();
+
+    let source = db::get_source(&conn, id).unwrap().unwrap();
+    let output = run_source_sync(&source, &SyncOptions::from(&source))
+        .await
+        .unwrap();
+
+    assert!(output.ics.contains("SUMMARY:Moved"));
+    assert!(log.lock().unwrap().is_empty());
+}
+
+// ---------------------------------------------------------------------------
+// Inline PROPFIND events tests
+// ---------------------------------------------------------------------------
+
+/// A calendar at `/cal/` whose PROPFIND carries each event's data inline and
+/// which does not implement REPORT.
+async fn start_propfind_only_server() -> SocketAddr {
+    let mut responses = String::from(
+        r#"<d:response>
+  <d:href>/cal/</d:href>
+  <d:propstat>
+    <d:prop>
+      <d:resourcetype><d:collection/><c:calendar/></d:resourcetype>
+    </d:prop>
+    <d:status>HTTP/1.1 200 OK</d:status>
+  </d:propstat>
+</d:response>"#,
+    );
+    for uid in ["inline-1", "inline-2"] {
+        responses.push_str(&format!(
+            r#"<d:response>
+  <d:href>/cal/{uid}.ics</d:href>
+  <d:propstat>
+    <d:prop>
+      <d:resourcetype/>
+      <c:calendar-data>BEGIN:VCALENDAR
+VERSION:2.0
+BEGIN:VEVENT
+UID:{uid}
+SUMMARY:Inline {uid}
+DTSTART:20250101T100000Z
+END:VEVENT
+END:VCALENDAR</c:calendar-data>
+    </d:prop>
+    <d:status>HTTP/1.1 200 OK</d:status>
+  </d:propstat>
+</d:response>"#,
+        ));
+    }
+    let body = format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">{responses}</d:multistatus>"#,
+    );
+    let app = Router::new().fallback(any(move |req: Request<Body>| {
+        let body = body.clone();
+        async move {
+            match req.method().as_str() {
+                "PROPFIND" => (StatusCode::MULTI_STATUS, body).into_response(),
+                _ => (StatusCode::NOT_IMPLEMENTED, "").into_response(),
+            }
+        }
+    }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    addr
+}
+
+#[tokio::test]
+async fn run_sync_uses_inline_propfind_events_when_enabled() {
+    let addr = start_propfind_only_server().await;
+    let url = format!("http://{}/cal/", addr);
+
+    let opts = SyncOptions {
+        propfind_events: true,
+        ..Default::default()
+    };
+    let output = run_sync(&url, "user", "pass", &opts).await.unwrap();
+
+    assert_eq!(output.calendars, 1);
+    assert_eq!(output.events, 2);
+    assert!(output.ics.contains("UID:inline-1"));
+    assert!(output.ics.contains("SUMMARY:Inline inline-2"));
+}
+
+#[tokio::test]
+async fn run_sync_ignores_inline_events_by_default() {
+    let addr = start_propfind_only_server().await;
+    let url = format!("http://{}/cal/", addr);
+
+    let output = run_sync(&url, "user", "pass", &SyncOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(output.calendars, 1);
+    assert_eq!(output.events, 0);
+}
+
 // ---------------------------------------------------------------------------
 // fetch_events tests
 // ---------------------------------------------------------------------------
@@ -315,13 +846,79 @@ async fn run_sync_returns_correct_counts() {
     });
     let addr = start_mock_server(state).await;
 
-    let (event_count, calendar_count, _ics) =
-        run_sync(&format!("http://{}/dav/", addr), "user", "pass")
-            .await
-            .unwrap();
+    let output = run_sync(
+        &format!("http://{}/dav/", addr),
+        "user",
+        "pass",
+        &SyncOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(output.calendars, 1);
+    assert_eq!(output.events, 2);
+}
+
+/// Collects formatted log output so tests can inspect what was logged.
+#[derive(Clone, Default)]
+struct LogBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
 
-    assert_eq!(calendar_count, 1);
-    assert_eq!(event_count, 2);
+async fn sync_with_captured_logs(debug_http: bool) -> String {
+    let events = [("uid-log", "Logged", "20250301T080000Z", "20250301T090000Z")];
+    let state = std::sync::Arc::new(MockState {
+        propfind_body: mock_propfind_response(&["/cal/default/"]),
+        report_body: mock_report_response(&events),
+        put_status: StatusCode::CREATED,
+    });
+    let addr = start_mock_server(state).await;
+
+    let logs = LogBuffer::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let opts = SyncOptions {
+        debug_http,
+        ..Default::default()
+    };
+    run_sync(&format!("http://{}/dav/", addr), "user", "secret", &opts)
+        .await
+        .unwrap();
+    String::from_utf8(logs.0.lock().unwrap().clone()).unwrap()
+}
+
+#[tokio::test]
+async fn debug_http_logs_exchanges_without_credentials() {
+    let logs = sync_with_captured_logs(true).await;
+    assert!(logs.contains("CalDAV request PROPFIND"), "{}", logs);
+    assert!(logs.contains("CalDAV request REPORT"));
+    assert!(logs.contains("<c:calendar-query"));
+    assert!(logs.contains("-> 207 Multi-Status"));
+    assert!(logs.contains("authorization: [redacted]"));
+    // base64("user:secret")
+    assert!(!logs.contains("dXNlcjpzZWNyZXQ="));
+    assert!(!logs.contains("secret"));
+}
+
+#[tokio::test]
+async fn debug_http_is_off_by_default() {
+    let logs = sync_with_captured_logs(false).await;
+    assert!(!logs.contains("CalDAV request"), "{}", logs);
 }
 
 #[tokio::test]
@@ -334,9 +931,15 @@ async fn run_sync_ics_output_has_vcalendar_wrapper() {
     });
     let addr = start_mock_server(state).await;
 
-    let (_ec, _cc, ics) = run_sync(&format!("http://{}/dav/", addr), "user", "pass")
-        .await
-        .unwrap();
+    let ics = run_sync(
+        &format!("http://{}/dav/", addr),
+        "user",
+        "pass",
+        &SyncOptions::default(),
+    )
+    .await
+    .unwrap()
+    .ics;
 
     assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
     assert!(ics.ends_with("END:VCALENDAR\r\n"));
@@ -347,211 +950,3187 @@ async fn run_sync_ics_output_has_vcalendar_wrapper() {
 }
 
 #[tokio::test]
-async fn run_sync_handles_multiple_calendars() {
-    // Each calendar path triggers the same REPORT response, so the mock
-    // returns the same events per calendar. Two calendars with 1 event each
-    // means 2 total events.
-    let events = [("uid-multi", "Multi", "20250501T140000Z", "20250501T150000Z")];
+async fn run_sync_adds_x_wr_timezone_when_configured() {
+    let events = [("uid-tz", "Tz", "20250401T120000Z", "20250401T130000Z")];
     let state = std::sync::Arc::new(MockState {
-        propfind_body: mock_propfind_response(&["/cal/a/", "/cal/b/"]),
+        propfind_body: mock_propfind_response(&["/cal/"]),
         report_body: mock_report_response(&events),
         put_status: StatusCode::CREATED,
     });
     let addr = start_mock_server(state).await;
+    let url = format!("http://{}/dav/", addr);
 
-    let (event_count, calendar_count, ics) =
-        run_sync(&format!("http://{}/dav/", addr), "user", "pass")
-            .await
-            .unwrap();
+    let opts = SyncOptions {
+        default_timezone: Some("Europe/Berlin".into()),
+        ..Default::default()
+    };
+    let ics = run_sync(&url, "user", "pass", &opts).await.unwrap().ics;
+    assert!(ics.contains("METHOD:PUBLISH\r\nX-WR-TIMEZONE:Europe/Berlin\r\n"));
 
-    assert_eq!(calendar_count, 2);
-    assert_eq!(event_count, 2);
-    // Both events are uid-multi so the VEVENT block should appear twice
-    assert_eq!(ics.matches("UID:uid-multi").count(), 2);
+    let ics = run_sync(&url, "user", "pass", &SyncOptions::default())
+        .await
+        .unwrap()
+        .ics;
+    assert!(!ics.contains("X-WR-TIMEZONE"));
+}
+
+#[tokio::test]
+async fn run_sync_sorts_events_by_dtstart_when_enabled() {
+    let events = [
+        ("uid-c", "Third", "20250403T090000Z", "20250403T100000Z"),
+        ("uid-a", "First", "20250401T090000Z", "20250401T100000Z"),
+        ("uid-b", "Second", "20250402T090000Z", "20250402T100000Z"),
+    ];
+    let state = std::sync::Arc::new(MockState {
+        propfind_body: mock_propfind_response(&["/cal/"]),
+        report_body: mock_report_response(&events),
+        put_status: StatusCode::CREATED,
+    });
+    let addr = start_mock_server(state).await;
+    let url = format!("http://{}/dav/", addr);
+    let uid_order = |ics: &str| -> Vec<String> {
+        ics.lines()
+            .filter_map(|l| l.strip_prefix("UID:"))
+            .map(str::to_owned)
+            .collect()
+    };
+
+    let opts = SyncOptions {
+        sort_events: true,
+        ..Default::default()
+    };
+    let ics = run_sync(&url, "user", "pass", &opts).await.unwrap().ics;
+    assert_eq!(uid_order(&ics), ["uid-a", "uid-b", "uid-c"]);
+
+    let ics = run_sync(&url, "user", "pass", &SyncOptions::default())
+        .await
+        .unwrap()
+        .ics;
+    assert_eq!(uid_order(&ics), ["uid-c", "uid-a", "uid-b"]);
+}
+
+/// Mock CalDAV server that records the client port of every request, so
+/// distinct ports equal distinct TCP connections (and TLS handshakes).
+async fn start_connection_counting_server(
+    state: std::sync::Arc<MockState>,
+) -> (SocketAddr, std::sync::Arc<std::sync::Mutex<HashSet<u16>>>) {
+    let peers = std::sync::Arc::new(std::sync::Mutex::new(HashSet::new()));
+    let seen = std::sync::Arc::clone(&peers);
+    let app = Router::new()
+        .fallback(any(
+            move |ConnectInfo(peer): ConnectInfo<SocketAddr>,
+                  st: axum::extract::State<std::sync::Arc<MockState>>,
+                  req: Request<Body>| {
+                seen.lock().unwrap().insert(peer.port());
+                caldav_handler(st, req)
+            },
+        ))
+        .with_state(state);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    (addr, peers)
+}
+
+#[tokio::test]
+async fn run_sync_reuses_connections_with_shared_pool() {
+    let events = [("uid-1", "Pooled", "20250401T120000Z", "20250401T130000Z")];
+    let mock = || {
+        std::sync::Arc::new(MockState {
+            propfind_body: mock_propfind_response(&["/cal/"]),
+            report_body: mock_report_response(&events),
+            put_status: StatusCode::CREATED,
+        })
+    };
+
+    let (addr, peers) = start_connection_counting_server(mock()).await;
+    let url = format!("http://{}/dav/", addr);
+    for _ in 0..3 {
+        run_sync(&url, "user", "pass", &SyncOptions::default())
+            .await
+            .unwrap();
+    }
+    assert_eq!(peers.lock().unwrap().len(), 3);
+
+    let (addr, peers) = start_connection_counting_server(mock()).await;
+    let url = format!("http://{}/dav/", addr);
+    let opts = SyncOptions::default().with_http(&HttpClients::new(HttpPoolConfig::default()));
+    for _ in 0..3 {
+        run_sync(&url, "user", "pass", &opts).await.unwrap();
+    }
+    assert_eq!(peers.lock().unwrap().len(), 1);
+}
+
+/// Bare HTTP/1.1 server: PROPFIND gets `propfind`, anything else `report`.
+/// Connections opening with the HTTP/2 preface are refused and counted.
+async fn start_http1_only_server(
+    propfind: String,
+    report: String,
+) -> (SocketAddr, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    let refused = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let counter = refused.clone();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (propfind, report, counter) = (propfind.clone(), report.clone(), counter.clone());
+            tokio::spawn(async move {
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 4096];
+                let head_end = loop {
+                    let n = stream.read(&mut chunk).await.unwrap_or(0);
+                    if n == 0 {
+                        return;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                    if let Some(i) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break i + 4;
+                    }
+                };
+                let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+                if head.starts_with("PRI * HTTP/2.0") {
+                    counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    let _ = stream
+                        .write_all(b"HTTP/1.1 505 HTTP Version Not Supported\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                        .await;
+                    return;
+                }
+                let length: usize = head
+                    .lines()
+                    .find_map(|l| {
+                        let (k, v) = l.split_once(':')?;
+                        k.eq_ignore_ascii_case("content-length")
+                            .then(|| v.trim().parse().ok())?
+                    })
+                    .unwrap_or(0);
+                while buf.len() < head_end + length {
+                    let n = stream.read(&mut chunk).await.unwrap_or(0);
+                    if n == 0 {
+                        break;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+                let body = if head.starts_with("PROPFIND") {
+                    &propfind
+                } else {
+                    &report
+                };
+                let response = format!(
+                    "HTTP/1.1 207 Multi-Status\r\nContent-Type: application/xml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    (addr, refused)
+}
+
+#[tokio::test]
+async fn prior_knowledge_http2_falls_back_to_http1() {
+    let events = [(
+        "uid-h1",
+        "Over HTTP/1.1",
+        "20250401T120000Z",
+        "20250401T130000Z",
+    )];
+    let (addr, refused) = start_http1_only_server(
+        mock_propfind_response(&["/cal/"]),
+        mock_report_response(&events),
+    )
+    .await;
+
+    let http = HttpClients::new(HttpPoolConfig {
+        http2: Http2Mode::PriorKnowledge,
+        ..Default::default()
+    });
+    let opts = SyncOptions::default().with_http(&http);
+    let output = run_sync(&format!("http://{}/dav/", addr), "user", "pass", &opts)
+        .await
+        .unwrap();
+
+    assert_eq!(output.events, 1);
+    assert!(output.ics.contains("SUMMARY:Over HTTP/1.1"));
+    assert!(refused.load(std::sync::atomic::Ordering::SeqCst) > 0);
+}
+
+#[tokio::test]
+async fn prior_knowledge_http2_is_used_when_supported() {
+    let versions = std::sync::Arc::new(std::sync::Mutex::new(HashSet::new()));
+    let seen = versions.clone();
+    let state = std::sync::Arc::new(MockState {
+        propfind_body: mock_propfind_response(&["/cal/"]),
+        report_body: mock_report_response(&[]),
+        put_status: StatusCode::CREATED,
+    });
+    let app = Router::new()
+        .fallback(any(
+            move |st: axum::extract::State<std::sync::Arc<MockState>>, req: Request<Body>| {
+                seen.lock().unwrap().insert(format!("{:?}", req.version()));
+                caldav_handler(st, req)
+            },
+        ))
+        .with_state(state);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let http = HttpClients::new(HttpPoolConfig {
+        http2: Http2Mode::PriorKnowledge,
+        ..Default::default()
+    });
+    let opts = SyncOptions::default().with_http(&http);
+    run_sync(&format!("http://{}/dav/", addr), "user", "pass", &opts)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        *versions.lock().unwrap(),
+        HashSet::from(["HTTP/2.0".to_string()])
+    );
+}
+
+/// Resolves every name to the given addresses.
+struct FixedResolver(Vec<std::net::IpAddr>);
+
+impl Resolve for FixedResolver {
+    fn resolve(&self, _: Name) -> Resolving {
+        let addrs: Vec<SocketAddr> = self.0.iter().map(|ip| SocketAddr::new(*ip, 0)).collect();
+        Box::pin(async move { Ok(Box::new(addrs.into_iter()) as Addrs) })
+    }
+}
+
+fn guarded_http(addrs: &[&str]) -> HttpClients {
+    let resolver = FixedResolver(addrs.iter().map(|a| a.parse().unwrap()).collect());
+    HttpClients::new(HttpPoolConfig {
+        private_address_guard: Some(PrivateAddressGuard::new(std::sync::Arc::new(resolver))),
+        ..Default::default()
+    })
+}
+
+#[tokio::test]
+async fn private_address_guard_drops_internal_addresses() {
+    let guard = PrivateAddressGuard::new(std::sync::Arc::new(FixedResolver(vec![
+        "10.0.0.7".parse().unwrap(),
+        "93.184.216.34".parse().unwrap(),
+    ])));
+    let addrs: Vec<SocketAddr> = guard
+        .resolve("calendar.test".parse().unwrap())
+        .await
+        .unwrap()
+        .collect();
+    assert_eq!(addrs, ["93.184.216.34:0".parse().unwrap()]);
+
+    let guard = PrivateAddressGuard::new(std::sync::Arc::new(FixedResolver(vec![
+        "169.254.169.254".parse().unwrap(),
+    ])));
+    let err = guard
+        .resolve("metadata.test".parse().unwrap())
+        .await
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("metadata.test (169.254.169.254)"));
+}
+
+#[tokio::test]
+async fn private_address_guard_checks_saved_urls_after_resolution() {
+    let guard = PrivateAddressGuard::new(std::sync::Arc::new(FixedResolver(vec![
+        "192.168.1.5".parse().unwrap(),
+    ])));
+    let check = |url: &str| {
+        let url = reqwest::Url::parse(url).unwrap();
+        let guard = guard.clone();
+        async move { guard.check_resolved(&url).await }
+    };
+
+    let blocked = check("https://nas.home.arpa/dav/").await.unwrap_err();
+    assert_eq!(blocked.host, "nas.home.arpa");
+    assert_eq!(blocked.addr.to_string(), "192.168.1.5");
+    assert!(check("http://10.0.0.2:5232/").await.is_err());
+    assert!(check("http://[::1]/cal/").await.is_err());
+    // Public literals never reach the resolver.
+    assert!(check("https://93.184.216.34/dav/").await.is_ok());
+
+    let public = PrivateAddressGuard::new(std::sync::Arc::new(FixedResolver(vec![
+        "93.184.216.34".parse().unwrap(),
+    ])));
+    let url = reqwest::Url::parse("https://caldav.example.com/dav/").unwrap();
+    assert!(public.check_resolved(&url).await.is_ok());
+}
+
+#[tokio::test]
+async fn guarded_sync_refuses_names_resolving_to_private_addresses() {
+    let state = std::sync::Arc::new(MockState {
+        propfind_body: mock_propfind_response(&["/cal/"]),
+        report_body: mock_report_response(&[]),
+        put_status: StatusCode::CREATED,
+    });
+    let addr = start_mock_server(state).await;
+    let http = guarded_http(&["127.0.0.1"]);
+
+    // The mock listens on loopback, so the name's only address is refused.
+    let opts = SyncOptions::default().with_http(&http);
+    let url = format!("http://caldav.test:{}/dav/", addr.port());
+    let err = run_sync(&url, "user", "pass", &opts).await.unwrap_err();
+    let msg = err.to_string();
+    assert!(msg.contains("caldav.test (127.0.0.1)"), "{msg}");
+    assert!(msg.contains("ALLOW_PRIVATE_FETCH=true"), "{msg}");
+
+    // IP literals skip the resolver and are checked before connecting.
+    let err = run_sync(&format!("http://{}/dav/", addr), "user", "pass", &opts)
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("ALLOW_PRIVATE_FETCH=true"),
+        "{err}"
+    );
+
+    // Without the guard the same server syncs.
+    let opts = SyncOptions::default().with_http(&HttpClients::default());
+    run_sync(&format!("http://{}/dav/", addr), "user", "pass", &opts)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn guarded_reverse_sync_refuses_private_feeds() {
+    let (ics_addr, caldav_addr) = start_reverse_sync_mocks(&[], StatusCode::CREATED).await;
+    let http = guarded_http(&["127.0.0.1"]);
+    let opts = ReverseSyncOptions::default().with_http(&http);
+
+    let err = run_reverse_sync(
+        &format!("http://feed.test:{}/feed.ics", ics_addr.port()),
+        &format!("http://{}/dav/calendars", caldav_addr),
+        "personal",
+        "user",
+        "pass",
+        false,
+        false,
+        &opts,
+    )
+    .await
+    .unwrap_err();
+    let msg = err.to_string();
+    assert!(msg.contains("feed.test (127.0.0.1)"), "{msg}");
+    assert!(msg.contains("ALLOW_PRIVATE_FETCH=true"), "{msg}");
+
+    let err = run_reverse_sync(
+        &format!("http://{}/feed.ics", ics_addr),
+        &format!("http://{}/dav/calendars", caldav_addr),
+        "personal",
+        "user",
+        "pass",
+        false,
+        false,
+        &opts,
+    )
+    .await
+    .unwrap_err();
+    assert!(
+        err.to_string().contains("ALLOW_PRIVATE_FETCH=true"),
+        "{err}"
+    );
+}
+
+#[tokio::test]
+async fn run_sync_tags_events_with_their_origin_calendar() {
+    let app = Router::new().fallback(any(|req: Request<Body>| async move {
+        let event = |uid: &str, summary: &str| {
+            mock_report_response(&[(uid, summary, "20250401T090000Z", "20250401T100000Z")])
+        };
+        match (req.method().as_str(), req.uri().path()) {
+            ("PROPFIND", "/dav/") => (
+                StatusCode::MULTI_STATUS,
+                mock_propfind_response(&["/dav/work/", "/dav/home/"]),
+            ),
+            ("REPORT", "/dav/work/") => (StatusCode::MULTI_STATUS, event("uid-w", "Standup")),
+            ("REPORT", "/dav/home/") => (StatusCode::MULTI_STATUS, event("uid-h", "Dinner")),
+            _ => (StatusCode::NOT_FOUND, String::new()),
+        }
+    }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    let url = format!("http://{}/dav/", addr);
+
+    let opts = SyncOptions {
+        tag_calendar_origin: true,
+        ..Default::default()
+    };
+    let output = run_sync(&url, "user", "pass", &opts).await.unwrap();
+    let tag_of = |uid: &str| {
+        let start = output.ics.find(&format!("UID:{uid}")).unwrap();
+        let event = &output.ics[start..];
+        let event = &event[..event.find("END:VEVENT").unwrap()];
+        event
+            .lines()
+            .find(|l| l.starts_with("X-ORIGIN-CALENDAR"))
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(
+        tag_of("uid-w"),
+        "X-ORIGIN-CALENDAR;X-CALENDAR-HREF=\"/dav/work/\":cal"
+    );
+    assert_eq!(
+        tag_of("uid-h"),
+        "X-ORIGIN-CALENDAR;X-CALENDAR-HREF=\"/dav/home/\":cal"
+    );
+
+    let output = run_sync(&url, "user", "pass", &SyncOptions::default())
+        .await
+        .unwrap();
+    assert!(!output.ics.contains("X-ORIGIN-CALENDAR"));
+}
+
+#[test]
+fn tag_origin_replaces_an_existing_tag() {
+    let event = "BEGIN:VEVENT\r\nUID:1\r\nX-ORIGIN-CALENDAR;X-CALENDAR-HREF=\"/old/\":Old\r\n  folded\r\nBEGIN:VALARM\r\nEND:VALARM\r\nEND:VEVENT\r\n";
+    assert_eq!(
+        tag_origin(event, "/new/", "Work, Team"),
+        "BEGIN:VEVENT\r\nUID:1\r\nBEGIN:VALARM\r\nEND:VALARM\r\nX-ORIGIN-CALENDAR;X-CALENDAR-HREF=\"/new/\":Work\\, Team\r\nEND:VEVENT\r\n"
+    );
+}
+
+#[tokio::test]
+async fn run_sync_applies_summary_template() {
+    let events = [("uid-1", "Standup", "20250401T090000Z", "20250401T100000Z")];
+    let state = std::sync::Arc::new(MockState {
+        propfind_body: mock_propfind_response(&["/cal/"]),
+        report_body: mock_report_response(&events),
+        put_status: StatusCode::CREATED,
+    });
+    let addr = start_mock_server(state).await;
+
+    let opts = SyncOptions {
+        summary_template: Some("[Work] {summary} ({calendar})".into()),
+        ..Default::default()
+    };
+    let ics = run_sync(&format!("http://{}/dav/", addr), "user", "pass", &opts)
+        .await
+        .unwrap()
+        .ics;
+    assert!(ics.contains("SUMMARY:[Work] Standup (cal)\r\n"));
+}
+
+#[test]
+fn summary_template_adds_summary_to_events_without_one() {
+    let vevent = "BEGIN:VEVENT\r\nUID:a\r\nEND:VEVENT\r\n";
+    assert_eq!(
+        summary_template::apply(vevent, "[Work] {summary}", "cal"),
+        "BEGIN:VEVENT\r\nUID:a\r\nSUMMARY:[Work]\r\nEND:VEVENT\r\n"
+    );
+    assert_eq!(summary_template::apply(vevent, "{summary}", "cal"), vevent);
+}
+
+#[test]
+fn summary_template_leaves_alarm_summary_and_escapes_calendar() {
+    let vevent = "BEGIN:VEVENT\r\nUID:a\r\nSUMMARY;LANGUAGE=en:Long meeting name that\r\n  continues\r\n\
+        BEGIN:VALARM\r\nACTION:EMAIL\r\nSUMMARY:Reminder\r\nEND:VALARM\r\nEND:VEVENT\r\n";
+    let out = summary_template::apply(vevent, "{calendar}: {summary}", "Home, Family");
+    assert!(
+        out.contains("SUMMARY;LANGUAGE=en:Home\\, Family: Long meeting name that continues\r\n")
+    );
+    assert!(out.contains("SUMMARY:Reminder\r\n"));
+}
+
+#[tokio::test]
+async fn run_sync_folds_long_lines() {
+    let summary = format!("Quarterly planning — {} ✓", "très long résumé ".repeat(8));
+    let events = [(
+        "uid-long",
+        summary.as_str(),
+        "20250401T090000Z",
+        "20250401T100000Z",
+    )];
+    let state = std::sync::Arc::new(MockState {
+        propfind_body: mock_propfind_response(&["/cal/"]),
+        report_body: mock_report_response(&events),
+        put_status: StatusCode::CREATED,
+    });
+    let addr = start_mock_server(state).await;
+
+    let ics = run_sync(
+        &format!("http://{}/dav/", addr),
+        "user",
+        "pass",
+        &SyncOptions::default(),
+    )
+    .await
+    .unwrap()
+    .ics;
+
+    for line in ics.split("\r\n") {
+        assert!(line.len() <= 75, "{} octets: {:?}", line.len(), line);
+        assert!(!line.contains('\n'));
+    }
+    assert!(ics.contains("\r\n "), "expected folded continuation lines");
+    let unfolded = ics.replace("\r\n ", "");
+    assert!(unfolded.contains(&format!("\r\nSUMMARY:{}\r\n", summary)));
+}
+
+#[test]
+fn ics_duration_formats_rfc5545_durations() {
+    assert_eq!(ics_duration(3600), "PT1H");
+    assert_eq!(ics_duration(900), "PT15M");
+    assert_eq!(ics_duration(45), "PT45S");
+    assert_eq!(ics_duration(5400), "PT1H30M");
+    assert_eq!(ics_duration(86_400), "P1D");
+    assert_eq!(ics_duration(90_061), "P1DT1H1M1S");
+}
+
+#[tokio::test]
+async fn run_sync_advertises_refresh_interval() {
+    let events = [("uid-1", "Standup", "20250401T090000Z", "20250401T100000Z")];
+    let state = std::sync::Arc::new(MockState {
+        propfind_body: mock_propfind_response(&["/cal/"]),
+        report_body: mock_report_response(&events),
+        put_status: StatusCode::CREATED,
+    });
+    let addr = start_mock_server(state).await;
+    let url = format!("http://{}/dav/", addr);
+
+    let opts = SyncOptions {
+        refresh_interval_secs: Some(3600),
+        ..Default::default()
+    };
+    let ics = run_sync(&url, "user", "pass", &opts).await.unwrap().ics;
+    let header = &ics[..ics.find("BEGIN:VEVENT").unwrap()];
+    assert!(header.contains("REFRESH-INTERVAL;VALUE=DURATION:PT1H\r\n"));
+    assert!(header.contains("X-PUBLISHED-TTL:PT1H\r\n"));
+
+    let ics = run_sync(&url, "user", "pass", &SyncOptions::default())
+        .await
+        .unwrap()
+        .ics;
+    assert!(!ics.contains("REFRESH-INTERVAL"));
+    assert!(!ics.contains("X-PUBLISHED-TTL"));
+}
+
+#[tokio::test]
+async fn run_sync_trims_oversized_events() {
+    let big_summary = format!(
+        "Big\r\nDESCRIPTION:{}\r\nATTACH;ENCODING=BASE64;VALUE=BINARY:{}",
+        "lorem ipsum ".repeat(400),
+        "QUFB".repeat(1000)
+    );
+    let events = [
+        (
+            "uid-big",
+            big_summary.as_str(),
+            "20250401T090000Z",
+            "20250401T100000Z",
+        ),
+        ("uid-small", "Small", "20250402T090000Z", "20250402T100000Z"),
+    ];
+    let state = std::sync::Arc::new(MockState {
+        propfind_body: mock_propfind_response(&["/cal/"]),
+        report_body: mock_report_response(&events),
+        put_status: StatusCode::CREATED,
+    });
+    let addr = start_mock_server(state).await;
+
+    let opts = SyncOptions {
+        max_event_bytes: 1024,
+        ..Default::default()
+    };
+    let output = run_sync(&format!("http://{}/dav/", addr), "user", "pass", &opts)
+        .await
+        .unwrap();
+    assert_eq!(output.events, 2);
+    assert_eq!(output.trimmed, 1);
+
+    let big_start = output.ics.find("BEGIN:VEVENT\r\nUID:uid-big").unwrap();
+    let big_len = output.ics[big_start..].find("END:VEVENT\r\n").unwrap() + 12;
+    let big = &output.ics[big_start..big_start + big_len];
+    assert!(big.len() <= 1024, "trimmed event is {} bytes", big.len());
+    assert!(!big.contains("ATTACH"));
+    assert!(big.contains("DESCRIPTION:lorem ipsum"));
+    assert!(big.replace("\r\n ", "").contains(TRUNCATION_NOTE));
+    assert!(output.ics.contains(
+        "BEGIN:VEVENT\r\nUID:uid-small\r\nSUMMARY:Small\r\nDTSTART:20250402T090000Z\r\nDTEND:20250402T100000Z\r\nEND:VEVENT\r\n"
+    ));
+}
+
+#[test]
+fn trim_event_keeps_escapes_and_quoted_parameters_intact() {
+    let vevent = format!(
+        "BEGIN:VEVENT\r\nUID:a\r\nDESCRIPTION;ALTREP=\"cid:part1\":{}\r\nEND:VEVENT\r\n",
+        "a\\,".repeat(100)
+    );
+    assert!(event_trim::trim_event(&vevent, vevent.len()).is_none());
+
+    let trimmed = event_trim::trim_event(&vevent, 200).unwrap();
+    let unfolded = trimmed.replace("\r\n ", "");
+    let value = unfolded
+        .split("DESCRIPTION;ALTREP=\"cid:part1\":")
+        .nth(1)
+        .unwrap()
+        .split("\r\n")
+        .next()
+        .unwrap();
+    let kept = value.strip_suffix(TRUNCATION_NOTE).unwrap();
+    assert!(!kept.is_empty());
+    assert!(!kept.ends_with('\\'), "cut inside an escape: {kept}");
+    assert!(trimmed.len() <= 200);
+}
+
+#[tokio::test]
+async fn run_sync_handles_multiple_calendars() {
+    // Each calendar path triggers the same REPORT response, so the mock
+    // returns the same events per calendar. Two calendars with 1 event each
+    // means 2 total events.
+    let events = [("uid-multi", "Multi", "20250501T140000Z", "20250501T150000Z")];
+    let state = std::sync::Arc::new(MockState {
+        propfind_body: mock_propfind_response(&["/cal/a/", "/cal/b/"]),
+        report_body: mock_report_response(&events),
+        put_status: StatusCode::CREATED,
+    });
+    let addr = start_mock_server(state).await;
+
+    let output = run_sync(
+        &format!("http://{}/dav/", addr),
+        "user",
+        "pass",
+        &SyncOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(output.calendars, 2);
+    assert_eq!(output.events, 2);
+    // Both events are uid-multi so the VEVENT block should appear twice
+    assert_eq!(output.ics.matches("UID:uid-multi").count(), 2);
+    assert_eq!(output.calendar_ics.len(), 2);
+    assert_eq!(output.calendar_ics[0].0, "/cal/a/");
+    assert!(output.calendar_ics[0].1.contains("UID:uid-multi"));
+}
+
+#[tokio::test]
+async fn run_sync_emits_vtimezones_before_vevents() {
+    let tz = |tzid: &str| {
+        format!(
+            "BEGIN:VTIMEZONE\r\nTZID:{tzid}\r\nBEGIN:STANDARD\r\nDTSTART:19701025T030000\r\nTZOFFSETFROM:+0200\r\nTZOFFSETTO:+0100\r\nEND:STANDARD\r\nEND:VTIMEZONE"
+        )
+    };
+    let object = |uid: &str, tzid: &str| {
+        // The zone follows the event, as some servers order it.
+        format!(
+            r#"<d:response>
+  <d:href>/cal/{uid}.ics</d:href>
+  <d:propstat>
+    <d:prop><c:calendar-data>BEGIN:VCALENDAR&#13;
+VERSION:2.0&#13;
+BEGIN:VEVENT&#13;
+UID:{uid}&#13;
+DTSTART;TZID={tzid}:20250601T100000&#13;
+END:VEVENT&#13;
+{}&#13;
+END:VCALENDAR</c:calendar-data></d:prop>
+    <d:status>HTTP/1.1 200 OK</d:status>
+  </d:propstat>
+</d:response>"#,
+            tz(tzid).replace("\r\n", "&#13;\n")
+        )
+    };
+    let report = format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  {}{}
+</d:multistatus>"#,
+        object("uid-berlin", "Europe/Berlin"),
+        object("uid-paris", "Europe/Paris")
+    );
+    // Both calendars return the same objects, so each zone arrives twice.
+    let state = std::sync::Arc::new(MockState {
+        propfind_body: mock_propfind_response(&["/cal/a/", "/cal/b/"]),
+        report_body: report,
+        put_status: StatusCode::CREATED,
+    });
+    let addr = start_mock_server(state).await;
+
+    let output = run_sync(
+        &format!("http://{}/dav/", addr),
+        "user",
+        "pass",
+        &SyncOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(output.events, 4);
+    assert_eq!(output.ics.matches("BEGIN:VTIMEZONE").count(), 2);
+    assert_eq!(output.ics.matches("TZID:Europe/Berlin\r\n").count(), 1);
+    assert_eq!(output.ics.matches("TZID:Europe/Paris\r\n").count(), 1);
+    let last_tz = output.ics.rfind("END:VTIMEZONE").unwrap();
+    let first_event = output.ics.find("BEGIN:VEVENT").unwrap();
+    assert!(last_tz < first_event, "{}", output.ics);
+    assert!(
+        output.ics.find("BEGIN:VTIMEZONE").unwrap() > output.ics.find("METHOD:PUBLISH").unwrap()
+    );
+}
+
+// ---------------------------------------------------------------------------
+// sync-collection tests
+// ---------------------------------------------------------------------------
+
+fn vevent_object(uid: &str) -> String {
+    format!(
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nUID:{uid}\r\nDTSTART:20250601T100000Z\r\nEND:VEVENT\r\nEND:VCALENDAR"
+    )
+}
+
+fn changed_member(uid: &str) -> String {
+    format!(
+        r#"<d:response>
+  <d:href>/cal/{uid}.ics</d:href>
+  <d:propstat>
+    <d:prop><c:calendar-data>{}</c:calendar-data></d:prop>
+    <d:status>HTTP/1.1 200 OK</d:status>
+  </d:propstat>
+</d:response>"#,
+        vevent_object(uid)
+    )
+}
+
+fn sync_collection_response(members: &[String], token: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  {}
+  <d:sync-token>{token}</d:sync-token>
+</d:multistatus>"#,
+        members.concat()
+    )
+}
+
+/// CalDAV mock that answers `sync-collection` by token: "" gets the full
+/// set, "tok-1" gets a delta and anything else is rejected with 409. Every
+/// REPORT body is recorded.
+async fn start_sync_collection_server()
+-> (SocketAddr, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+    let reports = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen = reports.clone();
+    let app = Router::new().fallback(any(move |req: Request<Body>| {
+        let seen = seen.clone();
+        async move {
+            if req.method().as_str() == "PROPFIND" {
+                return (StatusCode::MULTI_STATUS, mock_propfind_response(&["/cal/"]))
+                    .into_response();
+            }
+            let bytes = axum::body::to_bytes(req.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body = String::from_utf8(bytes.to_vec()).unwrap();
+            seen.lock().unwrap().push(body.clone());
+            if !body.contains("sync-collection") {
+                let full = [("uid-full", "Full", "20250601T100000Z", "20250601T110000Z")];
+                return (StatusCode::MULTI_STATUS, mock_report_response(&full)).into_response();
+            }
+            if body.contains("<d:sync-token></d:sync-token>") {
+                let members = [changed_member("uid-a"), changed_member("uid-b")];
+                (
+                    StatusCode::MULTI_STATUS,
+                    sync_collection_response(&members, "tok-1"),
+                )
+                    .into_response()
+            } else if body.contains("<d:sync-token>tok-1</d:sync-token>") {
+                let members = [
+                    r#"<d:response>
+  <d:href>/cal/uid-a.ics</d:href>
+  <d:status>HTTP/1.1 404 Not Found</d:status>
+</d:response>"#
+                        .to_string(),
+                    changed_member("uid-c"),
+                ];
+                (
+                    StatusCode::MULTI_STATUS,
+                    sync_collection_response(&members, "tok-2"),
+                )
+                    .into_response()
+            } else {
+                (StatusCode::CONFLICT, "").into_response()
+            }
+        }
+    }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    (addr, reports)
+}
+
+#[tokio::test]
+async fn run_sync_applies_sync_collection_delta() {
+    let (addr, reports) = start_sync_collection_server().await;
+    let url = format!("http://{}/dav/", addr);
+
+    let first = run_sync(&url, "user", "pass", &SyncOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(first.events, 2);
+    assert_eq!(first.sync_state.len(), 1);
+    assert_eq!(first.sync_state[0].sync_token.as_deref(), Some("tok-1"));
+
+    let previous = first
+        .sync_state
+        .into_iter()
+        .map(|cal| (cal.href.clone(), cal))
+        .collect();
+    let second = run_sync(
+        &url,
+        "user",
+        "pass",
+        &SyncOptions::default().with_sync_state(previous),
+    )
+    .await
+    .unwrap();
+    assert_eq!(second.events, 2);
+    assert!(!second.ics.contains("UID:uid-a"));
+    assert!(second.ics.contains("UID:uid-b"));
+    assert!(second.ics.contains("UID:uid-c"));
+    assert_eq!(second.sync_state[0].sync_token.as_deref(), Some("tok-2"));
+    let hrefs: Vec<&str> = second.sync_state[0]
+        .objects
+        .iter()
+        .map(|(href, _)| href.as_str())
+        .collect();
+    assert_eq!(hrefs, ["/cal/uid-b.ics", "/cal/uid-c.ics"]);
+
+    // Both runs were served by sync-collection alone.
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), 2);
+    assert!(reports.iter().all(|r| r.contains("sync-collection")));
+}
+
+#[tokio::test]
+async fn run_sync_fetches_etag_only_changes_with_multiget() {
+    // The sync-collection answer carries only etags, so the data has to
+    // come from a calendar-multiget. Each REPORT is logged with its Depth.
+    let reports = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen = reports.clone();
+    let app = Router::new().fallback(any(move |req: Request<Body>| {
+        let seen = seen.clone();
+        async move {
+            if req.method().as_str() == "PROPFIND" {
+                return (StatusCode::MULTI_STATUS, mock_propfind_response(&["/cal/"]))
+                    .into_response();
+            }
+            let depth = req
+                .headers()
+                .get("Depth")
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            let bytes = axum::body::to_bytes(req.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body = String::from_utf8(bytes.to_vec()).unwrap();
+            seen.lock().unwrap().push((depth, body.clone()));
+            if body.contains("sync-collection") {
+                let member = r#"<d:response>
+  <d:href>/cal/uid-etag.ics</d:href>
+  <d:propstat>
+    <d:prop><d:getetag>"1"</d:getetag></d:prop>
+    <d:status>HTTP/1.1 200 OK</d:status>
+  </d:propstat>
+</d:response>"#;
+                (
+                    StatusCode::MULTI_STATUS,
+                    sync_collection_response(&[member.to_string()], "tok-1"),
+                )
+                    .into_response()
+            } else if body.contains("calendar-multiget")
+                && body.contains("<d:href>/cal/uid-etag.ics</d:href>")
+            {
+                (
+                    StatusCode::MULTI_STATUS,
+                    format!(
+                        r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">{}</d:multistatus>"#,
+                        changed_member("uid-etag")
+                    ),
+                )
+                    .into_response()
+            } else {
+                (StatusCode::BAD_REQUEST, "").into_response()
+            }
+        }
+    }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let output = run_sync(
+        &format!("http://{}/dav/", addr),
+        "user",
+        "pass",
+        &SyncOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(output.events, 1);
+    assert!(output.ics.contains("UID:uid-etag"));
+    assert_eq!(output.sync_state[0].sync_token.as_deref(), Some("tok-1"));
+    assert_eq!(output.sync_state[0].objects[0].0, "/cal/uid-etag.ics");
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[0].0, "0");
+    assert!(reports[0].1.contains("sync-collection"));
+    assert!(reports[1].1.contains("calendar-multiget"));
+}
+
+#[tokio::test]
+async fn run_sync_falls_back_to_full_report_on_rejected_token() {
+    let (addr, reports) = start_sync_collection_server().await;
+    let previous = db::CalendarSyncState {
+        href: "/cal/".into(),
+        sync_token: Some("stale".into()),
+        objects: vec![("/cal/uid-old.ics".into(), vevent_object("uid-old"))],
+    };
+
+    let output = run_sync(
+        &format!("http://{}/dav/", addr),
+        "user",
+        "pass",
+        &SyncOptions::default().with_sync_state([("/cal/".to_string(), previous)].into()),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(output.events, 1);
+    assert!(output.ics.contains("UID:uid-full"));
+    assert!(!output.ics.contains("UID:uid-old"));
+    assert_eq!(output.sync_state[0].sync_token, None);
+    assert_eq!(output.sync_state[0].objects[0].0, "/cal/uid-full.ics");
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), 2);
+    assert!(reports[1].contains("calendar-query"));
+}
+
+#[tokio::test]
+async fn run_sync_falls_back_to_full_report_without_token() {
+    // The plain mock answers every REPORT with a calendar-query response,
+    // which carries no sync-token.
+    let events = [("uid-plain", "Plain", "20250501T140000Z", "20250501T150000Z")];
+    let state = std::sync::Arc::new(MockState {
+        propfind_body: mock_propfind_response(&["/cal/"]),
+        report_body: mock_report_response(&events),
+        put_status: StatusCode::CREATED,
+    });
+    let addr = start_mock_server(state).await;
+
+    let output = run_sync(
+        &format!("http://{}/dav/", addr),
+        "user",
+        "pass",
+        &SyncOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(output.events, 1);
+    assert_eq!(output.sync_state[0].sync_token, None);
+    assert_eq!(
+        output.sync_state[0].objects[0].0,
+        "/cal/uid-plain.ics".to_string()
+    );
+}
+
+// ---------------------------------------------------------------------------
+// run_reverse_sync tests
+// ---------------------------------------------------------------------------
+
+/// Helper: start a mock that serves an ICS feed on GET and accepts PUTs on
+/// a separate address. Returns (ics_server_addr, caldav_server_addr).
+async fn start_reverse_sync_mocks(
+    ics_events: &[(&str, &str, &str, &str)],
+    put_status: StatusCode,
+) -> (SocketAddr, SocketAddr) {
+    let ics_feed = mock_ics_feed(ics_events);
+
+    // ICS feed server (plain GET)
+    let ics_state = std::sync::Arc::new(MockState {
+        propfind_body: String::new(),
+        report_body: ics_feed,
+        put_status: StatusCode::OK,
+    });
+    let ics_addr = start_mock_server(ics_state).await;
+
+    // CalDAV server (accepts PUT) — needs valid empty REPORT response
+    let caldav_state = std::sync::Arc::new(MockState {
+        propfind_body: String::new(),
+        report_body: mock_report_response(&[]),
+        put_status,
+    });
+    let caldav_addr = start_mock_server(caldav_state).await;
+
+    (ics_addr, caldav_addr)
+}
+
+#[tokio::test]
+async fn reverse_sync_uploads_events() {
+    let events = [
+        ("uid-r1", "Rev1", "20270601T080000Z", "20270601T090000Z"),
+        ("uid-r2", "Rev2", "20270601T100000Z", "20270601T110000Z"),
+    ];
+    let (ics_addr, caldav_addr) = start_reverse_sync_mocks(&events, StatusCode::CREATED).await;
+
+    let stats = run_reverse_sync(
+        &format!("http://{}/feed.ics", ics_addr),
+        &format!("http://{}/dav/calendars", caldav_addr),
+        "personal",
+        "user",
+        "pass",
+        false,
+        false,
+        &ReverseSyncOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.uploaded, 2);
+    assert_eq!(stats.total, 2);
+}
+
+/// CalDAV server with an empty calendar that records each PUT's `Prefer`
+/// header and answers 204 with `Preference-Applied: return=minimal`.
+async fn start_minimal_put_server() -> (SocketAddr, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+    let log = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen = log.clone();
+    let app = Router::new().fallback(any(move |req: Request<Body>| {
+        let seen = seen.clone();
+        async move {
+            match req.method().as_str() {
+                "PUT" => {
+                    let prefer = req
+                        .headers()
+                        .get("prefer")
+                        .and_then(|v| v.to_str().ok())
+                        .unwrap_or_default();
+                    seen.lock().unwrap().push(prefer.to_string());
+                    (
+                        StatusCode::NO_CONTENT,
+                        [("Preference-Applied", "return=minimal")],
+                    )
+                        .into_response()
+                }
+                _ => (StatusCode::MULTI_STATUS, mock_report_response(&[])).into_response(),
+            }
+        }
+    }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    (addr, log)
+}
+
+#[tokio::test]
+async fn reverse_sync_sends_prefer_minimal_when_enabled() {
+    let events = [
+        ("uid-m1", "Min1", "20270601T080000Z", "20270601T090000Z"),
+        ("uid-m2", "Min2", "20270601T100000Z", "20270601T110000Z"),
+    ];
+    let (ics_addr, _) = start_reverse_sync_mocks(&events, StatusCode::CREATED).await;
+
+    for prefer_minimal in [true, false] {
+        let (caldav_addr, log) = start_minimal_put_server().await;
+        let stats = run_reverse_sync(
+            &format!("http://{}/feed.ics", ics_addr),
+            &format!("http://{}/dav/", caldav_addr),
+            "cal",
+            "user",
+            "pass",
+            false,
+            true,
+            &ReverseSyncOptions {
+                prefer_minimal,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(stats.uploaded, 2);
+        let expected = if prefer_minimal { "return=minimal" } else { "" };
+        assert_eq!(*log.lock().unwrap(), [expected, expected]);
+    }
+}
+
+/// CalDAV server with an empty calendar that logs each PUT as
+/// `(content-encoding, decoded body)`. With `reject_gzip`, gzip bodies get 415.
+async fn start_gzip_put_server(
+    reject_gzip: bool,
+) -> (
+    SocketAddr,
+    std::sync::Arc<std::sync::Mutex<Vec<(String, String)>>>,
+) {
+    let log = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen = log.clone();
+    let app = Router::new().fallback(any(move |req: Request<Body>| {
+        let seen = seen.clone();
+        async move {
+            if req.method() != "PUT" {
+                return (StatusCode::MULTI_STATUS, mock_report_response(&[])).into_response();
+            }
+            let encoding = req
+                .headers()
+                .get(header::CONTENT_ENCODING)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            let body = axum::body::to_bytes(req.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let text = if encoding == "gzip" {
+                use std::io::Read;
+                let mut text = String::new();
+                flate2::read::GzDecoder::new(&body[..])
+                    .read_to_string(&mut text)
+                    .unwrap();
+                text
+            } else {
+                String::from_utf8_lossy(&body).into_owned()
+            };
+            let rejected = reject_gzip && encoding == "gzip";
+            seen.lock().unwrap().push((encoding, text));
+            if rejected {
+                StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response()
+            } else {
+                StatusCode::CREATED.into_response()
+            }
+        }
+    }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    (addr, log)
+}
+
+#[tokio::test]
+async fn reverse_sync_compresses_uploads_and_falls_back_on_415() {
+    let events = [
+        ("uid-z1", "Zip1", "20270601T080000Z", "20270601T090000Z"),
+        ("uid-z2", "Zip2", "20270601T100000Z", "20270601T110000Z"),
+        ("uid-z3", "Zip3", "20270601T120000Z", "20270601T130000Z"),
+    ];
+    let (ics_addr, _) = start_reverse_sync_mocks(&events, StatusCode::CREATED).await;
+    let opts = ReverseSyncOptions {
+        compress_uploads: true,
+        ..Default::default()
+    };
+
+    for reject_gzip in [false, true] {
+        let (caldav_addr, log) = start_gzip_put_server(reject_gzip).await;
+        let stats = run_reverse_sync(
+            &format!("http://{}/feed.ics", ics_addr),
+            &format!("http://{}/dav/", caldav_addr),
+            "cal",
+            "user",
+            "pass",
+            false,
+            true,
+            &opts,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(stats.uploaded, 3);
+        let log = log.lock().unwrap();
+        assert!(
+            log.iter()
+                .all(|(_, body)| body.starts_with("BEGIN:VCALENDAR"))
+        );
+        let encodings: Vec<&str> = log.iter().map(|(e, _)| e.as_str()).collect();
+        if reject_gzip {
+            // One rejected gzip attempt, then everything uncompressed.
+            assert_eq!(encodings, ["gzip", "", "", ""]);
+        } else {
+            assert_eq!(encodings, ["gzip", "gzip", "gzip"]);
+        }
+    }
+}
+
+/// Feed server that answers 502 for the first `failures` requests.
+async fn start_flaky_feed(
+    failures: usize,
+    feed: String,
+) -> (SocketAddr, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    let hits = std::sync::Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    let app = Router::new().fallback(any(move || {
+        let (counter, feed) = (counter.clone(), feed.clone());
+        async move {
+            if counter.fetch_add(1, Ordering::SeqCst) < failures {
+                (StatusCode::BAD_GATEWAY, String::new())
+            } else {
+                (StatusCode::OK, feed)
+            }
+        }
+    }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    (addr, hits)
+}
+
+#[tokio::test]
+async fn reverse_sync_retries_transient_feed_errors() {
+    let events = [("uid-f1", "Flaky", "20270601T080000Z", "20270601T090000Z")];
+    let (feed_addr, hits) = start_flaky_feed(2, mock_ics_feed(&events)).await;
+    let (_, caldav_addr) = start_reverse_sync_mocks(&[], StatusCode::CREATED).await;
+
+    let stats = run_reverse_sync(
+        &format!("http://{}/feed.ics", feed_addr),
+        &format!("http://{}/dav/calendars", caldav_addr),
+        "personal",
+        "user",
+        "pass",
+        false,
+        false,
+        &ReverseSyncOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 3);
+    assert_eq!(stats.uploaded, 1);
+}
+
+#[tokio::test]
+async fn reverse_sync_gives_up_after_three_feed_errors() {
+    let (feed_addr, hits) = start_flaky_feed(usize::MAX, String::new()).await;
+    let (_, caldav_addr) = start_reverse_sync_mocks(&[], StatusCode::CREATED).await;
+
+    let err = run_reverse_sync(
+        &format!("http://{}/feed.ics", feed_addr),
+        &format!("http://{}/dav/calendars", caldav_addr),
+        "personal",
+        "user",
+        "pass",
+        false,
+        false,
+        &ReverseSyncOptions::default(),
+    )
+    .await
+    .unwrap_err();
+
+    assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 3);
+    assert!(format!("{:#}", err).contains("502"), "{:#}", err);
+}
+
+#[tokio::test]
+async fn reverse_sync_handles_double_calendar_path() {
+    // caldav_url already ends with the calendar name
+    let events = [("uid-d1", "Double", "20270701T080000Z", "20270701T090000Z")];
+    let (ics_addr, caldav_addr) = start_reverse_sync_mocks(&events, StatusCode::CREATED).await;
+
+    let stats = run_reverse_sync(
+        &format!("http://{}/feed.ics", ics_addr),
+        &format!("http://{}/dav/calendars/personal", caldav_addr),
+        "personal",
+        "user",
+        "pass",
+        false,
+        false,
+        &ReverseSyncOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.uploaded, 1);
+    assert_eq!(stats.total, 1);
+}
+
+#[tokio::test]
+async fn reverse_sync_reports_correct_uploaded_count() {
+    // 204 No Content is also a success status
+    let events = [
+        ("uid-c1", "Count1", "20270801T080000Z", "20270801T090000Z"),
+        ("uid-c2", "Count2", "20270801T100000Z", "20270801T110000Z"),
+        ("uid-c3", "Count3", "20270801T120000Z", "20270801T130000Z"),
+    ];
+    let (ics_addr, caldav_addr) = start_reverse_sync_mocks(&events, StatusCode::NO_CONTENT).await;
+
+    let stats = run_reverse_sync(
+        &format!("http://{}/feed.ics", ics_addr),
+        &format!("http://{}/dav/", caldav_addr),
+        "work",
+        "user",
+        "pass",
+        false,
+        false,
+        &ReverseSyncOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.uploaded, 3);
+    assert_eq!(stats.total, 3);
+}
+
+#[tokio::test]
+async fn reverse_sync_returns_error_when_uploads_fail() {
+    let events = [("uid-fail", "Fail", "20270901T080000Z", "20270901T090000Z")];
+    let (ics_addr, caldav_addr) =
+        start_reverse_sync_mocks(&events, StatusCode::INTERNAL_SERVER_ERROR).await;
+
+    let result = run_reverse_sync(
+        &format!("http://{}/feed.ics", ics_addr),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
+        "user",
+        "pass",
+        false,
+        false,
+        &ReverseSyncOptions::default(),
+    )
+    .await;
+
+    assert!(result.is_err());
+    let err_msg = result.unwrap_err().to_string();
+    assert!(
+        err_msg.contains("failed"),
+        "Expected failure message, got: {err_msg}"
+    );
+}
+
+#[tokio::test]
+async fn reverse_sync_skips_unchanged_events() {
+    let events = [
+        (
+            "uid-same",
+            "Same Event",
+            "20270601T080000Z",
+            "20270601T090000Z",
+        ),
+        (
+            "uid-new",
+            "New Event",
+            "20270601T100000Z",
+            "20270601T110000Z",
+        ),
+    ];
+    let ics_feed = mock_ics_feed(&events);
+
+    // ICS feed server
+    let ics_state = std::sync::Arc::new(MockState {
+        propfind_body: String::new(),
+        report_body: ics_feed,
+        put_status: StatusCode::OK,
+    });
+    let ics_addr = start_mock_server(ics_state).await;
+
+    // CalDAV server that already has uid-same (returned via REPORT)
+    let existing = [(
+        "uid-same",
+        "Same Event",
+        "20270601T080000Z",
+        "20270601T090000Z",
+    )];
+    let caldav_state = std::sync::Arc::new(MockState {
+        propfind_body: String::new(),
+        report_body: mock_report_response(&existing),
+        put_status: StatusCode::CREATED,
+    });
+    let caldav_addr = start_mock_server(caldav_state).await;
+
+    let stats = run_reverse_sync(
+        &format!("http://{}/feed.ics", ics_addr),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
+        "user",
+        "pass",
+        false,
+        false,
+        &ReverseSyncOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.total, 2);
+    assert_eq!(stats.skipped, 1, "uid-same should be skipped");
+    assert_eq!(stats.uploaded, 1, "only uid-new should be uploaded");
+    assert_eq!(stats.deleted, 0);
+}
+
+/// CalDAV server whose calendar holds `existing` `(uid, extra property
+/// line)` events; records PUT bodies and DELETE paths.
+async fn start_marked_caldav_server(
+    existing: &[(&str, &str)],
+) -> (SocketAddr, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+    let objects: Vec<(&str, String)> = existing
+        .iter()
+        .map(|(uid, extra)| (*uid, stored_object("", uid, extra)))
+        .collect();
+    start_caldav_objects_server(&objects).await
+}
+
+/// A stored calendar object with one event; `prodid` may be empty.
+fn stored_object(prodid: &str, uid: &str, extra: &str) -> String {
+    let prodid = if prodid.is_empty() {
+        String::new()
+    } else {
+        format!("PRODID:{prodid}\n")
+    };
+    format!(
+        "BEGIN:VCALENDAR\n{prodid}BEGIN:VEVENT\nUID:{uid}\nSUMMARY:{uid}\nDTSTART:20270601T080000Z\n{extra}\nEND:VEVENT\nEND:VCALENDAR"
+    )
+}
+
+/// CalDAV server holding `(uid, calendar-data)` objects at `/dav/cal/<uid>.ics`;
+/// records PUT bodies and DELETE paths.
+async fn start_caldav_objects_server(
+    objects: &[(&str, String)],
+) -> (SocketAddr, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+    let mut responses = String::new();
+    for (uid, data) in objects {
+        responses.push_str(&format!(
+            r#"<d:response>
+  <d:href>/dav/cal/{uid}.ics</d:href>
+  <d:propstat>
+    <d:prop>
+      <c:calendar-data>{data}</c:calendar-data>
+    </d:prop>
+  </d:propstat>
+</d:response>"#,
+        ));
+    }
+    let report = format!(
+        r#"<?xml version="1.0" encoding="utf-8" ?>
+<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">{responses}</d:multistatus>"#,
+    );
+    let log = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen = log.clone();
+    let app = Router::new().fallback(any(move |req: Request<Body>| {
+        let (report, seen) = (report.clone(), seen.clone());
+        async move {
+            let (method, path) = (req.method().to_string(), req.uri().path().to_string());
+            let body = axum::body::to_bytes(req.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            match method.as_str() {
+                "REPORT" => (StatusCode::MULTI_STATUS, report).into_response(),
+                "PUT" => {
+                    seen.lock().unwrap().push(format!(
+                        "PUT {}\n{}",
+                        path,
+                        String::from_utf8_lossy(&body)
+                    ));
+                    StatusCode::CREATED.into_response()
+                }
+                "DELETE" => {
+                    seen.lock().unwrap().push(format!("DELETE {}", path));
+                    StatusCode::NO_CONTENT.into_response()
+                }
+                _ => StatusCode::METHOD_NOT_ALLOWED.into_response(),
+            }
+        }
+    }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    (addr, log)
+}
+
+#[tokio::test]
+async fn reverse_sync_only_deletes_events_with_own_origin_marker() {
+    let feed = mock_ics_feed(&[("fresh", "Fresh", "20270601T080000Z", "20270601T090000Z")]);
+    let ics_addr = start_mock_server(std::sync::Arc::new(MockState {
+        propfind_body: String::new(),
+        report_body: feed,
+        put_status: StatusCode::OK,
+    }))
+    .await;
+    let (caldav_addr, log) = start_marked_caldav_server(&[
+        ("ours", "X-SYNC-ORIGIN:7"),
+        ("manual", "DESCRIPTION:made by hand"),
+        ("other-tool", "X-SYNC-ORIGIN:8"),
+    ])
+    .await;
+
+    let opts = ReverseSyncOptions {
+        origin: Some("7".into()),
+        ..Default::default()
+    };
+    let stats = run_reverse_sync(
+        &format!("http://{}/feed.ics", ics_addr),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
+        "user",
+        "pass",
+        true,
+        false,
+        &opts,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.uploaded, 1);
+    assert_eq!(stats.deleted, 1);
+    let log = log.lock().unwrap().clone();
+    let deletes: Vec<&String> = log.iter().filter(|l| l.starts_with("DELETE")).collect();
+    assert_eq!(deletes, ["DELETE /dav/cal/ours.ics"]);
+    let put = log.iter().find(|l| l.starts_with("PUT")).unwrap();
+    assert!(put.starts_with("PUT /dav/cal/fresh.ics"));
+    assert!(put.contains("X-SYNC-ORIGIN:7\r\nEND:VEVENT"));
+}
+
+#[tokio::test]
+async fn reverse_sync_deletes_unmarked_events_we_uploaded_before_markers() {
+    let feed = start_feed(&[("kept", "Kept", "20270601T080000Z", "20270601T090000Z")]).await;
+    let (caldav_addr, log) = start_caldav_objects_server(&[
+        ("kept", stored_object("-//CalDAV/ICS Sync//EN", "kept", "")),
+        // Uploaded by an older version, before X-SYNC-ORIGIN existed.
+        (
+            "pre-upgrade",
+            stored_object("-//CalDAV/ICS Sync//EN", "pre-upgrade", ""),
+        ),
+        (
+            "other-dest",
+            stored_object("-//CalDAV/ICS Sync//EN", "other-dest", "X-SYNC-ORIGIN:8"),
+        ),
+        ("manual", stored_object("-//Apple Inc.//EN", "manual", "")),
+    ])
+    .await;
+
+    let stats = run_reverse_sync(
+        &format!("http://{feed}/feed.ics"),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
+        "user",
+        "pass",
+        true,
+        false,
+        &ReverseSyncOptions {
+            origin: Some("7".into()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.deleted, 1);
+    let log = log.lock().unwrap().clone();
+    let deletes: Vec<&String> = log.iter().filter(|l| l.starts_with("DELETE")).collect();
+    assert_eq!(deletes, ["DELETE /dav/cal/pre-upgrade.ics"]);
+}
+
+#[tokio::test]
+async fn reverse_sync_skips_unchanged_marked_events() {
+    let feed = mock_ics_feed(&[("ours", "ours", "20270601T080000Z", "20270601T090000Z")]);
+    let ics_addr = start_mock_server(std::sync::Arc::new(MockState {
+        propfind_body: String::new(),
+        report_body: feed,
+        put_status: StatusCode::OK,
+    }))
+    .await;
+    let (caldav_addr, log) =
+        start_marked_caldav_server(&[("ours", "DTEND:20270601T090000Z\nX-SYNC-ORIGIN:7")]).await;
+
+    let opts = ReverseSyncOptions {
+        origin: Some("7".into()),
+        ..Default::default()
+    };
+    let stats = run_reverse_sync(
+        &format!("http://{}/feed.ics", ics_addr),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
+        "user",
+        "pass",
+        true,
+        false,
+        &opts,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.skipped, 1);
+    assert_eq!(stats.uploaded, 0);
+    assert!(log.lock().unwrap().is_empty());
+}
+
+// ---------------------------------------------------------------------------
+// ICS feed redirect tests
+// ---------------------------------------------------------------------------
+
+/// Serves `/feed.ics` only to requests carrying Basic auth for `user:pass`.
+async fn start_authed_feed_server(ics_feed: String) -> SocketAddr {
+    let app = Router::new().route(
+        "/feed.ics",
+        axum::routing::get(move |req: Request<Body>| {
+            let feed = ics_feed.clone();
+            async move {
+                let expected = format!(
+                    "Basic {}",
+                    base64::Engine::encode(&base64::engine::general_purpose::STANDARD, "user:pass")
+                );
+                match req.headers().get(header::AUTHORIZATION) {
+                    Some(v) if v.to_str().unwrap_or("") == expected => {
+                        (StatusCode::OK, feed).into_response()
+                    }
+                    _ => (StatusCode::UNAUTHORIZED, "").into_response(),
+                }
+            }
+        }),
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    addr
+}
+
+/// Serves a 302 chain: `/start` -> `/hop` -> `{target}`.
+async fn start_redirect_server(target: String) -> SocketAddr {
+    let app = Router::new()
+        .route(
+            "/start",
+            axum::routing::get(|| async {
+                (StatusCode::FOUND, [(header::LOCATION, "/hop")]).into_response()
+            }),
+        )
+        .route(
+            "/hop",
+            axum::routing::get(move || {
+                let target = target.clone();
+                async move { (StatusCode::FOUND, [(header::LOCATION, target)]).into_response() }
+            }),
+        );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    addr
+}
+
+async fn redirect_fixture() -> (SocketAddr, SocketAddr) {
+    let events = [(
+        "uid-redir",
+        "Redirected",
+        "20270601T080000Z",
+        "20270601T090000Z",
+    )];
+    let (ics_addr, caldav_addr) = start_reverse_sync_mocks(&events, StatusCode::CREATED).await;
+    let redirect_addr = start_redirect_server(format!("http://{}/feed.ics", ics_addr)).await;
+    (redirect_addr, caldav_addr)
+}
+
+#[tokio::test]
+async fn reverse_sync_follows_302_chain() {
+    let (redirect_addr, caldav_addr) = redirect_fixture().await;
+
+    let stats = run_reverse_sync(
+        &format!("http://{}/start", redirect_addr),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
+        "user",
+        "pass",
+        false,
+        false,
+        &ReverseSyncOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.uploaded, 1);
+    assert_eq!(stats.total, 1);
+}
+
+#[tokio::test]
+async fn reverse_sync_rejects_redirect_when_disabled() {
+    let (redirect_addr, caldav_addr) = redirect_fixture().await;
+
+    let result = run_reverse_sync(
+        &format!("http://{}/start", redirect_addr),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
+        "user",
+        "pass",
+        false,
+        false,
+        &ReverseSyncOptions {
+            allow_redirects: false,
+            ..Default::default()
+        },
+    )
+    .await;
+
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("redirects are disabled"), "got: {err}");
+}
+
+#[tokio::test]
+async fn reverse_sync_enforces_max_redirects() {
+    let (redirect_addr, caldav_addr) = redirect_fixture().await;
+
+    let result = run_reverse_sync(
+        &format!("http://{}/start", redirect_addr),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
+        "user",
+        "pass",
+        false,
+        false,
+        &ReverseSyncOptions {
+            max_redirects: 1,
+            ..Default::default()
+        },
+    )
+    .await;
+
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("limit of 1 redirects"), "got: {err}");
+}
+
+#[tokio::test]
+async fn reverse_sync_strips_auth_on_cross_host_redirect_by_default() {
+    let feed = mock_ics_feed(&[("uid-auth", "Authed", "20270601T080000Z", "20270601T090000Z")]);
+    let feed_addr = start_authed_feed_server(feed).await;
+    let redirect_addr = start_redirect_server(format!("http://{}/feed.ics", feed_addr)).await;
+    let (_, caldav_addr) = start_reverse_sync_mocks(&[], StatusCode::CREATED).await;
+
+    let err = run_reverse_sync(
+        &format!("http://user:pass@{}/start", redirect_addr),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
+        "user",
+        "pass",
+        false,
+        false,
+        &ReverseSyncOptions::default(),
+    )
+    .await
+    .unwrap_err();
+
+    // The feed server rejects the stripped request.
+    assert!(err.to_string().contains("401"), "got: {err}");
+}
+
+#[tokio::test]
+async fn reverse_sync_forwards_auth_cross_host_when_enabled() {
+    let feed = mock_ics_feed(&[("uid-auth", "Authed", "20270601T080000Z", "20270601T090000Z")]);
+    let feed_addr = start_authed_feed_server(feed).await;
+    let redirect_addr = start_redirect_server(format!("http://{}/feed.ics", feed_addr)).await;
+    let (_, caldav_addr) = start_reverse_sync_mocks(&[], StatusCode::CREATED).await;
+
+    let stats = run_reverse_sync(
+        &format!("http://user:pass@{}/start", redirect_addr),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
+        "user",
+        "pass",
+        false,
+        false,
+        &ReverseSyncOptions {
+            redirect_cross_host_headers: true,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.uploaded, 1);
+}
+
+#[tokio::test]
+async fn reverse_sync_drops_duplicate_uid_and_reports_conflict() {
+    let events = [
+        ("uid-dup", "First", "20270601T080000Z", "20270601T090000Z"),
+        ("uid-dup", "Second", "20270601T080000Z", "20270601T090000Z"),
+        ("uid-ok", "Other", "20270602T080000Z", "20270602T090000Z"),
+    ];
+    let (ics_addr, caldav_addr) = start_reverse_sync_mocks(&events, StatusCode::CREATED).await;
+
+    let stats = run_reverse_sync(
+        &format!("http://{}/feed.ics", ics_addr),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
+        "user",
+        "pass",
+        false,
+        true,
+        &ReverseSyncOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.conflicts, 1);
+    assert_eq!(stats.uploaded, 2);
+    assert_eq!(stats.total, 2);
+}
+
+#[tokio::test]
+async fn reverse_sync_reports_unparseable_dates() {
+    let events = [
+        ("uid-ok", "Fine", "20270601T080000Z", "20270601T090000Z"),
+        ("uid-bad", "Broken", "June 1st", "June 2nd"),
+    ];
+    let (ics_addr, caldav_addr) = start_reverse_sync_mocks(&events, StatusCode::CREATED).await;
+
+    let stats = run_reverse_sync(
+        &format!("http://{}/feed.ics", ics_addr),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
+        "user",
+        "pass",
+        false,
+        true,
+        &ReverseSyncOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    // Still uploaded, as an undated event might be upcoming.
+    assert_eq!(stats.uploaded, 2);
+    assert_eq!(stats.unparseable_dates.len(), 1);
+    assert_eq!(stats.unparseable_dates[0].uid, "uid-bad");
+    assert!(stats.unparseable_dates[0].reason.contains("June 2nd"));
+}
+
+// ---------------------------------------------------------------------------
+// DTSTAMP injection tests
+// ---------------------------------------------------------------------------
+
+type Captured = std::sync::Arc<std::sync::Mutex<Vec<(String, String)>>>;
+
+/// CalDAV server with an empty calendar that records the method and body of
+/// every PUT and PROPPATCH.
+async fn start_capturing_caldav() -> (SocketAddr, Captured) {
+    let requests: Captured = std::sync::Arc::default();
+    let captured = std::sync::Arc::clone(&requests);
+    let app = Router::new().fallback(any(move |req: Request<Body>| {
+        let captured = std::sync::Arc::clone(&captured);
+        async move {
+            let method = req.method().to_string();
+            let status = match method.as_str() {
+                "REPORT" => {
+                    return (StatusCode::MULTI_STATUS, mock_report_response(&[])).into_response();
+                }
+                "PUT" => StatusCode::CREATED,
+                "PROPPATCH" => StatusCode::MULTI_STATUS,
+                _ => return (StatusCode::METHOD_NOT_ALLOWED, "").into_response(),
+            };
+            let bytes = axum::body::to_bytes(req.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            captured
+                .lock()
+                .unwrap()
+                .push((method, String::from_utf8_lossy(&bytes).into_owned()));
+            (status, "").into_response()
+        }
+    }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    (addr, requests)
+}
+
+#[tokio::test]
+async fn reverse_sync_injects_missing_dtstamp() {
+    let (ics_addr, _) = start_reverse_sync_mocks(
+        &[("uid-1", "Standup", "20270601T080000Z", "20270601T090000Z")],
+        StatusCode::CREATED,
+    )
+    .await;
+    let (caldav_addr, requests) = start_capturing_caldav().await;
+
+    run_reverse_sync(
+        &format!("http://{}/feed.ics", ics_addr),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
+        "user",
+        "pass",
+        false,
+        true,
+        &ReverseSyncOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].0, "PUT");
+    let stamp = requests[0]
+        .1
+        .lines()
+        .find_map(|l| l.strip_prefix("DTSTAMP:"))
+        .expect("uploaded event should carry DTSTAMP");
+    assert!(chrono::NaiveDateTime::parse_from_str(stamp, "%Y%m%dT%H%M%SZ").is_ok());
+}
+
+// ---------------------------------------------------------------------------
+// Display name PROPPATCH tests
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn reverse_sync_proppatches_display_name_when_enabled() {
+    let (ics_addr, _) = start_reverse_sync_mocks(
+        &[("uid-1", "Standup", "20270601T080000Z", "20270601T090000Z")],
+        StatusCode::CREATED,
+    )
+    .await;
+    let (caldav_addr, requests) = start_capturing_caldav().await;
+
+    let opts = ReverseSyncOptions {
+        set_display_name: true,
+        ..Default::default()
+    };
+    run_reverse_sync(
+        &format!("http://{}/feed.ics", ics_addr),
+        &format!("http://{}/dav/", caldav_addr),
+        "Team & Co",
+        "user",
+        "pass",
+        false,
+        true,
+        &opts,
+    )
+    .await
+    .unwrap();
+
+    let requests = requests.lock().unwrap();
+    let (_, body) = requests
+        .iter()
+        .find(|(method, _)| method == "PROPPATCH")
+        .expect("PROPPATCH should be sent");
+    assert!(body.contains("<d:displayname>Team &amp; Co</d:displayname>"));
+}
+
+#[tokio::test]
+async fn reverse_sync_continues_when_proppatch_rejected() {
+    let events = [("uid-1", "Standup", "20270601T080000Z", "20270601T090000Z")];
+    // The plain mock answers PROPPATCH with 405.
+    let (ics_addr, caldav_addr) = start_reverse_sync_mocks(&events, StatusCode::CREATED).await;
+
+    let opts = ReverseSyncOptions {
+        set_display_name: true,
+        ..Default::default()
+    };
+    let stats = run_reverse_sync(
+        &format!("http://{}/feed.ics", ics_addr),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
+        "user",
+        "pass",
+        false,
+        true,
+        &opts,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.uploaded, 1);
+}
+
+// ---------------------------------------------------------------------------
+// Quota (507) tests
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn reverse_sync_stops_on_insufficient_storage() {
+    let events = [
+        ("uid-1", "One", "20270601T080000Z", "20270601T090000Z"),
+        ("uid-2", "Two", "20270602T080000Z", "20270602T090000Z"),
+    ];
+    let (ics_addr, caldav_addr) =
+        start_reverse_sync_mocks(&events, StatusCode::INSUFFICIENT_STORAGE).await;
+
+    let err = run_reverse_sync(
+        &format!("http://{}/feed.ics", ics_addr),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
+        "user",
+        "pass",
+        false,
+        true,
+        &ReverseSyncOptions::default(),
+    )
+    .await
+    .unwrap_err();
+
+    assert_eq!(err.downcast_ref::<QuotaExceeded>().unwrap().uploaded, 0);
+}
+
+#[tokio::test]
+async fn auto_sync_marks_quota_exceeded_without_retrying() {
+    let (ics_addr, caldav_addr) = start_reverse_sync_mocks(
+        &[("uid-1", "One", "20270601T080000Z", "20270601T090000Z")],
+        StatusCode::INSUFFICIENT_STORAGE,
+    )
+    .await;
+    let state = AppState::new(db::open_memory_pool().unwrap());
+    let dest: db::CreateDestination = serde_json::from_value(serde_json::json!({
+        "name": "Full",
+        "ics_url": format!("http://{}/feed.ics", ics_addr),
+        "caldav_url": format!("http://{}/dav/", caldav_addr),
+        "calendar_name": "cal",
+        "username": "user",
+        "password": "pass",
+        "sync_interval_secs": 3600,
+        "keep_local": true
+    }))
+    .unwrap();
+    let id = db::create_destination(&state.conn().unwrap(), &dest).unwrap();
+    let dest = db::get_destination(&state.conn().unwrap(), id)
+        .unwrap()
+        .unwrap();
+
+    auto_sync::register_destination(&state.sync_tasks, &state, &dest);
+
+    // A transient error would back off for 30s before the status is written.
+    let stored = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        loop {
+            let d = db::get_destination(&state.conn().unwrap(), id)
+                .unwrap()
+                .unwrap();
+            if d.last_sync_status.as_deref() == Some("error") {
+                return d;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("quota error should not be retried");
+    state.shutdown.cancel();
+
+    assert!(
+        stored
+            .last_sync_error
+            .unwrap()
+            .contains("quota exceeded (507 Insufficient Storage)")
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn auto_sync_reports_retry_exhaustion_once_per_failure_run() {
+    let state = AppState::new(db::open_memory_pool().unwrap());
+    let mut events = state.events.subscribe();
+    let dest: db::CreateDestination = serde_json::from_value(serde_json::json!({
+        "name": "Unreachable",
+        "ics_url": "http://127.0.0.1:9/feed.ics",
+        "caldav_url": "http://127.0.0.1:9/dav/",
+        "calendar_name": "cal",
+        "username": "user",
+        "password": "pass",
+        "sync_interval_secs": 3600
+    }))
+    .unwrap();
+    let id = db::create_destination(&state.conn().unwrap(), &dest).unwrap();
+    let dest = db::get_destination(&state.conn().unwrap(), id)
+        .unwrap()
+        .unwrap();
+
+    auto_sync::register_destination(&state.sync_tasks, &state, &dest);
+
+    // Two scheduled runs, each failing every attempt.
+    let mut statuses = Vec::new();
+    let mut exhausted = Vec::new();
+    while statuses.iter().filter(|s| *s == "error").count() < 2 {
+        let payload = events.recv().await.unwrap();
+        if payload.status == webhooks::RETRIES_EXHAUSTED {
+            exhausted.push(payload.clone());
+        }
+        statuses.push(payload.status);
+    }
+    state.shutdown.cancel();
+
+    assert_eq!(exhausted.len(), 1, "{:?}", statuses);
+    assert_eq!(exhausted[0].kind, "destination");
+    assert_eq!(exhausted[0].id, id);
+    assert_eq!(
+        exhausted[0].counts["consecutive_failures"],
+        auto_sync::MAX_RETRIES + 1
+    );
+    assert!(exhausted[0].error.is_some());
+}
+
+#[tokio::test(start_paused = true)]
+async fn auto_sync_disables_source_after_failure_threshold() {
+    let mut state = AppState::new(db::open_memory_pool().unwrap());
+    state.auto_disable_after_failures = 2;
+    let source: db::CreateSource = serde_json::from_value(serde_json::json!({
+        "name": "Unreachable",
+        "caldav_url": "http://127.0.0.1:9/dav/",
+        "username": "user",
+        "password": "pass",
+        "ics_path": "unreachable.ics",
+        "sync_interval_secs": 3600
+    }))
+    .unwrap();
+    let id = db::create_source(&state.conn().unwrap(), &source).unwrap();
+    let source = db::get_source(&state.conn().unwrap(), id).unwrap().unwrap();
+
+    auto_sync::register_source(&state.sync_tasks, &state, &source);
+
+    let stored = loop {
+        let s = db::get_source(&state.conn().unwrap(), id).unwrap().unwrap();
+        if !s.enabled {
+            break s;
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+    };
+    state.shutdown.cancel();
+
+    assert_eq!(stored.consecutive_failures, 2);
+    assert_eq!(stored.last_sync_status.as_deref(), Some("error"));
+    assert!(state.sync_tasks.lock().unwrap().is_empty());
+    // One history row per failed run, not one per retry attempt.
+    assert_eq!(
+        db::count_sync_history(&state.conn().unwrap(), id, None).unwrap(),
+        2
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn auto_sync_cron_schedule_waits_for_its_first_fire_time() {
+    use caldav_ics_sync::auto_sync::AutoSyncKey;
+
+    let state = AppState::new(db::open_memory_pool().unwrap());
+    let source: db::CreateSource = serde_json::from_value(serde_json::json!({
+        "name": "Nightly",
+        "caldav_url": "http://127.0.0.1:9/dav/",
+        "username": "user",
+        "password": "pass",
+        "ics_path": "nightly.ics",
+        "sync_interval_secs": 0,
+        "cron_schedule": "0 0 1 1 *"
+    }))
+    .unwrap();
+    let id = db::create_source(&state.conn().unwrap(), &source).unwrap();
+    let source = db::get_source(&state.conn().unwrap(), id).unwrap().unwrap();
+
+    auto_sync::register_source(&state.sync_tasks, &state, &source);
+    assert!(
+        state
+            .sync_tasks
+            .lock()
+            .unwrap()
+            .contains_key(&AutoSyncKey::Source(id))
+    );
+
+    // An interval source would have attempted (and failed) a sync by now.
+    tokio::time::sleep(std::time::Duration::from_secs(600)).await;
+    let stored = db::get_source(&state.conn().unwrap(), id).unwrap().unwrap();
+    assert!(stored.last_sync_status.is_none());
+    state.shutdown.cancel();
+}
+
+// ---------------------------------------------------------------------------
+// match_by tests
+// ---------------------------------------------------------------------------
+
+/// Feed carries `uid-new`; the CalDAV server already has the same event
+/// stored as `uid-old`.
+async fn start_renamed_uid_mocks() -> (SocketAddr, SocketAddr) {
+    let ics_state = std::sync::Arc::new(MockState {
+        propfind_body: String::new(),
+        report_body: mock_ics_feed(&[(
+            "uid-new",
+            "Team Sync",
+            "20270601T080000Z",
+            "20270601T090000Z",
+        )]),
+        put_status: StatusCode::OK,
+    });
+    let caldav_state = std::sync::Arc::new(MockState {
+        propfind_body: String::new(),
+        report_body: mock_report_response(&[(
+            "uid-old",
+            "Team Sync",
+            "20270601T080000Z",
+            "20270601T090000Z",
+        )]),
+        put_status: StatusCode::CREATED,
+    });
+    (
+        start_mock_server(ics_state).await,
+        start_mock_server(caldav_state).await,
+    )
+}
+
+#[tokio::test]
+async fn reverse_sync_match_by_uid_uploads_renamed_event() {
+    let (ics_addr, caldav_addr) = start_renamed_uid_mocks().await;
+
+    let stats = run_reverse_sync(
+        &format!("http://{}/feed.ics", ics_addr),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
+        "user",
+        "pass",
+        false,
+        true,
+        &ReverseSyncOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.uploaded, 1);
+    assert_eq!(stats.skipped, 0);
+}
+
+#[tokio::test]
+async fn reverse_sync_match_by_uid_and_dtstart_skips_renamed_event() {
+    let (ics_addr, caldav_addr) = start_renamed_uid_mocks().await;
+
+    let stats = run_reverse_sync(
+        &format!("http://{}/feed.ics", ics_addr),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
+        "user",
+        "pass",
+        false,
+        true,
+        &ReverseSyncOptions {
+            match_by: MatchBy::UidAndDtstart,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.uploaded, 0);
+    assert_eq!(stats.skipped, 1);
+}
+
+// ---------------------------------------------------------------------------
+// Active sync tracking
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn active_syncs_counts_guards() {
+    let active = ActiveSyncs::default();
+    let a = active.start();
+    let b = active.start();
+    assert_eq!(active.count(), 2);
+    drop(a);
+    assert_eq!(active.count(), 1);
+    drop(b);
+    assert_eq!(active.count(), 0);
+}
+
+#[tokio::test]
+async fn active_syncs_wait_idle_returns_when_last_guard_drops() {
+    let active = ActiveSyncs::default();
+    let guard = active.start();
+    tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        drop(guard);
+    });
+
+    tokio::time::timeout(std::time::Duration::from_secs(2), active.wait_idle())
+        .await
+        .expect("wait_idle should complete once the guard is dropped");
+    assert_eq!(active.count(), 0);
+}
+
+#[tokio::test]
+async fn active_syncs_wait_idle_times_out_while_busy() {
+    let active = ActiveSyncs::default();
+    let _guard = active.start();
+
+    let result =
+        tokio::time::timeout(std::time::Duration::from_millis(50), active.wait_idle()).await;
+    assert!(result.is_err());
+}
+
+// ---------------------------------------------------------------------------
+// TLS error hints
+// ---------------------------------------------------------------------------
+
+#[test]
+fn explain_tls_error_hints_at_allow_invalid_certs() {
+    let err = anyhow::anyhow!("invalid peer certificate: UnknownIssuer")
+        .context("Failed to fetch calendars");
+    let msg = explain_tls_error(err).to_string();
+    assert!(msg.starts_with("Failed to fetch calendars"));
+    assert!(msg.contains("allow_invalid_certs"));
+}
+
+#[test]
+fn explain_tls_error_leaves_other_errors_alone() {
+    let err = anyhow::anyhow!("connection refused").context("Failed to fetch calendars");
+    assert_eq!(
+        explain_tls_error(err).to_string(),
+        "Failed to fetch calendars"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Per-host serialization
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn host_locks_serialize_same_host() {
+    let locks = HostLocks::new(true);
+    let held = locks.acquire("https://dav.example.com/a/").await;
+    assert!(held.is_some());
+
+    let waiting = tokio::time::timeout(
+        std::time::Duration::from_millis(50),
+        locks.acquire("https://dav.example.com/b/"),
+    )
+    .await;
+    assert!(waiting.is_err(), "same host should wait for the held lock");
+
+    drop(held);
+    assert!(locks.acquire("https://dav.example.com/b/").await.is_some());
+}
+
+#[tokio::test]
+async fn host_locks_allow_different_hosts() {
+    let locks = HostLocks::new(true);
+    let _a = locks.acquire("https://one.example.com/").await;
+    let b = tokio::time::timeout(
+        std::time::Duration::from_millis(50),
+        locks.acquire("https://two.example.com/"),
+    )
+    .await;
+    assert!(b.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn host_locks_disabled_never_block() {
+    let locks = HostLocks::default();
+    let a = locks.acquire("https://dav.example.com/").await;
+    let b = locks.acquire("https://dav.example.com/").await;
+    assert!(a.is_none() && b.is_none());
+}
+
+// ---------------------------------------------------------------------------
+// Several feeds per destination
+// ---------------------------------------------------------------------------
+
+async fn start_feed(events: &[(&str, &str, &str, &str)]) -> SocketAddr {
+    start_mock_server(std::sync::Arc::new(MockState {
+        propfind_body: String::new(),
+        report_body: mock_ics_feed(events),
+        put_status: StatusCode::OK,
+    }))
+    .await
+}
+
+#[tokio::test]
+async fn reverse_sync_merges_disjoint_feeds_and_deletes_against_the_union() {
+    let feed_a = start_feed(&[("a1", "A", "20270601T080000Z", "20270601T090000Z")]).await;
+    let feed_b = start_feed(&[("b1", "B", "20270602T080000Z", "20270602T090000Z")]).await;
+    let (caldav_addr, log) =
+        start_marked_caldav_server(&[("b1", "X-SYNC-ORIGIN:7"), ("gone", "X-SYNC-ORIGIN:7")]).await;
+
+    let stats = run_reverse_sync(
+        &format!("http://{feed_a}/a.ics\nhttp://{feed_b}/b.ics"),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
+        "user",
+        "pass",
+        true,
+        false,
+        &ReverseSyncOptions {
+            origin: Some("7".into()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.total, 2);
+    assert_eq!(stats.deleted, 1);
+    let log = log.lock().unwrap().clone();
+    let deletes: Vec<&String> = log.iter().filter(|l| l.starts_with("DELETE")).collect();
+    assert_eq!(deletes, ["DELETE /dav/cal/gone.ics"]);
+    assert!(log.iter().any(|l| l.starts_with("PUT /dav/cal/a1.ics")));
+}
+
+#[tokio::test]
+async fn reverse_sync_dedups_overlapping_feeds_by_uid() {
+    let feed_a = start_feed(&[
+        ("shared", "From A", "20270601T080000Z", "20270601T090000Z"),
+        ("a1", "A", "20270601T100000Z", "20270601T110000Z"),
+    ])
+    .await;
+    let feed_b = start_feed(&[
+        ("shared", "From B", "20270601T080000Z", "20270601T090000Z"),
+        ("b1", "B", "20270601T120000Z", "20270601T130000Z"),
+    ])
+    .await;
+    let (caldav_addr, log) = start_marked_caldav_server(&[]).await;
+
+    let stats = run_reverse_sync(
+        &format!("http://{feed_a}/a.ics, http://{feed_b}/b.ics"),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
+        "user",
+        "pass",
+        true,
+        false,
+        &ReverseSyncOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.total, 3);
+    assert_eq!(stats.uploaded, 3);
+    assert_eq!(stats.conflicts, 1);
+    let log = log.lock().unwrap().clone();
+    let shared = log
+        .iter()
+        .find(|l| l.starts_with("PUT /dav/cal/shared.ics"))
+        .unwrap();
+    assert!(shared.contains("SUMMARY:From A"));
+}
+
+/// Feed host whose `/missing.ics` is a 404 page and `/login.ics` an HTML
+/// page served with 200.
+async fn start_broken_feed_server() -> SocketAddr {
+    let app = Router::new()
+        .route(
+            "/missing.ics",
+            any(|| async { (StatusCode::NOT_FOUND, "<html>Not Found</html>") }),
+        )
+        .route(
+            "/login.ics",
+            any(|| async { (StatusCode::OK, "<html>Sign in</html>") }),
+        );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    addr
+}
+
+#[tokio::test]
+async fn reverse_sync_fails_when_any_feed_fails() {
+    let feed_a = start_feed(&[("a1", "A", "20270601T080000Z", "20270601T090000Z")]).await;
+    let broken = start_broken_feed_server().await;
+
+    for (failing, expected) in [
+        (
+            "http://127.0.0.1:1/b.ics".to_string(),
+            "Failed to fetch ICS file",
+        ),
+        (format!("http://{broken}/missing.ics"), "404"),
+        (
+            format!("http://{broken}/login.ics"),
+            "did not return a VCALENDAR",
+        ),
+    ] {
+        let (caldav_addr, log) = start_marked_caldav_server(&[("b1", "X-SYNC-ORIGIN:7")]).await;
+        let result = run_reverse_sync(
+            &format!("http://{feed_a}/a.ics\n{failing}"),
+            &format!("http://{}/dav/", caldav_addr),
+            "cal",
+            "user",
+            "pass",
+            true,
+            false,
+            &ReverseSyncOptions {
+                origin: Some("7".into()),
+                ..Default::default()
+            },
+        )
+        .await;
+
+        let err = format!("{:#}", result.unwrap_err());
+        assert!(err.contains(expected), "{failing}: {err}");
+        assert!(log.lock().unwrap().iter().all(|l| !l.starts_with("DELETE")));
+    }
+}
+
+#[test]
+fn split_ics_urls_accepts_newlines_and_commas() {
+    assert_eq!(
+        split_ics_urls(" https://a.example/x.ics,\nhttps://b.example/y.ics\r\n\n"),
+        ["https://a.example/x.ics", "https://b.example/y.ics"]
+    );
+    assert!(split_ics_urls(" , ").is_empty());
+}
+
+#[test]
+fn split_ics_urls_keeps_commas_inside_a_url() {
+    assert_eq!(
+        split_ics_urls("https://a.example/x.ics?a=1,2"),
+        ["https://a.example/x.ics?a=1,2"]
+    );
+    assert_eq!(
+        split_ics_urls("https://a.example/x.ics?a=1,2,https://b.example/y.ics?b=3,4"),
+        [
+            "https://a.example/x.ics?a=1,2",
+            "https://b.example/y.ics?b=3,4"
+        ]
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Extra feed request headers
+// ---------------------------------------------------------------------------
+
+/// Feed host that only serves the calendar to requests with the expected `Referer`.
+async fn start_referer_checking_feed() -> SocketAddr {
+    let feed = mock_ics_feed(&[("r1", "Ref", "20270601T080000Z", "20270601T090000Z")]);
+    let app = Router::new().fallback(any(move |req: Request<Body>| {
+        let feed = feed.clone();
+        async move {
+            let referer = req.headers().get("referer").and_then(|v| v.to_str().ok());
+            if referer == Some("https://provider.example/calendar") {
+                (StatusCode::OK, feed).into_response()
+            } else {
+                StatusCode::FORBIDDEN.into_response()
+            }
+        }
+    }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    addr
+}
+
+#[tokio::test]
+async fn reverse_sync_sends_extra_headers_to_the_feed() {
+    let ics_addr = start_referer_checking_feed().await;
+    let (caldav_addr, _) = start_minimal_put_server().await;
+    let sync = |extra_headers: std::collections::BTreeMap<String, String>| {
+        let (ics_url, caldav_url) = (
+            format!("http://{}/feed.ics", ics_addr),
+            format!("http://{}/dav/", caldav_addr),
+        );
+        async move {
+            run_reverse_sync(
+                &ics_url,
+                &caldav_url,
+                "cal",
+                "user",
+                "pass",
+                true,
+                true,
+                &ReverseSyncOptions {
+                    extra_headers,
+                    ..Default::default()
+                },
+            )
+            .await
+        }
+    };
+
+    // The host answers 403 without the header, so the sync fails before
+    // touching the calendar.
+    let err = sync(Default::default()).await.unwrap_err();
+    assert!(err.to_string().contains("403"), "got: {err}");
+
+    let headers = [(
+        "Referer".to_string(),
+        "https://provider.example/calendar".to_string(),
+    )]
+    .into();
+    let stats = sync(headers).await.unwrap();
+    assert_eq!(stats.uploaded, 1);
+}
+
+// ---------------------------------------------------------------------------
+// Shrink guard
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn reverse_sync_refuses_a_drastically_shrunken_feed() {
+    let ics_addr = start_feed(&[
+        ("a1", "A", "20270601T080000Z", "20270601T090000Z"),
+        ("a2", "B", "20270602T080000Z", "20270602T090000Z"),
+    ])
+    .await;
+    let (caldav_addr, log) = start_marked_caldav_server(&[("old", "X-SYNC-ORIGIN:7")]).await;
+    let sync = |max_shrink_percent| {
+        let (ics_url, caldav_url) = (
+            format!("http://{}/feed.ics", ics_addr),
+            format!("http://{}/dav/", caldav_addr),
+        );
+        async move {
+            run_reverse_sync(
+                &ics_url,
+                &caldav_url,
+                "cal",
+                "user",
+                "pass",
+                true,
+                false,
+                &ReverseSyncOptions {
+                    origin: Some("7".into()),
+                    previous_total: Some(10),
+                    max_shrink_percent,
+                    ..Default::default()
+                },
+            )
+            .await
+        }
+    };
+
+    let err = sync(50).await.unwrap_err();
+    let shrink = err.downcast_ref::<SuspiciousShrink>().unwrap();
+    assert_eq!((shrink.previous, shrink.current), (10, 2));
+    assert!(log.lock().unwrap().is_empty(), "nothing may be written");
+
+    let stats = sync(0).await.unwrap();
+    assert_eq!(stats.uploaded, 2);
+    assert_eq!(stats.deleted, 1);
 }
 
-// ---------------------------------------------------------------------------
-// run_reverse_sync tests
-// ---------------------------------------------------------------------------
+const SPECIAL_UID: &str = "team/standup@example.com";
 
-/// Helper: start a mock that serves an ICS feed on GET and accepts PUTs on
-/// a separate address. Returns (ics_server_addr, caldav_server_addr).
-async fn start_reverse_sync_mocks(
-    ics_events: &[(&str, &str, &str, &str)],
-    put_status: StatusCode,
-) -> (SocketAddr, SocketAddr) {
-    let ics_feed = mock_ics_feed(ics_events);
+#[tokio::test]
+async fn reverse_sync_percent_encodes_uid_in_event_href() {
+    let feed = start_feed(&[(
+        SPECIAL_UID,
+        "Standup",
+        "20270601T080000Z",
+        "20270601T090000Z",
+    )])
+    .await;
+    let (caldav_addr, log) = start_marked_caldav_server(&[]).await;
 
-    // ICS feed server (plain GET)
-    let ics_state = std::sync::Arc::new(MockState {
-        propfind_body: String::new(),
-        report_body: ics_feed,
-        put_status: StatusCode::OK,
-    });
-    let ics_addr = start_mock_server(ics_state).await;
+    for _ in 0..2 {
+        let stats = run_reverse_sync(
+            &format!("http://{feed}/feed.ics"),
+            &format!("http://{}/dav/", caldav_addr),
+            "cal",
+            "user",
+            "pass",
+            true,
+            true,
+            &ReverseSyncOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(stats.uploaded, 1);
+        assert!(stats.hrefs.is_none());
+    }
 
-    // CalDAV server (accepts PUT) — needs valid empty REPORT response
-    let caldav_state = std::sync::Arc::new(MockState {
-        propfind_body: String::new(),
-        report_body: mock_report_response(&[]),
-        put_status,
-    });
-    let caldav_addr = start_mock_server(caldav_state).await;
+    let log = log.lock().unwrap().clone();
+    let paths: Vec<&str> = log.iter().map(|l| l.lines().next().unwrap()).collect();
+    assert_eq!(
+        paths,
+        [
+            "PUT /dav/cal/team%2Fstandup%40example.com.ics",
+            "PUT /dav/cal/team%2Fstandup%40example.com.ics"
+        ]
+    );
+}
 
-    (ics_addr, caldav_addr)
+#[tokio::test]
+async fn reverse_sync_uuid_hrefs_reuses_stored_name() {
+    let feed = start_feed(&[(
+        SPECIAL_UID,
+        "Standup",
+        "20270601T080000Z",
+        "20270601T090000Z",
+    )])
+    .await;
+    let (caldav_addr, log) = start_marked_caldav_server(&[]).await;
+    let sync = |known_hrefs: HashMap<String, String>| {
+        let (feed, caldav_addr) = (feed, caldav_addr);
+        async move {
+            run_reverse_sync(
+                &format!("http://{feed}/feed.ics"),
+                &format!("http://{}/dav/", caldav_addr),
+                "cal",
+                "user",
+                "pass",
+                true,
+                true,
+                &ReverseSyncOptions {
+                    uuid_hrefs: true,
+                    known_hrefs,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap()
+        }
+    };
+
+    let first = sync(HashMap::new()).await.hrefs.unwrap();
+    let name = first[SPECIAL_UID].clone();
+    assert!(name.ends_with(".ics"));
+    let stem = name.trim_end_matches(".ics");
+    assert!(stem.len() == 36 && stem.chars().all(|c| c.is_ascii_hexdigit() || c == '-'));
+
+    let second = sync(first.into_iter().collect()).await.hrefs.unwrap();
+    assert_eq!(second[SPECIAL_UID], name);
+
+    let log = log.lock().unwrap().clone();
+    let expected = format!("PUT /dav/cal/{}", name);
+    assert_eq!(log.len(), 2);
+    assert!(
+        log.iter()
+            .all(|l| l.lines().next() == Some(expected.as_str()))
+    );
 }
 
 #[tokio::test]
-async fn reverse_sync_uploads_events() {
-    let events = [
-        ("uid-r1", "Rev1", "20270601T080000Z", "20270601T090000Z"),
-        ("uid-r2", "Rev2", "20270601T100000Z", "20270601T110000Z"),
-    ];
-    let (ics_addr, caldav_addr) = start_reverse_sync_mocks(&events, StatusCode::CREATED).await;
+async fn reverse_sync_uuid_hrefs_delete_uses_server_href() {
+    let feed = start_feed(&[("kept", "Kept", "20270601T080000Z", "20270601T090000Z")]).await;
+    let (caldav_addr, log) =
+        start_marked_caldav_server(&[("kept", "X-SYNC-ORIGIN:7"), ("gone", "X-SYNC-ORIGIN:7")])
+            .await;
 
     let stats = run_reverse_sync(
-        &format!("http://{}/feed.ics", ics_addr),
-        &format!("http://{}/dav/calendars", caldav_addr),
-        "personal",
+        &format!("http://{feed}/feed.ics"),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
         "user",
         "pass",
+        true,
         false,
-        false,
+        &ReverseSyncOptions {
+            origin: Some("7".into()),
+            uuid_hrefs: true,
+            known_hrefs: HashMap::from([("gone".to_string(), "stale-name.ics".to_string())]),
+            ..Default::default()
+        },
     )
     .await
     .unwrap();
 
-    assert_eq!(stats.uploaded, 2);
-    assert_eq!(stats.total, 2);
+    assert_eq!(stats.deleted, 1);
+    let hrefs = stats.hrefs.unwrap();
+    assert_eq!(hrefs.get("kept").map(String::as_str), Some("kept.ics"));
+    assert!(!hrefs.contains_key("gone"));
+    let log = log.lock().unwrap().clone();
+    assert!(log.contains(&"DELETE /dav/cal/gone.ics".to_string()));
+    assert!(log.iter().any(|l| l.starts_with("PUT /dav/cal/kept.ics")));
 }
 
 #[tokio::test]
-async fn reverse_sync_handles_double_calendar_path() {
-    // caldav_url already ends with the calendar name
-    let events = [("uid-d1", "Double", "20270701T080000Z", "20270701T090000Z")];
-    let (ics_addr, caldav_addr) = start_reverse_sync_mocks(&events, StatusCode::CREATED).await;
+async fn reverse_sync_delete_uses_server_href_for_legacy_names() {
+    // Written before hrefs were percent-encoded, so the '@' is stored raw.
+    let feed = start_feed(&[("kept", "Kept", "20270601T080000Z", "20270601T090000Z")]).await;
+    let (caldav_addr, log) = start_marked_caldav_server(&[
+        ("kept", "X-SYNC-ORIGIN:7"),
+        ("gone@example.com", "X-SYNC-ORIGIN:7"),
+    ])
+    .await;
 
     let stats = run_reverse_sync(
-        &format!("http://{}/feed.ics", ics_addr),
-        &format!("http://{}/dav/calendars/personal", caldav_addr),
-        "personal",
+        &format!("http://{feed}/feed.ics"),
+        &format!("http://{}/dav/", caldav_addr),
+        "cal",
         "user",
         "pass",
+        true,
         false,
-        false,
+        &ReverseSyncOptions {
+            origin: Some("7".into()),
+            ..Default::default()
+        },
     )
     .await
     .unwrap();
 
-    assert_eq!(stats.uploaded, 1);
-    assert_eq!(stats.total, 1);
+    assert_eq!(stats.deleted, 1);
+    let log = log.lock().unwrap().clone();
+    let deletes: Vec<&String> = log.iter().filter(|l| l.starts_with("DELETE")).collect();
+    assert_eq!(deletes, ["DELETE /dav/cal/gone@example.com.ics"]);
+}
+
+// ---------------------------------------------------------------------------
+// Subscription sources
+// ---------------------------------------------------------------------------
+
+/// Serves a feed whose single event's SUMMARY counts the requests so far.
+async fn start_counting_feed() -> SocketAddr {
+    let hits = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let app = Router::new().fallback(any(move || {
+        let n = hits.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+        async move {
+            mock_ics_feed(&[(
+                "sub-1",
+                &format!("Fetch {n}"),
+                "20270601T080000Z",
+                "20270601T090000Z",
+            )])
+        }
+    }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    addr
+}
+
+async fn send(router: &Router, req: axum::http::Request<Body>) -> (StatusCode, String) {
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+    let resp = router.clone().oneshot(req).await.unwrap();
+    let status = resp.status();
+    let bytes = resp.into_body().collect().await.unwrap().to_bytes();
+    (status, String::from_utf8(bytes.to_vec()).unwrap())
 }
 
 #[tokio::test]
-async fn reverse_sync_reports_correct_uploaded_count() {
-    // 204 No Content is also a success status
-    let events = [
-        ("uid-c1", "Count1", "20270801T080000Z", "20270801T090000Z"),
-        ("uid-c2", "Count2", "20270801T100000Z", "20270801T110000Z"),
-        ("uid-c3", "Count3", "20270801T120000Z", "20270801T130000Z"),
-    ];
-    let (ics_addr, caldav_addr) = start_reverse_sync_mocks(&events, StatusCode::NO_CONTENT).await;
+async fn subscription_source_fetches_and_serves_feed_verbatim() {
+    let events = [("sub-1", "Mirrored", "20270601T080000Z", "20270601T090000Z")];
+    let feed = start_feed(&events).await;
+    let state = AppState::new(db::open_memory_pool().unwrap());
+    let router = caldav_ics_sync::server::build_router(state.clone(), "http://127.0.0.1:9").await;
 
-    let stats = run_reverse_sync(
-        &format!("http://{}/feed.ics", ics_addr),
-        &format!("http://{}/dav/", caldav_addr),
-        "work",
-        "user",
-        "pass",
-        false,
-        false,
+    let body = serde_json::json!({
+        "name": "Mirror",
+        "ics_url": format!("http://{feed}/export.ics"),
+        "ics_path": "mirror.ics",
+        "sync_interval_secs": 0
+    });
+    let (status, created) = send(
+        &router,
+        axum::http::Request::post("/api/sources/subscription")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{}", created);
+    let created: serde_json::Value = serde_json::from_str(&created).unwrap();
+    assert_eq!(created["source"]["kind"], "subscription");
+    let id = created["source"]["id"].as_i64().unwrap();
+
+    let (status, synced) = send(
+        &router,
+        axum::http::Request::post(format!("/api/sources/{id}/sync"))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", synced);
+    let synced: serde_json::Value = serde_json::from_str(&synced).unwrap();
+    assert_eq!(synced["events"], 1);
+
+    let (status, served) = send(
+        &router,
+        axum::http::Request::get("/ics/mirror.ics")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(served, mock_ics_feed(&events));
+}
+
+#[tokio::test]
+async fn manual_sync_reports_a_failed_save() {
+    let feed = start_feed(&[("sub-1", "Mirrored", "20270601T080000Z", "20270601T090000Z")]).await;
+    let state = AppState::new(db::open_memory_pool().unwrap());
+    let router = caldav_ics_sync::server::build_router(state.clone(), "http://127.0.0.1:9").await;
+    let id = db::create_subscription_source(
+        &state.conn().unwrap(),
+        &serde_json::from_value(serde_json::json!({
+            "name": "Mirror",
+            "ics_url": format!("http://{feed}/export.ics"),
+            "ics_path": "mirror.ics",
+            "sync_interval_secs": 0
+        }))
+        .unwrap(),
     )
-    .await
     .unwrap();
+    state
+        .conn()
+        .unwrap()
+        .execute_batch(
+            "CREATE TRIGGER fail_ics BEFORE INSERT ON ics_data
+             BEGIN SELECT RAISE(ABORT, 'disk full'); END;",
+        )
+        .unwrap();
 
-    assert_eq!(stats.uploaded, 3);
-    assert_eq!(stats.total, 3);
+    let (status, body) = send(
+        &router,
+        axum::http::Request::post(format!("/api/sources/{id}/sync"))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR, "{}", body);
+    assert!(body.contains("disk full"), "{}", body);
+    let db = state.conn().unwrap();
+    let source = db::get_source(&db, id).unwrap().unwrap();
+    assert_eq!(source.last_sync_status.as_deref(), Some("error"));
+    let history = db::list_sync_history(&db, id, None, 10, 0).unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].status, "error");
 }
 
 #[tokio::test]
-async fn reverse_sync_returns_error_when_uploads_fail() {
-    let events = [("uid-fail", "Fail", "20270901T080000Z", "20270901T090000Z")];
-    let (ics_addr, caldav_addr) =
-        start_reverse_sync_mocks(&events, StatusCode::INTERNAL_SERVER_ERROR).await;
+async fn subscription_source_rejects_non_calendar_body() {
+    let app = Router::new().fallback(any(|| async { (StatusCode::OK, "<html>Sign in</html>") }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
 
-    let result = run_reverse_sync(
-        &format!("http://{}/feed.ics", ics_addr),
-        &format!("http://{}/dav/", caldav_addr),
-        "cal",
-        "user",
-        "pass",
-        false,
-        false,
+    let err = caldav_ics_sync::api::sync::pull_subscription(
+        &format!("http://{addr}/gone.ics"),
+        &SyncOptions::default(),
     )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("did not return a VCALENDAR"));
+}
+
+#[tokio::test]
+async fn subscription_source_refreshes_on_its_interval() {
+    let feed = start_counting_feed().await;
+    let state = AppState::new(db::open_memory_pool().unwrap());
+    let id = db::create_subscription_source(
+        &state.conn().unwrap(),
+        &db::CreateSubscriptionSource {
+            name: "Mirror".into(),
+            ics_url: format!("http://{feed}/export.ics"),
+            ics_path: "mirror.ics".into(),
+            sync_interval_secs: 1,
+            public_ics: false,
+            public_ics_path: None,
+            allow_invalid_certs: false,
+        },
+    )
+    .unwrap();
+    let source = db::get_source(&state.conn().unwrap(), id).unwrap().unwrap();
+
+    auto_sync::register_source(&state.sync_tasks, &state, &source);
+
+    let refreshed = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        loop {
+            let ics = db::get_ics_data(&state.conn().unwrap(), id).unwrap();
+            if ics
+                .as_deref()
+                .is_some_and(|ics| ics.contains("SUMMARY:Fetch 2"))
+            {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+    })
     .await;
+    state.shutdown.cancel();
+    refreshed.expect("the feed should be fetched again after one interval");
 
-    assert!(result.is_err());
-    let err_msg = result.unwrap_err().to_string();
-    assert!(
-        err_msg.contains("failed"),
-        "Expected failure message, got: {err_msg}"
-    );
+    let source = db::get_source(&state.conn().unwrap(), id).unwrap().unwrap();
+    assert_eq!(source.last_sync_status.as_deref(), Some("ok"));
+    assert!(source.last_synced.is_some());
 }
 
+// ---------------------------------------------------------------------------
+// skip_own_origin
+// ---------------------------------------------------------------------------
+
 #[tokio::test]
-async fn reverse_sync_skips_unchanged_events() {
-    let events = [
-        (
-            "uid-same",
-            "Same Event",
-            "20270601T080000Z",
-            "20270601T090000Z",
-        ),
-        (
-            "uid-new",
-            "New Event",
-            "20270601T100000Z",
-            "20270601T110000Z",
-        ),
-    ];
-    let ics_feed = mock_ics_feed(&events);
+async fn reverse_sync_skip_own_origin_uploads_only_external_events() {
+    let feed_body = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//Example Corp//Calendar//EN\r\n\
+         BEGIN:VEVENT\r\nUID:external\r\nSUMMARY:External\r\nDTSTART:20270601T080000Z\r\nDTEND:20270601T090000Z\r\nEND:VEVENT\r\n\
+         BEGIN:VEVENT\r\nUID:ours\r\nSUMMARY:Ours\r\nDTSTART:20270602T080000Z\r\nDTEND:20270602T090000Z\r\nX-SYNC-ORIGIN:12\r\nEND:VEVENT\r\n\
+         END:VCALENDAR\r\n";
+    let feed = start_mock_server(std::sync::Arc::new(MockState {
+        propfind_body: String::new(),
+        report_body: feed_body.into(),
+        put_status: StatusCode::OK,
+    }))
+    .await;
 
-    // ICS feed server
-    let ics_state = std::sync::Arc::new(MockState {
+    for (skip_own_origin, expected) in [(true, vec!["external"]), (false, vec!["external", "ours"])]
+    {
+        let (caldav_addr, log) = start_marked_caldav_server(&[]).await;
+        let stats = run_reverse_sync(
+            &format!("http://{feed}/feed.ics"),
+            &format!("http://{}/dav/", caldav_addr),
+            "cal",
+            "user",
+            "pass",
+            true,
+            true,
+            &ReverseSyncOptions {
+                skip_own_origin,
+                collect_uids: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(stats.total, expected.len());
+        assert_eq!(stats.uids.unwrap().uploaded, expected);
+        assert_eq!(log.lock().unwrap().len(), expected.len());
+    }
+}
+
+// ---------------------------------------------------------------------------
+// future_window_days
+// ---------------------------------------------------------------------------
+
+fn days_from_now(days: i64) -> (String, String) {
+    let start = chrono::Utc::now() + chrono::Duration::days(days);
+    let end = start + chrono::Duration::hours(1);
+    (
+        start.format("%Y%m%dT%H%M%SZ").to_string(),
+        end.format("%Y%m%dT%H%M%SZ").to_string(),
+    )
+}
+
+#[tokio::test]
+async fn reverse_sync_future_window_excludes_distant_events() {
+    let (near_start, near_end) = days_from_now(10);
+    let (far_start, far_end) = days_from_now(400);
+    let feed = start_mock_server(std::sync::Arc::new(MockState {
         propfind_body: String::new(),
-        report_body: ics_feed,
+        report_body: mock_ics_feed(&[
+            ("near", "Near", &near_start, &near_end),
+            ("far", "Far", &far_start, &far_end),
+        ]),
         put_status: StatusCode::OK,
-    });
-    let ics_addr = start_mock_server(ics_state).await;
+    }))
+    .await;
 
-    // CalDAV server that already has uid-same (returned via REPORT)
-    let existing = [(
-        "uid-same",
-        "Same Event",
-        "20270601T080000Z",
-        "20270601T090000Z",
-    )];
-    let caldav_state = std::sync::Arc::new(MockState {
+    for (future_window_days, expected) in [(30, vec!["near"]), (0, vec!["far", "near"])] {
+        let (caldav_addr, _log) = start_marked_caldav_server(&[]).await;
+        let stats = run_reverse_sync(
+            &format!("http://{feed}/feed.ics"),
+            &format!("http://{}/dav/", caldav_addr),
+            "cal",
+            "user",
+            "pass",
+            false,
+            true,
+            &ReverseSyncOptions {
+                future_window_days,
+                collect_uids: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let mut uploaded = stats.uids.unwrap().uploaded;
+        uploaded.sort();
+        assert_eq!(uploaded, expected);
+    }
+}
+
+#[tokio::test]
+async fn reverse_sync_future_window_keeps_distant_server_events() {
+    let (near_start, near_end) = days_from_now(10);
+    let feed = start_mock_server(std::sync::Arc::new(MockState {
         propfind_body: String::new(),
-        report_body: mock_report_response(&existing),
-        put_status: StatusCode::CREATED,
-    });
-    let caldav_addr = start_mock_server(caldav_state).await;
+        report_body: mock_ics_feed(&[("near", "Near", &near_start, &near_end)]),
+        put_status: StatusCode::OK,
+    }))
+    .await;
 
-    let stats = run_reverse_sync(
-        &format!("http://{}/feed.ics", ics_addr),
-        &format!("http://{}/dav/", caldav_addr),
-        "cal",
-        "user",
-        "pass",
-        false,
-        false,
-    )
-    .await
-    .unwrap();
+    // The marked server event starts in June 2027, well past a 30-day window.
+    for (future_window_days, deleted) in [(30, 0), (0, 1)] {
+        let (caldav_addr, log) =
+            start_marked_caldav_server(&[("distant", "DTEND:20270601T090000Z\nX-SYNC-ORIGIN:7")])
+                .await;
+        let stats = run_reverse_sync(
+            &format!("http://{feed}/feed.ics"),
+            &format!("http://{}/dav/", caldav_addr),
+            "cal",
+            "user",
+            "pass",
+            false,
+            false,
+            &ReverseSyncOptions {
+                origin: Some("7".into()),
+                future_window_days,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
 
-    assert_eq!(stats.total, 2);
-    assert_eq!(stats.skipped, 1, "uid-same should be skipped");
-    assert_eq!(stats.uploaded, 1, "only uid-new should be uploaded");
-    assert_eq!(stats.deleted, 0);
+        assert_eq!(stats.deleted, deleted);
+        let deletes = log
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| entry.starts_with("DELETE"))
+            .count();
+        assert_eq!(deletes, deleted);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// calendar_url_override
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn reverse_sync_writes_to_calendar_url_override() {
+    let feed = start_mock_server(std::sync::Arc::new(MockState {
+        propfind_body: String::new(),
+        report_body: mock_ics_feed(&[("ev1", "Event", "20270601T080000Z", "20270601T090000Z")]),
+        put_status: StatusCode::OK,
+    }))
+    .await;
+
+    let (caldav_addr, log) = start_marked_caldav_server(&[]).await;
+    for (override_url, expected) in [
+        (
+            Some(format!(
+                "http://{caldav_addr}/remote.php/calendars/u/personal"
+            )),
+            "PUT /remote.php/calendars/u/personal/ev1.ics",
+        ),
+        (None, "PUT /dav/Work/ev1.ics"),
+    ] {
+        log.lock().unwrap().clear();
+        run_reverse_sync(
+            &format!("http://{feed}/feed.ics"),
+            &format!("http://{caldav_addr}/dav/"),
+            "Work",
+            "user",
+            "pass",
+            true,
+            true,
+            &ReverseSyncOptions {
+                calendar_url_override: override_url,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let log = log.lock().unwrap();
+        assert_eq!(log.len(), 1);
+        assert!(log[0].starts_with(&format!("{expected}\n")), "{}", log[0]);
+    }
 }